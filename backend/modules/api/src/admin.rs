@@ -0,0 +1,259 @@
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, get, post,
+    web::{Json, Path, Query},
+};
+use dto::{
+    admin::{
+        ArchiveGamesRequest, ArchiveRunDTO, MatchAnalyticsDTO, MatchAnalyticsQuery, RecomputeJobDTO, RecomputeRatingsRequest,
+        SuspiciousGameDTO, SuspiciousGamesQuery,
+    },
+    pagination::Page,
+    responses::{InvalidCredentialsResponse, NotFoundResponse},
+};
+use error::error::ApiError;
+use security::{Claims, is_admin};
+use serde_json::json;
+use service::pagination::page_bounds;
+use service::{archive, fair_play, match_analytics, rating_recompute};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Admin endpoints sit behind `JwtAuthMiddleware`; this additionally checks the caller is an admin.
+#[allow(clippy::result_large_err)]
+pub(crate) fn require_admin(req: &HttpRequest) -> Result<(), HttpResponse> {
+    match req.extensions().get::<Claims>() {
+        Some(claims) if is_admin(claims) => Ok(()),
+        Some(_) => Err(HttpResponse::Forbidden().json(json!({
+            "error": "Admin privileges required",
+            "code": 403
+        }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({
+            "error": "Invalid or missing authorization token",
+            "code": 401
+        }))),
+    }
+}
+
+fn job_accepted(message: &str, job: RecomputeJobDTO) -> HttpResponse {
+    HttpResponse::Accepted().json(json!({
+        "message": message,
+        "data": {
+            "job": job
+        }
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/admin/ratings/recompute",
+    request_body = RecomputeRatingsRequest,
+    responses(
+        (status = 202, description = "Recompute job started", body = RecomputeJobDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse),
+        (status = 409, description = "A recompute job is already running", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[post("/ratings/recompute")]
+pub async fn recompute_ratings(
+    req: HttpRequest,
+    payload: Option<Json<RecomputeRatingsRequest>>,
+) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    let dry_run = payload.map(|p| p.into_inner().dry_run).unwrap_or(false);
+    match rating_recompute::create_job(dry_run).await {
+        Ok(job) => {
+            actix_web::rt::spawn(rating_recompute::run_job(job.id));
+            job_accepted("Rating recompute started", job.into())
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/admin/ratings/recompute/{id}",
+    params(
+        ("id" = String, Path, description = "Job ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Job progress and, once completed, its report", body = RecomputeJobDTO),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse),
+        (status = 404, description = "Job not found", body = NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[get("/ratings/recompute/{id}")]
+pub async fn get_recompute_job(req: HttpRequest, id: Path<Uuid>) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    match rating_recompute::get_job(id.into_inner()).await {
+        Ok(job) => HttpResponse::Ok().json(json!({
+            "message": "Recompute job found",
+            "data": {
+                "job": RecomputeJobDTO::from(job)
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/admin/ratings/recompute/{id}/resume",
+    params(
+        ("id" = String, Path, description = "Job ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 202, description = "Job resumed from its last committed batch", body = RecomputeJobDTO),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse),
+        (status = 404, description = "Job not found", body = NotFoundResponse),
+        (status = 409, description = "Job is already running or has completed", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[post("/ratings/recompute/{id}/resume")]
+pub async fn resume_recompute_job(req: HttpRequest, id: Path<Uuid>) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    match rating_recompute::resume_job(id.into_inner()).await {
+        Ok(job) => {
+            actix_web::rt::spawn(rating_recompute::run_job(job.id));
+            job_accepted("Rating recompute resumed", job.into())
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/matchmaking/admin/analytics",
+    params(
+        ("hours" = Option<i64>, Query, description = "Window size in hours ending now (1-720, default 24)")
+    ),
+    responses(
+        (status = 200, description = "Rating gap and wait time statistics of the matches formed in the window", body = MatchAnalyticsDTO),
+        (status = 400, description = "Invalid window", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[get("/analytics")]
+pub async fn get_match_analytics(req: HttpRequest, query: Query<MatchAnalyticsQuery>) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match match_analytics::summarize(query.hours).await {
+        Ok(analytics) => HttpResponse::Ok().json(json!({
+            "message": "Match analytics computed",
+            "data": {
+                "analytics": analytics
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/admin/fair-play/games",
+    params(
+        ("min_score" = Option<f64>, Query, description = "Lowest suspicion score to list (0-1, default FAIR_PLAY_FLAG_THRESHOLD)"),
+        ("page" = Option<i32>, Query, description = "Page number (default 1)"),
+        ("limit" = Option<i32>, Query, description = "Games per page (1-100, default 10)")
+    ),
+    responses(
+        (status = 200, description = "Reviewed games at or above the score, most engine-like first", body = Page<SuspiciousGameDTO>),
+        (status = 400, description = "Invalid filter", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[get("/fair-play/games")]
+pub async fn list_suspicious_games(req: HttpRequest, query: Query<SuspiciousGamesQuery>) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    let (page, page_size) = page_bounds(query.page, query.limit);
+    match fair_play::suspicious_games(query.min_score, page, page_size).await {
+        Ok(games) => HttpResponse::Ok().json(json!({
+            "message": "Suspicious games found",
+            "data": games
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/admin/games/archive",
+    request_body = ArchiveGamesRequest,
+    responses(
+        (status = 200, description = "Finished games older than the cutoff moved to the archive", body = ArchiveRunDTO),
+        (status = 400, description = "Invalid age", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not an admin", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Admin"
+)]
+#[post("/games/archive")]
+pub async fn archive_games(req: HttpRequest, payload: Option<Json<ArchiveGamesRequest>>) -> HttpResponse {
+    if let Err(response) = require_admin(&req) {
+        return response;
+    }
+
+    let payload = payload.map(Json::into_inner).unwrap_or_default();
+    if let Err(errors) = payload.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match archive::archive_finished_games(payload.older_than_days).await {
+        Ok(run) => HttpResponse::Ok().json(json!({
+            "message": "Finished games archived",
+            "data": {
+                "run": run
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
@@ -6,8 +6,7 @@ use dto::{
     ai::{AiSuggestionRequest, AiSuggestionResponse, PositionAnalysisRequest, PositionAnalysisResponse},
     responses::ValidationErrorResponse,
 };
-use error::error::ApiError;
-use serde_json::json;
+use service::ai;
 use validator::Validate;
 
 #[utoipa::path(
@@ -16,7 +15,9 @@ use validator::Validate;
     request_body = AiSuggestionRequest,
     responses(
         (status = 200, description = "AI suggestion generated", body = AiSuggestionResponse),
-        (status = 400, description = "Invalid FEN position", body = ValidationErrorResponse)
+        (status = 400, description = "Invalid FEN position", body = ValidationErrorResponse),
+        (status = 503, description = "No chess engine is available"),
+        (status = 504, description = "The engine found nothing within the server's time limit")
     ),
     security(
         ("jwt_auth" = [])
@@ -26,17 +27,10 @@ use validator::Validate;
 #[post("/suggest")]
 pub async fn get_ai_suggestion(payload: Json<AiSuggestionRequest>) -> HttpResponse {
     match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would call the chess engine
-            // For now, we'll just return a mock response
-            HttpResponse::Ok().json(json!({
-                "best_move": "e2e4",
-                "evaluation": 0.3,
-                "depth": payload.0.depth.unwrap_or(10),
-                "principal_variation": ["e2e4", "e7e5", "Ng1f3"],
-                "computation_time_ms": 2345
-            }))
-        }
+        Ok(_) => match ai::suggest(&payload.0).await {
+            Ok(suggestion) => HttpResponse::Ok().json(suggestion),
+            Err(err) => err.error_response(),
+        },
         Err(errors) => {
             let error_strings: Vec<String> = errors
                 .field_errors()
@@ -59,7 +53,9 @@ pub async fn get_ai_suggestion(payload: Json<AiSuggestionRequest>) -> HttpRespon
     request_body = PositionAnalysisRequest,
     responses(
         (status = 200, description = "Position analysis completed", body = PositionAnalysisResponse),
-        (status = 400, description = "Invalid FEN position", body = ValidationErrorResponse)
+        (status = 400, description = "Invalid FEN position", body = ValidationErrorResponse),
+        (status = 503, description = "No chess engine is available"),
+        (status = 504, description = "The engine found nothing within the server's time limit")
     ),
     security(
         ("jwt_auth" = [])
@@ -69,25 +65,10 @@ pub async fn get_ai_suggestion(payload: Json<AiSuggestionRequest>) -> HttpRespon
 #[post("/analyze")]
 pub async fn analyze_position(payload: Json<PositionAnalysisRequest>) -> HttpResponse {
     match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would analyze the position
-            // For now, we'll just return a mock response
-            HttpResponse::Ok().json(json!({
-                "evaluation": 0.3,
-                "best_line": ["e2e4", "e7e5", "Ng1f3", "Nb8c6"],
-                "alternatives": [
-                    {
-                        "chess_move": "d2d4",
-                        "evaluation": 0.25
-                    },
-                    {
-                        "chess_move": "c2c4",
-                        "evaluation": 0.20
-                    }
-                ],
-                "position_type": "Open Game"
-            }))
-        }
+        Ok(_) => match ai::analyze(&payload.0).await {
+            Ok(analysis) => HttpResponse::Ok().json(analysis),
+            Err(err) => err.error_response(),
+        },
         Err(errors) => {
             let error_strings: Vec<String> = errors
                 .field_errors()
@@ -1,45 +1,64 @@
 use actix_web::{
-    HttpResponse, post,
+    HttpRequest, HttpResponse, post,
     web::Json,
 };
+use chrono::Utc;
 use dto::{
-    auth::{LoginRequest, LoginResponse, RegisterRequest, RefreshTokenRequest, TokenResponse},
+    auth::{
+        ConvertGuestRequest, GuestInfo, GuestResponse, LoginRequest, LoginResponse, RegisterRequest,
+        RefreshTokenRequest, TokenResponse, UserInfo,
+    },
     responses::{InvalidCredentialsResponse, ValidationErrorResponse},
 };
 use error::error::ApiError;
+use security::{Claims, JwtAuthMiddleware, encode_token};
 use serde_json::json;
+use service::guests::{GuestConfig, convert_guest, create_guest};
+use service::players::authenticate;
 use validator::Validate;
 use uuid::Uuid;
 
+use crate::games::authenticated_player;
+use crate::ws::jwt_secret;
+
 #[utoipa::path(
     post,
     path = "/v1/auth/login",
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 400, description = "Invalid request", body = InvalidCredentialsResponse),
         (status = 401, description = "Invalid credentials", body = InvalidCredentialsResponse)
     ),
     tag = "Authentication"
 )]
 #[post("/login")]
 pub async fn login(payload: Json<LoginRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would verify credentials
-            // For now, we'll just return a mock response
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    // Outdated password hashes are upgraded as part of a successful login
+    match authenticate(&payload.0.username, &payload.0.password).await {
+        Ok(player) => {
+            // Token issuance is still mocked
             HttpResponse::Ok().json(json!({
                 "access_token": "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...",
                 "refresh_token": "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...",
                 "token_type": "Bearer",
                 "expires_in": 3600,
                 "user": {
-                    "id": Uuid::new_v4(),
-                    "username": payload.0.username,
-                    "email": "user@example.com"
+                    "id": player.id,
+                    "username": player.username,
+                    "email": player.email
                 }
             }))
         }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+        Err(ApiError::InvalidCredentials) => HttpResponse::Unauthorized().json(json!({
+            "error": ApiError::InvalidCredentials.to_string(),
+            "code": 401
+        })),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -124,3 +143,80 @@ pub async fn logout() -> HttpResponse {
         "message": "Logout successful"
     }))
 }
+
+#[utoipa::path(
+    post,
+    path = "/v1/auth/guest",
+    responses(
+        (status = 201, description = "Guest created; the token is short-lived and cannot be refreshed", body = GuestResponse)
+    ),
+    tag = "Authentication"
+)]
+#[post("/guest")]
+pub async fn create_guest_session() -> HttpResponse {
+    let guest = match create_guest().await {
+        Ok(guest) => guest,
+        Err(err) => return err.error_response(),
+    };
+
+    let ttl = GuestConfig::from_env().token_ttl;
+    let now = Utc::now().timestamp() as usize;
+    let claims = Claims {
+        sub: guest.id.to_string(),
+        iat: now,
+        exp: now + ttl.as_secs() as usize,
+        guest: true,
+    };
+    match encode_token(&claims, &jwt_secret()) {
+        Ok(access_token) => HttpResponse::Created().json(GuestResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: ttl.as_secs() as i32,
+            user: GuestInfo { id: guest.id, username: guest.username },
+        }),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Could not issue guest token: {}", err),
+            "code": 500
+        })),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/auth/guest/convert",
+    request_body = ConvertGuestRequest,
+    responses(
+        (status = 200, description = "Guest converted to a full account, keeping its id and games", body = UserInfo),
+        (status = 400, description = "Validation error", body = ValidationErrorResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 409, description = "Not a guest, or username or email already taken", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+#[post("/guest/convert", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn convert_guest_account(req: HttpRequest, payload: Json<ConvertGuestRequest>) -> HttpResponse {
+    let guest_id = match authenticated_player(&req) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match convert_guest(guest_id, payload.into_inner()).await {
+        Ok(player) => HttpResponse::Ok().json(json!({
+            "message": "Guest account converted",
+            "data": {
+                "user": {
+                    "id": player.id,
+                    "username": player.username,
+                    "email": player.email
+                }
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
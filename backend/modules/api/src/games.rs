@@ -1,17 +1,33 @@
+use actix::Addr;
 use actix_web::{
-    HttpResponse, delete, get, post, put,
-    web::{Json, Path, Query},
+    HttpMessage, HttpRequest, HttpResponse, delete, get, post, put,
+    web::{Data, Json, Path, Query},
 };
+use chess::time_control::{TimeClass, TimeControl, time_class};
 use dto::{
-    games::{CreateGameRequest, GameDisplayDTO, MakeMoveRequest, JoinGameRequest, GameStatus},
+    games::{
+        CreateGameRequest, DrawAction, DrawActionRequest, GameDisplayDTO,
+        GameSeries, ListGamesQuery, MakeMoveRequest, RatingPreview, ReplayPage, ReplayQuery, ResultFormat,
+        ResultFormatQuery,
+    },
+    pagination::Page,
     responses::{InvalidCredentialsResponse, NotFoundResponse},
+    seeks::{CreateSeekRequest, SeekDTO, SeeksQuery},
 };
 use error::error::ApiError;
+use security::{Claims, JwtAuthMiddleware};
 use serde_json::json;
+use service::games::{create_game as start_game, ensure_can_join_game, ensure_can_start_game, find_game, list_games as list_filtered_games};
+use service::lifecycle::GameAction;
+use service::rating::rating_preview as preview_ratings;
+use service::replay::replay_game as replay_game_page;
+use service::series::game_series as load_game_series;
+use service::seeks::{accept_seek as claim_seek, create_seek as post_seek, open_seeks};
+use std::time::Duration;
 use validator::Validate;
 use uuid::Uuid;
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+
+use crate::ws::{LobbyState, WatchClock, jwt_secret, perform_action, perform_move};
 
 #[utoipa::path(
     post,
@@ -20,17 +36,41 @@ use utoipa::ToSchema;
     responses(
         (status = 201, description = "Game created successfully", body = GameDisplayDTO),
         (status = 400, description = "Invalid request parameters", body = InvalidCredentialsResponse),
-        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse)
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 409, description = "Too many games in progress, or `rematch_of` is still being played", body = InvalidCredentialsResponse),
+        (status = 422, description = "A player id does not exist, or `rematch_of` is not a game between the same players; `field` names which", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
     ),
     tag = "Games"
 )]
-#[post("")]
-pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
+#[post("", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn create_game(req: HttpRequest, payload: Json<CreateGameRequest>) -> HttpResponse {
+    let creator = match authenticated_player(&req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
     match payload.0.validate() {
         Ok(_) => {
+            // With both seats known the game starts right away, under the creator's cap
+            // of unfinished games; open games are seeks, which are capped when accepted
+            if let Some(opponent) = payload.0.opponent_id {
+                return match start_game(creator, opponent, &payload.0).await {
+                    Ok(game) => {
+                        watch_clock(&req, game.id, payload.0.time_control, payload.0.increment);
+                        HttpResponse::Created().json(json!({
+                        "message": "Game created successfully",
+                            "data": {
+                                "game": GameDisplayDTO::from(game),
+                                "warnings": payload.0.fen_warnings()
+                            }
+                        }))
+                    }
+                    Err(err) => err.error_response(),
+                };
+            }
+
             // The real implementation would create a game in the database
             // For now, we'll just return a mock response
             HttpResponse::Created().json(json!({
@@ -38,8 +78,10 @@ pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
                 "data": {
                     "game": {
                         "id": Uuid::new_v4(),
-                        "status": "waiting"
-                    }
+                        "status": "waiting",
+                        "variant": payload.0.variant
+                    },
+                    "warnings": payload.0.fen_warnings()
                 }
             }))
         }
@@ -47,11 +89,144 @@ pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/games/seek",
+    request_body = CreateSeekRequest,
+    responses(
+        (status = 201, description = "Seek posted and waiting for an opponent", body = SeekDTO),
+        (status = 400, description = "Invalid request parameters", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 409, description = "Too many games in progress", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/seek", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn create_seek(req: HttpRequest, payload: Json<CreateSeekRequest>) -> HttpResponse {
+    let player_id = match authenticated_player(&req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let request = payload.into_inner();
+    if let Err(errors) = request.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+    let class = clock_class(request.time_control, request.increment);
+    if let Err(err) = ensure_can_start_game(player_id, class).await {
+        return err.error_response();
+    }
+
+    match post_seek(player_id, request).await {
+        Ok(seek) => HttpResponse::Created().json(json!({
+            "message": "Seek created successfully",
+            "data": {
+                "seek": seek
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/seeks",
+    params(
+        ("page" = Option<i32>, Query, description = "Page number for pagination"),
+        ("limit" = Option<i32>, Query, description = "Number of items per page")
+    ),
+    responses(
+        (status = 200, description = "Seeks waiting for an opponent, oldest first", body = Page<SeekDTO>),
+        (status = 400, description = "Invalid pagination parameters", body = InvalidCredentialsResponse)
+    ),
+    tag = "Games"
+)]
+#[get("/seeks")]
+pub async fn list_seeks(query: Query<SeeksQuery>) -> HttpResponse {
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match open_seeks(&query).await {
+        Ok(seeks) => HttpResponse::Ok().json(json!({
+            "message": "Seeks found",
+            "data": seeks
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/seeks/{id}/accept",
+    params(
+        ("id" = String, Path, description = "Seek ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Seat claimed and game started", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 404, description = "Seek not found", body = NotFoundResponse),
+        (status = 409, description = "Seek already taken, own seek, or too many games in progress", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/seeks/{id}/accept", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn accept_seek(req: HttpRequest, id: Path<Uuid>) -> HttpResponse {
+    let player_id = match authenticated_player(&req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+
+    match claim_seek(id.into_inner(), player_id).await {
+        Ok(accepted) => {
+            watch_clock(&req, accepted.game.id, accepted.seek.initial_secs, accepted.seek.increment_secs);
+            HttpResponse::Ok().json(json!({
+                "message": "Seek accepted",
+                "data": {
+                    "seek": SeekDTO::from(accepted.seek),
+                    "game": GameDisplayDTO::from(accepted.game)
+                }
+            }))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/series/{root_id}",
+    params(
+        ("root_id" = String, Path, description = "UUID or public id of the series' first game")
+    ),
+    responses(
+        (status = 200, description = "The game and its rematches in the order they were played, with a running score", body = GameSeries),
+        (status = 404, description = "Game not found", body = NotFoundResponse)
+    ),
+    tag = "Games"
+)]
+#[get("/series/{root_id}")]
+pub async fn game_series(root_id: Path<String>) -> HttpResponse {
+    match load_game_series(&root_id).await {
+        Ok(series) => HttpResponse::Ok().json(json!({
+            "message": "Game series",
+            "data": series
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/v1/games/{id}",
     params(
-        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+        ("id" = String, Path, description = "Game UUID or its 8-character public id"),
+        ("result_format" = Option<ResultFormat>, Query, description = "Write the result by colour (default) or as a PGN token")
     ),
     responses(
         (status = 200, description = "Game found", body = GameDisplayDTO),
@@ -63,18 +238,72 @@ pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
     tag = "Games"
 )]
 #[get("/{id}")]
-pub async fn get_game(id: Path<Uuid>) -> HttpResponse {
-    // The real implementation would fetch the game from the database
-    // For now, we'll just return a mock response
-    HttpResponse::Ok().json(json!({
-        "message": "Game found",
-        "data": {
-            "game": {
-                "id": id.into_inner(),
-                "status": "in_progress"
+pub async fn get_game(id: Path<String>, query: Query<ResultFormatQuery>) -> HttpResponse {
+    match find_game(&id).await {
+        Ok(game) => HttpResponse::Ok().json(json!({
+            "message": "Game found",
+            "data": {
+                "game": GameDisplayDTO::new(game, query.result_format)
             }
-        }
-    }))
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/{id}/replay",
+    params(
+        ("id" = String, Path, description = "Game UUID or its 8-character public id"),
+        ("from" = Option<u32>, Query, description = "Plies to skip; 0 starts at the initial position"),
+        ("to" = Option<u32>, Query, description = "Last ply to return, at most 200 after `from` (default)")
+    ),
+    responses(
+        (status = 200, description = "The requested ply range with the position after each ply", body = ReplayPage),
+        (status = 400, description = "Invalid ply range", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is longer than the server's ply limit", body = InvalidCredentialsResponse)
+    ),
+    tag = "Games"
+)]
+#[get("/{id}/replay")]
+pub async fn replay_game(id: Path<String>, query: Query<ReplayQuery>) -> HttpResponse {
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match replay_game_page(&id, query).await {
+        Ok(page) => HttpResponse::Ok().json(json!({
+            "message": "Game replayed",
+            "data": page
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/{id}/rating-preview",
+    params(
+        ("id" = String, Path, description = "Game UUID or its 8-character public id")
+    ),
+    responses(
+        (status = 200, description = "Rating change of each player for every result", body = RatingPreview),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is already over", body = InvalidCredentialsResponse)
+    ),
+    tag = "Games"
+)]
+#[get("/{id}/rating-preview")]
+pub async fn rating_preview(id: Path<String>) -> HttpResponse {
+    match preview_ratings(&id).await {
+        Ok(preview) => HttpResponse::Ok().json(json!({
+            "message": "Rating preview",
+            "data": preview
+        })),
+        Err(err) => err.error_response(),
+    }
 }
 
 #[utoipa::path(
@@ -87,47 +316,41 @@ pub async fn get_game(id: Path<Uuid>) -> HttpResponse {
     responses(
         (status = 200, description = "Move made successfully", body = GameDisplayDTO),
         (status = 400, description = "Invalid move", body = InvalidCredentialsResponse),
-        (status = 404, description = "Game not found", body = NotFoundResponse)
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not playing in this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is over, or it is not the caller's turn (`reason` is `not_your_turn`)", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
     ),
     tag = "Games"
 )]
-#[put("/{id}/move")]
-pub async fn make_move(id: Path<Uuid>, payload: Json<MakeMoveRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would validate and make the move
-            // For now, we'll just return a mock response
-            HttpResponse::Ok().json(json!({
-                "message": "Move made successfully",
-                "data": {
-                    "game": {
-                        "id": id.into_inner(),
-                        "status": "in_progress",
-                        "last_move": payload.0.chess_move
-                    }
-                }
-            }))
-        }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+#[put("/{id}/move", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn make_move(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    payload: Json<MakeMoveRequest>,
+    lobby: Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
     }
-}
+    let player_id = match authenticated_player(&req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
 
-#[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct ListGamesQuery {
-    #[schema(example = "waiting")]
-    pub status: Option<String>,
-    
-    #[schema(value_type = Option<String>, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
-    pub player_id: Option<Uuid>,
-    
-    #[schema(default = 1, example = 1)]
-    pub page: Option<i32>,
-    
-    #[schema(default = 10, example = 10)]
-    pub limit: Option<i32>,
+    // Spectators see this move exactly as if it had been sent over the socket
+    match perform_move(&lobby, id.into_inner(), player_id, &payload.0.chess_move).await {
+        Ok(game) => HttpResponse::Ok().json(json!({
+            "message": "Move made successfully",
+            "data": {
+                "game": GameDisplayDTO::from(game)
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
 }
 
 #[utoipa::path(
@@ -136,11 +359,15 @@ pub struct ListGamesQuery {
     params(
         ("status" = Option<String>, Query, description = "Filter games by status (waiting, in_progress, completed, aborted)"),
         ("player_id" = Option<String>, Query, description = "Filter games by player ID", format = "uuid"),
+        ("eco" = Option<String>, Query, description = "Filter by ECO code (B20), volume prefix (B2*, B*) or 'none' for unclassified games"),
+        ("opening" = Option<String>, Query, description = "Case-insensitive match on the opening name"),
         ("page" = Option<i32>, Query, description = "Page number for pagination"),
-        ("limit" = Option<i32>, Query, description = "Number of items per page")
+        ("limit" = Option<i32>, Query, description = "Number of items per page"),
+        ("result_format" = Option<ResultFormat>, Query, description = "Write results by colour (default) or as PGN tokens")
     ),
     responses(
-        (status = 200, description = "List of games", body = Vec<GameDisplayDTO>)
+        (status = 200, description = "List of games", body = Page<GameDisplayDTO>),
+        (status = 400, description = "Invalid filter", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
@@ -149,79 +376,19 @@ pub struct ListGamesQuery {
 )]
 #[get("")]
 pub async fn list_games(query: Query<ListGamesQuery>) -> HttpResponse {
-    // The real implementation would fetch games from the database with filters
-    // For now, we'll just return a mock response with filtering logic
-    
-    // Default pagination values
-    let page = query.page.unwrap_or(1);
-    let limit = query.limit.unwrap_or(10);
-    
-    // Create a mock list of games
-    let mut mock_games = vec![
-        json!({
-            "id": Uuid::new_v4(),
-            "status": "waiting",
-            "white_player_id": Uuid::new_v4(),
-            "black_player_id": null,
-            "created_at": "2025-05-31T10:00:00Z"
-        }),
-        json!({
-            "id": Uuid::new_v4(),
-            "status": "in_progress",
-            "white_player_id": Uuid::new_v4(),
-            "black_player_id": Uuid::new_v4(),
-            "created_at": "2025-05-31T11:00:00Z"
-        }),
-        json!({
-            "id": Uuid::new_v4(),
-            "status": "completed",
-            "white_player_id": Uuid::new_v4(),
-            "black_player_id": Uuid::new_v4(),
-            "created_at": "2025-05-31T09:00:00Z"
-        })
-    ];
-    
-    // Apply status filter if provided
-    if let Some(status) = &query.status {
-        mock_games.retain(|game| {
-            game["status"].as_str().unwrap_or("") == status
-        });
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
     }
-    
-    // Apply player_id filter if provided
-    if let Some(player_id) = query.player_id {
-        mock_games.retain(|game| {
-            // Check if player is white or black player
-            let white_id = game["white_player_id"].as_str().unwrap_or("");
-            let black_id = game["black_player_id"].as_str().unwrap_or("");
-            white_id == player_id.to_string() || black_id == player_id.to_string()
-        });
+
+    let result_format = query.result_format;
+    match list_filtered_games(query).await {
+        Ok(games) => HttpResponse::Ok().json(json!({
+            "message": "Games found",
+            "data": games.map(|game| GameDisplayDTO::new(game, result_format))
+        })),
+        Err(err) => err.error_response(),
     }
-    
-    // Apply pagination
-    let total = mock_games.len();
-    let start_idx = ((page - 1) * limit) as usize;
-    let end_idx = (start_idx + limit as usize).min(total);
-    
-    // Get paginated subset (handle out of bounds)
-    let paginated_games = if start_idx < total {
-        mock_games[start_idx..end_idx].to_vec()
-    } else {
-        Vec::new()
-    };
-    
-    HttpResponse::Ok().json(json!({
-        "message": "Games found",
-        "data": {
-            "games": paginated_games,
-            "pagination": {
-                "total": total,
-                "page": page,
-                "limit": limit,
-                "pages": (total as f32 / limit as f32).ceil() as i32
-            }
-        }
-    }))
 }
 
 #[utoipa::path(
@@ -230,36 +397,40 @@ pub async fn list_games(query: Query<ListGamesQuery>) -> HttpResponse {
     params(
         ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
     ),
-    request_body = JoinGameRequest,
     responses(
         (status = 200, description = "Joined game successfully", body = GameDisplayDTO),
         (status = 400, description = "Cannot join game", body = InvalidCredentialsResponse),
-        (status = 404, description = "Game not found", body = NotFoundResponse)
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Too many games in progress", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
     ),
     tag = "Games"
 )]
-#[post("/{id}/join")]
-pub async fn join_game(id: Path<Uuid>, payload: Json<JoinGameRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would add the player to the game
-            // For now, we'll just return a mock response
-            HttpResponse::Ok().json(json!({
-                "message": "Joined game successfully",
-                "data": {
-                    "game": {
-                        "id": id.into_inner(),
-                        "status": "in_progress",
-                        "player_id": payload.0.player_id
-                    }
-                }
-            }))
-        }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+#[post("/{id}/join", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn join_game(req: HttpRequest, id: Path<Uuid>) -> HttpResponse {
+    let player_id = match authenticated_player(&req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let id = id.into_inner();
+    if let Err(err) = ensure_can_join_game(player_id, id).await {
+        return err.error_response();
     }
+    // The real implementation would add the player to the game
+    // For now, we'll just return a mock response
+    HttpResponse::Ok().json(json!({
+        "message": "Joined game successfully",
+        "data": {
+            "game": {
+                "id": id,
+                "status": "in_progress",
+                "player_id": player_id
+            }
+        }
+    }))
 }
 
 #[utoipa::path(
@@ -285,4 +456,154 @@ pub async fn abandon_game(id: Path<Uuid>) -> HttpResponse {
         "message": "Game abandoned successfully",
         "data": {}
     }))
-}
\ No newline at end of file
+}
+
+fn time_control(initial_secs: i32, increment_secs: i32) -> TimeControl {
+    TimeControl {
+        initial_time: Duration::from_secs(initial_secs as u64),
+        increment: Duration::from_secs(increment_secs as u64),
+        delay: Duration::ZERO,
+    }
+}
+
+/// Speed category of an `initial_secs`+`increment_secs` clock.
+fn clock_class(initial_secs: i32, increment_secs: i32) -> TimeClass {
+    time_class(&time_control(initial_secs, increment_secs))
+}
+
+/// Has the lobby warn the room of a game that just started when a clock runs low.
+/// Apps mounted without a lobby, such as handler tests, skip it.
+fn watch_clock(req: &HttpRequest, game_id: Uuid, initial_secs: i32, increment_secs: i32) {
+    if let Some(lobby) = req.app_data::<Data<Addr<LobbyState>>>() {
+        lobby.do_send(WatchClock {
+            game_id: game_id.to_string(),
+            time_control: time_control(initial_secs, increment_secs),
+        });
+    }
+}
+
+/// Player id carried by the JWT that `JwtAuthMiddleware` attached to the request.
+#[allow(clippy::result_large_err)]
+pub(crate) fn authenticated_player(req: &HttpRequest) -> Result<Uuid, HttpResponse> {
+    req.extensions()
+        .get::<Claims>()
+        .and_then(|claims| Uuid::parse_str(&claims.sub).ok())
+        .ok_or_else(|| {
+            HttpResponse::Unauthorized().json(json!({
+                "error": "Invalid or missing authorization token",
+                "code": 401
+            }))
+        })
+}
+
+/// Runs `action` for the authenticated player; the room is notified by `perform_action`.
+async fn game_action_response(
+    req: &HttpRequest,
+    lobby: &Addr<LobbyState>,
+    game_id: Uuid,
+    action: GameAction,
+    message: &str,
+) -> HttpResponse {
+    let player_id = match authenticated_player(req) {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+
+    match perform_action(lobby, game_id, player_id, action).await {
+        Ok(game) => HttpResponse::Ok().json(json!({
+            "message": message,
+            "data": {
+                "game": GameDisplayDTO::from(game)
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/{id}/resign",
+    params(
+        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Game resigned; the opponent wins", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not playing in this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is already over", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/{id}/resign", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn resign_game(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    lobby: Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    game_action_response(&req, &lobby, id.into_inner(), GameAction::Resign, "Game resigned").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/{id}/abort",
+    params(
+        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Game aborted without a result", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not playing in this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is over or past the abort window", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/{id}/abort", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn abort_game(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    lobby: Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    game_action_response(&req, &lobby, id.into_inner(), GameAction::Abort, "Game aborted").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/{id}/draw",
+    params(
+        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+    ),
+    request_body = DrawActionRequest,
+    responses(
+        (status = 200, description = "Draw offered, accepted or declined", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Caller is not playing in this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "No draw can be offered or no offer is pending", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/{id}/draw", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn draw_game(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    payload: Json<DrawActionRequest>,
+    lobby: Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    let (action, message) = match payload.action {
+        DrawAction::Offer => (GameAction::OfferDraw, "Draw offered"),
+        DrawAction::Accept => (GameAction::AcceptDraw, "Draw agreed"),
+        DrawAction::Decline => (GameAction::DeclineDraw, "Draw declined"),
+    };
+    game_action_response(&req, &lobby, id.into_inner(), action, message).await
+}
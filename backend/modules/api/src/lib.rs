@@ -3,6 +3,12 @@ pub mod games;
 pub mod server;
 pub mod auth;
 pub mod ai;
+pub mod admin;
 pub mod openapi;
 pub mod ws;
+pub mod stats;
+pub mod time;
+pub mod tournaments;
+pub mod webhooks;
+pub mod matchmaking;
 mod test;
\ No newline at end of file
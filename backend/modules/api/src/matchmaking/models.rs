@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use chess::time_control::{TimeClass, time_class};
+use dto::games::Variant;
+use std::time::Duration;
+use entity::sea_orm_active_enums::ResultSide;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MatchType {
+    Rated,
+    Casual,
+    Private,
+}
+
+/// How the matcher trades match quality against waiting time when widening a rated
+/// request's elo window.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchmakingMode {
+    /// Keeps the window tight for a while and widens it slowly.
+    Strict,
+    #[default]
+    Balanced,
+    /// Widens the window quickly to cut waiting time.
+    Fast,
+}
+
+impl std::str::FromStr for MatchmakingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "strict" => Ok(MatchmakingMode::Strict),
+            "balanced" => Ok(MatchmakingMode::Balanced),
+            "fast" => Ok(MatchmakingMode::Fast),
+            _ => Err(format!("Unknown matchmaking mode '{}'", s)),
+        }
+    }
+}
+
+impl MatchmakingMode {
+    /// Deployment-wide mode from `MATCHMAKING_MODE`, `balanced` when unset or unknown.
+    pub fn from_env() -> Self {
+        std::env::var("MATCHMAKING_MODE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Clock settings a player is looking to play with, e.g. 600+0 for rapid 10+0.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TimeControl {
+    pub initial_secs: u32,
+    pub increment_secs: u32,
+}
+
+impl std::str::FromStr for TimeControl {
+    type Err = String;
+
+    /// Parses `minutes+increment`, e.g. `10+0` or `3+2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time control '{}', expected minutes+increment", s);
+        let (minutes, increment) = s.trim().split_once('+').ok_or_else(invalid)?;
+        let minutes: u32 = minutes.trim().parse().map_err(|_| invalid())?;
+        let increment_secs: u32 = increment.trim().parse().map_err(|_| invalid())?;
+        if minutes == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { initial_secs: minutes * 60, increment_secs })
+    }
+}
+
+impl TimeControl {
+    pub fn time_class(&self) -> TimeClass {
+        time_class(&chess::time_control::TimeControl {
+            initial_time: Duration::from_secs(self.initial_secs.into()),
+            increment: Duration::from_secs(self.increment_secs.into()),
+            delay: Duration::ZERO,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub wallet_address: String,
+    pub elo: u32,
+    pub join_time: DateTime<Utc>, 
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRequest {
+    pub id: Uuid,
+    pub player: Player,
+    pub match_type: MatchType,
+    pub invite_address: Option<String>, // For private matches__
+    pub max_elo_diff: Option<u32>,      // For rated matches__
+    pub time_control: TimeControl,
+    pub variant: Variant,
+    /// The player's own preference; the deployment's mode applies without one.
+    #[serde(default)]
+    pub mode: Option<MatchmakingMode>,
+    /// Account named by the bearer token the request was queued with; `None` for
+    /// wallets that queued anonymously.
+    #[serde(default)]
+    pub player_id: Option<Uuid>,
+}
+
+impl MatchRequest {
+    /// Only requests for the same game settings are paired with each other.
+    pub fn same_settings(&self, other: &MatchRequest) -> bool {
+        self.time_control == other.time_control && self.variant == other.variant
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Match {
+    pub id: Uuid,
+    pub player1: Player,
+    pub player2: Player,
+    pub match_type: MatchType,
+    pub time_control: TimeControl,
+    pub variant: Variant,
+    pub created_at: DateTime<Utc>, 
+}
+
+/// What is kept of a match once its game is over, so lookups can tell a finished
+/// match from one that never existed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedMatch {
+    pub id: Uuid,
+    pub match_type: MatchType,
+    /// The game the match was played as.
+    pub game_id: Uuid,
+    /// `None` when the game was aborted without a result.
+    pub result: Option<ResultSide>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub enum MatchLookup {
+    Active(Match),
+    Completed(CompletedMatch),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MatchmakingQueue {
+    pub rated_queue: Vec<MatchRequest>,
+    pub casual_queue: Vec<MatchRequest>,
+    pub private_invites: HashMap<String, MatchRequest>, // wallet_address -> request
+}
+
+impl MatchmakingQueue {
+    pub fn new() -> Self {
+        Self {
+            rated_queue: Vec::new(),
+            casual_queue: Vec::new(),
+            private_invites: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub request_id: Uuid,
+    pub position: usize,
+    pub estimated_wait_time: Duration,
+    pub match_type: MatchType,
+    /// Mode the request is matched under, so clients can explain the wait.
+    pub mode: MatchmakingMode,
+    /// Current elo window of a rated request.
+    pub elo_window: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchmakingResponse {
+    pub status: String,
+    pub match_id: Option<Uuid>,
+    pub request_id: Uuid,
+}
\ No newline at end of file
@@ -0,0 +1,330 @@
+use actix_web::{http::header, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use chess::time_control::{self, time_class};
+use dto::games::Variant;
+use security::{decode_token, Claims, JwtAuthMiddleware};
+use service::games::ensure_can_start_game;
+use service::guests::touch_guest;
+use service::match_analytics::{self, MatchRecord};
+use service::rating::player_rating;
+
+use super::models::*;
+use super::service::{EloBounds, MatchmakingService, QuickplayDefaults};
+
+#[derive(Debug, Deserialize)]
+pub struct JoinQueueRequest {
+    pub wallet_address: String,
+    /// Only a hint for casual and private games; rated ones use `player_id`'s rating.
+    pub elo: u32,
+    pub match_type: MatchType,
+    pub invite_address: Option<String>,
+    pub max_elo_diff: Option<u32>,
+    /// Falls back to the quick-play time control when omitted.
+    pub time_control: Option<TimeControl>,
+    /// Falls back to the quick-play variant when omitted.
+    pub variant: Option<Variant>,
+    /// Trade match quality for speed differently from the deployment's mode.
+    pub mode: Option<MatchmakingMode>,
+    /// Account named by the bearer token, never read from the body; its unfinished
+    /// games count against the concurrent-game cap before it is queued.
+    #[serde(skip_deserializing)]
+    pub player_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickplayRequest {
+    pub wallet_address: String,
+    pub elo: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInviteRequest {
+    pub wallet_address: String,
+    pub elo: u32,
+    pub inviter_request_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelRequest {
+    pub request_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusResponse {
+    pub status: String,
+    pub queue_status: Option<QueueStatus>,
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/matchmaking")
+            .route("/join", web::post().to(join_queue))
+            .route("/quickplay", web::post().to(quickplay))
+            .route("/status/{request_id}", web::get().to(get_status))
+            .route("/cancel", web::post().to(cancel_request))
+            .service(
+                web::resource("/cancel-all")
+                    .wrap(JwtAuthMiddleware::new(jwt_secret()))
+                    .route(web::post().to(cancel_all)),
+            )
+            .route("/accept-invite", web::post().to(accept_invite))
+            .route("/match/{match_id}", web::get().to(get_match)),
+    );
+}
+
+async fn join_queue(
+    http_req: HttpRequest,
+    service: web::Data<MatchmakingService>,
+    req: web::Json<JoinQueueRequest>,
+) -> impl Responder {
+    let defaults = QuickplayDefaults::from_env();
+    let mut req = req.into_inner();
+    if let Some((player_id, guest)) = token_player(&http_req).await {
+        if guest && req.match_type == MatchType::Rated {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "status": "Guests can only play unrated games"
+            }));
+        }
+        req.player_id = Some(player_id);
+    }
+    let time_control = req.time_control.unwrap_or(defaults.time_control);
+    if let Err(response) = check_game_cap(req.player_id, time_control).await {
+        return response;
+    }
+    if let Err(response) = resolve_elo(&mut req).await {
+        return response;
+    }
+
+    let response = enqueue(&service, req, defaults);
+    actix_web::rt::spawn(record_pairings(service.take_pairings()));
+    HttpResponse::Ok().json(response)
+}
+
+/// One-tap "play now": joins the queue exactly like `/join`, with every setting taken
+/// from the deployment's quick-play defaults.
+async fn quickplay(
+    http_req: HttpRequest,
+    service: web::Data<MatchmakingService>,
+    req: web::Json<QuickplayRequest>,
+) -> impl Responder {
+    let defaults = QuickplayDefaults::from_env();
+    let req = req.into_inner();
+    let player = token_player(&http_req).await;
+    let match_type = defaults.match_type_for(matches!(player, Some((_, false))));
+    let player_id = player.map(|(player_id, _)| player_id);
+    if let Err(response) = check_game_cap(player_id, defaults.time_control).await {
+        return response;
+    }
+    let mut join = JoinQueueRequest {
+        wallet_address: req.wallet_address,
+        elo: req.elo,
+        match_type,
+        invite_address: None,
+        max_elo_diff: None,
+        time_control: Some(defaults.time_control),
+        variant: Some(defaults.variant),
+        mode: None,
+        player_id,
+    };
+    if let Err(response) = resolve_elo(&mut join).await {
+        return response;
+    }
+
+    let response = enqueue(&service, join, defaults);
+    actix_web::rt::spawn(record_pairings(service.take_pairings()));
+    HttpResponse::Ok().json(response)
+}
+
+/// Player named by a valid bearer token and whether it is a guest, who is marked as
+/// active. Wallets may queue without a token; they are then matched anonymously, with
+/// no account whose rating or unfinished games apply.
+async fn token_player(req: &HttpRequest) -> Option<(Uuid, bool)> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    let claims = decode_token(token, &jwt_secret()).ok()?;
+    let player_id = Uuid::parse_str(&claims.sub).ok()?;
+    if claims.guest
+        && let Err(err) = touch_guest(player_id).await
+    {
+        log::warn!("Could not record activity of guest {}: {}", player_id, err);
+    }
+    Some((player_id, claims.guest))
+}
+
+/// Replaces the client's elo with the one the request is matched on, refusing claims
+/// outside the configured bounds and rated requests without a player to look up.
+async fn resolve_elo(req: &mut JoinQueueRequest) -> Result<(), HttpResponse> {
+    let stored = match (req.match_type, req.player_id) {
+        (MatchType::Rated, Some(player_id)) => {
+            Some(player_rating(player_id).await.map_err(|err| err.error_response())?)
+        }
+        _ => None,
+    };
+    req.elo = EloBounds::from_env()
+        .matching_elo(req.match_type, req.elo, stored)
+        .map_err(|err| err.error_response())?;
+    Ok(())
+}
+
+/// Refuses to queue a player who already has as many unfinished games as a game of
+/// `time_control` allows.
+async fn check_game_cap(player_id: Option<Uuid>, time_control: TimeControl) -> Result<(), HttpResponse> {
+    let Some(player_id) = player_id else {
+        return Ok(());
+    };
+    let class = time_class(&time_control::TimeControl {
+        initial_time: std::time::Duration::from_secs(time_control.initial_secs as u64),
+        increment: std::time::Duration::from_secs(time_control.increment_secs as u64),
+        delay: std::time::Duration::ZERO,
+    });
+    ensure_can_start_game(player_id, class)
+        .await
+        .map_err(|err| err.error_response())
+}
+
+fn enqueue(
+    service: &MatchmakingService,
+    req: JoinQueueRequest,
+    defaults: QuickplayDefaults,
+) -> MatchmakingResponse {
+    let player = Player {
+        wallet_address: req.wallet_address,
+        elo: req.elo,
+        join_time: Utc::now(),
+    };
+
+    let match_request = MatchRequest {
+        id: Uuid::new_v4(),
+        player,
+        match_type: req.match_type,
+        invite_address: req.invite_address,
+        max_elo_diff: req.max_elo_diff,
+        time_control: req.time_control.unwrap_or(defaults.time_control),
+        variant: req.variant.unwrap_or(defaults.variant),
+        mode: req.mode,
+        player_id: req.player_id,
+    };
+
+    service.join_queue(match_request)
+}
+
+/// Writes the analytics of pairings the matcher has made. Handlers spawn this rather
+/// than awaiting it, so a slow write never delays their response.
+pub(crate) async fn record_pairings(records: Vec<MatchRecord>) {
+    for record in records {
+        match_analytics::record_match(record).await;
+    }
+}
+
+async fn get_status(
+    service: web::Data<MatchmakingService>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let request_id = path.into_inner();
+
+    if let Some(status) = service.get_queue_status(request_id) {
+        HttpResponse::Ok().json(StatusResponse {
+            status: "In queue".to_string(),
+            queue_status: Some(status),
+        })
+    } else {
+        HttpResponse::NotFound().json(StatusResponse {
+            status: "Request not found".to_string(),
+            queue_status: None,
+        })
+    }
+}
+
+async fn cancel_request(
+    service: web::Data<MatchmakingService>,
+    req: web::Json<CancelRequest>,
+) -> impl Responder {
+    let success = service.cancel_request(req.request_id);
+
+    if success {
+        HttpResponse::Ok().json(serde_json::json!({
+            "status": "Request cancelled successfully"
+        }))
+    } else {
+        HttpResponse::NotFound().json(serde_json::json!({
+            "status": "Request not found"
+        }))
+    }
+}
+
+/// Cancels every waiting request queued with the caller's token. Tokens name the
+/// player in `sub`, so only requests queued under that player's account are removed.
+async fn cancel_all(
+    http_req: HttpRequest,
+    service: web::Data<MatchmakingService>,
+) -> impl Responder {
+    let player_id = http_req
+        .extensions()
+        .get::<Claims>()
+        .and_then(|claims| Uuid::parse_str(&claims.sub).ok());
+    let Some(player_id) = player_id else {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": "Token does not name a player"
+        }));
+    };
+
+    let cancelled = service.cancel_all_for_player(player_id);
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "Requests cancelled",
+        "cancelled": cancelled
+    }))
+}
+
+async fn accept_invite(
+    service: web::Data<MatchmakingService>,
+    req: web::Json<AcceptInviteRequest>,
+) -> impl Responder {
+    if let Err(err) = EloBounds::from_env().matching_elo(MatchType::Private, req.elo, None) {
+        return err.error_response();
+    }
+    let player = Player {
+        wallet_address: req.wallet_address.clone(),
+        elo: req.elo,
+        join_time: Utc::now(),
+    };
+
+    match service.accept_private_invite(req.inviter_request_id, player) {
+        Some(response) => {
+            actix_web::rt::spawn(record_pairings(service.take_pairings()));
+            HttpResponse::Ok().json(response)
+        }
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "status": "Invite not found"
+        })),
+    }
+}
+
+async fn get_match(
+    service: web::Data<MatchmakingService>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let match_id = path.into_inner();
+
+    match service.get_match(match_id) {
+        Some(MatchLookup::Active(match_data)) => HttpResponse::Ok().json(match_data),
+        // The match existed and is over: 410 tells clients to move on to the game
+        Some(MatchLookup::Completed(completed)) => HttpResponse::Gone().json(serde_json::json!({
+            "status": "Match completed",
+            "match": completed
+        })),
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "status": "Match not found"
+        })),
+    }
+}
+
+fn jwt_secret() -> String {
+    std::env::var("JWT_SECRET_KEY").unwrap_or_else(|_| "development_secret_key".to_string())
+}
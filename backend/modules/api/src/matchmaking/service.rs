@@ -0,0 +1,766 @@
+use actix_web::web;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use dto::games::Variant;
+use entity::sea_orm_active_enums::{MatchType as RecordedMatchType, ResultSide};
+use error::error::ApiError;
+use service::match_analytics::MatchRecord;
+use validator::{ValidationError, ValidationErrors};
+
+use super::models::*;
+
+const ELO_RANGE_INCREMENT_PER_MINUTE: u32 = 50;
+/// Strict mode holds a request at its own window this long before widening it.
+const STRICT_GRACE_SECS: u64 = 120;
+const STRICT_ELO_RANGE_INCREMENT_PER_MINUTE: u32 = 25;
+const FAST_ELO_RANGE_INCREMENT_PER_MINUTE: u32 = 200;
+const DEFAULT_MAX_ELO_DIFF: u32 = 200;
+const DEFAULT_ESTIMATED_WAIT_TIME: Duration = Duration::from_secs(60);
+/// Quick-play settings used when a deployment does not configure its own.
+const DEFAULT_QUICKPLAY_TIME_CONTROL: TimeControl = TimeControl { initial_secs: 600, increment_secs: 0 };
+const DEFAULT_QUICKPLAY_VARIANT: Variant = Variant::Standard;
+const DEFAULT_QUICKPLAY_MATCH_TYPE: MatchType = MatchType::Rated;
+/// How long a finished match is still reported as completed rather than unknown.
+const COMPLETED_MATCH_RETENTION_HOURS: i64 = 24;
+const DEFAULT_MIN_ELO: u32 = 0;
+const DEFAULT_MAX_ELO: u32 = 4000;
+
+#[derive(Clone)]
+pub struct MatchmakingService {
+    queue: Arc<Mutex<MatchmakingQueue>>,
+    active_matches: Arc<Mutex<HashMap<Uuid, Match>>>,
+    completed_matches: Arc<Mutex<HashMap<Uuid, CompletedMatch>>>,
+    /// Pairings made since the last [`MatchmakingService::take_pairings`], waiting to
+    /// be written to the analytics table.
+    pairings: Arc<Mutex<Vec<MatchRecord>>>,
+    /// Mode applied to requests that do not ask for one.
+    mode: MatchmakingMode,
+}
+
+impl Default for MatchmakingService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchmakingService {
+    pub fn new() -> Self {
+        Self::with_mode(MatchmakingMode::from_env())
+    }
+
+    pub fn with_mode(mode: MatchmakingMode) -> Self {
+        Self {
+            queue: Arc::new(Mutex::new(MatchmakingQueue::new())),
+            active_matches: Arc::new(Mutex::new(HashMap::new())),
+            completed_matches: Arc::new(Mutex::new(HashMap::new())),
+            pairings: Arc::new(Mutex::new(Vec::new())),
+            mode,
+        }
+    }
+
+    pub fn mode(&self) -> MatchmakingMode {
+        self.mode
+    }
+
+    fn mode_of(&self, request: &MatchRequest) -> MatchmakingMode {
+        request.mode.unwrap_or(self.mode)
+    }
+
+    /// Elo window of a rated request at `now`, widened along its mode's curve.
+    fn elo_window_at(&self, request: &MatchRequest, now: DateTime<Utc>) -> u32 {
+        elo_window(
+            self.mode_of(request),
+            request.max_elo_diff.unwrap_or(DEFAULT_MAX_ELO_DIFF),
+            now.signed_duration_since(request.player.join_time),
+        )
+    }
+
+    /// Window two rated requests can be paired under at `now`: the gap between them has
+    /// to fit both players' windows, so a strict player is never handed a wide pairing
+    /// because their opponent asked for fast matching.
+    fn shared_window(&self, a: &MatchRequest, b: &MatchRequest, now: DateTime<Utc>) -> Option<u32> {
+        let window = self.elo_window_at(a, now).min(self.elo_window_at(b, now));
+        (a.same_settings(b) && elo_gap(a, b) <= window).then_some(window)
+    }
+
+    pub fn join_queue(&self, request: MatchRequest) -> MatchmakingResponse {
+        let mut queue = self.queue.lock().unwrap();
+        let request_id = request.id;
+
+        match request.match_type {
+            MatchType::Rated => {
+                if let Some(match_result) = self.find_rated_match(&request, &mut queue) {
+                    return match_result;
+                }
+                queue.rated_queue.push(request);
+            }
+            MatchType::Casual => {
+                if let Some(match_result) = self.find_casual_match(&request, &mut queue) {
+                    return match_result;
+                }
+                queue.casual_queue.push(request);
+            }
+            MatchType::Private => {
+                if let Some(invite_address) = &request.invite_address {
+                    queue.private_invites.insert(invite_address.clone(), request);
+                    return MatchmakingResponse {
+                        status: "Waiting for invited player".to_string(),
+                        match_id: None,
+                        request_id,
+                    };
+                } else {
+                    return MatchmakingResponse {
+                        status: "Invalid private match request: missing invite address".to_string(),
+                        match_id: None,
+                        request_id,
+                    };
+                }
+            }
+        }
+
+        MatchmakingResponse {
+            status: "Added to queue".to_string(),
+            match_id: None,
+            request_id,
+        }
+    }
+
+    pub fn check_private_invite(&self, wallet_address: &str) -> Option<MatchRequest> {
+        let queue = self.queue.lock().unwrap();
+        queue.private_invites.get(wallet_address).cloned()
+    }
+
+    pub fn accept_private_invite(
+        &self,
+        inviter_request_id: Uuid,
+        accepting_player: Player,
+    ) -> Option<MatchmakingResponse> {
+        let mut queue = self.queue.lock().unwrap();
+
+        let invite_entry = queue.private_invites.iter()
+            .find(|(_, req)| req.id == inviter_request_id)
+            .map(|(address, req)| (address.clone(), req.clone()));
+
+        if let Some((invite_address, invite_request)) = invite_entry {
+            queue.private_invites.remove(&invite_address);
+
+            let match_id = Uuid::new_v4();
+            let new_match = Match {
+                id: match_id,
+                player1: invite_request.player,
+                player2: accepting_player,
+                match_type: MatchType::Private,
+                time_control: invite_request.time_control,
+                variant: invite_request.variant,
+                created_at: Utc::now(),
+            };
+            self.record_pairing(&new_match, None);
+
+            let mut active_matches = self.active_matches.lock().unwrap();
+            active_matches.insert(match_id, new_match);
+
+            Some(MatchmakingResponse {
+                status: "Match created".to_string(),
+                match_id: Some(match_id),
+                request_id: inviter_request_id,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn cancel_request(&self, request_id: Uuid) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+
+        if let Some(index) = queue.rated_queue.iter().position(|req| req.id == request_id) {
+            queue.rated_queue.remove(index);
+            return true;
+        }
+
+        if let Some(index) = queue.casual_queue.iter().position(|req| req.id == request_id) {
+            queue.casual_queue.remove(index);
+            return true;
+        }
+
+        let invite_key = queue.private_invites.iter()
+            .find(|(_, req)| req.id == request_id)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = invite_key {
+            queue.private_invites.remove(&key);
+            return true;
+        }
+
+        false
+    }
+
+    /// Removes every waiting request queued by `player_id` from the rated, casual and
+    /// private sub-queues and returns how many were removed.
+    ///
+    /// The whole sweep holds the queue lock, the same lock pairing runs under, so each
+    /// request is either paired before the sweep (and kept out of the count) or removed
+    /// by it, never both.
+    pub fn cancel_all_for_player(&self, player_id: Uuid) -> usize {
+        let mut queue = self.queue.lock().unwrap();
+        let owned = |req: &MatchRequest| req.player_id == Some(player_id);
+        let before = queue.rated_queue.len() + queue.casual_queue.len() + queue.private_invites.len();
+
+        queue.rated_queue.retain(|req| !owned(req));
+        queue.casual_queue.retain(|req| !owned(req));
+        queue.private_invites.retain(|_, req| !owned(req));
+
+        before - (queue.rated_queue.len() + queue.casual_queue.len() + queue.private_invites.len())
+    }
+
+    pub fn get_queue_status(&self, request_id: Uuid) -> Option<QueueStatus> {
+        let queue = self.queue.lock().unwrap();
+
+        if let Some(index) = queue.rated_queue.iter().position(|req| req.id == request_id) {
+            let request = &queue.rated_queue[index];
+            return Some(QueueStatus {
+                request_id,
+                position: index + 1,
+                estimated_wait_time: self.estimate_wait_time(index, &MatchType::Rated),
+                match_type: MatchType::Rated,
+                mode: self.mode_of(request),
+                elo_window: Some(self.elo_window_at(request, Utc::now())),
+            });
+        }
+
+        if let Some(index) = queue.casual_queue.iter().position(|req| req.id == request_id) {
+            return Some(QueueStatus {
+                request_id,
+                position: index + 1,
+                estimated_wait_time: self.estimate_wait_time(index, &MatchType::Casual),
+                match_type: MatchType::Casual,
+                mode: self.mode_of(&queue.casual_queue[index]),
+                elo_window: None,
+            });
+        }
+
+        for (_, req) in queue.private_invites.iter() {
+            if req.id == request_id {
+                return Some(QueueStatus {
+                    request_id,
+                    position: 1,
+                    estimated_wait_time: DEFAULT_ESTIMATED_WAIT_TIME,
+                    match_type: MatchType::Private,
+                    mode: self.mode_of(req),
+                    elo_window: None,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn find_rated_match(
+        &self,
+        request: &MatchRequest,
+        queue: &mut MatchmakingQueue,
+    ) -> Option<MatchmakingResponse> {
+        let now = Utc::now();
+        let opponent = queue
+            .rated_queue
+            .iter()
+            .enumerate()
+            .find_map(|(index, req)| self.shared_window(req, request, now).map(|window| (index, window)));
+
+        if let Some((index, elo_window)) = opponent {
+            let opponent_request = queue.rated_queue.remove(index);
+            let match_id = self.open_rated_match(opponent_request, request.clone(), elo_window);
+
+            Some(MatchmakingResponse {
+                status: "Match found".to_string(),
+                match_id: Some(match_id),
+                request_id: request.id,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn open_rated_match(&self, waiting: MatchRequest, joining: MatchRequest, elo_window: u32) -> Uuid {
+        let match_id = Uuid::new_v4();
+        let new_match = Match {
+            id: match_id,
+            player1: waiting.player,
+            player2: joining.player,
+            match_type: MatchType::Rated,
+            time_control: joining.time_control,
+            variant: joining.variant,
+            created_at: Utc::now(),
+        };
+        self.record_pairing(&new_match, Some(elo_window));
+
+        let mut active_matches = self.active_matches.lock().unwrap();
+        active_matches.insert(match_id, new_match);
+        match_id
+    }
+
+    /// Pairs waiting rated requests whose windows have widened enough to cover each
+    /// other by `now`, oldest first, and returns the ids of the matches created. A
+    /// request is otherwise only compared against the queue when it joins, so the
+    /// server runs this every `MATCHMAKING_PAIR_SECS` to let the mode's expansion
+    /// curve take effect.
+    pub fn pair_waiting(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let mut queue = self.queue.lock().unwrap();
+        let pairings = self.plan_pairings(&queue.rated_queue, now);
+        let mut slots: Vec<Option<MatchRequest>> =
+            std::mem::take(&mut queue.rated_queue).into_iter().map(Some).collect();
+
+        let match_ids = pairings
+            .into_iter()
+            .map(|(first, second, window)| {
+                let waiting = slots[first].take().unwrap();
+                let joining = slots[second].take().unwrap();
+                self.open_rated_match(waiting, joining, window)
+            })
+            .collect();
+        queue.rated_queue = slots.into_iter().flatten().collect();
+        match_ids
+    }
+
+    /// Pairings `pair_waiting` makes from `queue` at `now`, as pairs of indices into it
+    /// (older request first) with the window the pair was matched under.
+    fn plan_pairings(&self, queue: &[MatchRequest], now: DateTime<Utc>) -> Vec<(usize, usize, u32)> {
+        let mut paired = vec![false; queue.len()];
+        let mut pairings = Vec::new();
+        for first in 0..queue.len() {
+            if paired[first] {
+                continue;
+            }
+            let partner = (first + 1..queue.len())
+                .filter(|&second| !paired[second])
+                .find_map(|second| {
+                    self.shared_window(&queue[first], &queue[second], now)
+                        .map(|window| (second, window))
+                });
+            if let Some((second, window)) = partner {
+                paired[first] = true;
+                paired[second] = true;
+                pairings.push((first, second, window));
+            }
+        }
+        pairings
+    }
+
+    fn find_casual_match(
+        &self,
+        request: &MatchRequest,
+        queue: &mut MatchmakingQueue,
+    ) -> Option<MatchmakingResponse> {
+        if let Some(index) = queue.casual_queue.iter().position(|req| req.same_settings(request)) {
+            let opponent_request = queue.casual_queue.remove(index);
+            let match_id = Uuid::new_v4();
+
+            let new_match = Match {
+                id: match_id,
+                player1: opponent_request.player,
+                player2: request.player.clone(),
+                match_type: MatchType::Casual,
+                time_control: request.time_control,
+                variant: request.variant,
+                created_at: Utc::now(),
+            };
+            self.record_pairing(&new_match, None);
+
+            let mut active_matches = self.active_matches.lock().unwrap();
+            active_matches.insert(match_id, new_match);
+
+            Some(MatchmakingResponse {
+                status: "Match found".to_string(),
+                match_id: Some(match_id),
+                request_id: request.id,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn estimate_wait_time(&self, position: usize, match_type: &MatchType) -> Duration {
+        match match_type {
+            MatchType::Rated => Duration::from_secs((30 + position as u64 * 15).min(300)),
+            MatchType::Casual => Duration::from_secs((15 + position as u64 * 10).min(180)),
+            MatchType::Private => DEFAULT_ESTIMATED_WAIT_TIME,
+        }
+    }
+
+    /// Queues a new pairing for the analytics writer. Nothing is written here: the
+    /// caller drains [`MatchmakingService::take_pairings`] once the queue lock is
+    /// released, so matching never waits on the database.
+    fn record_pairing(&self, new_match: &Match, elo_window: Option<u32>) {
+        self.pairings.lock().unwrap().push(pairing_record(new_match, elo_window));
+    }
+
+    /// Pairings made since the last call, for the analytics table.
+    pub fn take_pairings(&self) -> Vec<MatchRecord> {
+        std::mem::take(&mut *self.pairings.lock().unwrap())
+    }
+
+    pub fn get_match(&self, match_id: Uuid) -> Option<MatchLookup> {
+        if let Some(active) = self.active_matches.lock().unwrap().get(&match_id) {
+            return Some(MatchLookup::Active(active.clone()));
+        }
+        let completed_matches = self.completed_matches.lock().unwrap();
+        completed_matches.get(&match_id).cloned().map(MatchLookup::Completed)
+    }
+
+    /// Retires an active match once `game_id`, the game it was played as, has ended.
+    /// Returns `None` if the match is not active.
+    pub fn complete_match(
+        &self,
+        match_id: Uuid,
+        game_id: Uuid,
+        result: Option<ResultSide>,
+    ) -> Option<CompletedMatch> {
+        let finished = self.active_matches.lock().unwrap().remove(&match_id)?;
+        let now = Utc::now();
+        let completed = CompletedMatch {
+            id: finished.id,
+            match_type: finished.match_type,
+            game_id,
+            result,
+            created_at: finished.created_at,
+            completed_at: now,
+        };
+
+        let mut completed_matches = self.completed_matches.lock().unwrap();
+        let cutoff = now - chrono::Duration::hours(COMPLETED_MATCH_RETENTION_HOURS);
+        completed_matches.retain(|_, m| m.completed_at > cutoff);
+        completed_matches.insert(match_id, completed.clone());
+
+        Some(completed)
+    }
+}
+
+/// Sample of a new pairing for the analytics table.
+fn pairing_record(new_match: &Match, elo_window: Option<u32>) -> MatchRecord {
+    let waited_ms = |player: &Player| {
+        new_match
+            .created_at
+            .signed_duration_since(player.join_time)
+            .num_milliseconds()
+    };
+
+    MatchRecord {
+        match_id: new_match.id,
+        match_type: match new_match.match_type {
+            MatchType::Rated => RecordedMatchType::Rated,
+            MatchType::Casual => RecordedMatchType::Casual,
+            MatchType::Private => RecordedMatchType::Private,
+        },
+        rating_gap: (new_match.player1.elo as i32 - new_match.player2.elo as i32).abs(),
+        elo_window: elo_window.map(|window| window as i32),
+        wait_time_ms: waited_ms(&new_match.player1).max(waited_ms(&new_match.player2)).max(0),
+        created_at: new_match.created_at,
+    }
+}
+
+fn elo_gap(a: &MatchRequest, b: &MatchRequest) -> u32 {
+    (a.player.elo as i64 - b.player.elo as i64).unsigned_abs() as u32
+}
+
+/// Widens `base` by how long a request has waited. Strict keeps the window at `base`
+/// through a grace period and widens slowly after it; fast widens four times quicker
+/// than balanced.
+fn elo_window(mode: MatchmakingMode, base: u32, waited: chrono::Duration) -> u32 {
+    let waited_secs = waited.num_seconds().max(0) as u64;
+    let (widening_secs, per_minute) = match mode {
+        MatchmakingMode::Strict => (
+            waited_secs.saturating_sub(STRICT_GRACE_SECS),
+            STRICT_ELO_RANGE_INCREMENT_PER_MINUTE,
+        ),
+        MatchmakingMode::Balanced => (waited_secs, ELO_RANGE_INCREMENT_PER_MINUTE),
+        MatchmakingMode::Fast => (waited_secs, FAST_ELO_RANGE_INCREMENT_PER_MINUTE),
+    };
+    let widening = widening_secs * per_minute as u64 / 60;
+    base.saturating_add(widening.min(u32::MAX as u64) as u32)
+}
+
+/// Settings applied to one-tap quick-play requests, configurable per deployment through
+/// `QUICKPLAY_TIME_CONTROL` (`minutes+increment`), `QUICKPLAY_VARIANT` and
+/// `QUICKPLAY_MATCH_TYPE` (`rated` or `casual`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuickplayDefaults {
+    pub time_control: TimeControl,
+    pub variant: Variant,
+    pub match_type: MatchType,
+}
+
+impl QuickplayDefaults {
+    pub fn from_env() -> Self {
+        let setting = |name: &str| std::env::var(name).ok();
+        Self {
+            time_control: setting("QUICKPLAY_TIME_CONTROL")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_QUICKPLAY_TIME_CONTROL),
+            variant: setting("QUICKPLAY_VARIANT")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| Variant::enabled().contains(v))
+                .unwrap_or(DEFAULT_QUICKPLAY_VARIANT),
+            match_type: match setting("QUICKPLAY_MATCH_TYPE").as_deref() {
+                Some("casual") => MatchType::Casual,
+                Some("rated") => MatchType::Rated,
+                _ => DEFAULT_QUICKPLAY_MATCH_TYPE,
+            },
+        }
+    }
+
+    /// Queue a quick-play request joins. Rated play needs an account's rating, so
+    /// guests and callers without a token play casual whatever the default is.
+    pub fn match_type_for(&self, registered: bool) -> MatchType {
+        if registered { self.match_type } else { MatchType::Casual }
+    }
+}
+
+/// Elo a client may claim when joining, configurable through `MATCHMAKING_MIN_ELO` and
+/// `MATCHMAKING_MAX_ELO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EloBounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for EloBounds {
+    fn default() -> Self {
+        Self { min: DEFAULT_MIN_ELO, max: DEFAULT_MAX_ELO }
+    }
+}
+
+impl EloBounds {
+    /// An inverted range is ignored in favour of the defaults.
+    pub fn from_env() -> Self {
+        let setting = |name: &str, default: u32| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        let bounds = Self {
+            min: setting("MATCHMAKING_MIN_ELO", DEFAULT_MIN_ELO),
+            max: setting("MATCHMAKING_MAX_ELO", DEFAULT_MAX_ELO),
+        };
+        if bounds.min <= bounds.max { bounds } else { Self::default() }
+    }
+
+    /// Elo a request is matched on. The claimed elo must lie within the bounds, but
+    /// only casual and private games use it, as a hint. Rated games are matched on
+    /// `stored`, the player's rating on record, whatever the client claimed.
+    pub fn matching_elo(&self, match_type: MatchType, claimed: u32, stored: Option<i32>) -> Result<u32, ApiError> {
+        if !(self.min..=self.max).contains(&claimed) {
+            let message = format!("Elo must be between {} and {}", self.min, self.max);
+            return Err(invalid("elo", "elo_out_of_range", message));
+        }
+        match match_type {
+            MatchType::Rated => stored
+                .map(|rating| (rating.max(0) as u32).clamp(self.min, self.max))
+                .ok_or_else(|| {
+                    invalid("player_id", "rating_required", "Rated play needs a bearer token naming the player whose rating is used".to_string())
+                }),
+            MatchType::Casual | MatchType::Private => Ok(claimed),
+        }
+    }
+}
+
+fn invalid(field: &'static str, code: &'static str, message: String) -> ApiError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message.into());
+    let mut errors = ValidationErrors::new();
+    errors.add(field, error);
+    ApiError::ValidationError(errors)
+}
+
+pub fn get_matchmaking_service() -> web::Data<MatchmakingService> {
+    web::Data::new(MatchmakingService::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(wallet_address: &str, match_type: MatchType, initial_secs: u32) -> MatchRequest {
+        MatchRequest {
+            id: Uuid::new_v4(),
+            player: Player {
+                wallet_address: wallet_address.to_string(),
+                elo: 1500,
+                join_time: Utc::now(),
+            },
+            invite_address: (match_type == MatchType::Private).then(|| "0xfriend".to_string()),
+            match_type,
+            max_elo_diff: None,
+            // Distinct time controls keep the requests from pairing with each other
+            time_control: TimeControl { initial_secs, increment_secs: 0 },
+            variant: Variant::Standard,
+            mode: None,
+            player_id: None,
+        }
+    }
+
+    fn rated(wallet_address: &str, elo: u32, join_time: DateTime<Utc>) -> MatchRequest {
+        MatchRequest {
+            player: Player { wallet_address: wallet_address.to_string(), elo, join_time },
+            ..request(wallet_address, MatchType::Rated, 600)
+        }
+    }
+
+    #[test]
+    fn cancels_every_request_of_a_player() {
+        let service = MatchmakingService::new();
+        let player_id = Uuid::new_v4();
+        let own = |match_type, initial_secs| MatchRequest {
+            player_id: Some(player_id),
+            ..request("0xabc", match_type, initial_secs)
+        };
+        service.join_queue(own(MatchType::Rated, 180));
+        service.join_queue(own(MatchType::Rated, 300));
+        service.join_queue(own(MatchType::Casual, 600));
+        service.join_queue(own(MatchType::Private, 900));
+        // Same wallet, queued anonymously: not the player's to cancel
+        let anonymous = request("0xabc", MatchType::Casual, 60);
+        service.join_queue(anonymous.clone());
+
+        assert_eq!(service.cancel_all_for_player(player_id), 4);
+        assert_eq!(service.cancel_all_for_player(player_id), 0);
+        assert!(service.get_queue_status(anonymous.id).is_some());
+        assert!(service.check_private_invite("0xfriend").is_none());
+    }
+
+    #[test]
+    fn a_cancel_racing_opponents_never_both_pairs_and_cancels_a_request() {
+        const REQUESTS: u32 = 200;
+        let service = MatchmakingService::new();
+        let player_id = Uuid::new_v4();
+        // Each request has its own time control, which only one opponent asks for
+        for initial_secs in 1..=REQUESTS {
+            service.join_queue(MatchRequest {
+                player_id: Some(player_id),
+                ..request("0xabc", MatchType::Casual, initial_secs * 60)
+            });
+        }
+
+        let (cancelled, paired) = std::thread::scope(|scope| {
+            let opponents = scope.spawn(|| {
+                (1..=REQUESTS)
+                    .filter(|initial_secs| {
+                        let opponent = request("0xdef", MatchType::Casual, initial_secs * 60);
+                        service.join_queue(opponent).match_id.is_some()
+                    })
+                    .count()
+            });
+            let cancel = scope.spawn(|| service.cancel_all_for_player(player_id));
+            (cancel.join().unwrap(), opponents.join().unwrap())
+        });
+
+        assert_eq!(cancelled + paired, REQUESTS as usize);
+        assert_eq!(service.cancel_all_for_player(player_id), 0);
+    }
+
+    #[test]
+    fn strict_and_fast_pair_the_same_queue_differently() {
+        let joined = Utc::now();
+        let queue = [
+            rated("0xa", 1500, joined),
+            rated("0xb", 1900, joined),
+            rated("0xc", 1750, joined),
+        ];
+        let later = joined + chrono::Duration::minutes(3);
+
+        let pairings = |mode| MatchmakingService::with_mode(mode).plan_pairings(&queue, later);
+
+        // After three minutes the 200 window is 225 strict, 350 balanced and 800 fast,
+        // so only fast reaches 0xa's first choice and strict still waits for a close game
+        assert_eq!(pairings(MatchmakingMode::Strict), vec![(1, 2, 225)]);
+        assert_eq!(pairings(MatchmakingMode::Balanced), vec![(0, 2, 350)]);
+        assert_eq!(pairings(MatchmakingMode::Fast), vec![(0, 1, 800)]);
+    }
+
+    #[test]
+    fn a_strict_request_is_not_widened_by_a_fast_opponent() {
+        let joined = Utc::now();
+        let queue = [
+            MatchRequest { mode: Some(MatchmakingMode::Strict), ..rated("0xa", 1500, joined) },
+            rated("0xb", 1750, joined),
+        ];
+        let service = MatchmakingService::with_mode(MatchmakingMode::Fast);
+
+        assert_eq!(service.plan_pairings(&queue, joined + chrono::Duration::minutes(3)), vec![]);
+        assert_eq!(service.plan_pairings(&queue, joined + chrono::Duration::minutes(4)), vec![(0, 1, 250)]);
+    }
+
+    #[test]
+    fn a_claimed_elo_does_not_pair_a_rated_player_with_beginners() {
+        let bounds = EloBounds::default();
+        let service = MatchmakingService::new();
+        let joined = Utc::now();
+        service.join_queue(rated("0xbeginner", 800, joined));
+
+        // An absurd claim is refused before it reaches the queue
+        assert!(bounds.matching_elo(MatchType::Rated, 9999, Some(2400)).is_err());
+
+        // A plausible one is ignored in favour of the stored rating
+        let elo = bounds.matching_elo(MatchType::Rated, 800, Some(2400)).unwrap();
+        let response = service.join_queue(rated("0xstrong", elo, joined));
+
+        assert_eq!(elo, 2400);
+        assert_eq!(response.match_id, None);
+        assert_eq!(bounds.matching_elo(MatchType::Casual, 800, None).unwrap(), 800);
+    }
+
+    #[test]
+    fn status_reports_the_mode_and_current_window() {
+        let service = MatchmakingService::with_mode(MatchmakingMode::Strict);
+        let waiting = request("0xabc", MatchType::Rated, 600);
+        service.join_queue(waiting.clone());
+
+        let status = service.get_queue_status(waiting.id).unwrap();
+
+        assert_eq!((status.mode, status.elo_window), (MatchmakingMode::Strict, Some(DEFAULT_MAX_ELO_DIFF)));
+    }
+
+    #[test]
+    fn pairings_are_kept_for_analytics_without_a_runtime() {
+        let service = MatchmakingService::new();
+        let joined = Utc::now();
+        service.join_queue(MatchRequest { match_type: MatchType::Casual, ..rated("0xa", 1500, joined) });
+        let response = service.join_queue(MatchRequest { match_type: MatchType::Casual, ..rated("0xb", 1620, joined) });
+
+        let pairings = service.take_pairings();
+
+        assert_eq!(pairings.len(), 1);
+        assert_eq!(Some(pairings[0].match_id), response.match_id);
+        assert_eq!((pairings[0].match_type, pairings[0].rating_gap), (RecordedMatchType::Casual, 120));
+        assert!(service.take_pairings().is_empty());
+    }
+
+    #[test]
+    fn only_registered_players_quickplay_rated() {
+        let defaults = QuickplayDefaults {
+            time_control: DEFAULT_QUICKPLAY_TIME_CONTROL,
+            variant: DEFAULT_QUICKPLAY_VARIANT,
+            match_type: MatchType::Rated,
+        };
+
+        assert_eq!(defaults.match_type_for(true), MatchType::Rated);
+        assert_eq!(defaults.match_type_for(false), MatchType::Casual);
+        // Whatever is queued casual is matched on the claimed elo, with no rating to look up
+        assert!(EloBounds::default().matching_elo(defaults.match_type_for(false), 1200, None).is_ok());
+    }
+
+    #[test]
+    fn waiting_requests_pair_once_their_windows_cover_each_other() {
+        let service = MatchmakingService::with_mode(MatchmakingMode::Balanced);
+        let joined = Utc::now();
+        let (first, second) = (rated("0xa", 1500, joined), rated("0xb", 1800, joined));
+        service.join_queue(first.clone());
+        assert_eq!(service.join_queue(second.clone()).match_id, None);
+
+        assert!(service.pair_waiting(joined + chrono::Duration::minutes(1)).is_empty());
+        let paired = service.pair_waiting(joined + chrono::Duration::minutes(3));
+
+        assert_eq!(paired.len(), 1);
+        assert!(service.get_queue_status(first.id).is_none() && service.get_queue_status(second.id).is_none());
+        assert!(matches!(service.get_match(paired[0]), Some(MatchLookup::Active(_))));
+    }
+}
@@ -1,5 +1,5 @@
 use utoipa::OpenApi;
-use crate::{players, games, auth, ai};
+use crate::{players, games, auth, ai, admin, stats, time, tournaments, webhooks};
 use utoipa::openapi::security::{SecurityScheme, HttpAuthScheme, HttpBuilder};
 use utoipa::Modify;
 
@@ -30,42 +30,100 @@ impl Modify for SecurityAddon {
         players::add_player,
         players::find_player_by_id,
         players::update_player,
+        players::change_password,
+        players::get_notification_preferences,
+        players::update_notification_preferences,
         players::delete_player,
+        players::export_player_games,
         
         // Game endpoints
         games::create_game,
+        games::create_seek,
+        games::list_seeks,
+        games::accept_seek,
+        games::game_series,
         games::get_game,
+        games::replay_game,
+        games::rating_preview,
         games::make_move,
         games::list_games,
         games::join_game,
         games::abandon_game,
+        games::resign_game,
+        games::abort_game,
+        games::draw_game,
         
         // Authentication endpoints
         auth::login,
         auth::register,
         auth::refresh_token,
         auth::logout,
+        auth::create_guest_session,
+        auth::convert_guest_account,
         
         // AI suggestion endpoints
         ai::get_ai_suggestion,
         ai::analyze_position,
+
+        // Admin endpoints
+        admin::recompute_ratings,
+        admin::get_recompute_job,
+        admin::resume_recompute_job,
+        admin::get_match_analytics,
+        admin::list_suspicious_games,
+        admin::archive_games,
+
+        // Stats endpoints
+        stats::get_color_advantage,
+
+        // Tournament endpoints
+        tournaments::get_standings,
+
+        // Webhook endpoints
+        webhooks::list_webhook_deliveries,
+
+        // Time endpoints
+        time::get_time,
     ),
     components(
         schemas(
             // Player schemas
             dto::players::NewPlayer,
             dto::players::UpdatePlayer,
+            dto::players::ChangePassword,
             dto::players::DisplayPlayer,
             dto::players::UpdatedPlayer,
+            dto::games::ExportGamesQuery,
+            dto::notifications::NotificationEvent,
+            dto::notifications::NotificationPreferencesDTO,
+            dto::notifications::UpdateNotificationPreferences,
             
             // Game schemas
             dto::games::CreateGameRequest,
             dto::games::GameDisplayDTO,
             dto::games::MakeMoveRequest,
-            dto::games::JoinGameRequest,
             dto::games::GameStatus,
-            dto::games::GameResult,
-            games::ListGamesQuery,
+            dto::games::Side,
+            dto::games::Variant,
+            dto::games::FenStrictness,
+            dto::games::TimeClass,
+            dto::games::ResultFormat,
+            dto::games::ResultFormatQuery,
+            dto::games::ListGamesQuery,
+            dto::games::ReplayQuery,
+            dto::games::ReplayPly,
+            dto::games::ReplayPage,
+            dto::games::OutcomeDeltas,
+            dto::games::RatingPreview,
+            dto::games::SeriesScore,
+            dto::games::SeriesGame,
+            dto::games::GameSeries,
+            dto::games::DrawActionRequest,
+            dto::games::DrawAction,
+            dto::games::PlayerColor,
+            dto::seeks::CreateSeekRequest,
+            dto::seeks::SeekDTO,
+            dto::seeks::SeeksQuery,
             
             // Auth schemas
             dto::auth::LoginRequest,
@@ -74,6 +132,9 @@ impl Modify for SecurityAddon {
             dto::auth::RefreshTokenRequest,
             dto::auth::TokenResponse,
             dto::auth::UserInfo,
+            dto::auth::GuestResponse,
+            dto::auth::GuestInfo,
+            dto::auth::ConvertGuestRequest,
             
             // AI schemas
             dto::ai::AiSuggestionRequest,
@@ -87,8 +148,40 @@ impl Modify for SecurityAddon {
             dto::responses::PlayerFound,
             dto::responses::PlayerUpdated,
             dto::responses::PlayerDeleted,
+            dto::responses::PasswordChanged,
             dto::responses::InvalidCredentialsResponse,
             dto::responses::NotFoundResponse,
+            dto::pagination::Page<dto::games::GameDisplayDTO>,
+            dto::pagination::Page<dto::seeks::SeekDTO>,
+
+            // Admin schemas
+            dto::admin::RecomputeRatingsRequest,
+            dto::admin::RecomputeJobDTO,
+            dto::admin::MatchAnalyticsDTO,
+            dto::admin::AnalyticsBucketDTO,
+            dto::admin::MatchTypeAnalyticsDTO,
+            dto::admin::SuspiciousGamesQuery,
+            dto::admin::SuspiciousGameDTO,
+            dto::pagination::Page<dto::admin::SuspiciousGameDTO>,
+            dto::admin::ArchiveGamesRequest,
+            dto::admin::ArchiveRunDTO,
+
+            // Stats schemas
+            dto::stats::ColorAdvantageQuery,
+            dto::stats::ColorAdvantageDTO,
+
+            // Tournament schemas
+            dto::tournaments::TieBreak,
+            dto::tournaments::StandingDTO,
+            dto::tournaments::TournamentStandingsDTO,
+
+            // Webhook schemas
+            dto::webhooks::WebhookDeliveriesQuery,
+            dto::webhooks::WebhookDeliveryDTO,
+            dto::pagination::Page<dto::webhooks::WebhookDeliveryDTO>,
+
+            // Time schemas
+            dto::time::ServerTime,
         )
     ),
     modifiers(&SecurityAddon),
@@ -97,6 +190,11 @@ impl Modify for SecurityAddon {
         (name = "Games", description = "Game management operations"),
         (name = "Authentication", description = "Authentication operations"),
         (name = "AI", description = "AI suggestion operations"),
+        (name = "Admin", description = "Operator-only maintenance operations"),
+        (name = "Stats", description = "Platform-wide game statistics"),
+        (name = "Tournaments", description = "Tournament standings"),
+        (name = "Webhooks", description = "Webhook delivery history"),
+        (name = "Time", description = "Server clock synchronization"),
         (name = "WebSocket", description = "WebSocket communication protocol")
     ),
     info(
@@ -125,13 +223,44 @@ pub fn websocket_documentation() -> String {
 
 Connect to the WebSocket server:
 ```
-ws://hostname:port/ws/game/{game_id}?token={jwt_token}
+ws://hostname:port/ws/{game_id}
 ```
 
 ### Authentication
-JWT authentication is mandatory for all WebSocket connections. Provide your JWT token (obtained via login or token refresh) as a query parameter in the connection URL.
+JWT authentication is mandatory for all WebSocket connections. Use one of the following, in order of preference:
+
+1. **Subprotocol (preferred):** offer the `starkmate.v1` subprotocol together with your token as `bearer.{jwt_token}`:
+   ```
+   Sec-WebSocket-Protocol: starkmate.v1, bearer.{jwt_token}
+   ```
+   The server selects `starkmate.v1`; the token never appears in URLs or access logs.
+2. **`Authorization: Bearer {jwt_token}` header**, for clients that can set handshake headers.
+3. **Auth message:** connect without a token and send, within 10 seconds (`WS_AUTH_TIMEOUT_SECS`):
+   ```json
+   { "type": "auth", "payload": { "token": "{jwt_token}" } }
+   ```
+   No game events are delivered until the socket is authenticated.
+4. **Query parameter (deprecated):** `ws://hostname:port/ws/{game_id}?token={jwt_token}`. Still accepted, but the token leaks into logs; it will be removed in a future version.
+
+Tokens are validated exactly like the HTTP API's. A token supplied in the handshake that fails validation rejects the upgrade with `401`. A socket that sends an invalid token, sends anything else before authenticating, or does not authenticate within the grace period receives an `error` message with code `401` and is closed with close code `1008` (policy violation).
+
+### Message Limits
+Every frame a client sends counts against two limits, whatever it carries:
+
+- A text or binary message larger than 16 KiB (`WS_MAX_MESSAGE_BYTES`) closes the socket with close code `1009` (message too big).
+- More than 20 frames within one second (`WS_MAX_MESSAGES_PER_SEC`), pings included, closes it with close code `1008`.
+
+No `error` message precedes these closes; reconnect and authenticate again to continue.
 
-If authentication fails or the token is missing, the connection will be immediately closed with an authentication_error message.
+### Multiple Connections
+A player may have one socket per game that moves for them. When they connect again, from another tab for instance, what happens depends on `WS_DUPLICATE_CONNECTIONS`:
+
+- `latest` (default): the new socket takes over. The old one receives
+  ```json
+  { "type": "connection_superseded" }
+  ```
+  and keeps receiving the room's events, but its moves get a `move_reject` and its other actions an `error`, both with code `409`.
+- `reject`: the new socket receives `{ "type": "duplicate_connection" }` and is closed with close code `1008`; the first one is unaffected.
 
 ## Event Types
 
@@ -159,18 +288,39 @@ If authentication fails or the token is missing, the connection will be immediat
 ```
 
 ### Move Made
+The player on move sends the move in UCI or SAN notation:
+```json
+{ "type": "move", "payload": { "chess_move": "e2e4" } }
+```
+The server checks it against its own replay of the game and broadcasts it to the room:
 ```json
 {
   "type": "move",
-  "data": {
-    "player_id": "uuid",
-    "game_id": "uuid",
-    "move": "e2e4", 
-    "fen": "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
-    "time_remaining": 298 
+  "payload": {
+    "from": "e2",
+    "to": "e4",
+    "san": "e4",
+    "fen": "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+    "in_check": false,
+    "checked_king": null
   }
 }
 ```
+`in_check` tells whether the move left the side to move in check, and `checked_king` is the square of that king (`"e8"`), so clients can highlight it without a move generator of their own. The game returned by the REST API carries the same two fields.
+A move that ends the game by checkmate, stalemate or insufficient material is followed by a `state_update`. `PUT /v1/games/{id}/move` plays moves through the same path, so a move made over HTTP reaches socket clients as well.
+
+Clients may apply a move optimistically and add a `move_id` of their choosing to match the server's answer to it:
+```json
+{ "type": "move", "payload": { "chess_move": "e2e4", "move_id": "m-17" } }
+```
+If the move is legal in the last position broadcast to the room, the mover alone receives an immediate acknowledgement, always before the room's `move` broadcast:
+```json
+{ "type": "move_ack", "payload": { "move_id": "m-17", "chess_move": "e2e4" } }
+```
+The move counts only once the `move` broadcast arrives. If the server refuses it, whether acknowledged or not, the mover alone receives a `move_reject` and should roll back to the last broadcast position; other clients never see the move. `code` is `400` for illegal moves and `409` for moves out of turn or on a finished game. A move out of turn also carries `reason: "not_your_turn"`, the same `code` and `reason` that `PUT /v1/games/{id}/move` answers with, since both are judged by the side to move in the game's current FEN:
+```json
+{ "type": "move_reject", "payload": { "move_id": "m-17", "chess_move": "e2e4", "code": 409, "message": "It is not your turn", "reason": "not_your_turn" } }
+```
 
 ### Game State Update
 ```json
@@ -186,6 +336,46 @@ If authentication fails or the token is missing, the connection will be immediat
 }
 ```
 
+### Draw Offers
+Once both players have moved, either player may offer a draw:
+```json
+{ "type": "draw_offer" }
+```
+The room receives `{ "type": "draw_offered", "payload": { "by": "uuid" } }`. The opponent answers with `{ "type": "draw_accept" }` or `{ "type": "draw_decline" }`; a decline is broadcast as `draw_declined`. An accepted draw ends the game with `result = draw` and `termination = agreement`, rates it as a draw for both players, and is broadcast as:
+```json
+{
+  "type": "state_update",
+  "payload": {
+    "game_id": "uuid",
+    "status": "completed",
+    "result": "draw",
+    "termination": "agreement",
+    "fen": "string",
+    "material_balance": 0,
+    "captured_by_white": ["pawn"],
+    "captured_by_black": ["pawn"]
+  }
+}
+```
+`material_balance` is white's material minus black's in pawns (queen 9, rook 5, bishop and knight 3). `captured_by_white` and `captured_by_black` list the pieces each side has taken; in crazyhouse these are the pockets, a captured promoted piece counts as a pawn, and pocketed pieces add to the balance.
+Offers made before both sides have moved, on a finished game, or accepts without a pending offer receive an `error` with code `409`. So do offers that come too often: after offering, a player must make 5 moves (`DRAW_OFFER_COOLDOWN_MOVES`) before offering again, and may offer at most 3 draws per game (`DRAW_OFFER_MAX_PER_GAME`).
+
+### Resign and Abort
+```json
+{ "type": "resign" }
+{ "type": "abort" }
+```
+Resigning awards the game to the opponent (`termination = resignation`). Aborting is only possible before both players have moved; the game ends without a result (`status = aborted`) and is not rated. Both are broadcast to the room as a `state_update`.
+
+The REST endpoints `POST /v1/games/{id}/resign`, `POST /v1/games/{id}/abort` and `POST /v1/games/{id}/draw` perform the same transitions and broadcast the same events, so players and spectators stay in sync whichever transport the acting player uses.
+
+### Draw Claims
+A player may claim a draw on their own turn when the current position has occurred three times, or when 50 moves (100 plies) have passed without a capture or pawn move:
+```json
+{ "type": "claim_draw", "payload": { "reason": "repetition" } }
+```
+`reason` is `repetition` or `fifty_move`. The server replays the game's recorded moves to verify the claim; it never trusts the client's account of the position. A valid claim ends the game as a draw with `termination = repetition` or `fifty_move` and is broadcast as a `state_update`. An invalid claim receives an `error` with code `409` explaining why.
+
 ### Chat Message
 ```json
 {
@@ -199,6 +389,39 @@ If authentication fails or the token is missing, the connection will be immediat
 }
 ```
 
+### Time Sync
+Sent by the server right after the socket is authenticated and then every 30 seconds (`WS_TIME_SYNC_INTERVAL_SECS`). Clients may also request one at any time:
+```json
+{ "type": "time_sync", "payload": { "client_time_ms": 1792056600000 } }
+```
+The server replies immediately, echoing `client_time_ms` (it is `null` on unsolicited pushes):
+```json
+{
+  "type": "time_sync",
+  "payload": {
+    "server_time": "2026-10-15T09:30:00.123Z",
+    "epoch_ms": 1792056600123,
+    "monotonic_ms": 86400000,
+    "client_time_ms": 1792056600000
+  }
+}
+```
+The fields match `GET /time`. To estimate the clock offset, take `rtt = now - client_time_ms` when the reply arrives and `offset = epoch_ms + rtt / 2 - now`; render clocks with `now + offset`. `monotonic_ms` only has meaning as a difference between two readings and resets when the server restarts.
+
+### Clocks
+Timed games keep both clocks on the server. When a player connects to a game, the room is sent where they stand, in seconds:
+```json
+{ "type": "Clock", "payload": { "white": 292, "black": 300 } }
+```
+In real-time games the clock stops while neither player is connected, and when the server shuts down. It restarts from the same times when a player comes back, so dropped connections and downtime cost nobody time. Correspondence clocks never stop: the time since the last move counts whether anyone is connected or not. `white_time_remaining` and `black_time_remaining` in the REST API read the same clocks.
+
+### Low Time
+The room is told once when a player's clock, as the server counts it, drops to the low-time threshold of the game's time class, right after the `clock` message that crossed it:
+```json
+{ "type": "low_time", "payload": { "color": "white", "remaining": 14 } }
+```
+`remaining` is in seconds. A clock that climbs back above the threshold through increment warns again the next time it drops below. Correspondence games never send it.
+
 ## Error Messages
 ```json
 {
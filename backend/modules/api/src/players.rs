@@ -1,30 +1,61 @@
 use actix_web::{
-    HttpResponse, delete, get, post, put,
-    web::{Json, Path},
+    HttpRequest, HttpResponse, delete,
+    error::ErrorInternalServerError,
+    get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    middleware::Compress,
+    post, put,
+    web::{Bytes, Json, Path, Query},
 };
+use db::db::db::get_db;
 use dto::{
-    players::{DisplayPlayer, NewPlayer, UpdatePlayer, UpdatedPlayer},
+    games::ExportGamesQuery,
+    notifications::{NotificationPreferencesDTO, UpdateNotificationPreferences},
+    players::{ChangePassword, DisplayPlayer, NewPlayer, UpdatePlayer, UpdatedPlayer},
     responses::{
-        InvalidCredentialsResponse, NotFoundResponse, PlayerAdded, PlayerDeleted, PlayerFound,
-        PlayerUpdated,
+        InvalidCredentialsResponse, NotFoundResponse, PasswordChanged, PlayerAdded, PlayerDeleted,
+        PlayerFound, PlayerUpdated,
     },
 };
 use error::error::ApiError;
+use futures_util::stream;
+use security::JwtAuthMiddleware;
 use serde_json::json;
+use std::sync::Arc;
 use validator::Validate;
 
+use service::notifications::{preferences, update_preferences};
+use service::game_export::{EXPORT_BATCH_SIZE, ExportCursor, ensure_exportable, export_batch_with};
+use service::replay::max_plies;
 use service::players::{
-    add_player as add_new_player, delete_player as delete_player_by_id,
+    add_player as add_new_player, change_password as change_player_password,
+    delete_player as delete_player_by_id,
     find_player_by_id as get_single_player_by_id, update_player as update_player_by_id,
 };
 use uuid::Uuid;
 
+use crate::games::authenticated_player;
+use crate::ws::jwt_secret;
+
+/// Lets players read and change only their own settings.
+#[allow(clippy::result_large_err)]
+pub(crate) fn require_owner(req: &HttpRequest, player_id: Uuid) -> Result<(), HttpResponse> {
+    if authenticated_player(req)? != player_id {
+        return Err(HttpResponse::Forbidden().json(json!({
+            "error": "Players can only manage their own settings",
+            "code": 403
+        })));
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     post,
     path = "/v1/players",
     responses(
         (status = 200, description = "New player added", body=PlayerAdded),
-        (status = 400, description = "Bad request", body=InvalidCredentialsResponse)
+        (status = 400, description = "Bad request", body=InvalidCredentialsResponse),
+        (status = 409, description = "Username or email already taken", body = InvalidCredentialsResponse)
     )
 )]
 #[post("")]
@@ -79,7 +110,8 @@ pub async fn find_player_by_id(id: Path<Uuid>) -> HttpResponse {
     ),
     responses(
         (status = 200, description = "Player updated", body=PlayerUpdated),
-        (status = 404, description = "Not found", body=NotFoundResponse)
+        (status = 404, description = "Not found", body=NotFoundResponse),
+        (status = 409, description = "Username already taken", body = InvalidCredentialsResponse)
     )
 )]
 #[put("/{id}")]
@@ -102,6 +134,124 @@ pub async fn update_player(id: Path<Uuid>, payload: Json<UpdatePlayer>) -> HttpR
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/v1/players/{id}/password",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format="uuid")
+    ),
+    request_body = ChangePassword,
+    responses(
+        (status = 200, description = "Password changed", body=PasswordChanged),
+        (status = 400, description = "Current password is wrong or the new one is invalid", body=InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body=InvalidCredentialsResponse),
+        (status = 403, description = "Not the player's own password", body=InvalidCredentialsResponse),
+        (status = 404, description = "Not found", body=NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    )
+)]
+#[put("/{id}/password", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn change_password(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    payload: Json<ChangePassword>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    if let Err(response) = require_owner(&req, id) {
+        return response;
+    }
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match change_player_password(id, payload.0).await {
+        Ok(_) => HttpResponse::Ok().json(json!({
+            "message":"Password changed",
+            "data":{}
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/players/{id}/notifications",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format="uuid")
+    ),
+    responses(
+        (status = 200, description = "Notification channel per event; defaults if never set", body=NotificationPreferencesDTO),
+        (status = 401, description = "Unauthorized", body=InvalidCredentialsResponse),
+        (status = 403, description = "Not the player's own preferences", body=InvalidCredentialsResponse),
+        (status = 404, description = "Not found", body=NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    )
+)]
+#[get("/{id}/notifications", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn get_notification_preferences(req: HttpRequest, id: Path<Uuid>) -> HttpResponse {
+    let id = id.into_inner();
+    if let Err(response) = require_owner(&req, id) {
+        return response;
+    }
+
+    match preferences(id).await {
+        Ok(prefs) => HttpResponse::Ok().json(json!({
+            "message":"Notification preferences found",
+            "data":{
+                "preferences": prefs
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/players/{id}/notifications",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format="uuid")
+    ),
+    request_body = UpdateNotificationPreferences,
+    responses(
+        (status = 200, description = "Notification preferences updated", body=NotificationPreferencesDTO),
+        (status = 400, description = "Unknown event, invalid URL or webhook channel without a URL", body=InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body=InvalidCredentialsResponse),
+        (status = 403, description = "Not the player's own preferences", body=InvalidCredentialsResponse),
+        (status = 404, description = "Not found", body=NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    )
+)]
+#[put("/{id}/notifications", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn update_notification_preferences(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    payload: Json<UpdateNotificationPreferences>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    if let Err(response) = require_owner(&req, id) {
+        return response;
+    }
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match update_preferences(id, payload.0).await {
+        Ok(prefs) => HttpResponse::Ok().json(json!({
+            "message":"Notification preferences updated",
+            "data":{
+                "preferences": prefs
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
 #[utoipa::path(
     delete,
     path = "/v1/players/{id}",
@@ -123,3 +273,58 @@ pub async fn delete_player(id: Path<Uuid>) -> HttpResponse {
         Err(err) => err.error_response(),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/v1/players/{id}/games/export.pgn",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format = "uuid"),
+        ("from" = Option<String>, Query, description = "Only games started at or after this RFC 3339 instant", format = "date-time"),
+        ("to" = Option<String>, Query, description = "Only games started before this RFC 3339 instant", format = "date-time"),
+        ("variant" = Option<String>, Query, description = "Only games of this variant, e.g. `standard`")
+    ),
+    responses(
+        (status = 200, description = "Finished games as one multi-game PGN file, oldest first. Gzip encoded when the client accepts it", content_type = "application/x-chess-pgn", body = String),
+        (status = 400, description = "Invalid filter", body = InvalidCredentialsResponse),
+        (status = 404, description = "Player not found", body = NotFoundResponse)
+    )
+)]
+#[get("/{id}/games/export.pgn", wrap = "Compress::default()")]
+pub async fn export_player_games(id: Path<Uuid>, query: Query<ExportGamesQuery>) -> HttpResponse {
+    let player_id = id.into_inner();
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    // Shared with the body stream; `DatabaseConnection` is only `Clone` without the mock backend
+    let db = Arc::new(get_db().await);
+    if let Err(err) = ensure_exportable(db.as_ref(), player_id).await {
+        return err.error_response();
+    }
+
+    // Games are read one keyset batch at a time and written out as they arrive, so
+    // the whole history is never held in memory. `None` marks the export as done.
+    let start: Option<Option<ExportCursor>> = Some(None);
+    let max_plies = max_plies();
+    let body = stream::unfold(start, move |state| {
+        let db = Arc::clone(&db);
+        let query = query.clone();
+        async move {
+            let after = state?;
+            match export_batch_with(db.as_ref(), player_id, &query, after, EXPORT_BATCH_SIZE, max_plies).await {
+                Ok((text, next)) => Some((Ok(Bytes::from(text)), next.map(Some))),
+                // Headers are already sent, so the only option left is to abort the body
+                Err(err) => Some((Err(ErrorInternalServerError(err.to_string())), None)),
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-chess-pgn")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("starkmate_{}.pgn", player_id))],
+        })
+        .streaming(body)
+}
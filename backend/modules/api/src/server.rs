@@ -7,12 +7,22 @@ use error::error::custom_json_error;
 use utoipa_swagger_ui::SwaggerUi;
 use utoipa_redoc::Redoc;
 use std::env;
+use std::time::Duration;
 use security::JwtAuthMiddleware;
-use crate::players::{add_player, delete_player, find_player_by_id, update_player};
-use crate::games::{create_game, get_game, make_move, list_games, join_game, abandon_game};
-use crate::auth::{login, register, refresh_token, logout};
+use crate::players::{
+    add_player, change_password, delete_player, export_player_games, find_player_by_id,
+    get_notification_preferences, update_notification_preferences, update_player,
+};
+use crate::games::{create_game, create_seek, list_seeks, accept_seek, get_game, game_series, replay_game, rating_preview, make_move, list_games, join_game, abandon_game, resign_game, abort_game, draw_game};
+use crate::auth::{login, register, refresh_token, logout, create_guest_session, convert_guest_account};
 use crate::ai::{get_ai_suggestion, analyze_position};
+use crate::admin::{recompute_ratings, get_recompute_job, resume_recompute_job, get_match_analytics, list_suspicious_games, archive_games};
+use crate::stats::get_color_advantage;
+use crate::time::get_time;
+use crate::tournaments::get_standings;
+use crate::webhooks::list_webhook_deliveries;
 use crate::ws::{LobbyState, ws_route};
+use crate::matchmaking::{self, get_matchmaking_service, record_pairings};
 
 mod openapi;
 use openapi::ApiDoc;
@@ -49,10 +59,120 @@ pub async fn main() -> std::io::Result<()> {
     println!("Example: ALLOWED_ORIGINS=http://localhost:3000,https://starkmate.com");
     println!("If not set, all origins will be allowed (development mode only)");
 
+    // Engine settings beyond this machine are clamped on every search; say so once
+    for warning in service::engine::resource_warnings(service::engine::HostResources::current()) {
+        log::warn!("{}", warning);
+    }
+
     // Create a shared LobbyState actor
     let lobby = LobbyState::new().start();
 
-    HttpServer::new(move || {
+    // Matchmaking queues live in memory, shared by every worker
+    let matchmaking_service = get_matchmaking_service();
+
+    // Pair waiting rated requests as their elo windows widen
+    let pairing_sweep = Duration::from_secs(
+        env::var("MATCHMAKING_PAIR_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5),
+    );
+    let matcher = matchmaking_service.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(pairing_sweep);
+        loop {
+            interval.tick().await;
+            matcher.pair_waiting(chrono::Utc::now());
+            record_pairings(matcher.take_pairings()).await;
+        }
+    });
+
+    // Post queued webhook notifications in the background
+    let webhook_poll = Duration::from_secs(
+        env::var("WEBHOOK_POLL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5),
+    );
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(webhook_poll);
+        loop {
+            interval.tick().await;
+            service::webhooks::deliver_pending().await;
+        }
+    });
+
+    // Collect guests that have stopped playing
+    let guest_sweep = Duration::from_secs(
+        env::var("GUEST_SWEEP_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600),
+    );
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(guest_sweep);
+        loop {
+            interval.tick().await;
+            if let Err(err) = service::guests::collect_inactive_guests().await {
+                log::error!("Guest cleanup failed: {}", err);
+            }
+        }
+    });
+
+    // Forfeit tournament games whose players never showed up
+    let no_show_sweep = Duration::from_secs(
+        env::var("TOURNAMENT_SWEEP_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60),
+    );
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(no_show_sweep);
+        loop {
+            interval.tick().await;
+            if let Err(err) = service::tournaments::forfeit_no_shows().await {
+                log::error!("Tournament no-show sweep failed: {}", err);
+            }
+        }
+    });
+
+    // End games whose side to move has run out of time without moving
+    let clock_sweep = Duration::from_secs(
+        env::var("CLOCK_SWEEP_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1),
+    );
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(clock_sweep);
+        loop {
+            interval.tick().await;
+            if let Err(err) = service::clocks::flag_expired_clocks().await {
+                log::error!("Clock sweep failed: {}", err);
+            }
+        }
+    });
+
+    // Move old finished games out of the hot game table
+    if service::archive::ArchivePolicy::from_env().enabled {
+        let archive_sweep = Duration::from_secs(
+            env::var("GAME_ARCHIVE_SWEEP_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(86400),
+        );
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(archive_sweep);
+            loop {
+                interval.tick().await;
+                if let Err(err) = service::archive::archive_finished_games(None).await {
+                    log::error!("Game archival failed: {}", err);
+                }
+            }
+        });
+    }
+
+    let served = HttpServer::new(move || {
         // Configure CORS middleware with environment variables for flexibility
         let cors = {
             let mut cors = Cors::default()
@@ -90,24 +210,39 @@ pub async fn main() -> std::io::Result<()> {
             .route("/ws/{game_id}", web::get().to(ws_route))
             // Register your routes
             .route("/health", web::get().to(health))
+            .service(get_time)
             .route("/", web::get().to(greet))
             // Player routes
             .service(
                 web::scope("/v1/players")
                     .service(add_player)
+                    .service(export_player_games)
                     .service(find_player_by_id)
                     .service(update_player)
+                    .service(change_password)
+                    .service(get_notification_preferences)
+                    .service(update_notification_preferences)
                     .service(delete_player),
             )
             // Game routes
             .service(
                 web::scope("/v1/games")
                     .service(create_game)
+                    // Registered before `/{id}` so that `/seeks` is not read as a game id
+                    .service(create_seek)
+                    .service(list_seeks)
+                    .service(accept_seek)
+                    .service(game_series)
                     .service(get_game)
+                    .service(replay_game)
+                    .service(rating_preview)
                     .service(list_games)
                     .service(join_game)
-                    .route("/{id}/move", web::put().to(make_move))
-                    .service(abandon_game),
+                    .service(make_move)
+                    .service(abandon_game)
+                    .service(resign_game)
+                    .service(abort_game)
+                    .service(draw_game),
             )
             // Auth routes
             .service(
@@ -115,6 +250,8 @@ pub async fn main() -> std::io::Result<()> {
                     .service(login)
                     .service(register)
                     .service(refresh_token)
+                    .service(create_guest_session)
+                    .service(convert_guest_account)
                     // Protected route with JWT authentication
                     .service(
                         web::scope("/protected")
@@ -128,6 +265,27 @@ pub async fn main() -> std::io::Result<()> {
                     .service(get_ai_suggestion)
                     .service(analyze_position),
             )
+            // Admin routes
+            .service(
+                web::scope("/v1/admin")
+                    .wrap(JwtAuthMiddleware::new(jwt_secret.clone()))
+                    .service(recompute_ratings)
+                    .service(get_recompute_job)
+                    .service(resume_recompute_job)
+                    .service(list_suspicious_games)
+                    .service(archive_games),
+            )
+            .service(web::scope("/v1/stats").service(get_color_advantage))
+            .service(web::scope("/v1/tournaments").service(get_standings))
+            .service(web::scope("/v1/webhooks").service(list_webhook_deliveries))
+            .service(
+                web::scope("/v1/matchmaking/admin")
+                    .wrap(JwtAuthMiddleware::new(jwt_secret.clone()))
+                    .service(get_match_analytics),
+            )
+            // Registered after the admin scope, which it would otherwise shadow
+            .app_data(matchmaking_service.clone())
+            .configure(matchmaking::config)
             // Swagger UI integration
             .service(
                 SwaggerUi::new("/api/docs/{_:.*}")
@@ -148,5 +306,11 @@ pub async fn main() -> std::io::Result<()> {
     })
     .bind(&server_addr)?
     .run()
-    .await
+    .await;
+
+    // Nobody can reach a game until the server is back, so stop its real-time clocks
+    if let Err(err) = service::clocks::pause_all_clocks().await {
+        log::error!("Could not pause game clocks: {}", err);
+    }
+    served
 }
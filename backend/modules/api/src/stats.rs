@@ -0,0 +1,40 @@
+use actix_web::{HttpResponse, get, web::Query};
+use dto::{
+    responses::InvalidCredentialsResponse,
+    stats::{ColorAdvantageDTO, ColorAdvantageQuery},
+};
+use error::error::ApiError;
+use serde_json::json;
+use service::stats::color_advantage;
+use validator::Validate;
+
+#[utoipa::path(
+    get,
+    path = "/v1/stats/color-advantage",
+    params(
+        ("variant" = Option<String>, Query, description = "Variant to report on, e.g. `kingofthehill` (default `standard`)"),
+        ("min_games" = Option<i64>, Query, description = "Finished games required before percentages are reported (default 100)")
+    ),
+    responses(
+        (status = 200, description = "White, black and draw percentages over every finished game of the variant", body = ColorAdvantageDTO),
+        (status = 400, description = "Invalid query", body = InvalidCredentialsResponse)
+    ),
+    tag = "Stats"
+)]
+#[get("/color-advantage")]
+pub async fn get_color_advantage(query: Query<ColorAdvantageQuery>) -> HttpResponse {
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match color_advantage(query).await {
+        Ok(stats) => HttpResponse::Ok().json(json!({
+            "message": "Color advantage computed",
+            "data": {
+                "stats": stats
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
@@ -146,4 +146,184 @@ mod tests {
         );
         assert_eq!(status, StatusCode::BAD_REQUEST);
     }
+
+    #[actix_web::test]
+    async fn test_get_time_is_public_and_uncached() {
+        let app = test::init_service(App::new().service(crate::time::get_time)).await;
+        let req = test::TestRequest::get().uri("/time").to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("cache-control").unwrap(), "no-store");
+
+        let body = test::read_body(res).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let data = &response["data"];
+        assert!(data["epoch_ms"].as_i64().unwrap() > 0);
+        assert!(data["monotonic_ms"].is_u64());
+        assert!(data["server_time"].is_string());
+    }
+
+    fn bearer() -> (&'static str, String) {
+        let now = chrono::Utc::now().timestamp() as usize;
+        let claims = security::Claims { sub: uuid::Uuid::new_v4().to_string(), iat: now, exp: now + 600, guest: false };
+        let token = security::encode_token(&claims, &crate::ws::jwt_secret()).unwrap();
+        ("Authorization", format!("Bearer {}", token))
+    }
+
+    #[actix_web::test]
+    async fn test_create_game_requires_a_token() {
+        let app =
+            test::init_service(App::new().service(web::scope("/v1/games").service(crate::games::create_game)))
+                .await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/games")
+            .set_json(serde_json::json!({"time_control": 300, "increment": 2}))
+            .to_request();
+        let status = match app.call(req).await {
+            Ok(res) => res.status(),
+            Err(err) => err.as_response_error().status_code(),
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_create_game_variant_is_typed() {
+        let app =
+            test::init_service(App::new().service(web::scope("/v1/games").service(crate::games::create_game)))
+                .await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/games")
+            .insert_header(bearer())
+            .set_json(serde_json::json!({"time_control": 300, "increment": 2, "variant": "chess960"}))
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body = test::read_body(res).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["data"]["game"]["variant"], "chess960");
+
+        let req = test::TestRequest::post()
+            .uri("/v1/games")
+            .insert_header(bearer())
+            .set_json(serde_json::json!({"time_control": 300, "increment": 2, "variant": "atomic"}))
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_json_gets_structured_error() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().error_handler(error::error::custom_json_error))
+                .service(web::scope("/v1/games").service(crate::games::create_game)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/games")
+            .insert_header(bearer())
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{\"time_control\": 300,")
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(res).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["code"], 400);
+        assert!(response["error"].as_str().unwrap().starts_with("Malformed JSON body at line 1"));
+
+        let req = test::TestRequest::post()
+            .uri("/v1/games")
+            .insert_header(bearer())
+            .set_json(serde_json::json!({"time_control": "five minutes", "increment": 2}))
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(res).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(response["error"].as_str().unwrap().starts_with("Invalid request body"));
+    }
+
+    #[actix_web::test]
+    async fn test_recompute_ratings_requires_admin() {
+        use actix_web::HttpMessage;
+
+        let app = test::init_service(
+            App::new().service(web::scope("/v1/admin").service(crate::admin::recompute_ratings)),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/v1/admin/ratings/recompute").to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post().uri("/v1/admin/ratings/recompute").to_request();
+        req.extensions_mut().insert(security::Claims {
+            sub: "not-an-admin".to_string(),
+            exp: usize::MAX,
+            iat: 0,
+            guest: false,
+        });
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_match_analytics_requires_admin() {
+        use actix_web::HttpMessage;
+
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/v1/matchmaking/admin").service(crate::admin::get_match_analytics),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/matchmaking/admin/analytics?hours=24")
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/matchmaking/admin/analytics?hours=24")
+            .to_request();
+        req.extensions_mut().insert(security::Claims {
+            sub: "not-an-admin".to_string(),
+            exp: usize::MAX,
+            iat: 0,
+            guest: false,
+        });
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_change_password_is_for_the_owner_only() {
+        let app = test::init_service(
+            App::new().service(web::scope("/v1/players").service(crate::players::change_password)),
+        )
+        .await;
+        let uri = format!("/v1/players/{}/password", uuid::Uuid::new_v4());
+        let body = serde_json::json!({"current_password": "old password", "new_password": "new password"});
+
+        let req = test::TestRequest::put().uri(&uri).set_json(&body).to_request();
+        let status = match app.call(req).await {
+            Ok(res) => res.status(),
+            Err(err) => err.as_response_error().status_code(),
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // The token belongs to someone else
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(bearer())
+            .set_json(&body)
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
 }
@@ -0,0 +1,38 @@
+use actix_web::{HttpResponse, get, http::header::{CacheControl, CacheDirective}};
+use chrono::{SecondsFormat, Utc};
+use dto::time::ServerTime;
+use serde_json::json;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static MONOTONIC_ORIGIN: OnceLock<Instant> = OnceLock::new();
+
+/// Reads the wall clock and the monotonic clock together.
+pub fn server_time() -> ServerTime {
+    let origin = *MONOTONIC_ORIGIN.get_or_init(Instant::now);
+    let now = Utc::now();
+
+    ServerTime {
+        server_time: now.to_rfc3339_opts(SecondsFormat::Millis, true),
+        epoch_ms: now.timestamp_millis(),
+        monotonic_ms: origin.elapsed().as_millis() as u64,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/time",
+    responses(
+        (status = 200, description = "Current server time", body = ServerTime)
+    ),
+    tag = "Time"
+)]
+#[get("/time")]
+pub async fn get_time() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(json!({
+            "message": "Server time",
+            "data": server_time()
+        }))
+}
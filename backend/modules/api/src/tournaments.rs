@@ -0,0 +1,30 @@
+use actix_web::{HttpResponse, get, web::Path};
+use dto::{responses::InvalidCredentialsResponse, tournaments::TournamentStandingsDTO};
+use serde_json::json;
+use service::tournaments::standings;
+use uuid::Uuid;
+
+#[utoipa::path(
+    get,
+    path = "/v1/tournaments/{id}/standings",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Players ordered by points, then the tournament's tie-break", body = TournamentStandingsDTO),
+        (status = 404, description = "Tournament not found", body = InvalidCredentialsResponse)
+    ),
+    tag = "Tournaments"
+)]
+#[get("/{id}/standings")]
+pub async fn get_standings(id: Path<Uuid>) -> HttpResponse {
+    match standings(id.into_inner()).await {
+        Ok(standings) => HttpResponse::Ok().json(json!({
+            "message": "Standings computed",
+            "data": {
+                "standings": standings
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
@@ -0,0 +1,64 @@
+use actix_web::{
+    HttpRequest, HttpResponse, get,
+    web::{Path, Query},
+};
+use dto::{
+    pagination::Page,
+    responses::{InvalidCredentialsResponse, NotFoundResponse},
+    webhooks::{WebhookDeliveriesQuery, WebhookDeliveryDTO},
+};
+use error::error::ApiError;
+use security::JwtAuthMiddleware;
+use serde_json::json;
+use service::{pagination::page_bounds, webhooks::deliveries};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::players::require_owner;
+use crate::ws::jwt_secret;
+
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks/{id}/deliveries",
+    params(
+        ("id" = String, Path, description = "ID of the player whose webhook subscription to inspect", format = "uuid"),
+        ("page" = Option<i32>, Query, description = "Page number (default 1)"),
+        ("limit" = Option<i32>, Query, description = "Deliveries per page (1-100, default 10)")
+    ),
+    responses(
+        (status = 200, description = "Delivery attempts for the subscription, newest first", body = Page<WebhookDeliveryDTO>),
+        (status = 400, description = "Invalid paging", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Not the player's own subscription", body = InvalidCredentialsResponse),
+        (status = 404, description = "Player not found", body = NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Webhooks"
+)]
+#[get("/{id}/deliveries", wrap = "JwtAuthMiddleware::new(jwt_secret())")]
+pub async fn list_webhook_deliveries(
+    req: HttpRequest,
+    id: Path<Uuid>,
+    query: Query<WebhookDeliveriesQuery>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    if let Err(response) = require_owner(&req, id) {
+        return response;
+    }
+
+    let query = query.into_inner();
+    if let Err(errors) = query.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    let (page, page_size) = page_bounds(query.page, query.limit);
+    match deliveries(id, page, page_size).await {
+        Ok(page) => HttpResponse::Ok().json(json!({
+            "message": "Webhook deliveries found",
+            "data": page
+        })),
+        Err(err) => err.error_response(),
+    }
+}
@@ -1,23 +1,292 @@
 use actix::prelude::*;
 use actix_web::{HttpRequest, HttpResponse, Error, web};
+use actix_web::http::header;
 use actix_web_actors::ws;
-use serde::{Serialize};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::env;
-use security::jwt::Claims;
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use std::time::Duration;
+use security::jwt::{Claims, decode_token};
 use actix_web::error::ErrorUnauthorized;
 use serde_json::{Value, json};
+use crate::time::server_time;
+use db::db::db::get_db;
+use dto::games::{CapturedPiece, GameStatus, TimeClass, checked_king, game_time_class, material_of, pgn_moves};
+use dto::notifications::NotificationEvent;
+use entity::game;
+use entity::sea_orm_active_enums::{ResultSide, Termination};
+use error::error::ApiError;
+use sea_orm::{ConnectionTrait, TransactionTrait};
+use chess::bitboard::Board::Color;
+use chess::history::DrawClaim;
+use chess::position::{Position, square_name};
+use chess::time_control::{LowTimeThreshold, LowTimeThresholds, LowTimeWatch, TimeControl};
+use service::lifecycle::{self, ActionOutcome, GameAction, MoveOutcome};
+use service::{clocks, fair_play, guests, notifications};
+use service::rating::RatingEngine;
+use uuid::Uuid;
+
+/// Subprotocol negotiated with clients. The token travels as a sibling `bearer.<jwt>`
+/// entry in `Sec-WebSocket-Protocol`, which unlike the query string is not logged.
+pub const WS_PROTOCOL: &str = "starkmate.v1";
+const BEARER_PROTOCOL_PREFIX: &str = "bearer.";
+const DEFAULT_AUTH_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_TIME_SYNC_INTERVAL_SECS: u64 = 30;
+const DEFAULT_MAX_MESSAGE_BYTES: usize = 16 * 1024;
+const DEFAULT_MAX_MESSAGES_PER_SEC: u32 = 20;
+const NOT_SEATED: &str = "Moves are only accepted from this player's most recent connection";
+
+/// What happens when a player opens a second socket to a game they are connected to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Both sockets receive the room's events, but only the newest may move or act;
+    /// the older one is told with `connection_superseded`.
+    #[default]
+    LatestWins,
+    /// The second socket is refused with `duplicate_connection` and closed.
+    Reject,
+}
+
+impl DuplicatePolicy {
+    /// Reads `WS_DUPLICATE_CONNECTIONS`: `latest` (default) or `reject`.
+    pub fn from_env() -> Self {
+        match env::var("WS_DUPLICATE_CONNECTIONS").as_deref() {
+            Ok("reject") => DuplicatePolicy::Reject,
+            _ => DuplicatePolicy::LatestWins,
+        }
+    }
+}
+
+/// Transport-level limits on what a client may send, checked on every inbound frame
+/// before it is interpreted. Moves, chat and control frames all count alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimits {
+    /// Largest text or binary message accepted; bigger ones close the socket with `1009`.
+    pub max_message_bytes: usize,
+    /// Frames accepted in any one second; more close the socket with `1008`.
+    pub max_messages_per_sec: u32,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        FrameLimits {
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            max_messages_per_sec: DEFAULT_MAX_MESSAGES_PER_SEC,
+        }
+    }
+}
+
+impl FrameLimits {
+    /// Reads `WS_MAX_MESSAGE_BYTES` and `WS_MAX_MESSAGES_PER_SEC`; zero or unparsable
+    /// values keep the default.
+    pub fn from_env() -> Self {
+        let defaults = FrameLimits::default();
+        FrameLimits {
+            max_message_bytes: env::var("WS_MAX_MESSAGE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|bytes| *bytes > 0)
+                .unwrap_or(defaults.max_message_bytes),
+            max_messages_per_sec: env::var("WS_MAX_MESSAGES_PER_SEC")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|count| *count > 0)
+                .unwrap_or(defaults.max_messages_per_sec),
+        }
+    }
+}
 
 /// Core WebSocket message types
 #[derive(Message, Serialize, Clone, Debug, PartialEq)]
 #[rtype(result = "()")]
 #[serde(tag = "type", content = "payload")]
 pub enum WsMessage {
-    Move { from: String, to: String, san: String, fen: String },
+    /// `checked_king` is the square of the king the move puts in check, if any.
+    Move { from: String, to: String, san: String, fen: String, in_check: bool, checked_king: Option<String> },
     Clock { white: u32, black: u32 },
+    /// Sent once each time a player's clock drops to the low-time threshold of the
+    /// game's time class; `remaining` is in seconds, like `clock`.
+    #[serde(rename = "low_time")]
+    LowTime { color: String, remaining: u32 },
     End   { result: String, final_fen: String },
     Error { code: u16, message: String },
+    #[serde(rename = "time_sync")]
+    TimeSync {
+        server_time: String,
+        epoch_ms: i64,
+        monotonic_ms: u64,
+        /// Echo of the client's request timestamp, so it can measure the round trip.
+        client_time_ms: Option<i64>,
+    },
+    /// Sent only to the mover when a move passes the session's pre-check; the `move`
+    /// broadcast or a `move_reject` follows once the server has validated it.
+    #[serde(rename = "move_ack")]
+    MoveAck { move_id: Option<String>, chess_move: String },
+    /// Sent only to the mover when a move is refused. The move never reaches the room.
+    /// `reason` is the REST error's `reason`, such as `not_your_turn`, when it has one.
+    #[serde(rename = "move_reject")]
+    MoveReject {
+        move_id: Option<String>,
+        chess_move: String,
+        code: u16,
+        message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    #[serde(rename = "draw_offered")]
+    DrawOffered { by: String },
+    #[serde(rename = "draw_declined")]
+    DrawDeclined { by: String },
+    /// The player connected to this game from another socket, which now holds their
+    /// seat. This one keeps receiving events but can no longer move.
+    #[serde(rename = "connection_superseded")]
+    ConnectionSuperseded,
+    /// Sent before closing a second socket of a player already connected to the game.
+    #[serde(rename = "duplicate_connection")]
+    DuplicateConnection,
+    /// Authoritative game state, broadcast whenever the game reaches a terminal state.
+    #[serde(rename = "state_update")]
+    StateUpdate {
+        game_id: String,
+        status: GameStatus,
+        result: Option<ResultSide>,
+        termination: Option<Termination>,
+        fen: String,
+        material_balance: i32,
+        captured_by_white: Vec<CapturedPiece>,
+        captured_by_black: Vec<CapturedPiece>,
+    },
+}
+
+impl WsMessage {
+    pub fn state_update(game: &game::Model) -> Self {
+        let material = material_of(game);
+        WsMessage::StateUpdate {
+            game_id: game.id.to_string(),
+            status: GameStatus::of(game),
+            result: game.result,
+            termination: game.termination,
+            fen: game.fen.clone(),
+            material_balance: material.balance,
+            captured_by_white: material.captured_by_white.into_iter().map(CapturedPiece::from).collect(),
+            captured_by_black: material.captured_by_black.into_iter().map(CapturedPiece::from).collect(),
+        }
+    }
+
+    /// The room event announcing an accepted move.
+    pub fn for_move(outcome: &MoveOutcome) -> Self {
+        let checked_king = checked_king(&outcome.game);
+        WsMessage::Move {
+            from: square_name(outcome.mv.from),
+            to: square_name(outcome.mv.to),
+            san: outcome.san.clone(),
+            fen: outcome.game.fen.clone(),
+            in_check: checked_king.is_some(),
+            checked_king,
+        }
+    }
+
+    /// The room event announcing what `player_id`'s action did.
+    pub fn for_outcome(outcome: &ActionOutcome, player_id: Uuid) -> Self {
+        match outcome {
+            ActionOutcome::DrawOffered(_) => WsMessage::DrawOffered { by: player_id.to_string() },
+            ActionOutcome::DrawDeclined(_) => WsMessage::DrawDeclined { by: player_id.to_string() },
+            ActionOutcome::Finalized(finalized) => WsMessage::state_update(&finalized.game),
+        }
+    }
+
+    fn move_reject(move_id: Option<String>, chess_move: String, err: &ApiError) -> Self {
+        WsMessage::MoveReject {
+            move_id,
+            chess_move,
+            code: err.error_response().status().as_u16(),
+            message: err.to_string(),
+            reason: err.reason().map(str::to_string),
+        }
+    }
+
+    fn from_error(err: &ApiError) -> Self {
+        WsMessage::Error {
+            code: err.error_response().status().as_u16(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn time_sync(client_time_ms: Option<i64>) -> Self {
+        let now = server_time();
+        WsMessage::TimeSync {
+            server_time: now.server_time,
+            epoch_ms: now.epoch_ms,
+            monotonic_ms: now.monotonic_ms,
+            client_time_ms,
+        }
+    }
+}
+
+/// Messages sent by clients over the socket
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Auth { token: String },
+    TimeSync { client_time_ms: i64 },
+    /// A move in UCI (`e2e4`) or SAN (`e4`) notation. `move_id` is echoed in the
+    /// `move_ack` or `move_reject` so clients can match them to their optimistic moves.
+    Move {
+        chess_move: String,
+        #[serde(default)]
+        move_id: Option<String>,
+    },
+    DrawOffer,
+    DrawAccept,
+    DrawDecline,
+    Resign,
+    Abort,
+    ClaimDraw { reason: ClaimReason },
+}
+
+/// Rule under which a `claim_draw` is made.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimReason {
+    Repetition,
+    FiftyMove,
+}
+
+impl From<ClaimReason> for DrawClaim {
+    fn from(reason: ClaimReason) -> Self {
+        match reason {
+            ClaimReason::Repetition => DrawClaim::Repetition,
+            ClaimReason::FiftyMove => DrawClaim::FiftyMove,
+        }
+    }
+}
+
+impl ClientMessage {
+    fn game_action(&self) -> Option<GameAction> {
+        match self {
+            ClientMessage::DrawOffer => Some(GameAction::OfferDraw),
+            ClientMessage::DrawAccept => Some(GameAction::AcceptDraw),
+            ClientMessage::DrawDecline => Some(GameAction::DeclineDraw),
+            ClientMessage::Resign => Some(GameAction::Resign),
+            ClientMessage::Abort => Some(GameAction::Abort),
+            ClientMessage::ClaimDraw { reason } => Some(GameAction::ClaimDraw((*reason).into())),
+            ClientMessage::Auth { .. } | ClientMessage::TimeSync { .. } | ClientMessage::Move { .. } => None,
+        }
+    }
+}
+
+/// Where the handshake token was found, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    Subprotocol,
+    AuthorizationHeader,
+    /// Deprecated: query strings end up in proxy and access logs.
+    QueryParam,
+}
+
+#[derive(Deserialize)]
+struct WsAuthQuery {
+    token: Option<String>,
 }
 
 /// Actor messages
@@ -28,6 +297,16 @@ pub struct Connect {
     pub addr: Recipient<WsMessage>,
 }
 
+/// Makes `addr` the socket `player_id` plays from in `game_id`, the only one whose
+/// moves are accepted. Answers `false` when the duplicate policy refuses it.
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct ClaimSeat {
+    pub game_id: String,
+    pub player_id: String,
+    pub addr: Recipient<WsMessage>,
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Disconnect {
@@ -42,19 +321,151 @@ pub struct Broadcast {
     pub message: WsMessage,
 }
 
+/// Starts watching a game's clock for `low_time` warnings. Correspondence games are
+/// never watched.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WatchClock {
+    pub game_id: String,
+    pub time_control: TimeControl,
+}
+
 /// Lobby state actor
 pub struct LobbyState {
     sessions: HashMap<String, HashSet<Recipient<WsMessage>>>,
+    low_time: LowTimeThresholds,
+    /// Games whose `clock` broadcasts are checked for low time, dropped once they end.
+    clocks: HashMap<String, LowTimeWatch>,
+    /// The socket each player moves from, keyed by game and player.
+    seats: HashMap<(String, String), Recipient<WsMessage>>,
+    duplicate_policy: DuplicatePolicy,
+    /// Whether seats coming and going pause and resume the stored game clocks.
+    persist_clocks: bool,
+    clock_keeper: Option<Addr<ClockKeeper>>,
 }
 
 impl LobbyState {
     pub fn new() -> Self {
-        LobbyState { sessions: HashMap::new() }
+        Self::with_duplicate_policy(DuplicatePolicy::from_env())
+    }
+
+    pub fn with_duplicate_policy(duplicate_policy: DuplicatePolicy) -> Self {
+        LobbyState {
+            sessions: HashMap::new(),
+            low_time: low_time_thresholds(),
+            clocks: HashMap::new(),
+            seats: HashMap::new(),
+            duplicate_policy,
+            persist_clocks: true,
+            clock_keeper: None,
+        }
+    }
+
+    /// Leaves stored clocks alone, for lobbies running without a database.
+    pub fn without_persisted_clocks(mut self) -> Self {
+        self.persist_clocks = false;
+        self
+    }
+
+    /// Has the clock of `game_id` stopped if the seat just released left neither of
+    /// its players connected. Which seats are the players' is for the game to say.
+    fn pause_clock(&self, game_id: &str) {
+        let (Some(keeper), Ok(id)) = (&self.clock_keeper, Uuid::parse_str(game_id)) else { return };
+        let connected = self
+            .seats
+            .keys()
+            .filter(|(seat_game, _)| seat_game == game_id)
+            .filter_map(|(_, player_id)| Uuid::parse_str(player_id).ok())
+            .collect();
+        keeper.do_send(ClockUpdate::Pause { game_id: id, connected });
+    }
+
+    fn resume_clock(&self, game_id: &str, player_id: &str) {
+        let Some(keeper) = &self.clock_keeper else { return };
+        if let (Ok(game_id), Ok(player_id)) = (Uuid::parse_str(game_id), Uuid::parse_str(player_id)) {
+            keeper.do_send(ClockUpdate::Resume { game_id, player_id });
+        }
+    }
+
+    /// `low_time` warnings owed after `msg` goes out to its room.
+    fn low_time_warnings(&mut self, msg: &Broadcast) -> Vec<WsMessage> {
+        match &msg.message {
+            WsMessage::Clock { white, black } => {
+                let Some(watch) = self.clocks.get_mut(&msg.game_id) else { return Vec::new() };
+                watch
+                    .observe(Duration::from_secs(*white as u64), Duration::from_secs(*black as u64))
+                    .into_iter()
+                    .map(|color| match color {
+                        Color::White => WsMessage::LowTime { color: "white".to_string(), remaining: *white },
+                        Color::Black => WsMessage::LowTime { color: "black".to_string(), remaining: *black },
+                    })
+                    .collect()
+            }
+            WsMessage::StateUpdate { .. } => {
+                self.clocks.remove(&msg.game_id);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
     }
 }
 
 impl Actor for LobbyState {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if self.persist_clocks {
+            self.clock_keeper = Some(ClockKeeper { lobby: ctx.address() }.start());
+        }
+    }
+}
+
+/// A seat change that may stop or restart a game's clock.
+#[derive(Message)]
+#[rtype(result = "()")]
+enum ClockUpdate {
+    Pause { game_id: Uuid, connected: Vec<Uuid> },
+    Resume { game_id: Uuid, player_id: Uuid },
+}
+
+/// Applies [`ClockUpdate`]s one at a time in the order the seats changed, so a quick
+/// reconnect cannot overtake the pause it follows. A player coming back also sends
+/// the room both clocks as they stand.
+struct ClockKeeper {
+    lobby: Addr<LobbyState>,
+}
+
+impl Actor for ClockKeeper {
+    type Context = Context<Self>;
+}
+
+impl Handler<ClockUpdate> for ClockKeeper {
+    type Result = AtomicResponse<Self, ()>;
+
+    fn handle(&mut self, msg: ClockUpdate, _: &mut Context<Self>) -> Self::Result {
+        let lobby = self.lobby.clone();
+        AtomicResponse::new(Box::pin(
+            async move {
+                match msg {
+                    ClockUpdate::Pause { game_id, connected } => {
+                        if let Err(err) = clocks::pause_clock(game_id, connected).await {
+                            log::warn!("Could not pause the clock of game {}: {}", game_id, err);
+                        }
+                    }
+                    ClockUpdate::Resume { game_id, player_id } => match clocks::resume_clock(game_id, player_id).await {
+                        Ok(Some(clock)) => {
+                            let (white, black) = clock.secs();
+                            let message = WsMessage::Clock { white, black };
+                            lobby.do_send(Broadcast { game_id: game_id.to_string(), message });
+                        }
+                        Ok(None) => {}
+                        Err(err) => log::warn!("Could not resume the clock of game {}: {}", game_id, err),
+                    },
+                }
+            }
+            .into_actor(self),
+        ))
+    }
 }
 
 impl Handler<Connect> for LobbyState {
@@ -70,6 +481,11 @@ impl Handler<Disconnect> for LobbyState {
     type Result = ();
 
     fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
+        let seated = self.seats.len();
+        self.seats.retain(|(game_id, _), addr| !(*game_id == msg.game_id && *addr == msg.addr));
+        if self.seats.len() < seated {
+            self.pause_clock(&msg.game_id);
+        }
         if let Some(set) = self.sessions.get_mut(&msg.game_id) {
             set.remove(&msg.addr);
             if set.is_empty() {
@@ -79,14 +495,45 @@ impl Handler<Disconnect> for LobbyState {
     }
 }
 
+impl Handler<ClaimSeat> for LobbyState {
+    type Result = bool;
+
+    fn handle(&mut self, msg: ClaimSeat, _: &mut Context<Self>) -> bool {
+        let key = (msg.game_id, msg.player_id);
+        match self.seats.get(&key) {
+            Some(held) if *held != msg.addr => match self.duplicate_policy {
+                DuplicatePolicy::Reject => return false,
+                DuplicatePolicy::LatestWins => held.do_send(WsMessage::ConnectionSuperseded),
+            },
+            _ => {}
+        }
+        self.resume_clock(&key.0, &key.1);
+        self.seats.insert(key, msg.addr);
+        true
+    }
+}
+
+impl Handler<WatchClock> for LobbyState {
+    type Result = ();
+
+    fn handle(&mut self, msg: WatchClock, _: &mut Context<Self>) {
+        if let Some(watch) = self.low_time.watch(&msg.time_control) {
+            self.clocks.insert(msg.game_id, watch);
+        }
+    }
+}
+
 impl Handler<Broadcast> for LobbyState {
     type Result = ();
 
     fn handle(&mut self, msg: Broadcast, _: &mut Context<Self>) {
+        let warnings = self.low_time_warnings(&msg);
         if let Some(set) = self.sessions.get(&msg.game_id) {
-            for recipient in set.iter() {
-                // backpressure: drop if send fails
-                let _ = recipient.do_send(msg.message.clone());
+            for message in std::iter::once(&msg.message).chain(&warnings) {
+                for recipient in set.iter() {
+                    // backpressure: drop if send fails
+                    recipient.do_send(message.clone());
+                }
             }
         }
     }
@@ -96,6 +543,17 @@ impl Handler<Broadcast> for LobbyState {
 pub struct WsSession {
     pub game_id: String,
     pub lobby: Addr<LobbyState>,
+    /// Authenticated player (JWT `sub`); `None` until the handshake token or an `auth` message is accepted.
+    pub player_id: Option<String>,
+    /// Last authoritative position broadcast to the room, used to pre-check moves.
+    fen: Option<String>,
+    /// Whether this socket holds the player's seat; see [`ClaimSeat`].
+    seated: bool,
+    auth_timeout: Duration,
+    time_sync_interval: Duration,
+    limits: FrameLimits,
+    /// Start of the current one-second window and the frames received in it.
+    window: (std::time::Instant, u32),
     hb: std::time::Instant,
 }
 
@@ -103,6 +561,165 @@ impl WsSession {
     const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
     const CLIENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
 
+    pub fn new(game_id: String, lobby: Addr<LobbyState>, player_id: Option<String>) -> Self {
+        WsSession {
+            game_id,
+            lobby,
+            player_id,
+            fen: None,
+            seated: false,
+            auth_timeout: auth_timeout(),
+            time_sync_interval: time_sync_interval(),
+            limits: FrameLimits::from_env(),
+            window: (std::time::Instant::now(), 0),
+            hb: std::time::Instant::now(),
+        }
+    }
+
+    /// Claims the player's seat, then joins the game room; only authenticated sessions
+    /// receive broadcasts. Nothing else the client sent is handled until the lobby has
+    /// answered, so a move cannot slip in ahead of the claim.
+    fn join_lobby(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(player_id) = self.player_id.clone() else { return };
+        let claim = ClaimSeat { game_id: self.game_id.clone(), player_id, addr: ctx.address().recipient() };
+        self.lobby
+            .send(claim)
+            .into_actor(self)
+            .map(|claimed, act, ctx| {
+                if claimed.unwrap_or(false) {
+                    act.seated = true;
+                    let addr = ctx.address().recipient();
+                    act.lobby.do_send(Connect { game_id: act.game_id.clone(), addr });
+                    Self::send(ctx, &WsMessage::time_sync(None));
+                } else {
+                    Self::send(ctx, &WsMessage::DuplicateConnection);
+                    Self::close(ctx, ws::CloseCode::Policy, "Player is already connected to this game");
+                }
+            })
+            .wait(ctx);
+    }
+
+    /// Serializes a server message, injecting the protocol version field.
+    fn send(ctx: &mut ws::WebsocketContext<Self>, msg: &WsMessage) {
+        let mut val = serde_json::to_value(msg).unwrap();
+        if let Value::Object(ref mut m) = val {
+            m.insert("version".into(), json!("1.0"));
+        }
+        let text = serde_json::to_string(&val).unwrap();
+        ctx.text(text);
+    }
+
+    fn reject(&self, ctx: &mut ws::WebsocketContext<Self>, message: &str) {
+        Self::send(ctx, &WsMessage::Error { code: 401, message: message.to_string() });
+        Self::close(ctx, ws::CloseCode::Policy, message);
+    }
+
+    fn close(ctx: &mut ws::WebsocketContext<Self>, code: ws::CloseCode, message: &str) {
+        ctx.close(Some(ws::CloseReason { code, description: Some(message.to_string()) }));
+        ctx.stop();
+    }
+
+    /// Counts a frame against the per-second budget; `false` once it is exceeded.
+    fn within_rate(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        let (started, frames) = &mut self.window;
+        if now.duration_since(*started) >= Duration::from_secs(1) {
+            *started = now;
+            *frames = 0;
+        }
+        *frames += 1;
+        *frames <= self.limits.max_messages_per_sec
+    }
+
+    /// Closes the socket if `msg` breaks the frame limits; `true` if it did.
+    fn enforce_limits(&mut self, msg: &Result<ws::Message, ws::ProtocolError>, ctx: &mut ws::WebsocketContext<Self>) -> bool {
+        let oversized = match msg {
+            Ok(ws::Message::Text(text)) => text.len() > self.limits.max_message_bytes,
+            Ok(ws::Message::Binary(bytes)) => bytes.len() > self.limits.max_message_bytes,
+            Err(ws::ProtocolError::Overflow) => true,
+            _ => false,
+        };
+        if oversized {
+            Self::close(ctx, ws::CloseCode::Size, "Message too large");
+            return true;
+        }
+        if !self.within_rate() {
+            Self::close(ctx, ws::CloseCode::Policy, "Too many messages");
+            return true;
+        }
+        false
+    }
+
+    fn authenticate(&mut self, token: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.player_id.is_some() {
+            return;
+        }
+        match decode_token(token, &jwt_secret()) {
+            Ok(claims) => {
+                record_guest_activity(&claims);
+                self.player_id = Some(claims.sub);
+                self.join_lobby(ctx);
+            }
+            Err(_) => self.reject(ctx, "Invalid or expired token"),
+        }
+    }
+
+    /// The game and authenticated player this session acts for.
+    fn ids(&self, ctx: &mut ws::WebsocketContext<Self>) -> Option<(Uuid, Uuid)> {
+        let ids = Uuid::parse_str(&self.game_id)
+            .ok()
+            .zip(self.player_id.as_deref().and_then(|id| Uuid::parse_str(id).ok()));
+        if ids.is_none() {
+            Self::send(ctx, &WsMessage::Error { code: 400, message: "Invalid game or player id".to_string() });
+        }
+        ids
+    }
+
+    /// Runs a player action through [`perform_action`]; errors go back to this socket only.
+    fn handle_action(&self, action: GameAction, ctx: &mut ws::WebsocketContext<Self>) {
+        if !self.seated {
+            Self::send(ctx, &WsMessage::Error { code: 409, message: NOT_SEATED.to_string() });
+            return;
+        }
+        let Some((game_id, player_id)) = self.ids(ctx) else { return };
+
+        let lobby = self.lobby.clone();
+        let me = ctx.address().recipient::<WsMessage>();
+        actix::spawn(async move {
+            if let Err(err) = perform_action(&lobby, game_id, player_id, action).await {
+                me.do_send(WsMessage::from_error(&err));
+            }
+        });
+    }
+
+    /// Plays a move through [`perform_move`].
+    ///
+    /// A move that is legal in the last position this session saw is acknowledged at
+    /// once. The ack is written to the socket before the move is validated, so it always
+    /// precedes the room's `move` broadcast. Only `perform_move` broadcasts, and only
+    /// after the move is persisted, so a rejected move reaches the mover alone.
+    fn handle_move(&self, chess_move: String, move_id: Option<String>, ctx: &mut ws::WebsocketContext<Self>) {
+        if !self.seated {
+            let reject =
+                WsMessage::MoveReject { move_id, chess_move, code: 409, message: NOT_SEATED.to_string(), reason: None };
+            Self::send(ctx, &reject);
+            return;
+        }
+        let Some((game_id, player_id)) = self.ids(ctx) else { return };
+
+        if precheck_move(self.fen.as_deref(), &chess_move) {
+            Self::send(ctx, &WsMessage::MoveAck { move_id: move_id.clone(), chess_move: chess_move.clone() });
+        }
+
+        let lobby = self.lobby.clone();
+        let me = ctx.address().recipient::<WsMessage>();
+        actix::spawn(async move {
+            if let Err(err) = perform_move(&lobby, game_id, player_id, &chess_move).await {
+                me.do_send(WsMessage::move_reject(move_id, chess_move, &err));
+            }
+        });
+    }
+
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
         ctx.run_interval(Self::HEARTBEAT_INTERVAL, |act, ctx| {
             if std::time::Instant::now().duration_since(act.hb) > Self::CLIENT_TIMEOUT {
@@ -118,18 +735,35 @@ impl Actor for WsSession {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         self.hb(ctx);
-        let addr = ctx.address().recipient();
-        self.lobby.do_send(Connect { game_id: self.game_id.clone(), addr });
+        ctx.run_interval(self.time_sync_interval, |act, ctx| {
+            if act.player_id.is_some() {
+                Self::send(ctx, &WsMessage::time_sync(None));
+            }
+        });
+        if self.player_id.is_some() {
+            self.join_lobby(ctx);
+        } else {
+            ctx.run_later(self.auth_timeout, |act, ctx| {
+                if act.player_id.is_none() {
+                    act.reject(ctx, "Authentication timed out");
+                }
+            });
+        }
     }
 
     fn stopped(&mut self, ctx: &mut Self::Context) {
-        let addr = ctx.address().recipient();
-        self.lobby.do_send(Disconnect { game_id: self.game_id.clone(), addr });
+        if self.player_id.is_some() {
+            let addr = ctx.address().recipient();
+            self.lobby.do_send(Disconnect { game_id: self.game_id.clone(), addr });
+        }
     }
 }
 
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        if self.enforce_limits(&msg, ctx) {
+            return;
+        }
         match msg {
             Ok(ws::Message::Ping(msg)) => {
                 self.hb = std::time::Instant::now();
@@ -138,7 +772,32 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
             Ok(ws::Message::Pong(_)) => {
                 self.hb = std::time::Instant::now();
             }
-            Ok(ws::Message::Text(_)) | Ok(ws::Message::Binary(_)) => {}
+            Ok(ws::Message::Text(text)) => {
+                match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Auth { token }) => self.authenticate(&token, ctx),
+                    Ok(_) if self.player_id.is_none() => {
+                        self.reject(ctx, "Authentication required")
+                    }
+                    Ok(ClientMessage::TimeSync { client_time_ms }) => {
+                        Self::send(ctx, &WsMessage::time_sync(Some(client_time_ms)))
+                    }
+                    Ok(ClientMessage::Move { chess_move, move_id }) => {
+                        self.handle_move(chess_move, move_id, ctx)
+                    }
+                    Ok(message) => {
+                        if let Some(action) = message.game_action() {
+                            self.handle_action(action, ctx);
+                        }
+                    }
+                    Err(_) if self.player_id.is_none() => {
+                        self.reject(ctx, "Authentication required")
+                    }
+                    Err(_) => {}
+                }
+            }
+            Ok(ws::Message::Binary(_)) if self.player_id.is_none() => {
+                self.reject(ctx, "Authentication required");
+            }
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -152,43 +811,250 @@ impl Handler<WsMessage> for WsSession {
     type Result = ();
 
     fn handle(&mut self, msg: WsMessage, ctx: &mut ws::WebsocketContext<Self>) {
-        // Serialize message and inject version field
-        let mut val = serde_json::to_value(&msg).unwrap();
-        if let Value::Object(ref mut m) = val {
-            m.insert("version".into(), json!("1.0"));
+        if let WsMessage::Move { fen, .. } | WsMessage::StateUpdate { fen, .. } = &msg {
+            self.fen = Some(fen.clone());
         }
-        let text = serde_json::to_string(&val).unwrap();
-        ctx.text(text);
+        if msg == WsMessage::ConnectionSuperseded {
+            self.seated = false;
+        }
+        Self::send(ctx, &msg);
     }
 }
 
+/// Applies a player action to the authoritative game and broadcasts the result to its room.
+///
+/// REST endpoints and socket messages both come through here, so spectators see the
+/// same events whichever transport the acting player used.
+pub async fn perform_action(
+    lobby: &Addr<LobbyState>,
+    game_id: Uuid,
+    player_id: Uuid,
+    action: GameAction,
+) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    let act = if action == GameAction::OfferDraw { PlayerAct::OfferedDraw } else { PlayerAct::Other };
+    let game = perform_action_with(&db, &RatingEngine::from_env(), lobby, game_id, player_id, action).await?;
+    queue_notifications(&game, player_id, act);
+    Ok(game)
+}
+
+pub async fn perform_action_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    lobby: &Addr<LobbyState>,
+    game_id: Uuid,
+    player_id: Uuid,
+    action: GameAction,
+) -> Result<game::Model, ApiError> {
+    let outcome = lifecycle::apply_action_with(db, engine, game_id, player_id, action).await?;
+    lobby.do_send(Broadcast {
+        game_id: game_id.to_string(),
+        message: WsMessage::for_outcome(&outcome, player_id),
+    });
+    Ok(outcome.game().clone())
+}
+
+/// Plays a move on the authoritative game and broadcasts it to the room, followed by
+/// the final state when the move ends the game. Like [`perform_action`], this is shared
+/// by the REST endpoint and the socket.
+pub async fn perform_move(
+    lobby: &Addr<LobbyState>,
+    game_id: Uuid,
+    player_id: Uuid,
+    chess_move: &str,
+) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    let game = perform_move_with(&db, &RatingEngine::from_env(), lobby, game_id, player_id, chess_move).await?;
+    queue_notifications(&game, player_id, PlayerAct::Moved);
+    Ok(game)
+}
+
+/// What a player did to a game, for choosing whom to notify about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlayerAct {
+    Moved,
+    OfferedDraw,
+    Other,
+}
+
+/// Notifications for players outside the room, per their preferences: both players
+/// when `actor` ended the game, otherwise the opponent when offered a draw or handed
+/// the turn in a correspondence game.
+fn pending_notifications(game: &game::Model, actor: Uuid, act: PlayerAct) -> Vec<notifications::Pending> {
+    let opponent = if game.white_player == actor { game.black_player } else { game.white_player };
+    let ply = pgn_moves(&game.pgn).len();
+    let mut events = Vec::new();
+    if game.ended_at.is_some() {
+        let payload = serde_json::to_value(WsMessage::state_update(game)).unwrap_or(Value::Null);
+        let key = format!("game_result:{}", game.id);
+        events.push((actor, NotificationEvent::GameResult, key.clone(), payload.clone()));
+        events.push((opponent, NotificationEvent::GameResult, key, payload));
+    } else if act == PlayerAct::OfferedDraw {
+        let payload = json!({ "game_id": game.id, "by": actor });
+        // A draw can be offered again later in the game, so the ply tells offers apart
+        let key = format!("draw_offer:{}:{}", game.id, ply);
+        events.push((opponent, NotificationEvent::DrawOffer, key, payload));
+    } else if act == PlayerAct::Moved && game_time_class(game) == Some(TimeClass::Correspondence) {
+        let payload = json!({ "game_id": game.id, "by": actor, "ply": ply });
+        let key = format!("correspondence_turn:{}:{}", game.id, ply);
+        events.push((opponent, NotificationEvent::CorrespondenceTurn, key, payload));
+    }
+    events
+}
+
+/// Queues the notifications `actor` raised and the fair-play review of finished
+/// games. Both run in the background and never fail the request.
+fn queue_notifications(game: &game::Model, actor: Uuid, act: PlayerAct) {
+    notifications::queue(pending_notifications(game, actor, act));
+    if game.ended_at.is_some() {
+        actix::spawn(fair_play::review_finished_game(game.id));
+    }
+}
+
+pub async fn perform_move_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    lobby: &Addr<LobbyState>,
+    game_id: Uuid,
+    player_id: Uuid,
+    chess_move: &str,
+) -> Result<game::Model, ApiError> {
+    let outcome = lifecycle::apply_move_with(db, engine, game_id, player_id, chess_move).await?;
+    lobby.do_send(Broadcast {
+        game_id: game_id.to_string(),
+        message: WsMessage::for_move(&outcome),
+    });
+    if let Some(finalized) = &outcome.finalized {
+        lobby.do_send(Broadcast {
+            game_id: game_id.to_string(),
+            message: WsMessage::state_update(&finalized.game),
+        });
+    }
+    Ok(outcome.game)
+}
+
+/// Cheap check run before a move is acknowledged: it must be legal for the side to
+/// move in `fen`. Turn order and the game's state are left to [`perform_move`], so a
+/// session that has not seen a position yet acknowledges nothing.
+fn precheck_move(fen: Option<&str>, chess_move: &str) -> bool {
+    let Some(position) = fen.and_then(|fen| fen.parse::<Position>().ok()) else {
+        return false;
+    };
+    position.parse_uci(chess_move).is_ok() || position.parse_san(chess_move).is_ok()
+}
+
+/// Keeps a guest who connects to play from being collected as inactive.
+fn record_guest_activity(claims: &Claims) {
+    let Some(guest_id) = claims.guest.then(|| Uuid::parse_str(&claims.sub).ok()).flatten() else {
+        return;
+    };
+    actix::spawn(async move {
+        if let Err(err) = guests::touch_guest(guest_id).await {
+            log::warn!("Could not record activity of guest {}: {}", guest_id, err);
+        }
+    });
+}
+
+pub(crate) fn jwt_secret() -> String {
+    env::var("JWT_SECRET_KEY").unwrap_or_else(|_| "development_secret_key".to_string())
+}
+
+/// Grace period for clients that authenticate with an `auth` message after connecting.
+fn auth_timeout() -> Duration {
+    let secs = env::var("WS_AUTH_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_AUTH_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// How often authenticated sessions are pushed a `time_sync` message.
+fn time_sync_interval() -> Duration {
+    let secs = env::var("WS_TIME_SYNC_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_TIME_SYNC_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Low-time threshold of each real-time class, read from `LOW_TIME_BULLET`,
+/// `LOW_TIME_BLITZ`, `LOW_TIME_RAPID` and `LOW_TIME_CLASSICAL` as seconds (`15`) or a
+/// share of the initial time (`10%`).
+fn low_time_thresholds() -> LowTimeThresholds {
+    let threshold = |name: &str, default: LowTimeThreshold| {
+        env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let defaults = LowTimeThresholds::default();
+    LowTimeThresholds {
+        bullet: threshold("LOW_TIME_BULLET", defaults.bullet),
+        blitz: threshold("LOW_TIME_BLITZ", defaults.blitz),
+        rapid: threshold("LOW_TIME_RAPID", defaults.rapid),
+        classical: threshold("LOW_TIME_CLASSICAL", defaults.classical),
+    }
+}
+
+/// Finds a token supplied during the handshake, preferring the subprotocol header.
+pub fn handshake_token(req: &HttpRequest) -> Result<Option<(String, TokenSource)>, Error> {
+    let protocols = req
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+    if let Some(token) = protocols
+        .split(',')
+        .map(str::trim)
+        .find_map(|p| p.strip_prefix(BEARER_PROTOCOL_PREFIX))
+    {
+        return Ok(Some((token.to_string(), TokenSource::Subprotocol)));
+    }
+
+    if let Some(header) = req.headers().get("Authorization").and_then(|h| h.to_str().ok()) {
+        return match header.strip_prefix("Bearer ") {
+            Some(token) => Ok(Some((token.to_string(), TokenSource::AuthorizationHeader))),
+            None => Err(ErrorUnauthorized("Invalid authorization token format")),
+        };
+    }
+
+    let query = web::Query::<WsAuthQuery>::from_query(req.query_string())
+        .map(|q| q.into_inner().token)
+        .unwrap_or(None);
+    Ok(query.map(|token| (token, TokenSource::QueryParam)))
+}
+
 /// WebSocket route handler with auth
+///
+/// A token found in the handshake is validated up front. Without one the socket is
+/// opened unauthenticated and must send an `auth` message within the grace period.
 pub async fn ws_route(
     req: HttpRequest,
     stream: web::Payload,
     lobby: web::Data<Addr<LobbyState>>,
 ) -> Result<HttpResponse, Error> {
-    // Validate JWT token from header
-    let auth_header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
-    if let Some(header) = auth_header {
-        if !header.starts_with("Bearer ") {
-            return Err(ErrorUnauthorized("Invalid authorization token format"));
+    let player_id = match handshake_token(&req)? {
+        Some((token, source)) => {
+            if source == TokenSource::QueryParam {
+                log::warn!("WebSocket token passed as query parameter; this is deprecated");
+            }
+            let claims = decode_token(&token, &jwt_secret())
+                .map_err(|_| ErrorUnauthorized("Invalid or expired token"))?;
+            record_guest_activity(&claims);
+            Some(claims.sub)
         }
-        let token = &header[7..];
-        let secret = env::var("JWT_SECRET_KEY").unwrap_or_else(|_| "development_secret_key".to_string());
-        let validation = Validation::new(Algorithm::HS256);
-        decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
-            .map_err(|_| ErrorUnauthorized("Invalid or expired token"))?;
-    } else {
-        return Err(ErrorUnauthorized("Missing authorization token"));
-    }
+        None => None,
+    };
 
     let game_id = req.match_info().get("game_id").unwrap_or("").to_string();
-    ws::start(
-        WsSession { game_id, lobby: lobby.get_ref().clone(), hb: std::time::Instant::now() },
-        &req,
-        stream,
-    )
+    let session = WsSession::new(game_id, lobby.get_ref().clone(), player_id);
+    // Frames past the limit are refused by the codec without being buffered
+    let frame_size = session.limits.max_message_bytes;
+    ws::WsResponseBuilder::new(session, &req, stream)
+        .protocols(&[WS_PROTOCOL])
+        .frame_size(frame_size)
+        .start()
 }
 
 // Unit tests for LobbyState and session
@@ -231,4 +1097,493 @@ mod tests {
         assert_eq!(received1, msg);
         assert_eq!(received2, msg);
     }
+
+    #[actix_rt::test]
+    async fn test_low_time_is_sent_once_per_crossing() {
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let recipient = TestRecipient { tx }.start().recipient();
+        let game_id = "blitz-game".to_string();
+        lobby.send(Connect { game_id: game_id.clone(), addr: recipient }).await.unwrap();
+        let time_control = TimeControl {
+            initial_time: Duration::from_secs(180),
+            increment: Duration::ZERO,
+            delay: Duration::ZERO,
+        };
+        lobby.send(WatchClock { game_id: game_id.clone(), time_control }).await.unwrap();
+
+        for (white, black) in [(30, 40), (14, 40), (9, 38)] {
+            let message = WsMessage::Clock { white, black };
+            lobby.send(Broadcast { game_id: game_id.clone(), message }).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            received,
+            vec![
+                WsMessage::Clock { white: 30, black: 40 },
+                WsMessage::Clock { white: 14, black: 40 },
+                WsMessage::LowTime { color: "white".to_string(), remaining: 14 },
+                WsMessage::Clock { white: 9, black: 38 },
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_correspondence_clock_never_runs_low() {
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let recipient = TestRecipient { tx }.start().recipient();
+        let game_id = "correspondence-game".to_string();
+        lobby.send(Connect { game_id: game_id.clone(), addr: recipient }).await.unwrap();
+        let time_control = TimeControl {
+            initial_time: Duration::from_secs(3 * 86_400),
+            increment: Duration::ZERO,
+            delay: Duration::ZERO,
+        };
+        lobby.send(WatchClock { game_id: game_id.clone(), time_control }).await.unwrap();
+
+        for (white, black) in [(5, 5), (4, 5)] {
+            let message = WsMessage::Clock { white, black };
+            lobby.send(Broadcast { game_id: game_id.clone(), message }).await.unwrap();
+        }
+
+        assert_eq!(rx.recv().await.unwrap(), WsMessage::Clock { white: 5, black: 5 });
+        assert_eq!(rx.recv().await.unwrap(), WsMessage::Clock { white: 4, black: 5 });
+    }
+
+    #[test]
+    fn test_handshake_token_prefers_subprotocol() {
+        let req = actix_web::test::TestRequest::default()
+            .uri("/ws/game123?token=from-query")
+            .insert_header((header::SEC_WEBSOCKET_PROTOCOL, "starkmate.v1, bearer.from-protocol"))
+            .insert_header(("Authorization", "Bearer from-header"))
+            .to_http_request();
+        let found = handshake_token(&req).unwrap();
+        assert_eq!(found, Some(("from-protocol".to_string(), TokenSource::Subprotocol)));
+    }
+
+    fn ws_server() -> actix_test::TestServer {
+        let lobby = LobbyState::new().without_persisted_clocks().start();
+        actix_test::start(move || {
+            actix_web::App::new()
+                .app_data(web::Data::new(lobby.clone()))
+                .route("/ws/{game_id}", web::get().to(ws_route))
+        })
+    }
+
+    /// Reads frames until the server closes the socket and returns its close code.
+    async fn close_code<S>(framed: &mut S) -> Option<ws::CloseCode>
+    where
+        S: futures_util::Stream<Item = Result<awc::ws::Frame, ws::ProtocolError>> + Unpin,
+    {
+        use futures_util::StreamExt;
+        while let Some(frame) = framed.next().await {
+            if let Ok(awc::ws::Frame::Close(reason)) = frame {
+                return reason.map(|r| r.code);
+            }
+        }
+        None
+    }
+
+    #[actix_rt::test]
+    async fn test_unauthenticated_socket_is_closed_after_the_grace_period() {
+        use futures_util::StreamExt;
+        const GRACE: Duration = Duration::from_millis(200);
+
+        async fn impatient_route(
+            req: HttpRequest,
+            stream: web::Payload,
+            lobby: web::Data<Addr<LobbyState>>,
+        ) -> Result<HttpResponse, Error> {
+            let mut session = WsSession::new("game123".to_string(), lobby.get_ref().clone(), None);
+            session.auth_timeout = GRACE;
+            ws::start(session, &req, stream)
+        }
+        let lobby = LobbyState::new().without_persisted_clocks().start();
+        let mut srv = actix_test::start(move || {
+            actix_web::App::new()
+                .app_data(web::Data::new(lobby.clone()))
+                .route("/ws/{game_id}", web::get().to(impatient_route))
+        });
+        let connected = std::time::Instant::now();
+        let mut framed = srv.ws_at("/ws/game123").await.unwrap();
+
+        let mut errors = Vec::new();
+        let mut code = None;
+        while let Some(frame) = framed.next().await {
+            match frame {
+                Ok(awc::ws::Frame::Text(text)) => errors.push(serde_json::from_slice::<Value>(&text).unwrap()),
+                Ok(awc::ws::Frame::Close(reason)) => {
+                    code = reason.map(|r| r.code);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        assert!(connected.elapsed() >= GRACE);
+        assert_eq!(code, Some(ws::CloseCode::Policy));
+        assert!(errors.iter().any(|msg| msg["type"] == "Error"
+            && msg["payload"]["code"] == 401
+            && msg["payload"]["message"] == "Authentication timed out"), "{:?}", errors);
+    }
+
+    #[actix_rt::test]
+    async fn test_oversized_frame_closes_with_size_code() {
+        use futures_util::SinkExt;
+        let mut srv = ws_server();
+        let mut framed = srv.ws_at("/ws/game123").await.unwrap();
+
+        let oversized = "x".repeat(FrameLimits::from_env().max_message_bytes + 1);
+        framed.send(awc::ws::Message::Text(oversized.into())).await.unwrap();
+
+        assert_eq!(close_code(&mut framed).await, Some(ws::CloseCode::Size));
+    }
+
+    #[actix_rt::test]
+    async fn test_frame_flood_closes_with_policy_code() {
+        use futures_util::SinkExt;
+        let mut srv = ws_server();
+        let mut framed = srv.ws_at("/ws/game123").await.unwrap();
+
+        for _ in 0..=FrameLimits::from_env().max_messages_per_sec {
+            framed.send(awc::ws::Message::Ping("".into())).await.unwrap();
+        }
+
+        assert_eq!(close_code(&mut framed).await, Some(ws::CloseCode::Policy));
+    }
+
+    #[actix_rt::test]
+    async fn test_duplicate_policy_decides_the_seat() {
+        let (tx1, mut rx1) = unbounded_channel();
+        let (tx2, mut rx2) = unbounded_channel();
+        let first = TestRecipient { tx: tx1 }.start().recipient();
+        let second = TestRecipient { tx: tx2 }.start().recipient();
+        let claim = |addr: &Recipient<WsMessage>| ClaimSeat {
+            game_id: "game123".to_string(),
+            player_id: "player".to_string(),
+            addr: addr.clone(),
+        };
+
+        let rejecting = LobbyState::with_duplicate_policy(DuplicatePolicy::Reject).start();
+        assert!(rejecting.send(claim(&first)).await.unwrap());
+        assert!(!rejecting.send(claim(&second)).await.unwrap());
+
+        let latest = LobbyState::with_duplicate_policy(DuplicatePolicy::LatestWins).start();
+        assert!(latest.send(claim(&first)).await.unwrap());
+        assert!(latest.send(claim(&second)).await.unwrap());
+        for addr in [&first, &second] {
+            latest.send(Connect { game_id: "game123".to_string(), addr: addr.clone() }).await.unwrap();
+        }
+        let msg = WsMessage::Clock { white: 60, black: 60 };
+        latest.send(Broadcast { game_id: "game123".to_string(), message: msg.clone() }).await.unwrap();
+
+        // The older socket loses its seat but both still see the room's events
+        assert_eq!(rx1.recv().await.unwrap(), WsMessage::ConnectionSuperseded);
+        assert_eq!(rx1.recv().await.unwrap(), msg);
+        assert_eq!(rx2.recv().await.unwrap(), msg);
+    }
+
+    #[actix_rt::test]
+    async fn test_only_the_latest_socket_of_a_player_may_move() {
+        use futures_util::{SinkExt, StreamExt};
+
+        async fn next_json<S>(framed: &mut S) -> Value
+        where
+            S: futures_util::Stream<Item = Result<awc::ws::Frame, ws::ProtocolError>> + Unpin,
+        {
+            loop {
+                if let Some(Ok(awc::ws::Frame::Text(text))) = framed.next().await {
+                    return serde_json::from_slice(&text).unwrap();
+                }
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp() as usize;
+        let claims = Claims { sub: Uuid::new_v4().to_string(), iat: now, exp: now + 600, guest: false };
+        let token = security::jwt::encode_token(&claims, &jwt_secret()).unwrap();
+        let path = format!("/ws/{}?token={}", Uuid::new_v4(), token);
+        let mut srv = ws_server();
+
+        let mut first = srv.ws_at(&path).await.unwrap();
+        assert_eq!(next_json(&mut first).await["type"], "time_sync");
+        let mut second = srv.ws_at(&path).await.unwrap();
+        assert_eq!(next_json(&mut second).await["type"], "time_sync");
+        assert_eq!(next_json(&mut first).await["type"], "connection_superseded");
+
+        let mv = r#"{"type":"move","payload":{"chess_move":"e2e4","move_id":"m1"}}"#;
+        first.send(awc::ws::Message::Text(mv.into())).await.unwrap();
+
+        let reply = next_json(&mut first).await;
+        assert_eq!(reply["type"], "move_reject");
+        assert_eq!(reply["payload"]["code"], 409);
+        assert_eq!(reply["payload"]["move_id"], "m1");
+    }
+
+    #[test]
+    fn test_handshake_token_falls_back_to_query() {
+        let req = actix_web::test::TestRequest::default()
+            .uri("/ws/game123?token=from-query")
+            .to_http_request();
+        let found = handshake_token(&req).unwrap();
+        assert_eq!(found, Some(("from-query".to_string(), TokenSource::QueryParam)));
+
+        let req = actix_web::test::TestRequest::default().uri("/ws/game123").to_http_request();
+        assert_eq!(handshake_token(&req).unwrap(), None);
+    }
+
+    #[test]
+    fn test_handshake_token_rejects_non_bearer_header() {
+        let req = actix_web::test::TestRequest::default()
+            .insert_header(("Authorization", "Basic abc"))
+            .to_http_request();
+        assert!(handshake_token(&req).is_err());
+    }
+
+    #[test]
+    fn test_auth_message_parses() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"auth","payload":{"token":"abc"}}"#).unwrap();
+        assert_eq!(msg, ClientMessage::Auth { token: "abc".to_string() });
+    }
+
+    #[test]
+    fn test_draw_messages_parse_without_payload() {
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"draw_offer"}"#).unwrap();
+        assert_eq!(msg, ClientMessage::DrawOffer);
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"draw_accept"}"#).unwrap();
+        assert_eq!(msg, ClientMessage::DrawAccept);
+    }
+
+    #[test]
+    fn test_time_sync_echoes_client_timestamp() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"time_sync","payload":{"client_time_ms":1000}}"#).unwrap();
+        assert_eq!(msg, ClientMessage::TimeSync { client_time_ms: 1000 });
+
+        let reply = serde_json::to_value(WsMessage::time_sync(Some(1000))).unwrap();
+        assert_eq!(reply["type"], "time_sync");
+        assert_eq!(reply["payload"]["client_time_ms"], 1000);
+        assert!(reply["payload"]["epoch_ms"].as_i64().unwrap() > 0);
+        assert!(reply["payload"]["server_time"].as_str().unwrap().ends_with('Z'));
+    }
+
+    /// A game between `white` and `black` after 1.e4 e5, white to move.
+    fn live_game(white: Uuid, black: Uuid) -> game::Model {
+        game::Model {
+            public_id: "Ws4rT8bN".to_string(),
+            fen: "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2".to_string(),
+            pgn: json!({ "moves": ["e4", "e5"] }),
+            ..game::Model::fixture(white, black)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_resign_broadcasts_state_update_to_spectator() {
+        use entity::{player_rating, tournament_pairing};
+        use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+        use std::collections::BTreeMap;
+
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black);
+        let now = game.created_at;
+        let mut resigned = game.clone();
+        resigned.result = Some(ResultSide::Black);
+        resigned.termination = Some(Termination::Resignation);
+        resigned.ended_at = Some(now);
+        let ratings = [white, black].map(|player_id| player_rating::Model {
+            player_id,
+            rating: 1500,
+            games_played: 0,
+            updated_at: now,
+        });
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![resigned]])
+            .append_query_results([Vec::<tournament_pairing::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(0i64))])]])
+            .append_query_results([ratings.to_vec()])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let spectator = TestRecipient { tx }.start().recipient();
+        lobby.send(Connect { game_id: game.id.to_string(), addr: spectator }).await.unwrap();
+
+        // The same path the REST resign endpoint takes
+        let updated = perform_action_with(&db, &RatingEngine::default(), &lobby, game.id, white, GameAction::Resign)
+            .await
+            .unwrap();
+        assert_eq!(updated.result, Some(ResultSide::Black));
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(
+            received,
+            WsMessage::StateUpdate {
+                game_id: game.id.to_string(),
+                status: GameStatus::Completed,
+                result: Some(ResultSide::Black),
+                termination: Some(Termination::Resignation),
+                fen: game.fen.clone(),
+                material_balance: 0,
+                captured_by_white: Vec::new(),
+                captured_by_black: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_resign_and_abort_map_to_game_actions() {
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"resign"}"#).unwrap();
+        assert_eq!(msg.game_action(), Some(GameAction::Resign));
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"abort"}"#).unwrap();
+        assert_eq!(msg.game_action(), Some(GameAction::Abort));
+    }
+
+    #[test]
+    fn test_claim_draw_parses_reason() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"claim_draw","payload":{"reason":"fifty_move"}}"#).unwrap();
+        assert_eq!(msg.game_action(), Some(GameAction::ClaimDraw(DrawClaim::FiftyMove)));
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"claim_draw","payload":{"reason":"agreement"}}"#).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_rest_move_is_broadcast_to_spectator() {
+        use sea_orm::{DatabaseBackend, MockDatabase};
+
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black);
+        let after_fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+        let mut moved = game.clone();
+        moved.fen = after_fen.to_string();
+        moved.pgn = json!({ "moves": ["e4", "e5", "Nf3"] });
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![moved]])
+            .into_connection();
+
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let spectator = TestRecipient { tx }.start().recipient();
+        lobby.send(Connect { game_id: game.id.to_string(), addr: spectator }).await.unwrap();
+
+        // The REST move endpoint and the socket both call through here
+        let updated = perform_move_with(&db, &RatingEngine::default(), &lobby, game.id, white, "g1f3")
+            .await
+            .unwrap();
+        assert_eq!(updated.fen, after_fen);
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(
+            received,
+            WsMessage::Move {
+                from: "g1".to_string(),
+                to: "f3".to_string(),
+                san: "Nf3".to_string(),
+                fen: after_fen.to_string(),
+                in_check: false,
+                checked_king: None,
+            }
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_out_of_turn_move_is_rejected_alike_over_rest_and_socket() {
+        use sea_orm::{DatabaseBackend, MockDatabase};
+
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+        let lobby = LobbyState::new().start();
+
+        // Black tries to move on white's turn; both entry points go through here
+        let err = perform_move_with(&db, &RatingEngine::default(), &lobby, game.id, black, "d7d5")
+            .await
+            .unwrap_err();
+
+        let rest = err.error_response();
+        assert_eq!(rest.status(), actix_web::http::StatusCode::CONFLICT);
+        let body = actix_web::body::to_bytes(rest.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        let socket = serde_json::to_value(WsMessage::move_reject(None, "d7d5".to_string(), &err)).unwrap();
+        assert_eq!(socket["payload"]["code"], body["code"]);
+        assert_eq!(socket["payload"]["reason"], "not_your_turn");
+        assert_eq!(body["reason"], "not_your_turn");
+    }
+
+    #[test]
+    fn test_move_message_parses() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"move","payload":{"chess_move":"e2e4"}}"#).unwrap();
+        assert_eq!(msg, ClientMessage::Move { chess_move: "e2e4".to_string(), move_id: None });
+        assert_eq!(msg.game_action(), None);
+    }
+
+    #[test]
+    fn test_move_ack_and_reject_shapes() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"move","payload":{"chess_move":"e4","move_id":"m1"}}"#).unwrap();
+        assert_eq!(msg, ClientMessage::Move { chess_move: "e4".to_string(), move_id: Some("m1".to_string()) });
+
+        let ack = WsMessage::MoveAck { move_id: Some("m1".to_string()), chess_move: "e4".to_string() };
+        assert_eq!(
+            serde_json::to_value(&ack).unwrap(),
+            json!({ "type": "move_ack", "payload": { "move_id": "m1", "chess_move": "e4" } })
+        );
+        let err = ApiError::Conflict("It is not your turn".to_string());
+        let reject = WsMessage::move_reject(Some("m1".to_string()), "e4".to_string(), &err);
+        assert_eq!(
+            serde_json::to_value(&reject).unwrap(),
+            json!({
+                "type": "move_reject",
+                "payload": { "move_id": "m1", "chess_move": "e4", "code": 409, "message": err.to_string() }
+            })
+        );
+    }
+
+    #[test]
+    fn test_correspondence_move_notifies_the_opponent_of_their_turn() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let correspondence = game::Model {
+            clock_initial_secs: Some(3 * 24 * 60 * 60),
+            ..live_game(white, black)
+        };
+        let blitz = game::Model { clock_initial_secs: Some(180), ..live_game(white, black) };
+
+        let pending = pending_notifications(&correspondence, black, PlayerAct::Moved);
+
+        assert_eq!(pending.len(), 1);
+        let (player_id, event, key, payload) = &pending[0];
+        assert_eq!((*player_id, *event), (white, NotificationEvent::CorrespondenceTurn));
+        assert_eq!(key, &format!("correspondence_turn:{}:2", correspondence.id));
+        assert_eq!(payload, &json!({ "game_id": correspondence.id, "by": black, "ply": 2 }));
+        assert!(pending_notifications(&blitz, black, PlayerAct::Moved).is_empty());
+        assert!(pending_notifications(&correspondence, black, PlayerAct::Other).is_empty());
+    }
+
+    #[test]
+    fn test_precheck_acks_only_legal_moves_in_a_known_position() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(precheck_move(Some(start), "e2e4"));
+        assert!(precheck_move(Some(start), "Nf3"));
+        assert!(!precheck_move(Some(start), "e2e5"));
+        assert!(!precheck_move(Some(start), "e5"));
+        assert!(!precheck_move(None, "e2e4"));
+    }
 }
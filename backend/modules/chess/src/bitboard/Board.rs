@@ -37,6 +37,15 @@ impl Bitboard {
         squares
     }
 
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether `s` is one of the squares in the set.
+    pub fn contains(self, s: Square) -> bool {
+        (self.0 & s.bitboard().0) != 0
+    }
+
     /// If exactly one bit is set, returns that square.
     pub fn single_square(self) -> Option<Square> {
         if self.0 != 0 && (self.0 & (self.0 - 1)) == 0 {
@@ -110,18 +119,106 @@ pub struct Square {
 }
 
 impl Square {
+    /// The square on `file` and `rank`, both counted from 0, if it is on the board.
+    pub fn at(file: i8, rank: i8) -> Option<Square> {
+        ((0..8).contains(&file) && (0..8).contains(&rank)).then(|| Square { value: (rank * 8 + file) as u8 })
+    }
+
+    pub fn file(self) -> i8 {
+        (self.value % 8) as i8
+    }
+
+    pub fn rank(self) -> i8 {
+        (self.value / 8) as i8
+    }
+
+    /// The square `df` files and `dr` ranks away, if it is on the board.
+    pub fn offset(self, df: i8, dr: i8) -> Option<Square> {
+        Square::at(self.file() + df, self.rank() + dr)
+    }
+
     /// Returns the bitboard corresponding to this square.
     pub fn bitboard(self) -> Bitboard {
         Bitboard(1u64 << self.value)
     }
 }
 
+const KNIGHT_DELTAS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_DELTAS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+fn leaps(s: Square, deltas: &[(i8, i8)]) -> Bitboard {
+    deltas
+        .iter()
+        .filter_map(|&(df, dr)| s.offset(df, dr))
+        .fold(Bitboard::EMPTY, |bb, to| bb | to.bitboard())
+}
+
+/// Squares reached along each direction up to and including the first occupied one.
+fn slides(s: Square, directions: &[(i8, i8)], occupied: Bitboard) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for &(df, dr) in directions {
+        let mut current = s.offset(df, dr);
+        while let Some(to) = current {
+            attacks = attacks | to.bitboard();
+            if occupied.contains(to) {
+                break;
+            }
+            current = to.offset(df, dr);
+        }
+    }
+    attacks
+}
+
+pub fn knight_attacks(s: Square) -> Bitboard {
+    leaps(s, &KNIGHT_DELTAS)
+}
+
+pub fn king_attacks(s: Square) -> Bitboard {
+    leaps(s, &KING_DELTAS)
+}
+
+/// Squares a pawn of `color` standing on `s` captures on.
+pub fn pawn_attacks(color: Color, s: Square) -> Bitboard {
+    let dr = match color {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+    leaps(s, &[(-1, dr), (1, dr)])
+}
+
+pub fn bishop_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    slides(s, &BISHOP_DIRECTIONS, occupied)
+}
+
+pub fn rook_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    slides(s, &ROOK_DIRECTIONS, occupied)
+}
+
+pub fn queen_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(s, occupied) | rook_attacks(s, occupied)
+}
+
+/// Squares strictly between `a` and `b` when they share a rank, file or diagonal;
+/// empty otherwise.
+pub fn between(a: Square, b: Square) -> Bitboard {
+    let (df, dr) = (b.file() - a.file(), b.rank() - a.rank());
+    if a == b || (df != 0 && dr != 0 && df.abs() != dr.abs()) {
+        return Bitboard::EMPTY;
+    }
+    let step = (df.signum(), dr.signum());
+    let mut squares = Bitboard::EMPTY;
+    let mut current = a.offset(step.0, step.1);
+    while let Some(s) = current.filter(|s| *s != b) {
+        squares = squares | s.bitboard();
+        current = s.offset(step.0, step.1);
+    }
+    squares
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Piece {
-    //  Write a function that, given a square, an attacking color, and an occupied bitboard,
-    //   returns a Bitboard representing all pieces of that color that can attack the square.
-    // - Consider all piece attack patterns: rook, bishop, knight, king, and pawn.
-    
     pub color: Color,
     pub role: Role,
 }
@@ -432,22 +529,53 @@ impl Board {
     }
 
 
-    // ISSUE #1: Implement the `attackers` function.
-    pub fn attackers() -> Bitboard {
-        //Write your code here
-        Bitboard::EMPTY // Temporary placeholder
-    }
-
-    /// Returns true if there is any attack on the square.
-    pub fn attacks() -> bool {
-         //Write your code here
-         false // Temporary placeholder
+    /// Pieces of `attacker` that attack `s`, with sliders blocked by `occupied`
+    /// rather than the board's own occupancy, so callers can look through pieces.
+    pub fn attackers(&self, s: Square, attacker: Color, occupied: Bitboard) -> Bitboard {
+        let diagonal = self.bishops() | self.queens();
+        let straight = self.rooks() | self.queens();
+        let attackers = (bishop_attacks(s, occupied) & diagonal)
+            | (rook_attacks(s, occupied) & straight)
+            | (knight_attacks(s) & self.knights())
+            | (king_attacks(s) & self.kings())
+            | (pawn_attacks(attacker.opposite(), s) & self.pawns());
+        attackers & self.color(attacker)
+    }
+
+    /// Returns true if any piece of `attacker` attacks the square.
+    pub fn attacks(&self, s: Square, attacker: Color) -> bool {
+        !self.attackers(s, attacker, self.occupied).is_empty()
+    }
+
+    /// Squares the piece on `s` attacks, empty when the square is empty.
+    pub fn attacks_from(&self, s: Square) -> Bitboard {
+        let Some(piece) = self.piece_at(s) else {
+            return Bitboard::EMPTY;
+        };
+        match piece.role {
+            Role::Pawn => pawn_attacks(piece.color, s),
+            Role::Knight => knight_attacks(s),
+            Role::Bishop => bishop_attacks(s, self.occupied),
+            Role::Rook => rook_attacks(s, self.occupied),
+            Role::Queen => queen_attacks(s, self.occupied),
+            Role::King => king_attacks(s),
+        }
     }
 
-    // ISSUE #2: Implement the `slider_blockers` function.
+    /// Pieces of `us` that are the only thing between `our_king` and an enemy
+    /// slider, and so are pinned to it.
     pub fn slider_blockers(&self, our_king: Square, us: Color) -> Bitboard {
-        //Write your code here
-        Bitboard::EMPTY // Temporary placeholder
+        let snipers = ((bishop_attacks(our_king, Bitboard::EMPTY) & (self.bishops() | self.queens()))
+            | (rook_attacks(our_king, Bitboard::EMPTY) & (self.rooks() | self.queens())))
+            & self.color(us.opposite());
+        snipers.to_squares().into_iter().fold(Bitboard::EMPTY, |blockers, sniper| {
+            let between = between(our_king, sniper) & self.occupied;
+            if between.count() == 1 {
+                blockers | (between & self.color(us))
+            } else {
+                blockers
+            }
+        })
     }
 
     /// Discards the piece on a given square.
@@ -529,182 +657,13 @@ impl Board {
         }
     }
 
-    // ISSUE #3 Implement the `move_piece` function.
-    //
+    /// Moves the piece on `orig` to the empty square `dest`.
     pub fn move_piece(&self, orig: Square, dest: Square) -> Option<Board> {
-        // First check if the destination square is occupied
         if self.is_occupied_square(dest) {
             return None;
         }
-        
-        // Get the piece at the origin square
-        let piece_opt = self.piece_at(orig);
-        if piece_opt.is_none() {
-            return None;
-        }
-        
-        let piece = piece_opt.unwrap();
-        let piece_color = piece.color;
-        
-        // Create a new board with the piece moved
-        let new_board = self.discard_by_square(orig).put_or_replace(piece, dest);
-        
-        // Find our king's position
-        let king_pos = new_board.king_pos_of(piece_color);
-        if king_pos.is_none() {
-            // If there's no king, just return the new board
-            return Some(new_board);
-        }
-        
-        let king_square = king_pos.unwrap();
-        
-        // Find all blockers between our king and attacking slider pieces
-        let blockers = find_slider_blockers(&new_board, king_square, piece_color);
-        
-        // Store the blockers information somewhere or use it for move validation
-        // For now, we'll just return the new board
-        Some(new_board)
-    }
-    
-    // Helper function to find slider blockers
-    fn find_slider_blockers(board: &Board, our_king: Square, us: Color) -> Bitboard {
-        let them = us.opposite();
-        let mut blockers = Bitboard::EMPTY;
-        
-        // Get enemy bishops, rooks, and queens (all slider pieces)
-        let enemy_bishops_and_queens = board.by_color.get(them) & (board.by_role.bishop | board.by_role.queen);
-        let enemy_rooks_and_queens = board.by_color.get(them) & (board.by_role.rook | board.by_role.queen);
-        
-        // Get all pieces except our king
-        let occupied_except_king = board.occupied ^ our_king.bitboard();
-        
-        // Check for potential bishop-like attackers (bishops and queens on diagonals)
-        let mut potential_bishop_attackers = enemy_bishops_and_queens.0;
-        while potential_bishop_attackers != 0 {
-            // Get the square of the potential attacker
-            let attacker_square = Square {
-                value: potential_bishop_attackers.trailing_zeros() as u8,
-            };
-            
-            // Check if the attacker is on the same diagonal or anti-diagonal as our king
-            let king_file = our_king.value % 8;
-            let king_rank = our_king.value / 8;
-            let attacker_file = attacker_square.value % 8;
-            let attacker_rank = attacker_square.value / 8;
-            
-            // Check if they're on the same diagonal or anti-diagonal
-            let file_diff = (attacker_file as i8 - king_file as i8).abs();
-            let rank_diff = (attacker_rank as i8 - king_rank as i8).abs();
-            
-            if file_diff == rank_diff {
-                // They're on the same diagonal or anti-diagonal
-                // Calculate the ray between them
-                let mut ray = Bitboard::EMPTY;
-                
-                // Determine the direction
-                let file_step = if attacker_file > king_file { 1 } else { -1 };
-                let rank_step = if attacker_rank > king_rank { 1 } else { -1 };
-                
-                // Start from the king and move towards the attacker
-                let mut current_file = king_file as i8 + file_step;
-                let mut current_rank = king_rank as i8 + rank_step;
-                
-                // Add all squares between king and attacker to the ray
-                while current_file >= 0 && current_file < 8 && 
-                      current_rank >= 0 && current_rank < 8 && 
-                      (current_file != attacker_file as i8 || current_rank != attacker_rank as i8) {
-                    let square = Square {
-                        value: (current_rank * 8 + current_file) as u8,
-                    };
-                    ray = ray | square.bitboard();
-                    
-                    current_file += file_step;
-                    current_rank += rank_step;
-                }
-                
-                // Check if there's exactly one piece on the ray
-                let pieces_on_ray = ray & occupied_except_king;
-                if pieces_on_ray.count() == 1 {
-                    // There's exactly one blocker
-                    let blocker = pieces_on_ray & board.by_color.get(us);
-                    if blocker.0 != 0 {
-                        // The blocker is our piece, so it's pinned
-                        blockers = blockers | blocker;
-                    }
-                }
-            }
-            
-            // Clear the least significant bit
-            potential_bishop_attackers &= potential_bishop_attackers - 1;
-        }
-        
-        // Check for potential rook-like attackers (rooks and queens on ranks/files)
-        let mut potential_rook_attackers = enemy_rooks_and_queens.0;
-        while potential_rook_attackers != 0 {
-            // Get the square of the potential attacker
-            let attacker_square = Square {
-                value: potential_rook_attackers.trailing_zeros() as u8,
-            };
-            
-            // Check if the attacker is on the same file or rank as our king
-            let king_file = our_king.value % 8;
-            let king_rank = our_king.value / 8;
-            let attacker_file = attacker_square.value % 8;
-            let attacker_rank = attacker_square.value / 8;
-            
-            // Check if they're on the same file or rank
-            if king_file == attacker_file || king_rank == attacker_rank {
-                // They're on the same file or rank
-                // Calculate the ray between them
-                let mut ray = Bitboard::EMPTY;
-                
-                if king_file == attacker_file {
-                    // Same file
-                    let rank_step = if attacker_rank > king_rank { 1 } else { -1 };
-                    let mut current_rank = king_rank as i8 + rank_step;
-                    
-                    // Add all squares between king and attacker to the ray
-                    while current_rank >= 0 && current_rank < 8 && current_rank != attacker_rank as i8 {
-                        let square = Square {
-                            value: (current_rank * 8 + king_file) as u8,
-                        };
-                        ray = ray | square.bitboard();
-                        
-                        current_rank += rank_step;
-                    }
-                } else {
-                    // Same rank
-                    let file_step = if attacker_file > king_file { 1 } else { -1 };
-                    let mut current_file = king_file as i8 + file_step;
-                    
-                    // Add all squares between king and attacker to the ray
-                    while current_file >= 0 && current_file < 8 && current_file != attacker_file as i8 {
-                        let square = Square {
-                            value: (king_rank * 8 + current_file) as u8,
-                        };
-                        ray = ray | square.bitboard();
-                        
-                        current_file += file_step;
-                    }
-                }
-                
-                // Check if there's exactly one piece on the ray
-                let pieces_on_ray = ray & occupied_except_king;
-                if pieces_on_ray.count() == 1 {
-                    // There's exactly one blocker
-                    let blocker = pieces_on_ray & board.by_color.get(us);
-                    if blocker.0 != 0 {
-                        // The blocker is our piece, so it's pinned
-                        blockers = blockers | blocker;
-                    }
-                }
-            }
-            
-            // Clear the least significant bit
-            potential_rook_attackers &= potential_rook_attackers - 1;
-        }
-        
-        blockers
+        let piece = self.piece_at(orig)?;
+        Some(self.discard_by_square(orig).put_or_replace(piece, dest))
     }
 
     // Implement the `taking` function.
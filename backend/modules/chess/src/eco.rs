@@ -0,0 +1,127 @@
+/// An ECO (Encyclopaedia of Chess Openings) classification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening {
+    pub eco: &'static str,
+    pub name: &'static str,
+}
+
+/// Known openings keyed by their main-line SAN moves. Classification picks the
+/// longest entry that prefixes the game, so more specific lines win.
+const OPENINGS: &[(&str, &str, &str)] = &[
+    ("A00", "Polish Opening", "b4"),
+    ("A01", "Nimzo-Larsen Attack", "b3"),
+    ("A02", "Bird's Opening", "f4"),
+    ("A04", "Reti Opening", "Nf3"),
+    ("A05", "Reti Opening", "Nf3 Nf6"),
+    ("A10", "English Opening", "c4"),
+    ("A20", "English Opening", "c4 e5"),
+    ("A30", "English Opening, Symmetrical Variation", "c4 c5"),
+    ("A40", "Queen's Pawn Game", "d4"),
+    ("A43", "Old Benoni Defense", "d4 c5"),
+    ("A45", "Indian Defense", "d4 Nf6"),
+    ("A46", "Indian Defense", "d4 Nf6 Nf3"),
+    ("A51", "Budapest Gambit", "d4 Nf6 c4 e5"),
+    ("A56", "Benoni Defense", "d4 Nf6 c4 c5"),
+    ("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    ("A80", "Dutch Defense", "d4 f5"),
+    ("B00", "King's Pawn Opening", "e4"),
+    ("B01", "Scandinavian Defense", "e4 d5"),
+    ("B02", "Alekhine's Defense", "e4 Nf6"),
+    ("B06", "Modern Defense", "e4 g6"),
+    ("B07", "Pirc Defense", "e4 d6 d4 Nf6"),
+    ("B10", "Caro-Kann Defense", "e4 c6"),
+    ("B12", "Caro-Kann Defense, Advance Variation", "e4 c6 d4 d5 e5"),
+    ("B20", "Sicilian Defense", "e4 c5"),
+    ("B21", "Sicilian Defense, Smith-Morra Gambit", "e4 c5 d4 cxd4 c3"),
+    ("B22", "Sicilian Defense, Alapin Variation", "e4 c5 c3"),
+    ("B23", "Sicilian Defense, Closed", "e4 c5 Nc3"),
+    ("B27", "Sicilian Defense", "e4 c5 Nf3"),
+    ("B30", "Sicilian Defense", "e4 c5 Nf3 Nc6"),
+    ("B32", "Sicilian Defense, Open", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4"),
+    ("B40", "Sicilian Defense, French Variation", "e4 c5 Nf3 e6"),
+    ("B50", "Sicilian Defense", "e4 c5 Nf3 d6"),
+    ("B54", "Sicilian Defense, Open", "e4 c5 Nf3 d6 d4 cxd4 Nxd4"),
+    ("B70", "Sicilian Defense, Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+    ("B90", "Sicilian Defense, Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    ("C00", "French Defense", "e4 e6"),
+    ("C02", "French Defense, Advance Variation", "e4 e6 d4 d5 e5"),
+    ("C03", "French Defense, Tarrasch Variation", "e4 e6 d4 d5 Nd2"),
+    ("C15", "French Defense, Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4"),
+    ("C20", "King's Pawn Game", "e4 e5"),
+    ("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    ("C25", "Vienna Game", "e4 e5 Nc3"),
+    ("C30", "King's Gambit", "e4 e5 f4"),
+    ("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    ("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    ("C41", "Philidor Defense", "e4 e5 Nf3 d6"),
+    ("C42", "Petrov's Defense", "e4 e5 Nf3 Nf6"),
+    ("C44", "King's Knight Opening, Normal Variation", "e4 e5 Nf3 Nc6"),
+    ("C44", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    ("C45", "Scotch Game", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4"),
+    ("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    ("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    ("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    ("C51", "Italian Game, Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4"),
+    ("C53", "Italian Game, Classical Variation", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3"),
+    ("C55", "Italian Game, Two Knights Defense", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+    ("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    ("C65", "Ruy Lopez, Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    ("C68", "Ruy Lopez, Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6"),
+    ("C70", "Ruy Lopez, Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4"),
+    ("C84", "Ruy Lopez, Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7"),
+    ("D00", "Queen's Pawn Game", "d4 d5"),
+    ("D02", "Queen's Pawn Game", "d4 d5 Nf3"),
+    ("D06", "Queen's Gambit", "d4 d5 c4"),
+    ("D07", "Queen's Gambit Declined, Chigorin Defense", "d4 d5 c4 Nc6"),
+    ("D08", "Queen's Gambit Declined, Albin Countergambit", "d4 d5 c4 e5"),
+    ("D10", "Slav Defense", "d4 d5 c4 c6"),
+    ("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    ("D43", "Semi-Slav Defense", "d4 d5 c4 e6 Nf3 Nf6 Nc3 c6"),
+    ("D80", "Grunfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+    ("E00", "Queen's Pawn Game", "d4 Nf6 c4 e6"),
+    ("E01", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    ("E10", "Queen's Pawn Game", "d4 Nf6 c4 e6 Nf3"),
+    ("E12", "Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+    ("E20", "Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    ("E60", "King's Indian Defense", "d4 Nf6 c4 g6"),
+    ("E61", "King's Indian Defense", "d4 Nf6 c4 g6 Nc3 Bg7"),
+];
+
+/// Strips check, mate and annotation suffixes so `Bb5+` and `Bb5` compare equal.
+fn normalize_san(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// Classifies a game from its SAN move list, returning the most specific known
+/// opening, or `None` when even the first move is not covered.
+pub fn classify<S: AsRef<str>>(moves: &[S]) -> Option<Opening> {
+    let mut best: Option<(usize, Opening)> = None;
+
+    for (eco, name, line) in OPENINGS {
+        let line_moves: Vec<&str> = line.split_whitespace().collect();
+        if line_moves.len() > moves.len() {
+            continue;
+        }
+
+        let matches = line_moves
+            .iter()
+            .zip(moves.iter())
+            .all(|(expected, played)| *expected == normalize_san(played.as_ref()));
+
+        if matches && best.is_none_or(|(len, _)| line_moves.len() > len) {
+            best = Some((line_moves.len(), Opening { eco, name }));
+        }
+    }
+
+    best.map(|(_, opening)| opening)
+}
+
+/// Returns true if `code` is a well-formed ECO code such as `B20`.
+pub fn is_valid_code(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    bytes.len() == 3
+        && (b'A'..=b'E').contains(&bytes[0])
+        && bytes[1].is_ascii_digit()
+        && bytes[2].is_ascii_digit()
+}
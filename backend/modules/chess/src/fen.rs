@@ -0,0 +1,307 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::bitboard::Board::{Board, Color, Piece, Role, Square};
+
+/// The standard starting position.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Past 150 plies without a capture or pawn move the seventy-five-move rule has
+/// already ended the game, so no live position can carry a larger clock.
+pub const MAX_HALFMOVE_CLOCK: u32 = 150;
+
+/// How move counter inconsistencies are treated on import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    /// Accept the position and report the issues as warnings.
+    Lenient,
+    /// Reject the position.
+    Strict,
+}
+
+/// A halfmove clock or fullmove number that cannot occur in a real game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CounterIssue {
+    /// The fullmove number starts at 1.
+    FullmoveZero,
+    /// An en passant square means the last move was a pawn push, which resets the clock.
+    HalfmoveAfterPawnMove { halfmove_clock: u32 },
+    /// The clock is beyond the seventy-five-move limit.
+    HalfmoveBeyondLimit { halfmove_clock: u32 },
+    /// The clock counts more plies than the fullmove number says were played.
+    HalfmoveExceedsPliesPlayed { halfmove_clock: u32, plies_played: u32 },
+}
+
+impl fmt::Display for CounterIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CounterIssue::FullmoveZero => write!(f, "fullmove number must be at least 1"),
+            CounterIssue::HalfmoveAfterPawnMove { halfmove_clock } => write!(
+                f,
+                "halfmove clock is {} but the en passant square shows the last move was a pawn push",
+                halfmove_clock
+            ),
+            CounterIssue::HalfmoveBeyondLimit { halfmove_clock } => write!(
+                f,
+                "halfmove clock {} exceeds the limit of {}",
+                halfmove_clock, MAX_HALFMOVE_CLOCK
+            ),
+            CounterIssue::HalfmoveExceedsPliesPlayed { halfmove_clock, plies_played } => write!(
+                f,
+                "halfmove clock {} exceeds the {} plies implied by the fullmove number",
+                halfmove_clock, plies_played
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The string is not structurally a FEN.
+    Malformed(String),
+    /// The position is well-formed but its move counters are impossible.
+    InconsistentCounters(Vec<CounterIssue>),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::Malformed(reason) => write!(f, "malformed FEN: {}", reason),
+            FenError::InconsistentCounters(issues) => {
+                let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+                write!(f, "inconsistent move counters: {}", issues.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// A structurally valid FEN record.
+#[derive(Debug, Clone)]
+pub struct Fen {
+    pub board: Board,
+    pub side_to_move: Color,
+    pub castling: String,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Fen {
+    /// Plies played since the start of the game according to the fullmove number.
+    pub fn plies_played(&self) -> u32 {
+        let black_to_move = matches!(self.side_to_move, Color::Black) as u32;
+        self.fullmove_number.saturating_sub(1) * 2 + black_to_move
+    }
+
+    /// Checks the halfmove clock and fullmove number against the rest of the record.
+    pub fn counter_issues(&self) -> Vec<CounterIssue> {
+        let mut issues = Vec::new();
+        let halfmove_clock = self.halfmove_clock;
+
+        if self.fullmove_number == 0 {
+            issues.push(CounterIssue::FullmoveZero);
+        }
+        if self.en_passant.is_some() && halfmove_clock != 0 {
+            issues.push(CounterIssue::HalfmoveAfterPawnMove { halfmove_clock });
+        }
+        if halfmove_clock > MAX_HALFMOVE_CLOCK {
+            issues.push(CounterIssue::HalfmoveBeyondLimit { halfmove_clock });
+        }
+        let plies_played = self.plies_played();
+        if self.fullmove_number > 0 && halfmove_clock > plies_played {
+            issues.push(CounterIssue::HalfmoveExceedsPliesPlayed { halfmove_clock, plies_played });
+        }
+
+        issues
+    }
+}
+
+impl FromStr for Fen {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        let malformed = |reason: &str| FenError::Malformed(reason.to_string());
+
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 {
+            return Err(malformed("expected 6 space-separated fields"));
+        }
+
+        let board = parse_placement(fields[0])?;
+
+        let side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(malformed("side to move must be 'w' or 'b'")),
+        };
+
+        let castling = fields[2];
+        let mut seen = String::new();
+        if castling != "-" {
+            // Chess960 positions may name the castling rook by its file instead
+            for c in castling.chars() {
+                if !"KQkqABCDEFGHabcdefgh".contains(c) || seen.contains(c) {
+                    return Err(malformed("castling rights must be '-', a subset of 'KQkq' or rook files"));
+                }
+                seen.push(c);
+            }
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                let bytes = square.as_bytes();
+                // The capturable pawn just moved, so the target is behind the side not to move.
+                let expected_rank = match side_to_move {
+                    Color::White => b'6',
+                    Color::Black => b'3',
+                };
+                if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || bytes[1] != expected_rank {
+                    return Err(malformed("en passant square does not match the side to move"));
+                }
+                Some(Square { value: (bytes[1] - b'1') * 8 + (bytes[0] - b'a') })
+            }
+        };
+
+        let halfmove_clock = fields[4]
+            .parse()
+            .map_err(|_| malformed("halfmove clock must be a non-negative integer"))?;
+        let fullmove_number = fields[5]
+            .parse()
+            .map_err(|_| malformed("fullmove number must be a non-negative integer"))?;
+
+        Ok(Fen {
+            board,
+            side_to_move,
+            castling: castling.to_string(),
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+}
+
+fn parse_placement(placement: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::Malformed("piece placement must have 8 ranks".to_string()));
+    }
+
+    let mut board = Board::empty();
+    // FEN lists rank 8 first.
+    for (i, rank) in ranks.iter().enumerate() {
+        let rank_index = 7 - i as u8;
+        let mut file: u8 = 0;
+        for c in rank.chars() {
+            if file >= 8 {
+                file = 9;
+                break;
+            }
+            if let Some(skip) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                file += skip as u8;
+            } else {
+                let piece = piece_from_char(c).ok_or_else(|| {
+                    FenError::Malformed(format!("unknown piece '{}'", c))
+                })?;
+                board = board.put_or_replace(piece, Square { value: rank_index * 8 + file });
+                file += 1;
+            }
+        }
+        if file != 8 {
+            return Err(FenError::Malformed(format!("rank {} does not have 8 files", rank_index + 1)));
+        }
+    }
+
+    for color in [Color::White, Color::Black] {
+        if board.king_of(color).count() != 1 {
+            return Err(FenError::Malformed("each side must have exactly one king".to_string()));
+        }
+    }
+
+    Ok(board)
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let role = match c.to_ascii_lowercase() {
+        'p' => Role::Pawn,
+        'n' => Role::Knight,
+        'b' => Role::Bishop,
+        'r' => Role::Rook,
+        'q' => Role::Queen,
+        'k' => Role::King,
+        _ => return None,
+    };
+    let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+    Some(Piece { color, role })
+}
+
+/// Number of distinct Chess960 starting positions.
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// The Chess960 start with Scharnagl number `number` (0 to 959); 518 is the standard
+/// starting position. Bishops stand on opposite colours and the king between the
+/// rooks. Both rooks start outermost, so the `KQkq` castling letters are unambiguous.
+///
+/// # Panics
+///
+/// If `number` is not below [`CHESS960_POSITIONS`].
+pub fn chess960_start_fen(number: u16) -> String {
+    assert!(number < CHESS960_POSITIONS, "Chess960 positions are numbered 0 to 959");
+    // Pairs of free squares the knights take, indexed by what is left of the number
+    const KNIGHTS: [(usize, usize); 10] =
+        [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+    let mut rank: [Option<char>; 8] = [None; 8];
+    let mut n = number as usize;
+    rank[(n % 4) * 2 + 1] = Some('B');
+    n /= 4;
+    rank[(n % 4) * 2] = Some('B');
+    n /= 4;
+
+    let free = |rank: &[Option<char>; 8]| -> Vec<usize> { (0..8).filter(|&file| rank[file].is_none()).collect() };
+    rank[free(&rank)[n % 6]] = Some('Q');
+    let (first, second) = KNIGHTS[n / 6];
+    let squares = free(&rank);
+    rank[squares[first]] = Some('N');
+    rank[squares[second]] = Some('N');
+
+    let mut rest = ['R', 'K', 'R'].into_iter();
+    let rank: String = rank.iter().map(|square| square.unwrap_or_else(|| rest.next().unwrap())).collect();
+    format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", rank.to_lowercase(), rank)
+}
+
+/// A random Chess960 start, drawn uniformly from all 960.
+pub fn generate_chess960_fen() -> String {
+    chess960_start_fen(rand::thread_rng().gen_range(0..CHESS960_POSITIONS))
+}
+
+/// The FEN of the board alone, without crazyhouse pockets. Pockets are written either
+/// in brackets after the placement (`.../RNBQKBNR[Nq] w ...`) or as a ninth rank; the
+/// pieces on the board are the same either way. Other FENs are returned unchanged.
+pub fn without_pockets(fen: &str) -> String {
+    let Some((placement, rest)) = fen.split_once(' ') else { return fen.to_string() };
+    let placement = match placement.find('[') {
+        Some(bracket) => &placement[..bracket],
+        None if placement.split('/').count() == 9 => {
+            placement.rsplit_once('/').map_or(placement, |(board, _)| board)
+        }
+        None => placement,
+    };
+    format!("{} {}", placement, rest)
+}
+
+/// Parses `fen` and checks its move counters. Under `Strictness::Lenient` counter
+/// issues are returned as warnings; under `Strictness::Strict` they reject the position.
+pub fn validate(fen: &str, strictness: Strictness) -> Result<Vec<CounterIssue>, FenError> {
+    let parsed: Fen = fen.parse()?;
+    let issues = parsed.counter_issues();
+
+    match strictness {
+        Strictness::Strict if !issues.is_empty() => Err(FenError::InconsistentCounters(issues)),
+        _ => Ok(issues),
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use crate::fen::FenError;
+use crate::position::{Move, MoveError, Position};
+
+/// Plies without a capture or pawn move after which either player may claim a draw.
+pub const FIFTY_MOVE_PLIES: u32 = 100;
+/// Occurrences of the same position after which either player may claim a draw.
+pub const THREEFOLD_OCCURRENCES: usize = 3;
+
+/// Grounds on which a player can claim a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawClaim {
+    Repetition,
+    FiftyMove,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    StartingPosition(FenError),
+    /// The move at `ply` (0-based) cannot be played.
+    Move { ply: usize, error: MoveError },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::StartingPosition(err) => write!(f, "invalid starting position: {}", err),
+            ReplayError::Move { ply, error } => write!(f, "ply {}: {}", ply + 1, error),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Every position reached in a game, rebuilt from its starting position and move list.
+#[derive(Debug, Clone)]
+pub struct GameHistory {
+    positions: Vec<Position>,
+    moves: Vec<Move>,
+}
+
+impl GameHistory {
+    pub fn new(start: Position) -> Self {
+        GameHistory { positions: vec![start], moves: Vec::new() }
+    }
+
+    /// Replays `moves` (in SAN) from `start_fen`.
+    pub fn replay<S: AsRef<str>>(start_fen: &str, moves: &[S]) -> Result<Self, ReplayError> {
+        let start = start_fen.parse().map_err(ReplayError::StartingPosition)?;
+        let mut history = GameHistory::new(start);
+        for (ply, san) in moves.iter().enumerate() {
+            history
+                .push_san(san.as_ref())
+                .map_err(|error| ReplayError::Move { ply, error })?;
+        }
+        Ok(history)
+    }
+
+    /// Plays a SAN move on the current position.
+    pub fn push_san(&mut self, san: &str) -> Result<Move, MoveError> {
+        let mv = self.current().parse_san(san)?;
+        self.push(mv)?;
+        Ok(mv)
+    }
+
+    pub fn push(&mut self, mv: Move) -> Result<(), MoveError> {
+        let next = self.current().play(&mv)?;
+        self.positions.push(next);
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    pub fn current(&self) -> &Position {
+        self.positions.last().expect("history always holds its starting position")
+    }
+
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// How many times the current position has occurred, including now.
+    pub fn repetitions(&self) -> usize {
+        let key = self.current().key();
+        self.positions.iter().filter(|p| p.key() == key).count()
+    }
+
+    /// Whether `claim` is valid in the current position.
+    pub fn can_claim(&self, claim: DrawClaim) -> bool {
+        match claim {
+            DrawClaim::Repetition => self.repetitions() >= THREEFOLD_OCCURRENCES,
+            DrawClaim::FiftyMove => self.current().halfmove_clock >= FIFTY_MOVE_PLIES,
+        }
+    }
+}
@@ -1,2 +1,9 @@
 pub mod bitboard;
-pub mod time_control; // Add this line
\ No newline at end of file
+pub mod eco;
+pub mod fen;
+pub mod history;
+pub mod material;
+pub mod pgn;
+pub mod position;
+pub mod time_control; // Add this line
+pub mod variant;
\ No newline at end of file
@@ -0,0 +1,83 @@
+//! Material on the board and the pieces each side has taken.
+
+use crate::bitboard::Board::{Color, Role};
+use crate::history::GameHistory;
+use crate::position::{MoveKind, Position};
+
+/// Conventional value of a piece in pawns; kings are not counted.
+pub fn piece_value(role: Role) -> i32 {
+    match role {
+        Role::Pawn => 1,
+        Role::Knight | Role::Bishop => 3,
+        Role::Rook => 5,
+        Role::Queen => 9,
+        Role::King => 0,
+    }
+}
+
+const ROLES: [Role; 5] = [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen];
+
+/// Value of the pieces `color` has on the board.
+fn board_value(position: &Position, color: Color) -> i32 {
+    let pieces = position.board.by_role_of(color);
+    ROLES
+        .iter()
+        .map(|&role| pieces.get(role).count() as i32 * piece_value(role))
+        .sum()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Material {
+    /// Pieces White has captured, in the order they were taken. In variants with
+    /// pockets this is White's pocket, where a captured promoted piece is a pawn.
+    pub captured_by_white: Vec<Role>,
+    pub captured_by_black: Vec<Role>,
+    /// White's material minus Black's, in pawns. Pockets count as material.
+    pub balance: i32,
+}
+
+impl Material {
+    /// Material of a position whose move history is unknown: the balance on the
+    /// board, with no captures.
+    pub fn of_position(position: &Position) -> Self {
+        Material {
+            balance: board_value(position, Color::White) - board_value(position, Color::Black),
+            ..Material::default()
+        }
+    }
+
+    /// Material after the moves of `history`. With `pockets`, as in crazyhouse,
+    /// captured pieces are held by the capturer and count towards their material.
+    pub fn of(history: &GameHistory, pockets: bool) -> Self {
+        let mut material = Material::of_position(history.current());
+        // Squares holding a piece that was promoted from a pawn
+        let mut promoted = 0u64;
+        for (position, mv) in history.positions().iter().zip(history.moves()) {
+            if let Some(mut captured) = mv.capture {
+                let at = 1u64 << mv.to.value;
+                if mv.kind != MoveKind::EnPassant && promoted & at != 0 {
+                    promoted &= !at;
+                    if pockets {
+                        captured = Role::Pawn;
+                    }
+                }
+                match position.turn {
+                    Color::White => material.captured_by_white.push(captured),
+                    Color::Black => material.captured_by_black.push(captured),
+                }
+            }
+            let from = 1u64 << mv.from.value;
+            let to = 1u64 << mv.to.value;
+            if promoted & from != 0 || mv.promotion.is_some() {
+                promoted |= to;
+            }
+            promoted &= !from;
+        }
+
+        if pockets {
+            let pocket = |captured: &[Role]| captured.iter().map(|&role| piece_value(role)).sum::<i32>();
+            material.balance += pocket(&material.captured_by_white) - pocket(&material.captured_by_black);
+        }
+        material
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use crate::fen::STARTING_FEN;
+
+/// Tags every PGN game carries, in the order the standard requires.
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Movetext lines are wrapped before this many characters.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// A single game in export format. `Display` writes the tag pairs, a blank line and
+/// the wrapped movetext ending in the result, so games can be concatenated with a
+/// blank line between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    moves: Vec<String>,
+    result: String,
+    starting_fen: Option<String>,
+}
+
+impl PgnGame {
+    /// `result` is one of `1-0`, `0-1`, `1/2-1/2` or `*` for unfinished games.
+    pub fn new(result: &str) -> Self {
+        Self {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: result.to_string(),
+            starting_fen: None,
+        }
+    }
+
+    /// Sets a tag, replacing any previous value. `Result`, `SetUp` and `FEN` are
+    /// derived from the game and cannot be set directly.
+    pub fn tag(mut self, name: &str, value: impl Into<String>) -> Self {
+        if matches!(name, "Result" | "SetUp" | "FEN") {
+            return self;
+        }
+        let value = value.into();
+        match self.tags.iter_mut().find(|(existing, _)| existing == name) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+        self
+    }
+
+    /// SAN moves from the starting position.
+    pub fn moves<S: AsRef<str>>(mut self, moves: &[S]) -> Self {
+        self.moves = moves.iter().map(|m| m.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Position the moves start from; the standard position needs no `FEN` tag.
+    pub fn starting_fen(mut self, fen: &str) -> Self {
+        self.starting_fen = (fen != STARTING_FEN).then(|| fen.to_string());
+        self
+    }
+
+    fn tag_value(&self, name: &str) -> &str {
+        if name == "Result" {
+            return &self.result;
+        }
+        self.tags
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("?")
+    }
+
+    /// Movetext tokens: move numbers, SAN moves and the result.
+    fn tokens(&self) -> Vec<String> {
+        let (mut number, mut white_to_move) = match &self.starting_fen {
+            Some(fen) => {
+                let fields: Vec<&str> = fen.split_whitespace().collect();
+                let number = fields.get(5).and_then(|n| n.parse().ok()).unwrap_or(1u32);
+                (number, fields.get(1) != Some(&"b"))
+            }
+            None => (1, true),
+        };
+
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 / 2 + 2);
+        for (ply, san) in self.moves.iter().enumerate() {
+            if white_to_move {
+                tokens.push(format!("{}.", number));
+            } else if ply == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(san.clone());
+            if !white_to_move {
+                number += 1;
+            }
+            white_to_move = !white_to_move;
+        }
+        tokens.push(self.result.clone());
+        tokens
+    }
+}
+
+/// Escapes a tag value for the quoted PGN string token.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in SEVEN_TAG_ROSTER {
+            writeln!(f, "[{} \"{}\"]", name, escape(self.tag_value(name)))?;
+        }
+        if let Some(fen) = &self.starting_fen {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", escape(fen))?;
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+            }
+        }
+        writeln!(f)?;
+
+        let mut line_length = 0;
+        for token in self.tokens() {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            }
+            if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", token)?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
@@ -0,0 +1,733 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bitboard::Board::{
+    Bitboard, Board, Color, Piece, Role, Square, bishop_attacks, king_attacks, knight_attacks, pawn_attacks,
+    queen_attacks, rook_attacks,
+};
+use crate::fen::{Fen, FenError, STARTING_FEN};
+
+const PROMOTION_ROLES: [Role; 4] = [Role::Queen, Role::Rook, Role::Bishop, Role::Knight];
+
+fn file_of(s: Square) -> i8 {
+    s.file()
+}
+
+fn rank_of(s: Square) -> i8 {
+    s.rank()
+}
+
+fn square_at(file: i8, rank: i8) -> Option<Square> {
+    Square::at(file, rank)
+}
+
+/// Algebraic name of a square, e.g. `e4`.
+pub fn square_name(s: Square) -> String {
+    format!("{}{}", (b'a' + s.value % 8) as char, s.value / 8 + 1)
+}
+
+/// Parses an algebraic square name such as `e4`.
+pub fn parse_square(name: &str) -> Option<Square> {
+    match name.as_bytes() {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => square_at((file - b'a') as i8, (rank - b'1') as i8),
+        _ => None,
+    }
+}
+
+fn role_letter(role: Role) -> char {
+    match role {
+        Role::Pawn => 'P',
+        Role::Knight => 'N',
+        Role::Bishop => 'B',
+        Role::Rook => 'R',
+        Role::Queen => 'Q',
+        Role::King => 'K',
+    }
+}
+
+fn role_from_letter(c: char) -> Option<Role> {
+    match c.to_ascii_uppercase() {
+        'N' => Some(Role::Knight),
+        'B' => Some(Role::Bishop),
+        'R' => Some(Role::Rook),
+        'Q' => Some(Role::Queen),
+        'K' => Some(Role::King),
+        _ => None,
+    }
+}
+
+fn pawn_direction(color: Color) -> i8 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+fn back_rank(color: Color) -> i8 {
+    match color {
+        Color::White => 0,
+        Color::Black => 7,
+    }
+}
+
+/// Files of the back-rank rooks of `color` on `board`.
+fn back_rank_rooks(board: &Board, color: Color) -> impl Iterator<Item = i8> {
+    let rank = back_rank(color);
+    (0..8).filter(move |&file| {
+        square_at(file, rank).and_then(|s| board.piece_at(s)) == Some(Piece { color, role: Role::Rook })
+    })
+}
+
+/// Castling availability, as the file of the rook each side may still castle with.
+/// Standard chess starts them on the a- and h-files; in Chess960 they start wherever
+/// the back rank was shuffled to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CastlingRights {
+    pub white_king_side: Option<i8>,
+    pub white_queen_side: Option<i8>,
+    pub black_king_side: Option<i8>,
+    pub black_queen_side: Option<i8>,
+}
+
+impl CastlingRights {
+    /// Reads `KQkq` as the outermost rook on each side of the king, as X-FEN does,
+    /// and a file letter (`HAha`, as in Shredder-FEN) as the rook on that file.
+    /// Rights without a king on its back rank or a rook to castle with are dropped.
+    fn from_fen(castling: &str, board: &Board) -> Self {
+        let mut rights = CastlingRights::default();
+        for c in castling.chars() {
+            let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let Some(king) = board.king_pos_of(color).filter(|&king| rank_of(king) == back_rank(color)) else {
+                continue;
+            };
+            let king_file = file_of(king);
+            let mut rooks = back_rank_rooks(board, color);
+            let (king_side, rook) = match c.to_ascii_lowercase() {
+                'k' => (true, rooks.filter(|&file| file > king_file).max()),
+                'q' => (false, rooks.find(|&file| file < king_file)),
+                letter @ 'a'..='h' => {
+                    let file = letter as i8 - 'a' as i8;
+                    (file > king_file, rooks.find(|&rook| rook == file))
+                }
+                _ => continue,
+            };
+            *rights.side_mut(color, king_side) = rook;
+        }
+        rights
+    }
+
+    /// Writes each right as `KQkq` when its rook is the outermost one on that side,
+    /// and by the rook's file otherwise.
+    fn to_fen(self, board: &Board) -> String {
+        let mut rights = String::new();
+        for (color, king_side) in [(Color::White, true), (Color::White, false), (Color::Black, true), (Color::Black, false)] {
+            let Some(file) = self.rook_file(color, king_side) else { continue };
+            let outermost = back_rank_rooks(board, color).all(|rook| if king_side { rook <= file } else { rook >= file });
+            let letter = match (outermost, king_side) {
+                (true, true) => 'k',
+                (true, false) => 'q',
+                (false, _) => (b'a' + file as u8) as char,
+            };
+            rights.push(match color {
+                Color::White => letter.to_ascii_uppercase(),
+                Color::Black => letter,
+            });
+        }
+        if rights.is_empty() { "-".to_string() } else { rights }
+    }
+
+    /// File of the rook `color` may castle with on the given side.
+    pub fn rook_file(self, color: Color, king_side: bool) -> Option<i8> {
+        match (color, king_side) {
+            (Color::White, true) => self.white_king_side,
+            (Color::White, false) => self.white_queen_side,
+            (Color::Black, true) => self.black_king_side,
+            (Color::Black, false) => self.black_queen_side,
+        }
+    }
+
+    fn side_mut(&mut self, color: Color, king_side: bool) -> &mut Option<i8> {
+        match (color, king_side) {
+            (Color::White, true) => &mut self.white_king_side,
+            (Color::White, false) => &mut self.white_queen_side,
+            (Color::Black, true) => &mut self.black_king_side,
+            (Color::Black, false) => &mut self.black_queen_side,
+        }
+    }
+
+    /// Drops both rights of `color`, whose king has moved.
+    fn clear(&mut self, color: Color) {
+        *self.side_mut(color, true) = None;
+        *self.side_mut(color, false) = None;
+    }
+
+    /// Drops the rights that depend on a rook standing on `s`.
+    fn touch(&mut self, s: Square) {
+        for color in [Color::White, Color::Black] {
+            if rank_of(s) != back_rank(color) {
+                continue;
+            }
+            for king_side in [true, false] {
+                let right = self.side_mut(color, king_side);
+                if *right == Some(file_of(s)) {
+                    *right = None;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    Normal,
+    EnPassant,
+    Castle { king_side: bool },
+}
+
+/// A legal move in a particular position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub role: Role,
+    pub from: Square,
+    pub to: Square,
+    pub capture: Option<Role>,
+    pub promotion: Option<Role>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    /// Long algebraic notation as used by UCI engines, e.g. `e2e4` or `e7e8q`.
+    pub fn to_uci(&self) -> String {
+        let promotion = self
+            .promotion
+            .map(|role| role_letter(role).to_ascii_lowercase().to_string())
+            .unwrap_or_default();
+        format!("{}{}{}", square_name(self.from), square_name(self.to), promotion)
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.capture.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    /// The text is not a move in any notation we accept.
+    Unparseable(String),
+    /// Well-formed, but not legal in the position.
+    Illegal(String),
+    /// Matches more than one legal move.
+    Ambiguous(String),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::Unparseable(mv) => write!(f, "'{}' is not a valid move", mv),
+            MoveError::Illegal(mv) => write!(f, "'{}' is not legal in this position", mv),
+            MoveError::Ambiguous(mv) => write!(f, "'{}' is ambiguous in this position", mv),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Identifies a position for repetition purposes: same placement, side to move,
+/// castling rights and en passant possibilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PositionKey {
+    pieces: [u64; 8],
+    white_to_move: bool,
+    castling: CastlingRights,
+    en_passant: Option<u8>,
+}
+
+/// A full game state that can generate and play legal moves.
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub board: Board,
+    pub turn: Color,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        STARTING_FEN.parse().expect("starting position is valid")
+    }
+}
+
+impl From<Fen> for Position {
+    fn from(fen: Fen) -> Self {
+        Position {
+            board: fen.board,
+            turn: fen.side_to_move,
+            castling: CastlingRights::from_fen(&fen.castling, &fen.board),
+            en_passant: fen.en_passant,
+            halfmove_clock: fen.halfmove_clock,
+            fullmove_number: fen.fullmove_number,
+        }
+    }
+}
+
+impl FromStr for Position {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        fen.parse::<Fen>().map(Position::from)
+    }
+}
+
+impl Position {
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.board.king_pos_of(color)
+    }
+
+    /// Whether any piece of `by` attacks `target`.
+    pub fn is_attacked(&self, target: Square, by: Color) -> bool {
+        self.board.attacks(target, by)
+    }
+
+    /// Whether the side to move is in check.
+    pub fn is_check(&self) -> bool {
+        self.checked_king().is_some()
+    }
+
+    /// Square of the side to move's king when it is in check, for highlighting.
+    pub fn checked_king(&self) -> Option<Square> {
+        self.king_square(self.turn)
+            .filter(|&king| self.is_attacked(king, self.turn.opposite()))
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let us = self.turn;
+        let board = &self.board;
+        let mut moves = Vec::new();
+
+        for from in board.color(us).to_squares() {
+            let Some(piece) = board.piece_at(from) else { continue };
+            let targets = match piece.role {
+                Role::Pawn => {
+                    self.pawn_moves(from, &mut moves);
+                    continue;
+                }
+                Role::Knight => knight_attacks(from),
+                Role::Bishop => bishop_attacks(from, board.occupied),
+                Role::Rook => rook_attacks(from, board.occupied),
+                Role::Queen => queen_attacks(from, board.occupied),
+                Role::King => king_attacks(from),
+            };
+            for to in (targets & !board.color(us)).to_squares() {
+                moves.push(Move {
+                    role: piece.role,
+                    from,
+                    to,
+                    capture: board.role_at(to),
+                    promotion: None,
+                    kind: MoveKind::Normal,
+                });
+            }
+        }
+
+        self.castling_moves(&mut moves);
+        moves
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let us = self.turn;
+        let dir = pawn_direction(us);
+        let last_rank = back_rank(us.opposite());
+        let mut push = |to: Square, capture: Option<Role>, kind: MoveKind| {
+            let base = Move { role: Role::Pawn, from, to, capture, promotion: None, kind };
+            if rank_of(to) == last_rank {
+                moves.extend(PROMOTION_ROLES.iter().map(|&role| Move { promotion: Some(role), ..base }));
+            } else {
+                moves.push(base);
+            }
+        };
+
+        let empty = |s: &Square| !self.board.is_occupied_square(*s);
+        if let Some(one) = from.offset(0, dir).filter(empty) {
+            push(one, None, MoveKind::Normal);
+            let two = from.offset(0, 2 * dir).filter(empty);
+            if let Some(two) = two.filter(|_| rank_of(from) == back_rank(us) + dir) {
+                push(two, None, MoveKind::Normal);
+            }
+        }
+
+        let en_passant = self.en_passant.map_or(Bitboard::EMPTY, Square::bitboard);
+        let captures = pawn_attacks(us, from) & (self.board.color(us.opposite()) | en_passant);
+        for to in captures.to_squares() {
+            match self.board.role_at(to) {
+                Some(role) => push(to, Some(role), MoveKind::Normal),
+                None => push(to, Some(Role::Pawn), MoveKind::EnPassant),
+            }
+        }
+    }
+
+    /// Castling in standard chess and Chess960 alike: the king ends on the g- or
+    /// c-file and the rook next to it on the f- or d-file, wherever they started.
+    fn castling_moves(&self, moves: &mut Vec<Move>) {
+        let us = self.turn;
+        let rank = back_rank(us);
+        let Some(king) = self.king_square(us).filter(|&king| rank_of(king) == rank) else { return };
+        if self.is_check() {
+            return;
+        }
+        // Attacks along the back rank are not blocked by the king that is leaving it
+        let without_king = self.board.discard_by_square(king);
+        let span = |a: i8, b: i8| a.min(b)..=a.max(b);
+
+        for king_side in [true, false] {
+            let Some(rook) = self.castling.rook_file(us, king_side).and_then(|file| square_at(file, rank)) else {
+                continue;
+            };
+            if self.board.piece_at(rook) != Some(Piece { color: us, role: Role::Rook }) {
+                continue;
+            }
+            let (king_to, rook_to) = if king_side { (6, 5) } else { (2, 3) };
+            let clear = span(file_of(king), king_to)
+                .chain(span(file_of(rook), rook_to))
+                .filter_map(|f| square_at(f, rank))
+                .all(|s| s == king || s == rook || !self.board.is_occupied_square(s));
+            let safe = span(file_of(king), king_to)
+                .filter_map(|f| square_at(f, rank))
+                .all(|s| !without_king.attacks(s, us.opposite()));
+            let to = square_at(king_to, rank);
+            if let Some(to) = to.filter(|_| clear && safe) {
+                moves.push(Move {
+                    role: Role::King,
+                    from: king,
+                    to,
+                    capture: None,
+                    promotion: None,
+                    kind: MoveKind::Castle { king_side },
+                });
+            }
+        }
+    }
+
+    /// All legal moves for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let us = self.turn;
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let after = self.play_unchecked(mv);
+                after
+                    .king_square(us)
+                    .is_none_or(|king| !after.is_attacked(king, us.opposite()))
+            })
+            .collect()
+    }
+
+    fn play_unchecked(&self, mv: &Move) -> Position {
+        let us = self.turn;
+        let mut board = self.board.discard_by_square(mv.from);
+
+        let en_passant_victim = square_at(file_of(mv.to), rank_of(mv.from));
+        if let Some(captured) = en_passant_victim.filter(|_| mv.kind == MoveKind::EnPassant) {
+            board = board.discard_by_square(captured);
+        }
+        if let MoveKind::Castle { king_side } = mv.kind {
+            let rank = back_rank(us);
+            let rook_from = self.castling.rook_file(us, king_side).and_then(|file| square_at(file, rank));
+            let rook_to = square_at(if king_side { 5 } else { 3 }, rank);
+            if let (Some(rook_from), Some(rook_to)) = (rook_from, rook_to) {
+                board = board
+                    .discard_by_square(rook_from)
+                    .put_or_replace(Piece { color: us, role: Role::Rook }, rook_to);
+            }
+        }
+        board = board.put_or_replace(Piece { color: us, role: mv.promotion.unwrap_or(mv.role) }, mv.to);
+
+        let mut castling = self.castling;
+        if mv.role == Role::King {
+            castling.clear(us);
+        }
+        castling.touch(mv.from);
+        castling.touch(mv.to);
+
+        let double_push = mv.role == Role::Pawn && (rank_of(mv.to) - rank_of(mv.from)).abs() == 2;
+        let en_passant = double_push
+            .then(|| square_at(file_of(mv.from), (rank_of(mv.from) + rank_of(mv.to)) / 2))
+            .flatten();
+
+        Position {
+            board,
+            turn: us.opposite(),
+            castling,
+            en_passant,
+            halfmove_clock: if mv.role == Role::Pawn || mv.is_capture() { 0 } else { self.halfmove_clock + 1 },
+            fullmove_number: self.fullmove_number + matches!(us, Color::Black) as u32,
+        }
+    }
+
+    /// Plays `mv`, which must be one of [`Position::legal_moves`].
+    pub fn play(&self, mv: &Move) -> Result<Position, MoveError> {
+        if !self.legal_moves().contains(mv) {
+            return Err(MoveError::Illegal(mv.to_uci()));
+        }
+        Ok(self.play_unchecked(mv))
+    }
+
+    /// Resolves a move in standard algebraic notation, e.g. `Nf3`, `exd5`, `O-O` or `e8=Q+`.
+    pub fn parse_san(&self, san: &str) -> Result<Move, MoveError> {
+        let unparseable = || MoveError::Unparseable(san.to_string());
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.legal_moves();
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(king_side) = castle {
+            return legal
+                .into_iter()
+                .find(|mv| mv.kind == MoveKind::Castle { king_side })
+                .ok_or_else(|| MoveError::Illegal(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = text.chars().filter(|c| *c != 'x' && *c != '-').collect();
+        let role = match chars.first().copied().and_then(|c| c.is_ascii_uppercase().then(|| role_from_letter(c)).flatten()) {
+            Some(role) => {
+                chars.remove(0);
+                role
+            }
+            None => Role::Pawn,
+        };
+
+        let mut promotion = None;
+        if let Some(eq) = chars.iter().position(|c| *c == '=') {
+            promotion = Some(chars.get(eq + 1).copied().and_then(role_from_letter).ok_or_else(unparseable)?);
+            chars.truncate(eq);
+        } else if role == Role::Pawn && chars.last().is_some_and(|c| c.is_ascii_alphabetic() && c.is_ascii_uppercase()) {
+            promotion = Some(chars.pop().and_then(role_from_letter).ok_or_else(unparseable)?);
+        }
+
+        if chars.len() < 2 {
+            return Err(unparseable());
+        }
+        let dest: String = chars[chars.len() - 2..].iter().collect();
+        let to = parse_square(&dest).ok_or_else(unparseable)?;
+        let qualifiers = &chars[..chars.len() - 2];
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in qualifiers {
+            match c {
+                'a'..='h' => from_file = Some(*c as i8 - 'a' as i8),
+                '1'..='8' => from_rank = Some(*c as i8 - '1' as i8),
+                _ => return Err(unparseable()),
+            }
+        }
+
+        let candidates: Vec<Move> = legal
+            .into_iter()
+            .filter(|mv| {
+                mv.role == role
+                    && mv.to == to
+                    && mv.promotion == promotion
+                    && !matches!(mv.kind, MoveKind::Castle { .. })
+                    && from_file.is_none_or(|f| file_of(mv.from) == f)
+                    && from_rank.is_none_or(|r| rank_of(mv.from) == r)
+            })
+            .collect();
+
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(MoveError::Illegal(san.to_string())),
+            _ => Err(MoveError::Ambiguous(san.to_string())),
+        }
+    }
+
+    /// Resolves a move in UCI notation, e.g. `e2e4`, `e1g1` or `e7e8q`. Castling may
+    /// also be written as the king taking its own rook (`e1h1`), as Chess960 engines do.
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, MoveError> {
+        let text = uci.trim();
+        let unparseable = || MoveError::Unparseable(uci.to_string());
+        if !text.is_ascii() || !(4..=5).contains(&text.len()) {
+            return Err(unparseable());
+        }
+        let from = parse_square(&text[0..2]).ok_or_else(unparseable)?;
+        let to = parse_square(&text[2..4]).ok_or_else(unparseable)?;
+        let promotion = match text.chars().nth(4) {
+            Some(c) => Some(role_from_letter(c).filter(|r| *r != Role::King).ok_or_else(unparseable)?),
+            None => None,
+        };
+
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.from == from && (mv.to == to || self.castling_rook(mv) == Some(to)) && mv.promotion == promotion)
+            .ok_or_else(|| MoveError::Illegal(uci.to_string()))
+    }
+
+    /// Square of the rook that castles along with `mv`, if it is a castling move.
+    fn castling_rook(&self, mv: &Move) -> Option<Square> {
+        let MoveKind::Castle { king_side } = mv.kind else { return None };
+        square_at(self.castling.rook_file(self.turn, king_side)?, back_rank(self.turn))
+    }
+
+    /// Standard algebraic notation for a legal `mv`, including check and mate suffixes.
+    pub fn san(&self, mv: &Move) -> String {
+        let mut san = match mv.kind {
+            MoveKind::Castle { king_side: true } => "O-O".to_string(),
+            MoveKind::Castle { king_side: false } => "O-O-O".to_string(),
+            _ if mv.role == Role::Pawn => {
+                let mut san = String::new();
+                if mv.is_capture() {
+                    san.push((b'a' + mv.from.value % 8) as char);
+                    san.push('x');
+                }
+                san.push_str(&square_name(mv.to));
+                if let Some(role) = mv.promotion {
+                    san.push('=');
+                    san.push(role_letter(role));
+                }
+                san
+            }
+            _ => {
+                let mut san = role_letter(mv.role).to_string();
+                let rivals: Vec<Move> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|other| other.role == mv.role && other.to == mv.to && other.from != mv.from)
+                    .collect();
+                if !rivals.is_empty() {
+                    let file_unique = rivals.iter().all(|o| file_of(o.from) != file_of(mv.from));
+                    let rank_unique = rivals.iter().all(|o| rank_of(o.from) != rank_of(mv.from));
+                    let name = square_name(mv.from);
+                    if file_unique {
+                        san.push_str(&name[..1]);
+                    } else if rank_unique {
+                        san.push_str(&name[1..]);
+                    } else {
+                        san.push_str(&name);
+                    }
+                }
+                if mv.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&square_name(mv.to));
+                san
+            }
+        };
+
+        let after = self.play_unchecked(mv);
+        if after.is_check() {
+            san.push(if after.legal_moves().is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+
+    /// Renders a line of UCI moves, such as an engine's principal variation, in SAN.
+    /// Each move is resolved in the position the previous ones lead to; the first
+    /// move that is not legal there fails the whole line.
+    pub fn san_line<S: AsRef<str>>(&self, uci_moves: &[S]) -> Result<Vec<String>, MoveError> {
+        let mut position = *self;
+        let mut line = Vec::with_capacity(uci_moves.len());
+        for uci in uci_moves {
+            let mv = position.parse_uci(uci.as_ref())?;
+            line.push(position.san(&mv));
+            position = position.play_unchecked(&mv);
+        }
+        Ok(line)
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.legal_moves().is_empty()
+    }
+
+    /// Neither side can possibly mate: bare kings, a single minor piece, or
+    /// bishops that all stand on squares of one color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let board = &self.board;
+        if (board.pawns() | board.rooks() | board.queens()).0 != 0 {
+            return false;
+        }
+        let minors = board.knights() | board.bishops();
+        if minors.count() <= 1 {
+            return true;
+        }
+        if board.knights().0 != 0 {
+            return false;
+        }
+        let bishops = board.bishops().to_squares();
+        let on_light = |s: &Square| (file_of(*s) + rank_of(*s)) % 2 == 1;
+        bishops.iter().all(on_light) || !bishops.iter().any(on_light)
+    }
+
+    /// Key under which this position counts towards repetition. The en passant square
+    /// only matters when a pawn can actually capture there.
+    pub fn key(&self) -> PositionKey {
+        let board = &self.board;
+        let en_passant = self
+            .en_passant
+            .filter(|_| self.legal_moves().iter().any(|mv| mv.kind == MoveKind::EnPassant))
+            .map(|s| s.value);
+        PositionKey {
+            pieces: [
+                board.white().0,
+                board.black().0,
+                board.pawns().0,
+                board.knights().0,
+                board.bishops().0,
+                board.rooks().0,
+                board.queens().0,
+                board.kings().0,
+            ],
+            white_to_move: self.turn == Color::White,
+            castling: self.castling,
+            en_passant,
+        }
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let piece = square_at(file, rank).and_then(|s| self.board.piece_at(s));
+                match piece {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let letter = role_letter(piece.role);
+                        placement.push(match piece.color {
+                            Color::White => letter,
+                            Color::Black => letter.to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            match self.turn {
+                Color::White => "w",
+                Color::Black => "b",
+            },
+            self.castling.to_fen(&self.board),
+            self.en_passant.map(square_name).unwrap_or_else(|| "-".to_string()),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
@@ -1,5 +1,8 @@
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
+use crate::bitboard::Board::Color;
+
 #[derive(Debug, Clone)]
 pub struct TimeControl {
     pub initial_time: Duration,
@@ -7,6 +10,151 @@ pub struct TimeControl {
     pub delay: Duration,
 }
 
+/// Speed category of a time control, used to bucket games for pairing and ratings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TimeClass {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+/// Moves per side assumed when estimating how long a game lasts.
+pub const ESTIMATED_MOVES: u64 = 40;
+
+/// Upper bounds (exclusive) of the estimated duration of each time class, in seconds.
+pub const BULLET_LIMIT_SECS: u64 = 180;
+pub const BLITZ_LIMIT_SECS: u64 = 480;
+pub const RAPID_LIMIT_SECS: u64 = 1500;
+pub const CLASSICAL_LIMIT_SECS: u64 = 86_400;
+
+impl TimeControl {
+    /// Estimated time per side: the base time plus 40 moves of increment and delay.
+    pub fn estimated_duration(&self) -> Duration {
+        self.initial_time + (self.increment + self.delay) * ESTIMATED_MOVES as u32
+    }
+}
+
+/// Buckets a time control by its estimated duration, e.g. 3+2 (300s) is blitz and
+/// 10+0 (600s) is rapid. Anything estimated at a day or more is correspondence.
+pub fn time_class(tc: &TimeControl) -> TimeClass {
+    match tc.estimated_duration().as_secs() {
+        secs if secs < BULLET_LIMIT_SECS => TimeClass::Bullet,
+        secs if secs < BLITZ_LIMIT_SECS => TimeClass::Blitz,
+        secs if secs < RAPID_LIMIT_SECS => TimeClass::Rapid,
+        secs if secs < CLASSICAL_LIMIT_SECS => TimeClass::Classical,
+        _ => TimeClass::Correspondence,
+    }
+}
+
+/// When a clock counts as low: a fixed amount of time left, or a share of the
+/// initial time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LowTimeThreshold {
+    Secs(u64),
+    Percent(u8),
+}
+
+impl LowTimeThreshold {
+    /// Remaining time at or below which a clock started at `initial_time` is low.
+    pub fn limit(&self, initial_time: Duration) -> Duration {
+        match *self {
+            LowTimeThreshold::Secs(secs) => Duration::from_secs(secs),
+            LowTimeThreshold::Percent(percent) => initial_time * percent.min(100) as u32 / 100,
+        }
+    }
+}
+
+/// Parses `15` as fifteen seconds and `10%` as a tenth of the initial time.
+impl FromStr for LowTimeThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let parsed = match s.strip_suffix('%') {
+            Some(percent) => percent.trim().parse().ok().filter(|p| *p <= 100).map(LowTimeThreshold::Percent),
+            None => s.parse().ok().map(LowTimeThreshold::Secs),
+        };
+        parsed.ok_or_else(|| format!("invalid low-time threshold '{}'", s))
+    }
+}
+
+/// Low-time threshold of each real-time class. Correspondence clocks are never low
+/// in a way worth warning about, so they have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowTimeThresholds {
+    pub bullet: LowTimeThreshold,
+    pub blitz: LowTimeThreshold,
+    pub rapid: LowTimeThreshold,
+    pub classical: LowTimeThreshold,
+}
+
+impl Default for LowTimeThresholds {
+    fn default() -> Self {
+        Self {
+            bullet: LowTimeThreshold::Percent(10),
+            blitz: LowTimeThreshold::Secs(15),
+            rapid: LowTimeThreshold::Secs(30),
+            classical: LowTimeThreshold::Percent(5),
+        }
+    }
+}
+
+impl LowTimeThresholds {
+    pub fn for_class(&self, class: TimeClass) -> Option<LowTimeThreshold> {
+        match class {
+            TimeClass::Bullet => Some(self.bullet),
+            TimeClass::Blitz => Some(self.blitz),
+            TimeClass::Rapid => Some(self.rapid),
+            TimeClass::Classical => Some(self.classical),
+            TimeClass::Correspondence => None,
+        }
+    }
+
+    /// Watch for a game played at `tc`, or `None` for correspondence games.
+    pub fn watch(&self, tc: &TimeControl) -> Option<LowTimeWatch> {
+        self.for_class(time_class(tc))
+            .map(|threshold| LowTimeWatch::new(threshold.limit(tc.initial_time)))
+    }
+}
+
+/// Follows both clocks of a game and reports each side once per crossing of the
+/// low-time limit. A side whose clock climbs back above the limit, through increment
+/// or delay, is reported again the next time it drops below.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowTimeWatch {
+    limit: Duration,
+    white_low: bool,
+    black_low: bool,
+}
+
+impl LowTimeWatch {
+    pub fn new(limit: Duration) -> Self {
+        Self { limit, white_low: false, black_low: false }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Records a reading of both clocks and returns the sides that have just gone low.
+    pub fn observe(&mut self, white: Duration, black: Duration) -> Vec<Color> {
+        let mut crossed = Vec::new();
+        for (color, remaining, low) in [
+            (Color::White, white, &mut self.white_low),
+            (Color::Black, black, &mut self.black_low),
+        ] {
+            let is_low = remaining <= self.limit;
+            if is_low && !*low {
+                crossed.push(color);
+            }
+            *low = is_low;
+        }
+        crossed
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PlayerClock {
     pub remaining_time: Duration,
@@ -0,0 +1,255 @@
+use std::str::FromStr;
+
+use crate::bitboard::Board::{Color, Piece, Role, Square};
+use crate::history::GameHistory;
+use crate::material::Material;
+use crate::position::Position;
+
+/// Score of a finished game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Color),
+    Draw,
+}
+
+/// How a stalemate is scored, from the point of view of the stalemated side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StalemateRule {
+    #[default]
+    Draw,
+    /// The side left without a move wins, as in antichess.
+    StalematedWins,
+    /// The side left without a move loses, as in some historical rule sets.
+    StalematedLoses,
+}
+
+impl FromStr for StalemateRule {
+    type Err = String;
+
+    /// Parses `draw`, `stalemated_wins` or `stalemated_loses`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "draw" => Ok(StalemateRule::Draw),
+            "stalemated_wins" => Ok(StalemateRule::StalematedWins),
+            "stalemated_loses" => Ok(StalemateRule::StalematedLoses),
+            other => Err(format!("Unknown stalemate rule '{}'", other)),
+        }
+    }
+}
+
+impl StalemateRule {
+    /// Result when `stalemated` is to move and has no legal move.
+    pub fn outcome(self, stalemated: Color) -> Outcome {
+        match self {
+            StalemateRule::Draw => Outcome::Draw,
+            StalemateRule::StalematedWins => Outcome::Win(stalemated),
+            StalemateRule::StalematedLoses => Outcome::Win(stalemated.opposite()),
+        }
+    }
+}
+
+/// Why a position is (or is not) the end of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate(Outcome),
+    Stalemate(Outcome),
+    InsufficientMaterial,
+}
+
+impl GameStatus {
+    pub fn outcome(self) -> Option<Outcome> {
+        match self {
+            GameStatus::Ongoing => None,
+            GameStatus::Checkmate(outcome) | GameStatus::Stalemate(outcome) => Some(outcome),
+            GameStatus::InsufficientMaterial => Some(Outcome::Draw),
+        }
+    }
+}
+
+/// Pieces each side holds in hand to drop, as in crazyhouse.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pockets {
+    pub white: Vec<Role>,
+    pub black: Vec<Role>,
+}
+
+impl Pockets {
+    /// Pockets after the moves of `history`: every piece a side captured, with a
+    /// captured promoted piece held as a pawn. Drops are not played yet, so nothing
+    /// leaves a pocket.
+    pub fn of(history: &GameHistory) -> Self {
+        let material = Material::of(history, true);
+        Pockets { white: material.captured_by_white, black: material.captured_by_black }
+    }
+
+    pub fn get(&self, color: Color) -> &[Role] {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.white.is_empty() && self.black.is_empty()
+    }
+}
+
+/// Rules that differ between variants. The defaults are the standard rules, so a
+/// variant only overrides what it changes.
+///
+/// None of the built-in variants score stalemate differently: it is a draw in
+/// standard chess, Chess960, Crazyhouse and King of the Hill. Use
+/// [`WithStalemateRule`] to play any of them under another stalemate rule.
+pub trait Variant {
+    fn name(&self) -> &'static str;
+
+    fn stalemate_rule(&self) -> StalemateRule {
+        StalemateRule::Draw
+    }
+
+    /// Whether the side to move can play anything, with `pockets` in hand.
+    fn has_legal_move(&self, position: &Position, _pockets: &Pockets) -> bool {
+        !position.legal_moves().is_empty()
+    }
+
+    /// Whether neither side can possibly mate, with `pockets` in hand.
+    fn is_insufficient_material(&self, position: &Position, _pockets: &Pockets) -> bool {
+        position.is_insufficient_material()
+    }
+
+    /// Status of `position` with the side to move about to play and nothing in hand.
+    fn game_status(&self, position: &Position) -> GameStatus {
+        self.game_status_with_pockets(position, &Pockets::default())
+    }
+
+    /// Status of `position` with the side to move about to play and `pockets` in hand.
+    fn game_status_with_pockets(&self, position: &Position, pockets: &Pockets) -> GameStatus {
+        if self.has_legal_move(position, pockets) {
+            if self.is_insufficient_material(position, pockets) {
+                return GameStatus::InsufficientMaterial;
+            }
+            return GameStatus::Ongoing;
+        }
+        if position.is_check() {
+            GameStatus::Checkmate(Outcome::Win(position.turn.opposite()))
+        } else {
+            GameStatus::Stalemate(self.stalemate_rule().outcome(position.turn))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chess960;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crazyhouse;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KingOfTheHill;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+impl Variant for Chess960 {
+    fn name(&self) -> &'static str {
+        "Chess960"
+    }
+}
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    /// A piece in hand may be dropped on any empty square that leaves the king safe,
+    /// so a drop can block a check that no move on the board answers.
+    fn has_legal_move(&self, position: &Position, pockets: &Pockets) -> bool {
+        !position.legal_moves().is_empty() || has_legal_drop(position, pockets.get(position.turn))
+    }
+
+    /// Captured pieces come back as drops, so pockets count as material.
+    fn is_insufficient_material(&self, position: &Position, pockets: &Pockets) -> bool {
+        pockets.is_empty() && position.is_insufficient_material()
+    }
+}
+
+/// Whether the side to move can drop one of `in_hand` without leaving its king in
+/// check. Pawns may not be dropped on the first or last rank.
+fn has_legal_drop(position: &Position, in_hand: &[Role]) -> bool {
+    let us = position.turn;
+    let empty: Vec<Square> = (0..64)
+        .map(|value| Square { value })
+        .filter(|&s| !position.board.is_occupied_square(s))
+        .collect();
+    in_hand.iter().any(|&role| {
+        empty
+            .iter()
+            .filter(|s| role != Role::Pawn || (1..7).contains(&s.rank()))
+            .any(|&s| {
+                let board = position.board.put_or_replace(Piece { color: us, role }, s);
+                board.king_pos_of(us).is_none_or(|king| !board.attacks(king, us.opposite()))
+            })
+    })
+}
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+}
+
+/// `V` played with `rule` in place of its own stalemate rule.
+#[derive(Debug, Clone, Copy)]
+pub struct WithStalemateRule<V> {
+    pub variant: V,
+    pub rule: StalemateRule,
+}
+
+impl<V: Variant> Variant for WithStalemateRule<V> {
+    fn name(&self) -> &'static str {
+        self.variant.name()
+    }
+
+    fn stalemate_rule(&self) -> StalemateRule {
+        self.rule
+    }
+
+    fn has_legal_move(&self, position: &Position, pockets: &Pockets) -> bool {
+        self.variant.has_legal_move(position, pockets)
+    }
+
+    fn is_insufficient_material(&self, position: &Position, pockets: &Pockets) -> bool {
+        self.variant.is_insufficient_material(position, pockets)
+    }
+}
+
+/// Lets a variant chosen at runtime, such as a `&'static dyn Variant`, be wrapped in
+/// [`WithStalemateRule`].
+impl<V: Variant + ?Sized> Variant for &V {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn stalemate_rule(&self) -> StalemateRule {
+        (**self).stalemate_rule()
+    }
+
+    fn has_legal_move(&self, position: &Position, pockets: &Pockets) -> bool {
+        (**self).has_legal_move(position, pockets)
+    }
+
+    fn is_insufficient_material(&self, position: &Position, pockets: &Pockets) -> bool {
+        (**self).is_insufficient_material(position, pockets)
+    }
+
+    fn game_status_with_pockets(&self, position: &Position, pockets: &Pockets) -> GameStatus {
+        (**self).game_status_with_pockets(position, pockets)
+    }
+}
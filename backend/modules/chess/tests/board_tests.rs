@@ -38,4 +38,30 @@ mod tests {
         let empty_map = empty_board.piece_map();
         assert_eq!(empty_map.len(), 0);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_attackers_and_pinned_blockers() {
+        let white_king = Piece { color: Color::White, role: Role::King };
+        let white_knight = Piece { color: Color::White, role: Role::Knight };
+        let black_rook = Piece { color: Color::Black, role: Role::Rook };
+        let black_bishop = Piece { color: Color::Black, role: Role::Bishop };
+        let e1 = Square { value: 4 };
+        let e2 = Square { value: 12 };
+        let e8 = Square { value: 60 };
+        let a5 = Square { value: 32 };
+
+        let board = Board::empty()
+            .put_or_replace(white_king, e1)
+            .put_or_replace(white_knight, e2)
+            .put_or_replace(black_rook, e8)
+            .put_or_replace(black_bishop, a5);
+
+        // The knight shields the king from the rook, so only the bishop gives check
+        assert_eq!(board.attackers(e1, Color::Black, board.occupied), a5.bitboard());
+        assert!(board.attacks(e1, Color::Black));
+        // Looking through the knight reveals the rook as well
+        let without_knight = board.occupied & !e2.bitboard();
+        assert_eq!(board.attackers(e1, Color::Black, without_knight), a5.bitboard() | e8.bitboard());
+        assert_eq!(board.slider_blockers(e1, Color::White), e2.bitboard());
+    }
+}
@@ -0,0 +1,32 @@
+use chess::eco::{classify, is_valid_code};
+
+#[test]
+fn test_classify_picks_most_specific_line() {
+    let najdorf = ["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "Be3"];
+    let opening = classify(&najdorf).unwrap();
+    assert_eq!(opening.eco, "B90");
+    assert_eq!(opening.name, "Sicilian Defense, Najdorf Variation");
+
+    let sicilian = ["e4", "c5", "Nc3"];
+    assert_eq!(classify(&sicilian).unwrap().eco, "B23");
+}
+
+#[test]
+fn test_classify_ignores_check_and_annotation_suffixes() {
+    let ruy = ["e4", "e5", "Nf3!", "Nc6", "Bb5+"];
+    assert_eq!(classify(&ruy).unwrap().eco, "C60");
+}
+
+#[test]
+fn test_classify_unknown_first_move() {
+    assert!(classify(&["h4", "e5"]).is_none());
+    assert!(classify::<&str>(&[]).is_none());
+}
+
+#[test]
+fn test_is_valid_code() {
+    assert!(is_valid_code("B20"));
+    assert!(!is_valid_code("F20"));
+    assert!(!is_valid_code("B2"));
+    assert!(!is_valid_code("b20"));
+}
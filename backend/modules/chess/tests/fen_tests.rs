@@ -0,0 +1,105 @@
+use chess::bitboard::Board::Color;
+use std::collections::HashSet;
+
+use chess::fen::{
+    chess960_start_fen, generate_chess960_fen, validate, CounterIssue, Fen, FenError, Strictness, CHESS960_POSITIONS,
+    STARTING_FEN,
+};
+
+#[test]
+fn test_starting_position_is_consistent() {
+    let fen: Fen = STARTING_FEN.parse().unwrap();
+    assert_eq!(fen.side_to_move, Color::White);
+    assert_eq!(fen.plies_played(), 0);
+    assert!(fen.counter_issues().is_empty());
+    assert_eq!(validate(STARTING_FEN, Strictness::Strict), Ok(vec![]));
+}
+
+#[test]
+fn test_halfmove_clock_contradicts_double_pawn_push() {
+    // e6 is an en passant target, so 1...e5 was just played and the clock must be 0.
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 3 2";
+    let expected = vec![
+        CounterIssue::HalfmoveAfterPawnMove { halfmove_clock: 3 },
+        CounterIssue::HalfmoveExceedsPliesPlayed { halfmove_clock: 3, plies_played: 2 },
+    ];
+
+    assert_eq!(validate(fen, Strictness::Lenient), Ok(expected.clone()));
+    assert_eq!(
+        validate(fen, Strictness::Strict),
+        Err(FenError::InconsistentCounters(expected))
+    );
+}
+
+#[test]
+fn test_halfmove_clock_bounds() {
+    let beyond_limit = "8/8/4k3/8/8/4K3/8/8 w - - 151 200";
+    assert_eq!(
+        validate(beyond_limit, Strictness::Lenient),
+        Ok(vec![CounterIssue::HalfmoveBeyondLimit { halfmove_clock: 151 }])
+    );
+
+    let too_early = "8/8/4k3/8/8/4K3/8/8 b - - 20 5";
+    assert_eq!(
+        validate(too_early, Strictness::Lenient),
+        Ok(vec![CounterIssue::HalfmoveExceedsPliesPlayed { halfmove_clock: 20, plies_played: 9 }])
+    );
+
+    let plausible = "8/8/4k3/8/8/4K3/8/8 b - - 9 5";
+    assert_eq!(validate(plausible, Strictness::Strict), Ok(vec![]));
+}
+
+#[test]
+fn test_fullmove_zero_is_flagged() {
+    let fen = "8/8/4k3/8/8/4K3/8/8 w - - 0 0";
+    assert_eq!(validate(fen, Strictness::Lenient), Ok(vec![CounterIssue::FullmoveZero]));
+}
+
+#[test]
+fn test_malformed_fens_are_rejected_regardless_of_strictness() {
+    let malformed = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1",
+    ];
+
+    for fen in malformed {
+        assert!(
+            matches!(validate(fen, Strictness::Lenient), Err(FenError::Malformed(_))),
+            "{} should be malformed",
+            fen
+        );
+    }
+}
+
+#[test]
+fn test_chess960_number_518_is_the_standard_start() {
+    assert_eq!(chess960_start_fen(518), STARTING_FEN);
+}
+
+#[test]
+fn test_chess960_starts_are_distinct_and_legal() {
+    let mut seen = HashSet::new();
+    for number in 0..CHESS960_POSITIONS {
+        let fen = chess960_start_fen(number);
+        let back_rank: Vec<char> = fen.split(['/', ' ']).nth(7).unwrap().chars().collect();
+        let files = |piece: char| -> Vec<usize> { (0..8).filter(|&file| back_rank[file] == piece).collect() };
+
+        let bishops = files('B');
+        assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", fen);
+        let (rooks, king) = (files('R'), files('K')[0]);
+        assert!(rooks[0] < king && king < rooks[1], "{}", fen);
+        assert_eq!(validate(&fen, Strictness::Strict), Ok(vec![]));
+        assert!(seen.insert(fen));
+    }
+}
+
+#[test]
+fn test_generated_chess960_start_is_numbered() {
+    let fen = generate_chess960_fen();
+    assert!((0..CHESS960_POSITIONS).any(|number| chess960_start_fen(number) == fen), "{}", fen);
+}
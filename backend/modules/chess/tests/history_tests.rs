@@ -0,0 +1,39 @@
+use chess::fen::STARTING_FEN;
+use chess::history::{DrawClaim, GameHistory, ReplayError};
+use chess::position::MoveError;
+
+#[test]
+fn test_threefold_repetition_counts_the_starting_position() {
+    let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"];
+
+    let twice = GameHistory::replay(STARTING_FEN, &shuffle[..4]).unwrap();
+    assert_eq!(twice.repetitions(), 2);
+    assert!(!twice.can_claim(DrawClaim::Repetition));
+
+    let thrice = GameHistory::replay(STARTING_FEN, &shuffle).unwrap();
+    assert_eq!(thrice.repetitions(), 3);
+    assert!(thrice.can_claim(DrawClaim::Repetition));
+}
+
+#[test]
+fn test_lost_castling_rights_make_a_different_position() {
+    // The kings return to their squares but can no longer castle.
+    let moves = ["e4", "e5", "Ke2", "Ke7", "Ke1", "Ke8", "Ke2", "Ke7", "Ke1", "Ke8"];
+    let history = GameHistory::replay(STARTING_FEN, &moves).unwrap();
+    assert_eq!(history.repetitions(), 2);
+}
+
+#[test]
+fn test_fifty_move_claim_uses_the_halfmove_clock() {
+    let history = GameHistory::replay("8/8/4k3/8/8/4K3/8/R7 w - - 99 80", &["Ra2"]).unwrap();
+    assert!(history.can_claim(DrawClaim::FiftyMove));
+
+    let history = GameHistory::replay("8/8/4k3/8/8/4K3/8/R7 w - - 98 80", &["Ra2"]).unwrap();
+    assert!(!history.can_claim(DrawClaim::FiftyMove));
+}
+
+#[test]
+fn test_replay_reports_the_offending_ply() {
+    let err = GameHistory::replay(STARTING_FEN, &["e4", "e5", "Ke3"]).unwrap_err();
+    assert_eq!(err, ReplayError::Move { ply: 2, error: MoveError::Illegal("Ke3".to_string()) });
+}
@@ -0,0 +1,43 @@
+use chess::bitboard::Board::Color;
+use chess::time_control::{LowTimeThreshold, LowTimeThresholds, LowTimeWatch, TimeControl};
+use std::time::Duration;
+
+fn tc(initial_secs: u64, increment_secs: u64) -> TimeControl {
+    TimeControl {
+        initial_time: Duration::from_secs(initial_secs),
+        increment: Duration::from_secs(increment_secs),
+        delay: Duration::ZERO,
+    }
+}
+
+#[test]
+fn thresholds_parse_as_seconds_or_percent() {
+    assert_eq!("15".parse(), Ok(LowTimeThreshold::Secs(15)));
+    assert_eq!(" 10% ".parse(), Ok(LowTimeThreshold::Percent(10)));
+    assert!("150%".parse::<LowTimeThreshold>().is_err());
+    assert!("soon".parse::<LowTimeThreshold>().is_err());
+    assert_eq!(LowTimeThreshold::Percent(10).limit(Duration::from_secs(60)), Duration::from_secs(6));
+}
+
+#[test]
+fn each_side_is_reported_once_per_crossing() {
+    let mut watch = LowTimeWatch::new(Duration::from_secs(15));
+    let secs = Duration::from_secs;
+
+    assert!(watch.observe(secs(40), secs(40)).is_empty());
+    assert_eq!(watch.observe(secs(15), secs(40)), vec![Color::White]);
+    assert!(watch.observe(secs(9), secs(30)).is_empty(), "already reported");
+    assert_eq!(watch.observe(secs(8), secs(14)), vec![Color::Black]);
+    // An increment takes white back above the limit, so the next drop warns again
+    assert!(watch.observe(secs(17), secs(12)).is_empty());
+    assert_eq!(watch.observe(secs(13), secs(11)), vec![Color::White]);
+}
+
+#[test]
+fn correspondence_games_are_not_watched() {
+    let thresholds = LowTimeThresholds::default();
+
+    assert!(thresholds.watch(&tc(3 * 86_400, 0)).is_none());
+    assert_eq!(thresholds.watch(&tc(60, 0)).map(|w| w.limit()), Some(Duration::from_secs(6)));
+    assert_eq!(thresholds.watch(&tc(180, 2)).map(|w| w.limit()), Some(Duration::from_secs(15)));
+}
@@ -0,0 +1,39 @@
+use chess::bitboard::Board::Role;
+use chess::fen::STARTING_FEN;
+use chess::history::GameHistory;
+use chess::material::Material;
+
+#[test]
+fn test_balance_and_captures_after_a_queen_blunder() {
+    let history = GameHistory::replay(STARTING_FEN, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxa2", "Rxa2"]).unwrap();
+
+    let material = Material::of(&history, false);
+
+    assert_eq!(material.captured_by_white, vec![Role::Pawn, Role::Queen]);
+    assert_eq!(material.captured_by_black, vec![Role::Pawn, Role::Pawn]);
+    assert_eq!(material.balance, 8);
+    assert_eq!(Material::of_position(history.current()).balance, 8);
+}
+
+#[test]
+fn test_pockets_count_towards_material() {
+    let history = GameHistory::replay(STARTING_FEN, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxa2", "Rxa2"]).unwrap();
+
+    let material = Material::of(&history, true);
+
+    // 8 on the board, plus a queen and pawn in White's pocket against two pawns in Black's
+    assert_eq!(material.balance, 16);
+}
+
+#[test]
+fn test_captured_promoted_piece_goes_to_the_pocket_as_a_pawn() {
+    let history = GameHistory::replay("7r/1P2k3/8/8/8/8/8/4K3 w - - 0 1", &["b8=Q", "Rxb8"]).unwrap();
+
+    let standard = Material::of(&history, false);
+    let crazyhouse = Material::of(&history, true);
+
+    assert_eq!(standard.captured_by_black, vec![Role::Queen]);
+    assert_eq!(standard.balance, -5);
+    assert_eq!(crazyhouse.captured_by_black, vec![Role::Pawn]);
+    assert_eq!(crazyhouse.balance, -6);
+}
@@ -0,0 +1,55 @@
+use chess::pgn::PgnGame;
+
+#[test]
+fn test_seven_tag_roster_comes_first_with_placeholders() {
+    let pgn = PgnGame::new("1-0")
+        .tag("ECO", "C20")
+        .tag("White", "alice")
+        .tag("Black", "bob")
+        .moves(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"])
+        .to_string();
+
+    assert_eq!(
+        pgn,
+        "[Event \"?\"]\n[Site \"?\"]\n[Date \"?\"]\n[Round \"?\"]\n[White \"alice\"]\n\
+         [Black \"bob\"]\n[Result \"1-0\"]\n[ECO \"C20\"]\n\n\
+         1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"
+    );
+}
+
+#[test]
+fn test_custom_start_with_black_to_move_writes_setup_and_ellipsis() {
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+    let pgn = PgnGame::new("*").starting_fen(fen).moves(&["Kd7", "e4", "Ke6"]).to_string();
+
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n"));
+    assert!(pgn.ends_with("\n12... Kd7 13. e4 Ke6 *\n"));
+}
+
+#[test]
+fn test_standard_start_has_no_fen_tag() {
+    let pgn = PgnGame::new("*").starting_fen(chess::fen::STARTING_FEN).to_string();
+    assert!(!pgn.contains("FEN"));
+    assert!(pgn.ends_with("\n\n*\n"));
+}
+
+#[test]
+fn test_tag_values_are_escaped_and_result_cannot_be_overridden() {
+    let pgn = PgnGame::new("1/2-1/2")
+        .tag("White", "say \"hi\"")
+        .tag("Result", "1-0")
+        .to_string();
+
+    assert!(pgn.contains("[White \"say \\\"hi\\\"\"]"));
+    assert!(pgn.contains("[Result \"1/2-1/2\"]"));
+}
+
+#[test]
+fn test_movetext_is_wrapped() {
+    let moves: Vec<&str> = ["Nf3", "Nf6", "Ng1", "Ng8"].iter().copied().cycle().take(80).collect();
+    let pgn = PgnGame::new("1/2-1/2").moves(&moves).to_string();
+
+    let movetext = pgn.split("\n\n").nth(1).unwrap();
+    assert!(movetext.lines().count() > 1);
+    assert!(movetext.lines().all(|line| line.len() < 80));
+}
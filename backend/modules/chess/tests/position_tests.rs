@@ -0,0 +1,191 @@
+use chess::bitboard::Board::Role;
+use chess::fen::{STARTING_FEN, without_pockets};
+use chess::position::{MoveError, Position, parse_square};
+
+fn perft(position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    position
+        .legal_moves()
+        .iter()
+        .map(|mv| perft(&position.play(mv).unwrap(), depth - 1))
+        .sum()
+}
+
+fn play_all(position: Position, sans: &[&str]) -> Position {
+    sans.iter().fold(position, |position, san| {
+        let mv = position.parse_san(san).unwrap();
+        position.play(&mv).unwrap()
+    })
+}
+
+#[test]
+fn test_perft_from_starting_position() {
+    let position = Position::default();
+    assert_eq!(perft(&position, 1), 20);
+    assert_eq!(perft(&position, 2), 400);
+    assert_eq!(perft(&position, 3), 8902);
+}
+
+#[test]
+fn test_perft_with_castling_en_passant_and_promotions() {
+    // "Kiwipete", a standard move generator test position.
+    let position: Position = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+        .parse()
+        .unwrap();
+    assert_eq!(perft(&position, 1), 48);
+    assert_eq!(perft(&position, 2), 2039);
+}
+
+#[test]
+fn test_san_round_trip_and_fen_output() {
+    let position = play_all(Position::default(), &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "O-O"]);
+    assert_eq!(
+        position.to_fen(),
+        "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 4"
+    );
+
+    for mv in position.legal_moves() {
+        let san = position.san(&mv);
+        assert_eq!(position.parse_san(&san), Ok(mv), "{} should parse back", san);
+    }
+}
+
+#[test]
+fn test_san_disambiguation_and_check_suffixes() {
+    let position: Position = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1".parse().unwrap();
+    let mv = position.parse_san("Rad1").unwrap();
+    assert_eq!(position.san(&mv), "Rad1");
+    assert_eq!(position.parse_san("Rd1"), Err(MoveError::Ambiguous("Rd1".to_string())));
+
+    let mate = play_all(Position::default(), &["f3", "e5", "g4"]);
+    let mv = mate.parse_san("Qh4").unwrap();
+    assert_eq!(mate.san(&mv), "Qh4#");
+    assert!(mate.play(&mv).unwrap().is_checkmate());
+}
+
+#[test]
+fn test_uci_moves_and_promotion() {
+    let position: Position = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1".parse().unwrap();
+    let mv = position.parse_uci("e7e8n").unwrap();
+    assert_eq!(mv.promotion, Some(Role::Knight));
+    assert_eq!(position.san(&mv), "e8=N");
+    assert_eq!(position.parse_san("e8=Q+").unwrap().to_uci(), "e7e8q");
+    assert!(matches!(position.parse_uci("e7e6"), Err(MoveError::Illegal(_))));
+}
+
+#[test]
+fn test_game_end_detection() {
+    let stalemate: Position = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1".parse().unwrap();
+    assert!(stalemate.is_stalemate());
+    assert!(!stalemate.is_checkmate());
+
+    let bare_kings: Position = "8/8/4k3/8/8/4K3/8/8 w - - 0 1".parse().unwrap();
+    assert!(bare_kings.is_insufficient_material());
+    assert!(!STARTING_FEN.parse::<Position>().unwrap().is_insufficient_material());
+}
+
+#[test]
+fn test_checked_king_after_a_checking_move() {
+    let before = play_all(Position::default(), &["e4", "f6", "d4", "g5"]);
+    assert_eq!(before.checked_king(), None);
+
+    let after = play_all(before, &["Qh5+"]);
+    assert!(after.is_check());
+    assert_eq!(after.checked_king(), parse_square("e8"));
+}
+
+#[test]
+fn test_checked_king_after_a_crazyhouse_drop() {
+    // White has dropped a knight on d6
+    for fen in [
+        "rnbqkbnr/ppp2ppp/3N4/4p3/4P3/8/PPPP1PPP/RNBQKBNR[Pp] b KQkq - 0 4",
+        "rnbqkbnr/ppp2ppp/3N4/4p3/4P3/8/PPPP1PPP/RNBQKBNR/Pp b KQkq - 0 4",
+    ] {
+        let position: Position = without_pockets(fen).parse().unwrap();
+        assert_eq!(position.checked_king(), parse_square("e8"));
+    }
+    assert_eq!(without_pockets(STARTING_FEN), STARTING_FEN);
+}
+
+#[test]
+fn test_uci_line_renders_as_san() {
+    // Legal's mate: the bishop is pinned, the queen is given up and mate follows
+    let position: Position = "r2qkbnr/ppp2ppp/2np4/4p2b/2B1P3/2N2N1P/PPPP1PP1/R1BQK2R w KQkq - 1 6"
+        .parse()
+        .unwrap();
+    let pv = ["f3e5", "h5d1", "c4f7", "e8e7", "c3d5"];
+    assert_eq!(
+        position.san_line(&pv),
+        Ok(vec!["Nxe5".to_string(), "Bxd1".into(), "Bxf7+".into(), "Ke7".into(), "Nd5#".into()])
+    );
+    assert_eq!(
+        position.san_line(&["f3e5", "f3e5"]),
+        Err(MoveError::Illegal("f3e5".to_string()))
+    );
+}
+
+#[test]
+fn test_uci_line_disambiguates_against_the_evolving_position() {
+    let position: Position = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1".parse().unwrap();
+    // After the a-rook leaves, the h-rook no longer needs its file
+    assert_eq!(
+        position.san_line(&["h1d1", "e8f8", "a1a8", "f8g7", "d1d7"]),
+        Ok(vec!["Rhd1".to_string(), "Kf8".into(), "Ra8+".into(), "Kg7".into(), "Rd7+".into()])
+    );
+}
+
+#[test]
+fn test_perft_in_chess960_positions() {
+    // Positions from the published Chess960 perft suite, castling rights in Shredder-FEN
+    let position: Position = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9".parse().unwrap();
+    assert_eq!(perft(&position, 1), 21);
+    assert_eq!(perft(&position, 2), 528);
+    assert_eq!(perft(&position, 3), 12189);
+
+    let position: Position = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9".parse().unwrap();
+    assert_eq!(perft(&position, 1), 21);
+    assert_eq!(perft(&position, 2), 807);
+    assert_eq!(perft(&position, 3), 18002);
+}
+
+#[test]
+fn test_chess960_castling_finds_rooks_off_the_corners() {
+    // King on c1 with rooks on b1 and g1
+    let position: Position = "4k3/8/8/8/8/8/8/1RK3R1 w KQ - 0 1".parse().unwrap();
+
+    let short = position.play(&position.parse_san("O-O").unwrap()).unwrap();
+    assert_eq!(short.to_fen(), "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+
+    // Queenside the king is already on c1, so only the rook moves; engines write it
+    // as the king taking its rook
+    let long = position.parse_uci("c1b1").unwrap();
+    assert_eq!(long, position.parse_san("O-O-O").unwrap());
+    assert_eq!(position.play(&long).unwrap().to_fen(), "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+}
+
+#[test]
+fn test_chess960_castling_needs_the_paths_clear_and_safe() {
+    // A knight stands on the rook's landing square
+    let blocked: Position = "4k3/8/8/8/8/8/8/1RK2NR1 w KQ - 0 1".parse().unwrap();
+    assert!(blocked.parse_san("O-O").is_err());
+    // The bishop on a6 covers f1, which the king crosses on its way to g1
+    let attacked: Position = "4k3/8/b7/8/8/8/8/1RK3R1 w KQ - 0 1".parse().unwrap();
+    assert!(attacked.parse_san("O-O").is_err());
+    assert!(attacked.parse_san("O-O-O").is_ok());
+}
+
+#[test]
+fn test_castling_rights_follow_their_rook() {
+    // Two rooks on the queenside: `Q` is the outer one, `B` names the inner one
+    let outer: Position = "4k3/8/8/8/8/8/8/RR2K3 w Q - 0 1".parse().unwrap();
+    let inner: Position = "4k3/8/8/8/8/8/8/RR2K3 w B - 0 1".parse().unwrap();
+    assert_eq!(outer.to_fen(), "4k3/8/8/8/8/8/8/RR2K3 w Q - 0 1");
+    assert_eq!(inner.to_fen(), "4k3/8/8/8/8/8/8/RR2K3 w B - 0 1");
+
+    // Moving the castling rook gives up its side only
+    let position: Position = "4k3/8/8/8/8/8/8/1RK3R1 w KQ - 0 1".parse().unwrap();
+    let after = play_all(position, &["Rb2"]);
+    assert_eq!(after.to_fen(), "4k3/8/8/8/8/8/1R6/2K3R1 b K - 1 1");
+}
@@ -0,0 +1,48 @@
+use chess::time_control::{TimeClass, TimeControl, time_class};
+use std::time::Duration;
+
+fn tc(initial_secs: u64, increment_secs: u64) -> TimeControl {
+    TimeControl {
+        initial_time: Duration::from_secs(initial_secs),
+        increment: Duration::from_secs(increment_secs),
+        delay: Duration::ZERO,
+    }
+}
+
+#[test]
+fn common_time_controls() {
+    assert_eq!(time_class(&tc(60, 0)), TimeClass::Bullet);
+    assert_eq!(time_class(&tc(180, 2)), TimeClass::Blitz);
+    assert_eq!(time_class(&tc(600, 0)), TimeClass::Rapid);
+    assert_eq!(time_class(&tc(1800, 20)), TimeClass::Classical);
+    assert_eq!(time_class(&tc(0, 86_400)), TimeClass::Correspondence);
+}
+
+#[test]
+fn bullet_blitz_cutoff() {
+    // 2+1 is 120 + 40 = 160s, 2+2 is exactly 200s, 3+0 exactly 180s
+    assert_eq!(time_class(&tc(179, 0)), TimeClass::Bullet);
+    assert_eq!(time_class(&tc(180, 0)), TimeClass::Blitz);
+    assert_eq!(time_class(&tc(120, 1)), TimeClass::Bullet);
+    assert_eq!(time_class(&tc(120, 2)), TimeClass::Blitz);
+}
+
+#[test]
+fn blitz_rapid_and_rapid_classical_cutoffs() {
+    assert_eq!(time_class(&tc(479, 0)), TimeClass::Blitz);
+    assert_eq!(time_class(&tc(480, 0)), TimeClass::Rapid);
+    assert_eq!(time_class(&tc(1499, 0)), TimeClass::Rapid);
+    assert_eq!(time_class(&tc(1500, 0)), TimeClass::Classical);
+    assert_eq!(time_class(&tc(86_399, 0)), TimeClass::Classical);
+    assert_eq!(time_class(&tc(86_400, 0)), TimeClass::Correspondence);
+}
+
+#[test]
+fn delay_counts_like_increment() {
+    let delayed = TimeControl {
+        initial_time: Duration::from_secs(120),
+        increment: Duration::ZERO,
+        delay: Duration::from_secs(2),
+    };
+    assert_eq!(time_class(&delayed), TimeClass::Blitz);
+}
@@ -0,0 +1,88 @@
+use chess::bitboard::Board::{Color, Role};
+use chess::position::Position;
+use chess::variant::{
+    Chess960, Crazyhouse, GameStatus, KingOfTheHill, Outcome, Pockets, StalemateRule, Standard, Variant,
+    WithStalemateRule,
+};
+
+fn stalemated_black() -> Position {
+    "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1".parse().unwrap()
+}
+
+#[test]
+fn test_built_in_variants_draw_stalemate() {
+    let position = stalemated_black();
+    for variant in [&Standard as &dyn Variant, &Chess960, &KingOfTheHill] {
+        assert_eq!(variant.game_status(&position), GameStatus::Stalemate(Outcome::Draw), "{}", variant.name());
+    }
+}
+
+#[test]
+fn test_stalemate_rule_decides_the_same_position() {
+    let position = stalemated_black();
+    let wins = WithStalemateRule { variant: Standard, rule: StalemateRule::StalematedWins };
+    let loses = WithStalemateRule { variant: Standard, rule: StalemateRule::StalematedLoses };
+
+    assert_eq!(wins.game_status(&position), GameStatus::Stalemate(Outcome::Win(Color::Black)));
+    assert_eq!(loses.game_status(&position), GameStatus::Stalemate(Outcome::Win(Color::White)));
+    assert_eq!(wins.name(), "Standard");
+}
+
+#[test]
+fn test_stalemate_rule_leaves_other_endings_alone() {
+    let wins = WithStalemateRule { variant: Standard, rule: StalemateRule::StalematedWins };
+    let mate: Position = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".parse().unwrap();
+    let bare_kings: Position = "8/8/4k3/8/8/4K3/8/8 w - - 0 1".parse().unwrap();
+
+    assert_eq!(wins.game_status(&mate), GameStatus::Checkmate(Outcome::Win(Color::Black)));
+    assert_eq!(wins.game_status(&bare_kings), GameStatus::InsufficientMaterial);
+    assert_eq!(wins.game_status(&Position::default()), GameStatus::Ongoing);
+}
+
+fn black_holds(roles: &[Role]) -> Pockets {
+    Pockets { white: vec![], black: roles.to_vec() }
+}
+
+#[test]
+fn test_crazyhouse_drops_can_block_mate() {
+    // Back-rank mate, unless Black drops a piece between the rook and the king
+    let mate: Position = "R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1".parse().unwrap();
+
+    assert_eq!(Crazyhouse.game_status(&mate), GameStatus::Checkmate(Outcome::Win(Color::White)));
+    assert_eq!(Crazyhouse.game_status_with_pockets(&mate, &black_holds(&[Role::Knight])), GameStatus::Ongoing);
+    // Pawns may not be dropped on the last rank
+    assert_eq!(
+        Crazyhouse.game_status_with_pockets(&mate, &black_holds(&[Role::Pawn])),
+        GameStatus::Checkmate(Outcome::Win(Color::White))
+    );
+    // Other variants have no drops
+    assert_eq!(
+        Standard.game_status_with_pockets(&mate, &black_holds(&[Role::Knight])),
+        GameStatus::Checkmate(Outcome::Win(Color::White))
+    );
+}
+
+#[test]
+fn test_crazyhouse_pockets_count_as_material() {
+    let bare_kings: Position = "8/8/4k3/8/8/4K3/8/8 w - - 0 1".parse().unwrap();
+
+    assert_eq!(Crazyhouse.game_status(&bare_kings), GameStatus::InsufficientMaterial);
+    assert_eq!(Crazyhouse.game_status_with_pockets(&bare_kings, &black_holds(&[Role::Pawn])), GameStatus::Ongoing);
+    // A piece in hand is a move for the stalemated side
+    assert_eq!(
+        Crazyhouse.game_status_with_pockets(&stalemated_black(), &black_holds(&[Role::Bishop])),
+        GameStatus::Ongoing
+    );
+}
+
+#[test]
+fn test_stalemate_rule_wraps_a_variant_chosen_at_runtime() {
+    let variant: &'static dyn Variant = &Crazyhouse;
+    let wins = WithStalemateRule { variant, rule: StalemateRule::StalematedWins };
+
+    assert_eq!(wins.game_status(&stalemated_black()), GameStatus::Stalemate(Outcome::Win(Color::Black)));
+    assert_eq!(wins.game_status_with_pockets(&stalemated_black(), &black_holds(&[Role::Rook])), GameStatus::Ongoing);
+    assert_eq!(wins.name(), "Crazyhouse");
+    assert_eq!("stalemated_loses".parse(), Ok(StalemateRule::StalematedLoses));
+    assert!("checkmate".parse::<StalemateRule>().is_err());
+}
@@ -1,6 +1,10 @@
 use sea_orm::{*, ActiveValue::Set, EntityTrait, QueryFilter, QuerySelect, sea_query::Expr};
-use db_entity::prelude::{Game, Player};
+use db_entity::bulk::{insert_batch, BatchMode, InsertSummary};
+use db_entity::prelude::Game;
+use db_entity::sea_orm_active_enums::ResultSide;
+use db_entity::seed::{self, SeedMode};
 use db_entity::{game, player};
+use chess::fen::generate_chess960_fen;
 use serde_json::{json, Value as JsonValue};
 use uuid::Uuid;
 use std::env;
@@ -14,6 +18,8 @@ use dotenv::dotenv;
 const NUM_PLAYERS_TO_CREATE: usize = 100;
 const NUM_GAMES_TO_INSERT: usize = 10_000;
 const BATCH_SIZE: usize = 100; // Insert games in batches
+// Every benchmark player's username starts with this, so reruns find their own rows
+const TAG: &str = "bench_user_";
 
 // Helper to connect to the database
 async fn setup_db() -> Result<DatabaseConnection, DbErr> {
@@ -23,8 +29,40 @@ async fn setup_db() -> Result<DatabaseConnection, DbErr> {
     Database::connect(&db_url).await
 }
 
-// Helper to generate random PGN-like JSON data
-fn generate_random_pgn(rng: &mut ThreadRng) -> JsonValue {
+// Pass `--skip-bad-rows` to retry failed batches row by row instead of aborting
+fn batch_mode() -> BatchMode {
+    if env::args().any(|arg| arg == "--skip-bad-rows") {
+        BatchMode::FallbackPerRow
+    } else {
+        BatchMode::AllOrNothing
+    }
+}
+
+// Pass `--reset` to drop data left by an earlier run first, or `--skip-existing` to
+// reuse it as is. By default an earlier run is topped up to the configured counts.
+fn seed_mode() -> SeedMode {
+    SeedMode::from_args(env::args())
+}
+
+// Pass `--keep` to leave the benchmark data in place for the next run
+fn keep_data() -> bool {
+    env::args().any(|arg| arg == "--keep")
+}
+
+// Pass `--realistic-variants` to shape Chess960 and crazyhouse games like production
+// rows: a real 960 start position and pockets with drops. Generation is slower.
+fn realistic_variants() -> bool {
+    env::args().any(|arg| arg == "--realistic-variants")
+}
+
+fn report_failures(what: &str, summary: &InsertSummary) {
+    for failed in &summary.failed {
+        eprintln!("  Skipped {} #{}: {}", what, failed.index, failed.error);
+    }
+}
+
+// Helper to generate random PGN-like JSON data for a game ending in `result`
+fn generate_random_pgn(rng: &mut ThreadRng, result: ResultSide) -> JsonValue {
     let num_moves: usize = rng.gen_range(20..100);
     let moves: Vec<String> = (0..num_moves)
         .map(|_| {
@@ -43,13 +81,49 @@ fn generate_random_pgn(rng: &mut ThreadRng) -> JsonValue {
         "round": rng.gen_range(1..10).to_string(),
         "white": format!("Bench Player W{}", rng.gen::<u16>()),
         "black": format!("Bench Player B{}", rng.gen::<u16>()),
-        "result": match rng.gen_range(0..3) { 0 => "1-0", 1 => "0-1", _ => "1/2-1/2" },
+        "result": result.as_pgn(),
         "moves": moves,
         "clock_start": 180.0,
         "final_ply": num_moves
     })
 }
 
+// Captured pieces a side holds in hand, as piece letters
+fn crazyhouse_pocket(rng: &mut ThreadRng, white: bool) -> Vec<String> {
+    let pieces = ['P', 'P', 'P', 'N', 'B', 'R', 'Q'];
+    (0..rng.gen_range(0..6))
+        .map(|_| {
+            let piece = pieces[rng.gen_range(0..pieces.len())];
+            let piece = if white { piece } else { piece.to_ascii_lowercase() };
+            piece.to_string()
+        })
+        .collect()
+}
+
+// Adds what a game of `variant` stores on top of the common PGN fields
+fn add_variant_data(variant: &str, pgn: &mut JsonValue, rng: &mut ThreadRng) {
+    match variant {
+        "chess960" => {
+            pgn["starting_fen"] = json!(generate_chess960_fen());
+        }
+        "crazyhouse" => {
+            pgn["pockets"] = json!({
+                "white": crazyhouse_pocket(rng, true),
+                "black": crazyhouse_pocket(rng, false),
+            });
+            // Sprinkle drops among the moves
+            if let Some(moves) = pgn["moves"].as_array_mut() {
+                for mv in moves.iter_mut().skip(10).step_by(7) {
+                    let piece = ['N', 'B', 'R', 'Q', 'P'][rng.gen_range(0..5)];
+                    let file = (b'a' + rng.gen_range(0..8)) as char;
+                    *mv = json!(format!("{}@{}{}", piece, file, rng.gen_range(3..7)));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 // Helper to generate random FEN-like string
 fn generate_random_fen(rng: &mut ThreadRng) -> String {
     let len = rng.gen_range(40..70); // Calculate len first
@@ -65,69 +139,83 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Starting game benchmark...");
     let db = setup_db().await?;
     let mut rng = thread_rng();
+    let mode = batch_mode();
+    let realistic = realistic_variants();
+    if realistic {
+        println!("Generating variant-specific data (--realistic-variants)");
+    }
 
     // === Setup: Create Players ===
-    println!("Creating {} players...", NUM_PLAYERS_TO_CREATE);
-    let mut player_models = Vec::with_capacity(NUM_PLAYERS_TO_CREATE);
-    for i in 0..NUM_PLAYERS_TO_CREATE {
-        let player_id = Uuid::new_v4(); // Generate UUID here
-        player_models.push(player::ActiveModel {
-            id: Set(player_id), // Explicitly set the ID
-            username: Set(format!("bench_user_{}_{}", i, Uuid::new_v4().simple())),
-            email: Set(format!("bench_email_{}_{}@bench.com", i, Uuid::new_v4().simple())),
+    let seed_mode = seed_mode();
+    println!("Seeding {} players ({:?})...", NUM_PLAYERS_TO_CREATE, seed_mode);
+    let players = seed::seed_players(&db, TAG, NUM_PLAYERS_TO_CREATE, seed_mode, mode, |i, username| {
+        player::ActiveModel {
+            id: Set(Uuid::new_v4()), // Explicitly set the ID
+            email: Set(format!("{}@bench.com", username)),
+            username: Set(username),
             password_hash: Set(b"bench_hash".to_vec()),
-            biography: Set("Benchmark player biography".to_string()), // Provide a non-null value
-            country: Set("Unknown".to_string()), // Add default
-            flair: Set("Bench Flair".to_string()), // Add default
-            real_name: Set("Bench Real Name".to_string()), // Add default
-            location: Set("Bench Location".to_string()), // Add default
-            fide_rating: Set(1500), // Add default
-            social_links: Set(vec![]), // Add default (empty vec)
+            biography: Set(Some("Benchmark player biography".to_string())), // Provide a non-null value
+            country: Set(Some("XX".to_string())), // Prefer not to say
+            flair: Set(Some("Bench Flair".to_string())), // Add default
+            real_name: Set(format!("Bench Real Name {}", i)),
+            location: Set(Some("Bench Location".to_string())), // Add default
+            fide_rating: Set(Some(1500)), // Add default
+            social_links: Set(Some(vec![])), // Add default (empty vec)
             ..Default::default()
-        });
+        }
+    })
+    .await?;
+    if players.removed.players > 0 {
+        println!(
+            "Removed {} players and {} games from an earlier run.",
+            players.removed.players, players.removed.games
+        );
     }
-    let _insert_res = Player::insert_many(player_models).exec(&db).await?;
-    println!("Inserted {} players.", NUM_PLAYERS_TO_CREATE);
-
-    // Fetch the IDs of the created players
-    let players = Player::find()
-        .filter(player::Column::Username.starts_with("bench_user_"))
-        .limit(NUM_PLAYERS_TO_CREATE as u64)
-        .all(&db)
-        .await?;
-    let player_ids: Vec<Uuid> = players.into_iter().map(|p| p.id).collect();
+    println!("Inserted {} players.", players.inserted);
+    let player_ids = players.ids;
 
     if player_ids.len() < 2 {
         panic!("Need at least 2 players to create games");
     }
-    println!("Fetched {} player IDs for game creation.", player_ids.len());
+    println!("Using {} player IDs for game creation.", player_ids.len());
 
     // === Benchmark: Insertions ===
-    println!("Inserting {} games in batches of {}...", NUM_GAMES_TO_INSERT, BATCH_SIZE);
+    let games_to_insert = seed::games_to_add(&db, &player_ids, NUM_GAMES_TO_INSERT, seed_mode).await?;
+    println!("Inserting {} games in batches of {}...", games_to_insert, BATCH_SIZE);
     let mut game_models = Vec::with_capacity(BATCH_SIZE);
     let variants = ["standard", "chess960", "crazyhouse", "kingofthehill"];
-    let results = ["white", "black", "draw"];
+    let results = [ResultSide::White, ResultSide::Black, ResultSide::Draw];
     let insert_start = Instant::now();
+    let mut games_inserted: u64 = 0;
 
-    for i in 0..NUM_GAMES_TO_INSERT {
+    for i in 0..games_to_insert {
         let white_player_id = player_ids[rng.gen_range(0..player_ids.len())];
         let black_player_id = player_ids[rng.gen_range(0..player_ids.len())];
         let game_id = Uuid::new_v4(); // Generate UUID for the game
+        let variant = variants[rng.gen_range(0..variants.len())];
+        let result = results[rng.gen_range(0..results.len())];
+        let mut pgn = generate_random_pgn(&mut rng, result);
+        if realistic {
+            add_variant_data(variant, &mut pgn, &mut rng);
+        }
 
         game_models.push(game::ActiveModel {
             id: Set(game_id), // Explicitly set the game ID
+            public_id: Set(rng.sample_iter(&Alphanumeric).take(8).map(char::from).collect()),
             white_player: Set(white_player_id),
             black_player: Set(black_player_id),
             fen: Set(generate_random_fen(&mut rng)),
-            pgn: Set(generate_random_pgn(&mut rng)),
-            result: Set(results[rng.gen_range(0..results.len())].to_string()),
-            variant: Set(variants[rng.gen_range(0..variants.len())].to_string()),
+            pgn: Set(pgn),
+            result: Set(Some(result)),
+            variant: Set(variant.to_string()),
             duration_sec: Set(rng.gen_range(30..600)),
             ..Default::default() // started_at has default
         });
 
-        if game_models.len() >= BATCH_SIZE || i == NUM_GAMES_TO_INSERT - 1 {
-            Game::insert_many(game_models.drain(..)).exec(&db).await?;
+        if game_models.len() >= BATCH_SIZE || i == games_to_insert - 1 {
+            let summary = insert_batch(&db, game_models.drain(..).collect(), mode).await?;
+            report_failures("game", &summary);
+            games_inserted += summary.inserted;
             if (i + 1) % (BATCH_SIZE * 10) == 0 { // Print progress
                  println!("  Inserted {} games...", i + 1);
             }
@@ -137,9 +225,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let insert_duration = insert_start.elapsed();
     println!(
         "Finished inserting {} games in {:.2?}. Average: {:.2} games/sec",
-        NUM_GAMES_TO_INSERT,
+        games_inserted,
         insert_duration,
-        NUM_GAMES_TO_INSERT as f64 / insert_duration.as_secs_f64()
+        games_inserted as f64 / insert_duration.as_secs_f64()
     );
 
     // Add a small delay to ensure data is queryable
@@ -198,23 +286,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     // === Cleanup (Optional but recommended) ===
+    if keep_data() {
+        println!("\nKeeping benchmark data (--keep).");
+        return Ok(());
+    }
     println!("\nStarting cleanup (deleting benchmark games and players)... This might take a while.");
     let cleanup_start = Instant::now();
 
-    // Delete games associated with the benchmark players
-    let delete_games_res = Game::delete_many()
-        .filter(
-            game::Column::WhitePlayer.is_in(player_ids.clone())
-            .or(game::Column::BlackPlayer.is_in(player_ids.clone()))
-        )
-        .exec(&db).await?;
-    println!("  Deleted {} game records.", delete_games_res.rows_affected);
-
-    // Delete benchmark players
-    let delete_players_res = Player::delete_many()
-        .filter(player::Column::Username.starts_with("bench_user_"))
-        .exec(&db).await?;
-    println!("  Deleted {} player records.", delete_players_res.rows_affected);
+    // Only rows tagged by this benchmark are removed
+    let removed = seed::remove_seeded(&db, TAG).await?;
+    println!("  Deleted {} game records.", removed.games);
+    println!("  Deleted {} player records.", removed.players);
 
     let cleanup_duration = cleanup_start.elapsed();
     println!("Cleanup finished in {:.2?}.", cleanup_duration);
@@ -0,0 +1,161 @@
+//! Batch inserts that can survive a single bad row.
+//!
+//! `insert_many` is all-or-nothing: one duplicate key fails the whole statement and
+//! the error does not say which row caused it. [`insert_batch`] keeps that fast path
+//! by default and, when asked to, retries a failed batch row by row so the offending
+//! rows are skipped and reported while the rest are still inserted.
+
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, TransactionTrait};
+
+/// What [`insert_batch`] does when the multi-row insert fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Fail the whole batch with the database error.
+    #[default]
+    AllOrNothing,
+    /// Retry each row on its own, skipping and reporting the rows that fail.
+    FallbackPerRow,
+}
+
+/// A row rejected during a per-row fallback.
+#[derive(Debug)]
+pub struct FailedRow {
+    /// Position of the row in the batch passed to [`insert_batch`].
+    pub index: usize,
+    pub error: DbErr,
+}
+
+/// Outcome of [`insert_batch`].
+#[derive(Debug, Default)]
+pub struct InsertSummary {
+    pub inserted: u64,
+    pub failed: Vec<FailedRow>,
+}
+
+impl InsertSummary {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Errors that say nothing about the row itself; retrying the other rows would only
+/// repeat them.
+fn is_connection_error(error: &DbErr) -> bool {
+    matches!(error, DbErr::Conn(_) | DbErr::ConnectionAcquire(_))
+}
+
+/// Inserts `rows` with a single statement. The batch runs in its own (nested)
+/// transaction so that, in [`BatchMode::FallbackPerRow`], a failure leaves the
+/// connection usable for the per-row retry even inside a caller's transaction.
+pub async fn insert_batch<A, C>(db: &C, rows: Vec<A>, mode: BatchMode) -> Result<InsertSummary, DbErr>
+where
+    A: ActiveModelTrait + Clone + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait + TransactionTrait,
+{
+    if rows.is_empty() {
+        return Ok(InsertSummary::default());
+    }
+
+    let txn = db.begin().await?;
+    let batch_error = match A::Entity::insert_many(rows.clone()).exec_without_returning(&txn).await {
+        Ok(inserted) => {
+            txn.commit().await?;
+            return Ok(InsertSummary { inserted, failed: Vec::new() });
+        }
+        Err(error) => {
+            txn.rollback().await?;
+            error
+        }
+    };
+
+    if mode == BatchMode::AllOrNothing || is_connection_error(&batch_error) {
+        return Err(batch_error);
+    }
+
+    let mut summary = InsertSummary::default();
+    for (index, row) in rows.into_iter().enumerate() {
+        let txn = db.begin().await?;
+        match A::Entity::insert(row).exec_without_returning(&txn).await {
+            Ok(inserted) => {
+                txn.commit().await?;
+                summary.inserted += inserted;
+            }
+            Err(error) if is_connection_error(&error) => return Err(error),
+            Err(error) => {
+                txn.rollback().await?;
+                summary.failed.push(FailedRow { index, error });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player;
+    use sea_orm::{ActiveValue::Set, DatabaseBackend, MockDatabase, MockExecResult};
+    use uuid::Uuid;
+
+    fn players(count: usize) -> Vec<player::ActiveModel> {
+        (0..count)
+            .map(|i| player::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                username: Set(format!("bulk_{}", i)),
+                email: Set(format!("bulk_{}@example.com", i)),
+                password_hash: Set(b"hash".to_vec()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn affected(rows: u64) -> MockExecResult {
+        MockExecResult { last_insert_id: 0, rows_affected: rows }
+    }
+
+    fn duplicate() -> DbErr {
+        DbErr::Custom("duplicate key value violates unique constraint \"player_username_key\"".into())
+    }
+
+    #[async_std::test]
+    async fn clean_batch_is_a_single_statement() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([affected(3)])
+            .into_connection();
+
+        let summary = insert_batch(&db, players(3), BatchMode::FallbackPerRow).await.unwrap();
+
+        assert_eq!(summary.inserted, 3);
+        assert!(summary.is_complete());
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn all_or_nothing_surfaces_batch_error() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_errors([duplicate()])
+            .into_connection();
+
+        let result = insert_batch(&db, players(3), BatchMode::AllOrNothing).await;
+
+        assert!(result.is_err());
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn fallback_skips_and_reports_bad_rows() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_errors([duplicate()])
+            .append_exec_results([affected(1)])
+            .append_exec_errors([duplicate()])
+            .append_exec_results([affected(1)])
+            .into_connection();
+
+        let summary = insert_batch(&db, players(3), BatchMode::FallbackPerRow).await.unwrap();
+
+        assert_eq!(summary.inserted, 2);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].index, 1);
+    }
+}
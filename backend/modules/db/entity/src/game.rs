@@ -4,21 +4,55 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
+use super::sea_orm_active_enums::{GameVariant, ResultSide, Termination};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DeriveEntityModel)]
 #[sea_orm(table_name = "game", schema_name = "smdb")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
+    /// Short code used in shareable links.
+    #[sea_orm(unique)]
+    pub public_id: String,
     pub white_player: Uuid,
     pub black_player: Uuid,
     #[sea_orm(column_type = "Text")]
     pub fen: String,
     #[sea_orm(column_type = "JsonBinary")]
     pub pgn: Json,
-    pub result: ResultSide,
+    pub result: Option<ResultSide>,
+    pub termination: Option<Termination>,
+    pub draw_offered_by: Option<Uuid>,
     pub variant: GameVariant,
     pub started_at: DateTimeWithTimeZone,
     pub duration_sec: i32,
+    pub ended_at: Option<DateTimeWithTimeZone>,
+    /// When the game was rated. Tournament games are rated together once their round is over.
+    pub rated_at: Option<DateTimeWithTimeZone>,
+    pub eco: Option<String>,
+    pub opening_name: Option<String>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub suspicion_score: Option<f64>,
+    /// Game this one is a rematch of.
+    pub parent_game_id: Option<Uuid>,
+    /// Draw offers each side has made in this game.
+    pub white_draw_offers: i32,
+    pub black_draw_offers: i32,
+    /// Ply count when each side last offered a draw.
+    pub white_last_draw_offer_ply: Option<i32>,
+    pub black_last_draw_offer_ply: Option<i32>,
+    /// Time control, `None` for untimed games.
+    pub clock_initial_secs: Option<i32>,
+    pub clock_increment_secs: Option<i32>,
+    /// Each side's remaining time as of `last_move_at`.
+    pub white_remaining_ms: Option<i64>,
+    pub black_remaining_ms: Option<i64>,
+    /// When the side to move's clock started; `None` until the first move.
+    pub last_move_at: Option<DateTimeWithTimeZone>,
+    /// Set while a real-time clock is stopped because neither player is connected.
+    pub clock_paused_at: Option<DateTimeWithTimeZone>,
+    /// Time the clock has stood stopped since `last_move_at`, not charged to anyone.
+    pub clock_paused_ms: i64,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -50,3 +84,46 @@ impl Related<super::player::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Model {
+    /// An untimed, unfinished standard game between `white` and `black` in the
+    /// starting position, created now. Tests set what they need on top of it with
+    /// struct update syntax.
+    pub fn fixture(white: Uuid, black: Uuid) -> Self {
+        let now: DateTimeWithTimeZone = DateTimeUtc::from(std::time::SystemTime::now()).into();
+        Self {
+            id: Uuid::new_v4(),
+            public_id: "Gm7kQ2xP".to_string(),
+            white_player: white,
+            black_player: black,
+            fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+            pgn: serde_json::json!({ "moves": [] }),
+            result: None,
+            termination: None,
+            draw_offered_by: None,
+            variant: GameVariant::Standard,
+            started_at: now,
+            duration_sec: 0,
+            ended_at: None,
+            rated_at: None,
+            eco: None,
+            opening_name: None,
+            suspicion_score: None,
+            parent_game_id: None,
+            white_draw_offers: 0,
+            black_draw_offers: 0,
+            white_last_draw_offer_ply: None,
+            black_last_draw_offer_ply: None,
+            clock_initial_secs: None,
+            clock_increment_secs: None,
+            white_remaining_ms: None,
+            black_remaining_ms: None,
+            last_move_at: None,
+            clock_paused_at: None,
+            clock_paused_ms: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
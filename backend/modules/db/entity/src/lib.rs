@@ -1,6 +1,23 @@
 pub mod prelude;
+pub mod bulk;
 pub mod game;
+pub mod game_archive;
+pub mod game_seek;
+pub mod match_analytics;
+pub mod notification_preference;
 pub mod player;
+pub mod player_rating;
+pub mod rating_history;
+pub mod rating_recompute_history;
+pub mod rating_recompute_job;
+pub mod rating_recompute_rating;
+pub mod result;
+pub mod sea_orm_active_enums;
+pub mod seed;
+pub mod tournament;
+pub mod tournament_pairing;
+pub mod webhook_attempt;
+pub mod webhook_delivery;
 
 // You could also potentially just use the mod.rs generated by sea-orm
 // by uncommenting the line below, but explicitly declaring modules
@@ -13,15 +13,17 @@ pub struct Model {
     pub email: String,
     #[sea_orm(column_type = "VarBinary(StringLen::None)")]
     pub password_hash: Vec<u8>,
-    #[sea_orm(column_type = "Text")]
-    pub biography: String,
-    pub country: String,
-    pub flair: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub biography: Option<String>,
+    pub country: Option<String>,
+    pub flair: Option<String>,
     pub real_name: String,
     pub location: Option<String>,
     pub fide_rating: Option<i32>,
     pub social_links: Option<Vec<String>>,
-    pub is_enabled: bool
+    pub is_enabled: bool,
+    pub is_guest: bool,
+    pub last_seen_at: DateTimeWithTimeZone,
 }
 
 
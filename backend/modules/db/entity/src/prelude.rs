@@ -2,3 +2,5 @@
 
 pub use super::game::Entity as Game;
 pub use super::player::Entity as Player;
+pub use super::player_rating::Entity as PlayerRating;
+pub use super::rating_history::Entity as RatingHistory;
@@ -0,0 +1,98 @@
+//! Text forms of a game result.
+//!
+//! The `result` column stores the colour form (`white`, `black`, `draw`), which is
+//! [`ResultSide`]'s active enum value, while PGN writes `1-0`, `0-1` and `1/2-1/2`,
+//! with `*` for a game that has not finished. [`ResultSide`] is the one value both
+//! are read into and written from, so the column, exports and generated data cannot
+//! drift apart.
+
+use std::fmt;
+use std::str::FromStr;
+
+use sea_orm::ActiveEnum;
+
+use crate::sea_orm_active_enums::ResultSide;
+
+/// PGN result token of a game without a result.
+pub const PGN_UNFINISHED: &str = "*";
+
+/// A result string that is neither a colour nor a PGN token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownResult(pub String);
+
+impl fmt::Display for UnknownResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown game result '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownResult {}
+
+impl ResultSide {
+    pub fn as_pgn(self) -> &'static str {
+        match self {
+            ResultSide::White => "1-0",
+            ResultSide::Black => "0-1",
+            ResultSide::Draw => "1/2-1/2",
+        }
+    }
+
+    /// Reads a decisive or drawn PGN token; `*` is not a result, see [`from_pgn_token`].
+    pub fn from_pgn(value: &str) -> Option<Self> {
+        match value {
+            "1-0" => Some(ResultSide::White),
+            "0-1" => Some(ResultSide::Black),
+            "1/2-1/2" => Some(ResultSide::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// Accepts either form, so callers need not know where a string came from.
+impl FromStr for ResultSide {
+    type Err = UnknownResult;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ResultSide::try_from_value(&value.to_string())
+            .ok()
+            .or_else(|| ResultSide::from_pgn(value))
+            .ok_or_else(|| UnknownResult(value.to_string()))
+    }
+}
+
+/// PGN `Result` token of a stored result, `*` while the game is unfinished.
+pub fn pgn_token(result: Option<ResultSide>) -> &'static str {
+    result.map_or(PGN_UNFINISHED, ResultSide::as_pgn)
+}
+
+/// Inverse of [`pgn_token`].
+pub fn from_pgn_token(token: &str) -> Result<Option<ResultSide>, UnknownResult> {
+    if token == PGN_UNFINISHED {
+        return Ok(None);
+    }
+    ResultSide::from_pgn(token).map(Some).ok_or_else(|| UnknownResult(token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::Iterable;
+
+    #[test]
+    fn pgn_form_round_trips_including_unfinished() {
+        for result in ResultSide::iter().map(Some).chain([None]) {
+            assert_eq!(from_pgn_token(pgn_token(result)), Ok(result));
+        }
+        assert_eq!(pgn_token(Some(ResultSide::Draw)), "1/2-1/2");
+    }
+
+    #[test]
+    fn either_form_parses_to_the_same_side() {
+        for side in ResultSide::iter() {
+            assert_eq!(side.to_value().parse(), Ok(side));
+            assert_eq!(side.as_pgn().parse(), Ok(side));
+        }
+        assert!("1-1".parse::<ResultSide>().is_err());
+        assert!(from_pgn_token("white").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Re-runnable seeding for the development tools.
+//!
+//! The seeder and the game benchmark tag every player they create with a username
+//! prefix. Before inserting, a tool looks for what an earlier run left under its own
+//! prefix and, depending on [`SeedMode`], keeps it, tops it up to the requested size
+//! or removes it and starts over. Rows without the prefix are never read or deleted.
+
+use std::collections::HashSet;
+
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::bulk::{insert_batch, BatchMode};
+use crate::{game, player};
+
+/// How a tool treats the rows it left behind on an earlier run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeedMode {
+    /// Insert only what is missing to reach the requested counts.
+    #[default]
+    TopUp,
+    /// Leave earlier data as it is and insert nothing if any exists.
+    Skip,
+    /// Remove the tool's own rows, then seed from scratch.
+    Reset,
+}
+
+impl SeedMode {
+    /// `--reset` wins over `--skip-existing`; with neither the data is topped up.
+    pub fn from_args<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut mode = SeedMode::TopUp;
+        for arg in args {
+            match arg.as_ref() {
+                "--reset" => return SeedMode::Reset,
+                "--skip-existing" => mode = SeedMode::Skip,
+                _ => {}
+            }
+        }
+        mode
+    }
+}
+
+/// Username of the `index`-th player a tool seeds under `prefix`. Stable across runs,
+/// so a top-up can tell which players are already there.
+pub fn username(prefix: &str, index: usize) -> String {
+    format!("{}{}", prefix, index)
+}
+
+/// Rows deleted by [`remove_seeded`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Removed {
+    pub games: u64,
+    pub players: u64,
+}
+
+/// Outcome of [`seed_players`].
+#[derive(Debug, Default)]
+pub struct SeededPlayers {
+    /// Every player under the prefix once seeding is done, old and new.
+    pub ids: Vec<Uuid>,
+    pub inserted: u64,
+    pub removed: Removed,
+}
+
+/// Ids and usernames of the players tagged with `prefix`.
+pub async fn seeded_players<C>(db: &C, prefix: &str) -> Result<Vec<(Uuid, String)>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    player::Entity::find()
+        .select_only()
+        .column(player::Column::Id)
+        .column(player::Column::Username)
+        .filter(player::Column::Username.starts_with(prefix))
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+fn involving(player_ids: &[Uuid]) -> Condition {
+    Condition::any()
+        .add(game::Column::WhitePlayer.is_in(player_ids.to_vec()))
+        .add(game::Column::BlackPlayer.is_in(player_ids.to_vec()))
+}
+
+/// Number of games played by any of `player_ids`.
+pub async fn seeded_game_count<C>(db: &C, player_ids: &[Uuid]) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if player_ids.is_empty() {
+        return Ok(0);
+    }
+    game::Entity::find().filter(involving(player_ids)).count(db).await
+}
+
+/// Deletes the players tagged with `prefix` and the games they played.
+pub async fn remove_seeded<C>(db: &C, prefix: &str) -> Result<Removed, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let ids: Vec<Uuid> = seeded_players(db, prefix).await?.into_iter().map(|(id, _)| id).collect();
+    if ids.is_empty() {
+        return Ok(Removed::default());
+    }
+
+    let games = game::Entity::delete_many().filter(involving(&ids)).exec(db).await?;
+    let players = player::Entity::delete_many()
+        .filter(player::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+    Ok(Removed { games: games.rows_affected, players: players.rows_affected })
+}
+
+/// Brings the players under `prefix` up to `target`. `make` builds the player for an
+/// index and the username it must carry.
+pub async fn seed_players<C, F>(
+    db: &C,
+    prefix: &str,
+    target: usize,
+    mode: SeedMode,
+    batch: BatchMode,
+    mut make: F,
+) -> Result<SeededPlayers, DbErr>
+where
+    C: ConnectionTrait + TransactionTrait,
+    F: FnMut(usize, String) -> player::ActiveModel,
+{
+    let removed = match mode {
+        SeedMode::Reset => remove_seeded(db, prefix).await?,
+        _ => Removed::default(),
+    };
+    let existing = match mode {
+        SeedMode::Reset => Vec::new(),
+        _ => seeded_players(db, prefix).await?,
+    };
+
+    let missing: Vec<usize> = if mode == SeedMode::Skip && !existing.is_empty() {
+        Vec::new()
+    } else {
+        let taken: HashSet<&str> = existing.iter().map(|(_, name)| name.as_str()).collect();
+        (0..target).filter(|&i| !taken.contains(username(prefix, i).as_str())).collect()
+    };
+    if missing.is_empty() {
+        let ids = existing.into_iter().map(|(id, _)| id).collect();
+        return Ok(SeededPlayers { ids, inserted: 0, removed });
+    }
+
+    let rows = missing.into_iter().map(|i| make(i, username(prefix, i))).collect();
+    let summary = insert_batch(db, rows, batch).await?;
+    let ids = seeded_players(db, prefix).await?.into_iter().map(|(id, _)| id).collect();
+    Ok(SeededPlayers { ids, inserted: summary.inserted, removed })
+}
+
+/// How many games to insert so that `player_ids` have played `target` between them.
+pub async fn games_to_add<C>(
+    db: &C,
+    player_ids: &[Uuid],
+    target: usize,
+    mode: SeedMode,
+) -> Result<usize, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let existing = seeded_game_count(db, player_ids).await? as usize;
+    Ok(match mode {
+        SeedMode::Skip if existing > 0 => 0,
+        _ => target.saturating_sub(existing),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{
+        ActiveValue::Set, DatabaseBackend, DatabaseConnection, MockDatabase, MockExecResult, Value,
+    };
+    use std::collections::BTreeMap;
+
+    const PREFIX: &str = "seed_test_";
+
+    fn make(_: usize, name: String) -> player::ActiveModel {
+        player::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            email: Set(format!("{}@example.com", name)),
+            username: Set(name),
+            password_hash: Set(b"hash".to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn rows(players: &[(Uuid, String)]) -> Vec<BTreeMap<&'static str, Value>> {
+        players
+            .iter()
+            .map(|(id, name)| {
+                BTreeMap::from([("id", Value::from(*id)), ("username", Value::from(name.clone()))])
+            })
+            .collect()
+    }
+
+    fn count(n: i64) -> Vec<BTreeMap<&'static str, Value>> {
+        vec![BTreeMap::from([("num_items", Value::from(n))])]
+    }
+
+    fn affected(rows: u64) -> MockExecResult {
+        MockExecResult { last_insert_id: 0, rows_affected: rows }
+    }
+
+    // What the tools do: players first, then games among them
+    async fn seed(db: &DatabaseConnection, players: usize, games: usize) -> (SeededPlayers, usize) {
+        let seeded = seed_players(db, PREFIX, players, SeedMode::TopUp, BatchMode::AllOrNothing, make)
+            .await
+            .unwrap();
+        let to_add = games_to_add(db, &seeded.ids, games, SeedMode::TopUp).await.unwrap();
+        (seeded, to_add)
+    }
+
+    #[test]
+    fn mode_comes_from_flags() {
+        assert_eq!(SeedMode::from_args(["seeder"]), SeedMode::TopUp);
+        assert_eq!(SeedMode::from_args(["seeder", "--skip-existing"]), SeedMode::Skip);
+        assert_eq!(SeedMode::from_args(["seeder", "--skip-existing", "--reset"]), SeedMode::Reset);
+    }
+
+    #[async_std::test]
+    async fn seeding_twice_keeps_counts_stable() {
+        let players: Vec<(Uuid, String)> =
+            (0..3).map(|i| (Uuid::new_v4(), username(PREFIX, i))).collect();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            // First run: nothing tagged yet, so everything is inserted
+            .append_query_results([rows(&[])])
+            .append_exec_results([affected(3)])
+            .append_query_results([rows(&players), count(0)])
+            // Second run: the tagged rows are found and nothing is missing
+            .append_query_results([rows(&players), count(4)])
+            .into_connection();
+
+        let (first, first_games) = seed(&db, 3, 4).await;
+        let (second, second_games) = seed(&db, 3, 4).await;
+
+        assert_eq!((first.inserted, first_games), (3, 4));
+        assert_eq!((second.inserted, second_games), (0, 0));
+        assert_eq!(first.ids, second.ids);
+
+        let inserts = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .filter(|s| s.sql.starts_with("INSERT"))
+            .count();
+        assert_eq!(inserts, 1, "the second run inserts nothing");
+    }
+
+    #[async_std::test]
+    async fn top_up_inserts_only_missing_players() {
+        let kept = vec![(Uuid::new_v4(), username(PREFIX, 1))];
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([rows(&kept)])
+            .append_exec_results([affected(2)])
+            .append_query_results([rows(&kept)])
+            .into_connection();
+        let mut made = Vec::new();
+
+        seed_players(&db, PREFIX, 3, SeedMode::TopUp, BatchMode::AllOrNothing, |i, name| {
+            made.push(i);
+            make(i, name)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(made, vec![0, 2]);
+    }
+
+    #[async_std::test]
+    async fn reset_deletes_only_tagged_rows() {
+        let players = vec![(Uuid::new_v4(), username(PREFIX, 0))];
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([rows(&players)])
+            .append_exec_results([affected(5), affected(1)])
+            .into_connection();
+
+        let removed = remove_seeded(&db, PREFIX).await.unwrap();
+
+        assert_eq!(removed, Removed { games: 5, players: 1 });
+        let log = db.into_transaction_log();
+        let deletes: Vec<String> = log
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .filter(|s| s.sql.starts_with("DELETE"))
+            .map(|s| s.sql)
+            .collect();
+        assert_eq!(deletes.len(), 2);
+        assert!(deletes.iter().all(|sql| sql.contains("WHERE")), "{:?}", deletes);
+    }
+}
@@ -53,6 +53,7 @@ async fn test_insert_and_verify_game() -> Result<(), Box<dyn std::error::Error>>
 
     // 3. Create the ActiveModel for the new game
     let game_model = game::ActiveModel {
+        public_id: Set(Uuid::new_v4().simple().to_string()[..8].to_string()),
         white_player: Set(player_id),
         black_player: Set(player_id), // Using same player for white/black for simplicity
         fen: Set(game_fen.to_string()),
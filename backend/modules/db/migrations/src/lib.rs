@@ -3,6 +3,28 @@ pub use sea_orm_migration::prelude::*;
 mod m20250428_121011_create_players_table;
 mod m20250429_163843_create_games_table;
 mod m20250429_192832_add_common_indexes;
+mod m20261015_090000_add_game_opening_columns;
+mod m20261015_100000_create_rating_tables;
+mod m20261015_110000_add_game_termination;
+mod m20261015_120000_create_match_analytics;
+mod m20261015_130000_normalize_player_country;
+mod m20261015_135000_add_game_rated_at;
+mod m20261015_140000_add_game_ended_at;
+mod m20261015_150000_create_notification_preferences;
+mod m20261015_160000_add_game_suspicion_score;
+mod m20261015_170000_create_tournament_tables;
+mod m20261015_180000_create_webhook_deliveries;
+mod m20261015_190000_add_player_guest_columns;
+mod m20261015_200000_create_game_seeks;
+mod m20261015_210000_add_game_public_id;
+mod m20261015_220000_add_player_username_lower_index;
+mod m20261015_230000_add_tournament_rounds;
+mod m20261015_240000_add_game_parent_game_id;
+mod m20261015_250000_add_game_draw_offer_counts;
+mod m20261015_260000_add_game_clock_state;
+mod m20261015_270000_create_game_archive;
+mod m20261015_280000_create_webhook_attempts;
+mod m20261015_290000_rename_recompute_cursor;
 
 pub struct Migrator;
 
@@ -13,6 +35,28 @@ impl MigratorTrait for Migrator {
             Box::new(m20250428_121011_create_players_table::Migration),
             Box::new(m20250429_163843_create_games_table::Migration),
             Box::new(m20250429_192832_add_common_indexes::Migration),
+            Box::new(m20261015_090000_add_game_opening_columns::Migration),
+            Box::new(m20261015_100000_create_rating_tables::Migration),
+            Box::new(m20261015_110000_add_game_termination::Migration),
+            Box::new(m20261015_120000_create_match_analytics::Migration),
+            Box::new(m20261015_130000_normalize_player_country::Migration),
+            Box::new(m20261015_135000_add_game_rated_at::Migration),
+            Box::new(m20261015_140000_add_game_ended_at::Migration),
+            Box::new(m20261015_150000_create_notification_preferences::Migration),
+            Box::new(m20261015_160000_add_game_suspicion_score::Migration),
+            Box::new(m20261015_170000_create_tournament_tables::Migration),
+            Box::new(m20261015_180000_create_webhook_deliveries::Migration),
+            Box::new(m20261015_190000_add_player_guest_columns::Migration),
+            Box::new(m20261015_200000_create_game_seeks::Migration),
+            Box::new(m20261015_210000_add_game_public_id::Migration),
+            Box::new(m20261015_220000_add_player_username_lower_index::Migration),
+            Box::new(m20261015_230000_add_tournament_rounds::Migration),
+            Box::new(m20261015_240000_add_game_parent_game_id::Migration),
+            Box::new(m20261015_250000_add_game_draw_offer_counts::Migration),
+            Box::new(m20261015_260000_add_game_clock_state::Migration),
+            Box::new(m20261015_270000_create_game_archive::Migration),
+            Box::new(m20261015_280000_create_webhook_attempts::Migration),
+            Box::new(m20261015_290000_rename_recompute_cursor::Migration),
         ]
     }
 }
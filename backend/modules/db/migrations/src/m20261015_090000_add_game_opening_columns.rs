@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::Eco).string_len(3).null())
+                    .add_column(ColumnDef::new(Game::OpeningName).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // varchar_pattern_ops lets the same index serve both `eco = 'B20'` and the
+        // `eco LIKE 'B2%'` prefix lookups used by the list filter.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_eco" ON "smdb"."game" ("eco" varchar_pattern_ops)"#,
+            )
+            .await?;
+
+        println!("Game opening columns added successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_game_eco""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::Eco)
+                    .drop_column(Game::OpeningName)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Eco,
+    OpeningName,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
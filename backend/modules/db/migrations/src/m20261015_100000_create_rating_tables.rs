@@ -0,0 +1,278 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Current rating per player
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, PlayerRating::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(PlayerRating::PlayerId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(PlayerRating::Rating).integer().not_null().default(1500))
+                    .col(ColumnDef::new(PlayerRating::GamesPlayed).integer().not_null().default(0))
+                    .col(
+                        ColumnDef::new(PlayerRating::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_player_rating_player")
+                            .from(PlayerRating::Table, PlayerRating::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per player per rated game
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, RatingHistory::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(RatingHistory::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RatingHistory::PlayerId).uuid().not_null())
+                    .col(ColumnDef::new(RatingHistory::GameId).uuid().not_null())
+                    .col(ColumnDef::new(RatingHistory::RatingBefore).integer().not_null())
+                    .col(ColumnDef::new(RatingHistory::RatingAfter).integer().not_null())
+                    .col(
+                        ColumnDef::new(RatingHistory::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rating_history_player")
+                            .from(RatingHistory::Table, RatingHistory::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rating_history_game")
+                            .from(RatingHistory::Table, RatingHistory::GameId)
+                            .to((Smdb, Game::Table), Game::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rating_history_player_created_at")
+                    .table((Smdb, RatingHistory::Table))
+                    .col(RatingHistory::PlayerId)
+                    .col(RatingHistory::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Recompute jobs and their progress cursor
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, RatingRecomputeJob::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(RatingRecomputeJob::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RatingRecomputeJob::Status).string().not_null())
+                    .col(ColumnDef::new(RatingRecomputeJob::DryRun).boolean().not_null())
+                    .col(ColumnDef::new(RatingRecomputeJob::TotalGames).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(RatingRecomputeJob::ProcessedGames)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(RatingRecomputeJob::CursorStartedAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(RatingRecomputeJob::CursorGameId).uuid().null())
+                    .col(ColumnDef::new(RatingRecomputeJob::Report).json_binary().null())
+                    .col(ColumnDef::new(RatingRecomputeJob::Error).text().null())
+                    .col(
+                        ColumnDef::new(RatingRecomputeJob::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RatingRecomputeJob::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RatingRecomputeJob::FinishedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."rating_recompute_job" ADD CONSTRAINT "check_rating_recompute_job_status" CHECK ("status" IN ('running', 'completed', 'failed'))"#,
+            )
+            .await?;
+
+        // Shadow tables: a job replays into these and swaps them in when it finishes
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, RatingRecomputeRating::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(RatingRecomputeRating::JobId).uuid().not_null())
+                    .col(ColumnDef::new(RatingRecomputeRating::PlayerId).uuid().not_null())
+                    .col(ColumnDef::new(RatingRecomputeRating::Rating).integer().not_null())
+                    .col(ColumnDef::new(RatingRecomputeRating::GamesPlayed).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(RatingRecomputeRating::JobId)
+                            .col(RatingRecomputeRating::PlayerId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rating_recompute_rating_job")
+                            .from(RatingRecomputeRating::Table, RatingRecomputeRating::JobId)
+                            .to((Smdb, RatingRecomputeJob::Table), RatingRecomputeJob::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, RatingRecomputeHistory::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(RatingRecomputeHistory::JobId).uuid().not_null())
+                    .col(ColumnDef::new(RatingRecomputeHistory::GameId).uuid().not_null())
+                    .col(ColumnDef::new(RatingRecomputeHistory::PlayerId).uuid().not_null())
+                    .col(ColumnDef::new(RatingRecomputeHistory::RatingBefore).integer().not_null())
+                    .col(ColumnDef::new(RatingRecomputeHistory::RatingAfter).integer().not_null())
+                    .col(ColumnDef::new(RatingRecomputeHistory::CreatedAt).timestamp_with_time_zone().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(RatingRecomputeHistory::JobId)
+                            .col(RatingRecomputeHistory::GameId)
+                            .col(RatingRecomputeHistory::PlayerId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rating_recompute_history_job")
+                            .from(RatingRecomputeHistory::Table, RatingRecomputeHistory::JobId)
+                            .to((Smdb, RatingRecomputeJob::Table), RatingRecomputeJob::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Rating tables created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, RatingRecomputeHistory::Table)).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, RatingRecomputeRating::Table)).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, RatingRecomputeJob::Table)).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, RatingHistory::Table)).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, PlayerRating::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Rating tables dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PlayerRating {
+    Table,
+    PlayerId,
+    Rating,
+    GamesPlayed,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RatingHistory {
+    Table,
+    Id,
+    PlayerId,
+    GameId,
+    RatingBefore,
+    RatingAfter,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RatingRecomputeJob {
+    Table,
+    Id,
+    Status,
+    DryRun,
+    TotalGames,
+    ProcessedGames,
+    CursorStartedAt,
+    CursorGameId,
+    Report,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+    FinishedAt,
+}
+
+#[derive(DeriveIden)]
+enum RatingRecomputeRating {
+    Table,
+    JobId,
+    PlayerId,
+    Rating,
+    GamesPlayed,
+}
+
+#[derive(DeriveIden)]
+enum RatingRecomputeHistory {
+    Table,
+    JobId,
+    GameId,
+    PlayerId,
+    RatingBefore,
+    RatingAfter,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
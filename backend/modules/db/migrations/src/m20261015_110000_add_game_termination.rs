@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Live games are stored too: `result` stays NULL until the game is finalized
+        // and `termination` records how it ended. Rows stored before this migration
+        // keep their result and a NULL termination, since how they ended is unknown.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .modify_column(ColumnDef::new(Game::Result).string().null())
+                    .add_column(ColumnDef::new(Game::Termination).string().null())
+                    .add_column(ColumnDef::new(Game::DrawOfferedBy).uuid().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."game" ADD CONSTRAINT "check_game_termination" CHECK ("termination" IN ('checkmate', 'resignation', 'agreement', 'timeout', 'stalemate', 'repetition', 'fifty_move', 'insufficient_material', 'abandonment', 'aborted'))"#,
+            )
+            .await?;
+
+        println!("Game termination columns added successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"ALTER TABLE "smdb"."game" DROP CONSTRAINT IF EXISTS "check_game_termination""#)
+            .await?;
+
+        // Unfinished games cannot be represented without a result
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DELETE FROM "smdb"."game" WHERE "result" IS NULL"#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::Termination)
+                    .drop_column(Game::DrawOfferedBy)
+                    .modify_column(ColumnDef::new(Game::Result).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Result,
+    Termination,
+    DrawOfferedBy,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
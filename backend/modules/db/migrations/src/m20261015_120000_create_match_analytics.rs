@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per match formed by the matchmaker
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, MatchAnalytics::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(MatchAnalytics::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(MatchAnalytics::MatchId).uuid().not_null())
+                    .col(ColumnDef::new(MatchAnalytics::MatchType).string().not_null())
+                    .col(ColumnDef::new(MatchAnalytics::RatingGap).integer().not_null())
+                    .col(ColumnDef::new(MatchAnalytics::EloWindow).integer().null())
+                    .col(ColumnDef::new(MatchAnalytics::WaitTimeMs).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(MatchAnalytics::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."match_analytics" ADD CONSTRAINT "check_match_analytics_match_type" CHECK ("match_type" IN ('rated', 'casual', 'private'))"#,
+            )
+            .await?;
+
+        // Analytics are always read over a time window
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_match_analytics_created_at")
+                    .table((Smdb, MatchAnalytics::Table))
+                    .col(MatchAnalytics::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Match analytics table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, MatchAnalytics::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Match analytics table dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MatchAnalytics {
+    Table,
+    Id,
+    MatchId,
+    MatchType,
+    RatingGap,
+    EloWindow,
+    WaitTimeMs,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
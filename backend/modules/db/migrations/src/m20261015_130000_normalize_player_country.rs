@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Kept in step with `dto::country::ISO_COUNTRY_CODES`
+const ISO_COUNTRY_CODES: &str = "
+    'AD', 'AE', 'AF', 'AG', 'AI', 'AL', 'AM', 'AO', 'AQ', 'AR', 'AS', 'AT', 'AU', 'AW', 'AX', 'AZ',
+    'BA', 'BB', 'BD', 'BE', 'BF', 'BG', 'BH', 'BI', 'BJ', 'BL', 'BM', 'BN', 'BO', 'BQ', 'BR', 'BS',
+    'BT', 'BV', 'BW', 'BY', 'BZ', 'CA', 'CC', 'CD', 'CF', 'CG', 'CH', 'CI', 'CK', 'CL', 'CM', 'CN',
+    'CO', 'CR', 'CU', 'CV', 'CW', 'CX', 'CY', 'CZ', 'DE', 'DJ', 'DK', 'DM', 'DO', 'DZ', 'EC', 'EE',
+    'EG', 'EH', 'ER', 'ES', 'ET', 'FI', 'FJ', 'FK', 'FM', 'FO', 'FR', 'GA', 'GB', 'GD', 'GE', 'GF',
+    'GG', 'GH', 'GI', 'GL', 'GM', 'GN', 'GP', 'GQ', 'GR', 'GS', 'GT', 'GU', 'GW', 'GY', 'HK', 'HM',
+    'HN', 'HR', 'HT', 'HU', 'ID', 'IE', 'IL', 'IM', 'IN', 'IO', 'IQ', 'IR', 'IS', 'IT', 'JE', 'JM',
+    'JO', 'JP', 'KE', 'KG', 'KH', 'KI', 'KM', 'KN', 'KP', 'KR', 'KW', 'KY', 'KZ', 'LA', 'LB', 'LC',
+    'LI', 'LK', 'LR', 'LS', 'LT', 'LU', 'LV', 'LY', 'MA', 'MC', 'MD', 'ME', 'MF', 'MG', 'MH', 'MK',
+    'ML', 'MM', 'MN', 'MO', 'MP', 'MQ', 'MR', 'MS', 'MT', 'MU', 'MV', 'MW', 'MX', 'MY', 'MZ', 'NA',
+    'NC', 'NE', 'NF', 'NG', 'NI', 'NL', 'NO', 'NP', 'NR', 'NU', 'NZ', 'OM', 'PA', 'PE', 'PF', 'PG',
+    'PH', 'PK', 'PL', 'PM', 'PN', 'PR', 'PS', 'PT', 'PW', 'PY', 'QA', 'RE', 'RO', 'RS', 'RU', 'RW',
+    'SA', 'SB', 'SC', 'SD', 'SE', 'SG', 'SH', 'SI', 'SJ', 'SK', 'SL', 'SM', 'SN', 'SO', 'SR', 'SS',
+    'ST', 'SV', 'SX', 'SY', 'SZ', 'TC', 'TD', 'TF', 'TG', 'TH', 'TJ', 'TK', 'TL', 'TM', 'TN', 'TO',
+    'TR', 'TT', 'TV', 'TW', 'TZ', 'UA', 'UG', 'UM', 'US', 'UY', 'UZ', 'VA', 'VC', 'VE', 'VG', 'VI',
+    'VN', 'VU', 'WF', 'WS', 'YE', 'YT', 'ZA', 'ZM', 'ZW'
+";
+
+// Free-text values seen in existing rows, mapped to the code they most likely meant
+const LEGACY_COUNTRY_NAMES: &str = "
+    ('USA', 'US'), ('UNITED STATES', 'US'), ('UNITED STATES OF AMERICA', 'US'), ('AMERICA', 'US'),
+    ('UK', 'GB'), ('GBR', 'GB'), ('UNITED KINGDOM', 'GB'), ('GREAT BRITAIN', 'GB'), ('ENGLAND', 'GB'),
+    ('NGA', 'NG'), ('NIGERIA', 'NG'), ('CAN', 'CA'), ('CANADA', 'CA'), ('IND', 'IN'), ('INDIA', 'IN'),
+    ('DEU', 'DE'), ('GERMANY', 'DE'), ('FRA', 'FR'), ('FRANCE', 'FR'), ('ESP', 'ES'), ('SPAIN', 'ES'),
+    ('ITA', 'IT'), ('ITALY', 'IT'), ('RUS', 'RU'), ('RUSSIA', 'RU'), ('CHN', 'CN'), ('CHINA', 'CN'),
+    ('BRA', 'BR'), ('BRAZIL', 'BR'), ('AUS', 'AU'), ('AUSTRALIA', 'AU'), ('NLD', 'NL'),
+    ('NETHERLANDS', 'NL'), ('KEN', 'KE'), ('KENYA', 'KE'), ('GHA', 'GH'), ('GHANA', 'GH'),
+    ('ZAF', 'ZA'), ('SOUTH AFRICA', 'ZA'), ('JPN', 'JP'), ('JAPAN', 'JP'), ('MEX', 'MX'), ('MEXICO', 'MX'),
+    ('ARG', 'AR'), ('ARGENTINA', 'AR'), ('UKR', 'UA'), ('UKRAINE', 'UA'), ('POL', 'PL'), ('POLAND', 'PL'),
+    ('NOR', 'NO'), ('NORWAY', 'NO')
+";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Valid codes are upper-cased, recognizable names and alpha-3 codes are mapped,
+        // and anything else ("Unknown" from the benchmark seeder included) becomes the
+        // "prefer not to say" sentinel.
+        let normalize = format!(
+            r#"WITH legacy (name, code) AS (VALUES {LEGACY_COUNTRY_NAMES})
+            UPDATE "player" SET "country" = CASE
+                WHEN upper(btrim("country")) IN ({ISO_COUNTRY_CODES}) THEN upper(btrim("country"))
+                ELSE COALESCE(
+                    (SELECT legacy.code FROM legacy WHERE legacy.name = upper(btrim("player"."country"))),
+                    'XX'
+                )
+            END
+            WHERE "country" IS NOT NULL"#
+        );
+        let connection = manager.get_connection();
+        connection.execute_unprepared(&normalize).await?;
+
+        connection
+            .execute_unprepared(&format!(
+                r#"ALTER TABLE "player" ADD CONSTRAINT "check_player_country" CHECK ("country" IN ({ISO_COUNTRY_CODES}, 'XX'))"#
+            ))
+            .await?;
+
+        println!("Player countries normalized to ISO 3166-1 alpha-2 codes successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The original free-text values are not recoverable; only the constraint is lifted
+        manager
+            .get_connection()
+            .execute_unprepared(r#"ALTER TABLE "player" DROP CONSTRAINT IF EXISTS "check_player_country""#)
+            .await?;
+
+        println!("Player country constraint dropped successfully.");
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `ended_at` is NULL while a game is in progress and is the authoritative
+        // marker for it; `result` and `termination` only describe how a game ended.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::EndedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Finished games stored so far only know their duration
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE "smdb"."game" SET "ended_at" = "started_at" + make_interval(secs => "duration_sec") WHERE "result" IS NOT NULL OR "termination" IS NOT NULL"#,
+            )
+            .await?;
+
+        // Partial index for the live-games query; it stays as small as the set of
+        // games currently being played.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_live" ON "smdb"."game" ("started_at") WHERE "ended_at" IS NULL"#,
+            )
+            .await?;
+
+        println!("Game ended_at column added successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_game_live""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::EndedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    EndedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
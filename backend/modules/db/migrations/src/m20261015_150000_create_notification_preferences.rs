@@ -0,0 +1,109 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per player who changed a setting; everyone else gets the defaults
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, NotificationPreference::Table))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NotificationPreference::PlayerId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::CorrespondenceTurn)
+                            .string()
+                            .not_null()
+                            .default("email"),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::GameResult)
+                            .string()
+                            .not_null()
+                            .default("none"),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::DrawOffer)
+                            .string()
+                            .not_null()
+                            .default("none"),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::TournamentStart)
+                            .string()
+                            .not_null()
+                            .default("email"),
+                    )
+                    .col(ColumnDef::new(NotificationPreference::WebhookUrl).text().null())
+                    .col(
+                        ColumnDef::new(NotificationPreference::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notification_preference_player")
+                            .from(NotificationPreference::Table, NotificationPreference::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in ["correspondence_turn", "game_result", "draw_offer", "tournament_start"] {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"ALTER TABLE "smdb"."notification_preference" ADD CONSTRAINT "check_notification_preference_{0}" CHECK ("{0}" IN ('email', 'webhook', 'none'))"#,
+                    column
+                ))
+                .await?;
+        }
+
+        println!("Notification preference table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table((Smdb, NotificationPreference::Table))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreference {
+    Table,
+    PlayerId,
+    CorrespondenceTurn,
+    GameResult,
+    DrawOffer,
+    TournamentStart,
+    WebhookUrl,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
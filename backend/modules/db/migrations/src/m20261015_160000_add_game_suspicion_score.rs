@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NULL until the fair-play review has analysed the game
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::SuspicionScore).double().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Admins list reviewed games by score; unreviewed games stay out of the index
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_suspicion_score" ON "smdb"."game" ("suspicion_score" DESC) WHERE "suspicion_score" IS NOT NULL"#,
+            )
+            .await?;
+
+        println!("Game suspicion_score column added successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_game_suspicion_score""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::SuspicionScore)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    SuspicionScore,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,172 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, Tournament::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(Tournament::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Tournament::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(Tournament::TieBreak)
+                            .string()
+                            .not_null()
+                            .default("buchholz"),
+                    )
+                    .col(
+                        ColumnDef::new(Tournament::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Tournament::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."tournament" ADD CONSTRAINT "check_tournament_tie_break" CHECK ("tie_break" IN ('buchholz', 'sonneborn_berger'))"#,
+            )
+            .await?;
+
+        // One row per board of a round; a bye is a row without a black player
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, TournamentPairing::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(TournamentPairing::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(TournamentPairing::TournamentId).uuid().not_null())
+                    .col(ColumnDef::new(TournamentPairing::Round).integer().not_null())
+                    .col(ColumnDef::new(TournamentPairing::WhitePlayer).uuid().not_null())
+                    .col(ColumnDef::new(TournamentPairing::BlackPlayer).uuid().null())
+                    .col(ColumnDef::new(TournamentPairing::GameId).uuid().null())
+                    .col(ColumnDef::new(TournamentPairing::Result).string().null())
+                    .col(
+                        ColumnDef::new(TournamentPairing::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tournament_pairing_tournament")
+                            .from(TournamentPairing::Table, TournamentPairing::TournamentId)
+                            .to(Tournament::Table, Tournament::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tournament_pairing_white_player")
+                            .from(TournamentPairing::Table, TournamentPairing::WhitePlayer)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tournament_pairing_black_player")
+                            .from(TournamentPairing::Table, TournamentPairing::BlackPlayer)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tournament_pairing_game")
+                            .from(TournamentPairing::Table, TournamentPairing::GameId)
+                            .to(Game::Table, Game::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."tournament_pairing" ADD CONSTRAINT "check_tournament_pairing_result" CHECK ("result" IN ('white', 'black', 'draw'))"#,
+            )
+            .await?;
+
+        // Standings read every pairing of a tournament, usually round by round
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tournament_pairing_tournament_round")
+                    .table((Smdb, TournamentPairing::Table))
+                    .col(TournamentPairing::TournamentId)
+                    .col(TournamentPairing::Round)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Tournament tables created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, TournamentPairing::Table)).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, Tournament::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Tournament tables dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tournament {
+    Table,
+    Id,
+    Name,
+    TieBreak,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TournamentPairing {
+    Table,
+    Id,
+    TournamentId,
+    Round,
+    WhitePlayer,
+    BlackPlayer,
+    GameId,
+    Result,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,146 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Outbox of webhook notifications; rows are kept after delivery for debugging
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, WebhookDelivery::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(WebhookDelivery::Id).uuid().not_null().primary_key())
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Sequence)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment(),
+                    )
+                    .col(ColumnDef::new(WebhookDelivery::PlayerId).uuid().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Event).string().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::IdempotencyKey).string().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::TargetUrl).text().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Payload).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::NextAttemptAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::LastAttemptAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(WebhookDelivery::LastError).text().null())
+                    .col(
+                        ColumnDef::new(WebhookDelivery::DeliveredAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_delivery_player")
+                            .from(WebhookDelivery::Table, WebhookDelivery::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."webhook_delivery" ADD CONSTRAINT "check_webhook_delivery_status" CHECK ("status" IN ('pending', 'delivered', 'dead'))"#,
+            )
+            .await?;
+
+        // An event is queued at most once per subscriber
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_delivery_idempotency_key")
+                    .table((Smdb, WebhookDelivery::Table))
+                    .col(WebhookDelivery::PlayerId)
+                    .col(WebhookDelivery::IdempotencyKey)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The worker walks each subscriber's queue in order
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_delivery_player_sequence")
+                    .table((Smdb, WebhookDelivery::Table))
+                    .col(WebhookDelivery::PlayerId)
+                    .col(WebhookDelivery::Sequence)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Webhook delivery table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, WebhookDelivery::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Webhook delivery table dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookDelivery {
+    Table,
+    Id,
+    Sequence,
+    PlayerId,
+    Event,
+    IdempotencyKey,
+    TargetUrl,
+    Payload,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    LastAttemptAt,
+    LastError,
+    DeliveredAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // An advertised game waiting for an opponent; the game row only exists once
+        // someone claims the seat
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GameSeek::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(GameSeek::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GameSeek::Creator).uuid().not_null())
+                    .col(ColumnDef::new(GameSeek::Color).string().not_null().default("random"))
+                    .col(ColumnDef::new(GameSeek::Variant).string().not_null().default("standard"))
+                    .col(ColumnDef::new(GameSeek::InitialSecs).integer().not_null())
+                    .col(ColumnDef::new(GameSeek::IncrementSecs).integer().not_null().default(0))
+                    .col(ColumnDef::new(GameSeek::Status).string().not_null().default("waiting"))
+                    .col(ColumnDef::new(GameSeek::AcceptedBy).uuid().null())
+                    .col(ColumnDef::new(GameSeek::GameId).uuid().null())
+                    .col(
+                        ColumnDef::new(GameSeek::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GameSeek::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_game_seek_creator")
+                            .from(GameSeek::Table, GameSeek::Creator)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_game_seek_accepted_by")
+                            .from(GameSeek::Table, GameSeek::AcceptedBy)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_game_seek_game")
+                            .from(GameSeek::Table, GameSeek::GameId)
+                            .to(Game::Table, Game::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."game_seek" ADD CONSTRAINT "check_game_seek_color" CHECK ("color" IN ('white', 'black', 'random'))"#,
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."game_seek" ADD CONSTRAINT "check_game_seek_status" CHECK ("status" IN ('waiting', 'accepted'))"#,
+            )
+            .await?;
+
+        // The seek board only ever lists open seeks, oldest first
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_seek_waiting" ON "smdb"."game_seek" ("created_at") WHERE "status" = 'waiting'"#,
+            )
+            .await?;
+
+        println!("Game seek table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, GameSeek::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Game seek table dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameSeek {
+    Table,
+    Id,
+    Creator,
+    Color,
+    Variant,
+    InitialSecs,
+    IncrementSecs,
+    Status,
+    AcceptedBy,
+    GameId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
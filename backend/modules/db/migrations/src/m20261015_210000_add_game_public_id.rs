@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Short code used in shareable links; the UUID stays the primary key
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::PublicId).string_len(8).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE UNIQUE INDEX IF NOT EXISTS "idx_game_public_id" ON "smdb"."game" ("public_id")"#,
+            )
+            .await?;
+
+        // Existing games get a random base62 code each, drawn again on the rare collision
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DO $$
+                DECLARE
+                    existing RECORD;
+                    code TEXT;
+                BEGIN
+                    FOR existing IN SELECT "id" FROM "smdb"."game" WHERE "public_id" IS NULL LOOP
+                        LOOP
+                            SELECT string_agg(substr('0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz', 1 + floor(random() * 62)::int, 1), '')
+                              INTO code
+                              FROM generate_series(1, 8);
+                            BEGIN
+                                UPDATE "smdb"."game" SET "public_id" = code WHERE "id" = existing."id";
+                                EXIT;
+                            EXCEPTION WHEN unique_violation THEN
+                                -- Draw another code
+                            END;
+                        END LOOP;
+                    END LOOP;
+                END $$;
+                "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .modify_column(ColumnDef::new(Game::PublicId).string_len(8).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Game public_id column added successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_game_public_id""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::PublicId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    PublicId,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Later rounds are paired automatically until `rounds` have been played
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Tournament::Table))
+                    .add_column(ColumnDef::new(Tournament::Rounds).integer().not_null().default(5))
+                    .add_column(
+                        ColumnDef::new(Tournament::ForfeitPolicy)
+                            .string()
+                            .not_null()
+                            .default("loss"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."tournament" ADD CONSTRAINT "check_tournament_rounds" CHECK ("rounds" > 0)"#,
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."tournament" ADD CONSTRAINT "check_tournament_forfeit_policy" CHECK ("forfeit_policy" IN ('loss', 'draw'))"#,
+            )
+            .await?;
+
+        // Finished games are matched back to their board by game id
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE UNIQUE INDEX IF NOT EXISTS "idx_tournament_pairing_game" ON "smdb"."tournament_pairing" ("game_id") WHERE "game_id" IS NOT NULL"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_tournament_pairing_game""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Tournament::Table))
+                    .drop_column(Tournament::ForfeitPolicy)
+                    .drop_column(Tournament::Rounds)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tournament {
+    Table,
+    Rounds,
+    ForfeitPolicy,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
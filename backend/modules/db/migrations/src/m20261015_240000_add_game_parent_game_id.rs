@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A rematch points at the game it follows, chaining games into a series
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE "smdb"."game" ADD COLUMN IF NOT EXISTS "parent_game_id" UUID NULL REFERENCES "smdb"."game" ("id") ON DELETE SET NULL"#,
+            )
+            .await?;
+
+        // Series are walked from a game to its rematches, newest first
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_parent_game_id" ON "smdb"."game" ("parent_game_id", "created_at") WHERE "parent_game_id" IS NOT NULL"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP INDEX IF EXISTS "smdb"."idx_game_parent_game_id""#)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::ParentGameId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    ParentGameId,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Draw offers each side has made, and the ply of its latest, for throttling
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::WhiteDrawOffers).integer().not_null().default(0))
+                    .add_column(ColumnDef::new(Game::BlackDrawOffers).integer().not_null().default(0))
+                    .add_column(ColumnDef::new(Game::WhiteLastDrawOfferPly).integer().null())
+                    .add_column(ColumnDef::new(Game::BlackLastDrawOfferPly).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::BlackLastDrawOfferPly)
+                    .drop_column(Game::WhiteLastDrawOfferPly)
+                    .drop_column(Game::BlackDrawOffers)
+                    .drop_column(Game::WhiteDrawOffers)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    WhiteDrawOffers,
+    BlackDrawOffers,
+    WhiteLastDrawOfferPly,
+    BlackLastDrawOfferPly,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Time control and clocks of timed games; all NULL for untimed ones
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::ClockInitialSecs).integer().null())
+                    .add_column(ColumnDef::new(Game::ClockIncrementSecs).integer().null())
+                    .add_column(ColumnDef::new(Game::WhiteRemainingMs).big_integer().null())
+                    .add_column(ColumnDef::new(Game::BlackRemainingMs).big_integer().null())
+                    .add_column(ColumnDef::new(Game::LastMoveAt).timestamp_with_time_zone().null())
+                    .add_column(ColumnDef::new(Game::ClockPausedAt).timestamp_with_time_zone().null())
+                    .add_column(ColumnDef::new(Game::ClockPausedMs).big_integer().not_null().default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::ClockPausedMs)
+                    .drop_column(Game::ClockPausedAt)
+                    .drop_column(Game::LastMoveAt)
+                    .drop_column(Game::BlackRemainingMs)
+                    .drop_column(Game::WhiteRemainingMs)
+                    .drop_column(Game::ClockIncrementSecs)
+                    .drop_column(Game::ClockInitialSecs)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    ClockInitialSecs,
+    ClockIncrementSecs,
+    WhiteRemainingMs,
+    BlackRemainingMs,
+    LastMoveAt,
+    ClockPausedAt,
+    ClockPausedMs,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
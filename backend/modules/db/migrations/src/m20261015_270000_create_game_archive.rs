@@ -0,0 +1,123 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Foreign keys into `game` as they stood before archival, restored by `down`.
+const GAME_REFERENCES: [(&str, &str, &str, &str); 4] = [
+    ("rating_history", "fk_rating_history_game", "game_id", "ON DELETE CASCADE ON UPDATE CASCADE"),
+    ("tournament_pairing", "fk_tournament_pairing_game", "game_id", "ON DELETE SET NULL ON UPDATE CASCADE"),
+    ("game_seek", "fk_game_seek_game", "game_id", "ON DELETE SET NULL ON UPDATE CASCADE"),
+    ("game", "game_parent_game_id_fkey", "parent_game_id", "ON DELETE SET NULL"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Finished games moved out of the hot table, each kept whole as the JSON of its game row
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GameArchive::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(GameArchive::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GameArchive::PublicId).string().not_null().unique_key())
+                    .col(ColumnDef::new(GameArchive::WhitePlayer).uuid().not_null())
+                    .col(ColumnDef::new(GameArchive::BlackPlayer).uuid().not_null())
+                    .col(ColumnDef::new(GameArchive::EndedAt).timestamp_with_time_zone().not_null())
+                    .col(
+                        ColumnDef::new(GameArchive::ArchivedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(GameArchive::Game).json_binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_archive_white_player" ON "smdb"."game_archive" ("white_player", "ended_at")"#,
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE INDEX IF NOT EXISTS "idx_game_archive_black_player" ON "smdb"."game_archive" ("black_player", "ended_at")"#,
+            )
+            .await?;
+
+        // Archiving deletes the hot row; rating history, pairings, seeks and rematches
+        // keep pointing at the game rather than being removed or cleared with it
+        for (table, constraint, _, _) in GAME_REFERENCES {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"ALTER TABLE "smdb"."{}" DROP CONSTRAINT IF EXISTS "{}""#,
+                    table, constraint
+                ))
+                .await?;
+        }
+
+        // Rating recomputes, series, stats and exports read finished games through this
+        // view. It has `game`'s columns as of now, so changing them means recreating it.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE OR REPLACE VIEW "smdb"."game_with_archive" AS SELECT * FROM "smdb"."game" UNION ALL SELECT (jsonb_populate_record(NULL::"smdb"."game", "game")).* FROM "smdb"."game_archive""#,
+            )
+            .await?;
+
+        println!("Game archive table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP VIEW IF EXISTS "smdb"."game_with_archive""#)
+            .await?;
+
+        // Archived games go back to the hot table before the references to them are enforced again
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"INSERT INTO "smdb"."game" SELECT (jsonb_populate_record(NULL::"smdb"."game", "game")).* FROM "smdb"."game_archive" ON CONFLICT ("id") DO NOTHING"#,
+            )
+            .await?;
+
+        for (table, constraint, column, actions) in GAME_REFERENCES {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"ALTER TABLE "smdb"."{}" ADD CONSTRAINT "{}" FOREIGN KEY ("{}") REFERENCES "smdb"."game" ("id") {}"#,
+                    table, constraint, column, actions
+                ))
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table((Smdb, GameArchive::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Game archive table dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameArchive {
+    Table,
+    Id,
+    PublicId,
+    WhitePlayer,
+    BlackPlayer,
+    EndedAt,
+    ArchivedAt,
+    Game,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per delivery attempt; the per-subscriber rate limit counts these
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, WebhookAttempt::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(WebhookAttempt::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(WebhookAttempt::DeliveryId).uuid().not_null())
+                    .col(ColumnDef::new(WebhookAttempt::PlayerId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(WebhookAttempt::AttemptedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookAttempt::Error).text().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_attempt_delivery")
+                            .from(WebhookAttempt::Table, WebhookAttempt::DeliveryId)
+                            .to(WebhookDelivery::Table, WebhookDelivery::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_attempt_player")
+                            .from(WebhookAttempt::Table, WebhookAttempt::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webhook_attempt_player_attempted_at")
+                    .table((Smdb, WebhookAttempt::Table))
+                    .col(WebhookAttempt::PlayerId)
+                    .col(WebhookAttempt::AttemptedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Webhook attempt table created successfully.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, WebhookAttempt::Table)).if_exists().to_owned())
+            .await?;
+
+        println!("Webhook attempt table dropped successfully.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookAttempt {
+    Table,
+    Id,
+    DeliveryId,
+    PlayerId,
+    AttemptedAt,
+    Error,
+}
+
+#[derive(DeriveIden)]
+enum WebhookDelivery {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Player {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
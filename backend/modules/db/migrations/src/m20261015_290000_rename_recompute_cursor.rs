@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Recompute jobs replay games in the order they ended, as they were rated
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, RatingRecomputeJob::Table))
+                    .rename_column(RatingRecomputeJob::CursorStartedAt, RatingRecomputeJob::CursorEndedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A cursor taken in start order means nothing in end order, so unfinished jobs
+        // start their replay over
+        let db = manager.get_connection();
+        for table in ["rating_recompute_rating", "rating_recompute_history"] {
+            db.execute_unprepared(&format!(
+                r#"DELETE FROM "smdb"."{}" WHERE "job_id" IN (SELECT "id" FROM "smdb"."rating_recompute_job" WHERE "status" <> 'completed')"#,
+                table
+            ))
+            .await?;
+        }
+        db.execute_unprepared(
+            r#"UPDATE "smdb"."rating_recompute_job" SET "cursor_ended_at" = NULL, "cursor_game_id" = NULL, "processed_games" = 0 WHERE "status" <> 'completed'"#,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, RatingRecomputeJob::Table))
+                    .rename_column(RatingRecomputeJob::CursorEndedAt, RatingRecomputeJob::CursorStartedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RatingRecomputeJob {
+    Table,
+    CursorStartedAt,
+    CursorEndedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
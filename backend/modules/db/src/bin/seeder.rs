@@ -1,5 +1,7 @@
-use db_entity::prelude::*;
 use db_entity::{player, game};
+use db_entity::bulk::{insert_batch, BatchMode};
+use db_entity::sea_orm_active_enums::ResultSide;
+use db_entity::seed::{self, SeedMode};
 use sea_orm::{*, prelude::*};
 use std::env;
 use dotenv::dotenv;
@@ -10,6 +12,9 @@ use serde_json::json;
 
 const NUM_PLAYERS: usize = 100;
 const NUM_GAMES: usize = 5000;
+const BATCH_SIZE: usize = 500;
+// Seeded usernames start with this; only rows carrying it are ever topped up or removed
+const TAG: &str = "seed_player_";
 
 // Basic starting FEN position
 const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -20,47 +25,44 @@ async fn main() -> Result<(), DbErr> {
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let db = Database::connect(&db_url).await?;
 
-    println!("Clearing existing data...");
-    // Use execute_unprepared for TRUNCATE as it's not directly supported by query builder
-    // Make sure the schema is correct if not using the default 'public'
-    // Using CASCADE to handle foreign keys if necessary
-    db.execute_unprepared("TRUNCATE TABLE smdb.game, smdb.player CASCADE;").await?;
-    println!("Existing data cleared.");
-
-    println!("Seeding database...");
+    // `--reset` removes this seeder's own rows first, `--skip-existing` leaves an
+    // earlier run untouched; by default the data is topped up to the counts above
+    let mode = SeedMode::from_args(env::args());
+    println!("Seeding database ({:?})...", mode);
 
     // --- Seed Players ---
     println!("Seeding {} players...", NUM_PLAYERS);
-    let models: Vec<player::ActiveModel> = (0..NUM_PLAYERS).map(|i| {
-        let player_id = Uuid::new_v4();
+    let players = seed::seed_players(&db, TAG, NUM_PLAYERS, mode, BatchMode::AllOrNothing, |i, username| {
         player::ActiveModel {
-            id: Set(player_id),
-            username: Set(format!("Player_{}", i + 1)),
-            email: Set(format!("player{}@example.com", i + 1)),
+            id: Set(Uuid::new_v4()),
+            email: Set(format!("{}@example.com", username)),
+            username: Set(username),
             password_hash: Set(b"dummy_hash".to_vec()),
-            biography: Set(format!("Biography for Player {}", i + 1)),
-            country: Set("USA".to_string()),
-            flair: Set("GM".to_string()),
+            biography: Set(Some(format!("Biography for Player {}", i + 1))),
+            country: Set(Some("US".to_string())),
+            flair: Set(Some("GM".to_string())),
             real_name: Set(format!("Real Name {}", i + 1)),
-            location: Set("New York, NY".to_string()),
-            fide_rating: Set(rand::thread_rng().gen_range(800..2800)),
-            social_links: Set(vec!["http://twitter.com/player".to_string()]),
+            location: Set(Some("New York, NY".to_string())),
+            fide_rating: Set(Some(rand::thread_rng().gen_range(800..2800))),
+            social_links: Set(Some(vec!["http://twitter.com/player".to_string()])),
+            ..Default::default()
         }
-    }).collect();
-
-    // Extract player IDs before inserting for game seeding
-    let player_ids: Vec<Uuid> = models.iter().map(|m| m.id.clone().unwrap()).collect();
-
-    Player::insert_many(models).exec(&db).await?;
-    println!("Players seeded successfully.");
+    }).await?;
+    if players.removed.players > 0 {
+        println!("Removed {} players and {} games from an earlier run.", players.removed.players, players.removed.games);
+    }
+    println!("Inserted {} players ({} in total).", players.inserted, players.ids.len());
+    let player_ids = players.ids;
 
     // --- Seed Games ---
     let mut rng = rand::thread_rng();
     let variants = vec!["Standard", "Chess960", "Atomic", "Crazyhouse"];
-    let results = vec!["white", "black", "draw"];
+    let results = vec![ResultSide::White, ResultSide::Black, ResultSide::Draw];
 
-    println!("Seeding {} games...", NUM_GAMES);
-    for i in 0..NUM_GAMES {
+    let num_games = seed::games_to_add(&db, &player_ids, NUM_GAMES, mode).await?;
+    println!("Seeding {} games...", num_games);
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for i in 0..num_games {
         let white_player_id = *player_ids.choose(&mut rng).unwrap();
         let black_player_id = loop {
             let id = *player_ids.choose(&mut rng).unwrap();
@@ -72,21 +74,24 @@ async fn main() -> Result<(), DbErr> {
         let started_at = Utc::now() - Duration::days(rng.gen_range(0..365));
         let duration_sec = rng.gen_range(30..3600); // 30 seconds to 1 hour
 
+        let result = *results.choose(&mut rng).unwrap();
         let game = game::ActiveModel {
             id: Set(Uuid::new_v4()),
             white_player: Set(white_player_id),
             black_player: Set(black_player_id),
             fen: Set(STARTING_FEN.to_string()), // Simple FEN for now
-            pgn: Set(json!({ "moves": "e4 c5 ...", "final_ply": rng.gen_range(10..150) })), // Added final_ply for benchmark
-            result: Set(results.choose(&mut rng).unwrap().to_string()),
+            pgn: Set(json!({ "moves": "e4 c5 ...", "result": result.as_pgn(), "final_ply": rng.gen_range(10..150) })), // Added final_ply for benchmark
+            result: Set(Some(result)),
             variant: Set(variants.choose(&mut rng).unwrap().to_string()),
             started_at: Set(started_at.into()),
             duration_sec: Set(duration_sec),
+            ..Default::default()
         };
 
-        Game::insert(game).exec(&db).await?;
-        if (i + 1) % 500 == 0 {
-            println!("  Inserted {}/{} games", i + 1, NUM_GAMES);
+        batch.push(game);
+        if batch.len() == BATCH_SIZE || i + 1 == num_games {
+            insert_batch(&db, batch.drain(..).collect(), BatchMode::AllOrNothing).await?;
+            println!("  Inserted {}/{} games", i + 1, num_games);
         }
     }
     println!("Games seeded successfully.");
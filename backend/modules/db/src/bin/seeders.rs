@@ -1,6 +1,7 @@
 use db::entity::player::{self, Player};
 use db::entity::sea_orm_active_enums::GameStatus;
 use db::entity::{game, player};
+use db_entity::bulk::{insert_batch, BatchMode};
 use rand::Rng;
 use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
 use uuid::Uuid;
@@ -26,21 +27,21 @@ async fn seed_players(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
                 username: Set(format!("Player_{}", i + 1)),
                 email: Set(format!("player{}@example.com", i + 1)),
                 password_hash: Set(b"dummy_hash".to_vec()), // Dummy hash
-                biography: Set(format!("Biography for Player {}", i + 1)),
-                country: Set("USA".to_string()), // Dummy country
-                flair: Set("GM".to_string()), // Dummy flair
+                biography: Set(Some(format!("Biography for Player {}", i + 1))),
+                country: Set(Some("US".to_string())), // Dummy country
+                flair: Set(Some("GM".to_string())), // Dummy flair
                 real_name: Set(format!("Real Name {}", i + 1)),
-                location: Set("New York, NY".to_string()), // Dummy location
-                fide_rating: Set(rand::thread_rng().gen_range(800..2800)), // Use fide_rating
-                social_links: Set(vec!["http://twitter.com/player".to_string()]), // Dummy links
+                location: Set(Some("New York, NY".to_string())), // Dummy location
+                fide_rating: Set(Some(rand::thread_rng().gen_range(800..2800))), // Use fide_rating
+                social_links: Set(Some(vec!["http://twitter.com/player".to_string()])), // Dummy links
                 created_at: Default::default(),
                 updated_at: Default::default(),
             }
         })
         .collect();
 
-    Player::insert_many(players).exec(&db).await?;
-    println!("Inserted {} players", NUM_PLAYERS);
+    let summary = insert_batch(db, players, BatchMode::AllOrNothing).await?;
+    println!("Inserted {} players", summary.inserted);
 
     // Fetch player IDs after insertion for game seeding
     let player_ids: Vec<Uuid> = Player::find()
@@ -80,20 +81,20 @@ async fn main() -> Result<(), DbErr> {
             username: Set(format!("Player_{}", i + 1)),
             email: Set(format!("player{}@example.com", i + 1)),
             password_hash: Set(b"dummy_hash".to_vec()),
-            biography: Set(format!("Biography for Player {}", i + 1)),
-            country: Set("USA".to_string()),
-            flair: Set("GM".to_string()),
+            biography: Set(Some(format!("Biography for Player {}", i + 1))),
+            country: Set(Some("US".to_string())),
+            flair: Set(Some("GM".to_string())),
             real_name: Set(format!("Real Name {}", i + 1)),
-            location: Set("New York, NY".to_string()),
-            fide_rating: Set(rng.gen_range(800..2800)),
-            social_links: Set(vec![format!("http://twitter.com/player{}", i+1)]),
+            location: Set(Some("New York, NY".to_string())),
+            fide_rating: Set(Some(rng.gen_range(800..2800))),
+            social_links: Set(Some(vec![format!("http://twitter.com/player{}", i+1)])),
             ..Default::default() // Ensure other defaults are handled if any added later
         }
     }).collect();
 
     // Insert players in batches
-    let insert_result = Player::insert_many(player_models).exec(&db).await?;
-    println!("Inserted {} players in {:.2?}.", NUM_PLAYERS, start_players.elapsed());
+    let summary = insert_batch(&db, player_models, BatchMode::AllOrNothing).await?;
+    println!("Inserted {} players in {:.2?}.", summary.inserted, start_players.elapsed());
 
     // Fetch the IDs of the newly created players
     let players = Player::find().limit(NUM_PLAYERS as u64).all(&db).await?;
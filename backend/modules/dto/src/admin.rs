@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use entity::rating_recompute_job::Model;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RecomputeRatingsRequest {
+    /// Replay without replacing current ratings; the job report lists what would change.
+    #[serde(default)]
+    #[schema(example = true)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecomputeJobDTO {
+    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+
+    #[schema(example = "running")]
+    pub status: String,
+
+    pub dry_run: bool,
+
+    #[schema(example = 12000)]
+    pub total_games: i64,
+
+    #[schema(example = 4500)]
+    pub processed_games: i64,
+
+    /// Percentage of games replayed so far.
+    #[schema(example = 37.5)]
+    pub progress: f64,
+
+    /// Summary of rating differences, present once the job has completed.
+    #[schema(value_type = Option<Object>)]
+    pub report: Option<serde_json::Value>,
+
+    pub error: Option<String>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: DateTime<Utc>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub updated_at: DateTime<Utc>,
+
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<Model> for RecomputeJobDTO {
+    fn from(value: Model) -> Self {
+        let progress = if value.total_games == 0 {
+            100.0
+        } else {
+            (value.processed_games as f64 / value.total_games as f64 * 100.0).min(100.0)
+        };
+
+        Self {
+            id: value.id,
+            status: serde_json::to_value(value.status)
+                .ok()
+                .and_then(|s| s.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            dry_run: value.dry_run,
+            total_games: value.total_games,
+            processed_games: value.processed_games,
+            progress,
+            report: value.report,
+            error: value.error,
+            created_at: value.created_at.with_timezone(&Utc),
+            updated_at: value.updated_at.with_timezone(&Utc),
+            finished_at: value.finished_at.map(|t| t.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema, Validate)]
+pub struct MatchAnalyticsQuery {
+    /// Size of the window in hours, ending now. Defaults to 24.
+    #[validate(range(min = 1, max = 720, message = "Window must be between 1 and 720 hours"))]
+    #[schema(example = 24)]
+    pub hours: Option<i64>,
+}
+
+/// Number of matches whose value fell in `[min, max)`; `max` is absent for the open-ended last bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsBucketDTO {
+    #[schema(example = 100)]
+    pub min: i64,
+    #[schema(example = 200)]
+    pub max: Option<i64>,
+    #[schema(example = 42)]
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MatchTypeAnalyticsDTO {
+    #[schema(example = "rated")]
+    pub match_type: String,
+    pub matches: u64,
+    pub avg_rating_gap: f64,
+    pub avg_wait_time_ms: f64,
+}
+
+/// Pairing quality of the matches formed within a time window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MatchAnalyticsDTO {
+    #[schema(value_type = String, format = "date-time")]
+    pub window_start: DateTime<Utc>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub window_end: DateTime<Utc>,
+
+    #[schema(example = 1250)]
+    pub total_matches: u64,
+
+    #[schema(example = 87.4)]
+    pub avg_rating_gap: f64,
+
+    #[schema(example = 95)]
+    pub p90_rating_gap: i64,
+
+    #[schema(example = 21450.0)]
+    pub avg_wait_time_ms: f64,
+
+    #[schema(example = 14000)]
+    pub p50_wait_time_ms: i64,
+
+    #[schema(example = 62000)]
+    pub p90_wait_time_ms: i64,
+
+    pub rating_gap_distribution: Vec<AnalyticsBucketDTO>,
+
+    pub wait_time_distribution: Vec<AnalyticsBucketDTO>,
+
+    pub by_match_type: Vec<MatchTypeAnalyticsDTO>,
+}
+
+/// Filter for the fair-play review queue. `min_score` defaults to the configured
+/// flag threshold.
+#[derive(Debug, Default, Deserialize, ToSchema, Validate)]
+pub struct SuspiciousGamesQuery {
+    #[validate(range(min = 0.0, max = 1.0, message = "Score must be between 0 and 1"))]
+    #[schema(example = 0.9)]
+    pub min_score: Option<f64>,
+
+    #[validate(range(min = 1, message = "Page must be at least 1"))]
+    #[schema(example = 1)]
+    pub page: Option<i32>,
+
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    #[schema(example = 20)]
+    pub limit: Option<i32>,
+}
+
+/// A reviewed game and how engine-like its more accurate side played. The score is a
+/// heuristic for prioritising manual review, not a finding of cheating.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SuspiciousGameDTO {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+
+    #[schema(value_type = String, format = "uuid")]
+    pub white_player: Uuid,
+
+    #[schema(value_type = String, format = "uuid")]
+    pub black_player: Uuid,
+
+    #[schema(example = 0.94)]
+    pub suspicion_score: f64,
+
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl From<entity::game::Model> for SuspiciousGameDTO {
+    fn from(value: entity::game::Model) -> Self {
+        Self {
+            game_id: value.id,
+            white_player: value.white_player,
+            black_player: value.black_player,
+            suspicion_score: value.suspicion_score.unwrap_or_default(),
+            ended_at: value.ended_at.map(|t| t.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema, Validate)]
+pub struct ArchiveGamesRequest {
+    /// Archive games that ended more than this many days ago. Defaults to `GAME_ARCHIVE_AFTER_DAYS`.
+    #[validate(range(min = 1, max = 36500, message = "Age must be between 1 and 36500 days"))]
+    #[schema(example = 365)]
+    pub older_than_days: Option<i64>,
+}
+
+/// Outcome of an archival run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ArchiveRunDTO {
+    /// Finished games moved to the archive by this run.
+    #[schema(example = 1200)]
+    pub archived: u64,
+
+    /// Games that ended before this moment were archived.
+    #[schema(value_type = String, format = "date-time")]
+    pub cutoff: DateTime<Utc>,
+}
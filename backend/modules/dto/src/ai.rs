@@ -27,12 +27,21 @@ pub struct AiSuggestionRequest {
     #[validate(range(min = 1000, max = 60000, message = "Time limit must be between 1 and 60 seconds"))]
     #[schema(example = 5000)]
     pub time_limit_ms: Option<u32>,
+
+    /// Moves to suggest besides the best one; 3 when left out.
+    #[validate(range(max = 5, message = "At most 5 alternatives can be requested"))]
+    #[schema(example = 3)]
+    pub alternatives: Option<u8>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AiSuggestionResponse {
     #[schema(example = "e2e4")]
     pub best_move: String,
+
+    /// `best_move` in standard algebraic notation.
+    #[schema(example = "e4")]
+    pub best_move_san: String,
     
     #[schema(example = 0.3)]
     pub evaluation: f32,
@@ -40,10 +49,23 @@ pub struct AiSuggestionResponse {
     #[schema(example = 12)]
     pub depth: u8,
     
+    #[schema(example = json!(["e2e4", "e7e5", "g1f3"]))]
     pub principal_variation: Vec<String>,
+
+    /// `principal_variation` in standard algebraic notation.
+    #[schema(example = json!(["e4", "e5", "Nf3"]))]
+    pub principal_variation_san: Vec<String>,
+
+    /// Next best moves, best first; equal evaluations are ordered by move.
+    pub alternatives: Vec<AlternativeMove>,
     
     #[schema(example = 2345)]
     pub computation_time_ms: u32,
+
+    /// The search hit the server's time limit and was stopped early; the result is
+    /// the best found by then, possibly short of `depth`.
+    #[schema(example = false)]
+    pub truncated: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
@@ -65,18 +87,32 @@ pub struct PositionAnalysisResponse {
     #[schema(example = 0.3)]
     pub evaluation: f32,
     
+    #[schema(example = json!(["e2e4", "e7e5", "g1f3", "b8c6"]))]
     pub best_line: Vec<String>,
+
+    /// `best_line` in standard algebraic notation.
+    #[schema(example = json!(["e4", "e5", "Nf3", "Nc6"]))]
+    pub best_line_san: Vec<String>,
     
+    /// Next best moves, best first; equal evaluations are ordered by move.
     pub alternatives: Vec<AlternativeMove>,
     
     #[schema(example = "Open Game")]
     pub position_type: String,
+
+    /// The search hit the server's time limit and was stopped early.
+    #[schema(example = false)]
+    pub truncated: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AlternativeMove {
-    #[schema(example = "e2e4")]
+    #[schema(example = "d2d4")]
     pub chess_move: String,
+
+    /// `chess_move` in standard algebraic notation.
+    #[schema(example = "d4")]
+    pub san: String,
     
     #[schema(example = 0.25)]
     pub evaluation: f32,
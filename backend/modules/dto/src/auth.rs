@@ -5,6 +5,8 @@ use uuid::Uuid;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::username::validate_username;
+
 // Define a regex for strong password validation
 // Requires at least one uppercase, one lowercase, one digit, and one special character
 static STRONG_PASSWORD_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -65,7 +67,7 @@ pub struct UserInfo {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct RegisterRequest {
-    #[validate(length(min = 4, max = 20, message = "Username must be between 4 and 20 characters"))]
+    #[validate(custom = "validate_username")]
     #[schema(example = "chess_master")]
     pub username: String,
     
@@ -112,3 +114,49 @@ pub struct TokenResponse {
     #[schema(example = 3600)]
     pub expires_in: i32,
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GuestResponse {
+    #[schema(example = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...")]
+    pub access_token: String,
+
+    #[schema(example = "Bearer")]
+    pub token_type: String,
+
+    /// Lifetime of the token in seconds; guests get no refresh token.
+    #[schema(example = 14400)]
+    pub expires_in: i32,
+
+    pub user: GuestInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GuestInfo {
+    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+
+    #[schema(example = "guest_3f2a9c41d07b")]
+    pub username: String,
+}
+
+/// Turns the guest of the bearer token into a full account, keeping its games.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct ConvertGuestRequest {
+    #[validate(custom = "validate_username")]
+    #[schema(example = "chess_master")]
+    pub username: String,
+
+    #[validate(email(message = "Email must be valid"))]
+    #[schema(example = "chess@example.com")]
+    pub email: String,
+
+    #[validate(
+        length(min = 8, message = "Password must be at least 8 characters"),
+        regex(
+            path = "STRONG_PASSWORD_REGEX",
+            message = "Password must contain at least one uppercase letter, one lowercase letter, one digit, and one special character"
+        )
+    )]
+    #[schema(example = "Secure_password123!")]
+    pub password: String,
+}
@@ -0,0 +1,74 @@
+use validator::ValidationError;
+
+/// Stored for players who prefer not to say. `XX` is in the ISO 3166 user-assigned
+/// range, so it can never collide with a real country.
+pub const COUNTRY_NOT_SPECIFIED: &str = "XX";
+
+/// ISO 3166-1 alpha-2 codes of all officially assigned countries and territories.
+pub const ISO_COUNTRY_CODES: [&str; 249] = [
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+/// Canonical (upper-case) form of `country`, or `None` if it is neither an
+/// ISO 3166-1 alpha-2 code nor [`COUNTRY_NOT_SPECIFIED`]. Case and surrounding
+/// whitespace are ignored.
+pub fn canonical_country(country: &str) -> Option<&'static str> {
+    let code = country.trim().to_ascii_uppercase();
+    if code == COUNTRY_NOT_SPECIFIED {
+        return Some(COUNTRY_NOT_SPECIFIED);
+    }
+    ISO_COUNTRY_CODES
+        .binary_search(&code.as_str())
+        .ok()
+        .map(|index| ISO_COUNTRY_CODES[index])
+}
+
+pub fn validate_country(country: &str) -> Result<(), ValidationError> {
+    if canonical_country(country).is_none() {
+        let mut error = ValidationError::new("invalid_country");
+        error.message = Some(
+            format!(
+                "Country '{}' is not an ISO 3166-1 alpha-2 code; use '{}' to leave it unspecified",
+                country, COUNTRY_NOT_SPECIFIED
+            )
+            .into(),
+        );
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_sorted_and_unique() {
+        assert!(ISO_COUNTRY_CODES.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn canonicalizes_known_codes_and_sentinel() {
+        assert_eq!(canonical_country("us"), Some("US"));
+        assert_eq!(canonical_country(" ng "), Some("NG"));
+        assert_eq!(canonical_country("xx"), Some(COUNTRY_NOT_SPECIFIED));
+        assert_eq!(canonical_country("USA"), None);
+        assert_eq!(canonical_country("Unknown"), None);
+        assert_eq!(canonical_country("ZZ"), None);
+    }
+}
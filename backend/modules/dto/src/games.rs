@@ -3,6 +3,19 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::{Validate, ValidationError};
 use chrono::{DateTime, Utc};
+use entity::game::Model;
+use entity::result::pgn_token;
+use entity::sea_orm_active_enums::{GameVariant, ResultSide, Termination};
+use sea_orm::ActiveEnum;
+use chess::bitboard::Board::{Color, Role};
+use chess::fen::{Fen, without_pockets};
+use chess::history::GameHistory;
+use chess::material::Material;
+use chess::position::{Position, square_name};
+use chess::time_control;
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
@@ -11,7 +24,12 @@ static CHESS_MOVE_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^[a-h][1-8][a-h][1-8][qrbnQRBN]?$").unwrap()
 });
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+// Define a regex for ECO filters: an exact code (B20), a volume prefix (B2*, B*) or "none"
+static ECO_FILTER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?i:[a-e][0-9]{2}|[a-e][0-9]?\*|none)$").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum PlayerColor {
     #[serde(rename = "white")]
     White,
@@ -21,8 +39,161 @@ pub enum PlayerColor {
     Random,
 }
 
+/// Side to move in a game's current position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Side {
+    #[serde(rename = "white")]
+    White,
+    #[serde(rename = "black")]
+    Black,
+}
+
+impl From<Color> for Side {
+    fn from(value: Color) -> Self {
+        match value {
+            Color::White => Side::White,
+            Color::Black => Side::Black,
+        }
+    }
+}
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum Variant {
+    #[default]
+    #[serde(rename = "standard")]
+    Standard,
+    #[serde(rename = "chess960")]
+    Chess960,
+    #[serde(rename = "crazyhouse")]
+    Crazyhouse,
+    #[serde(rename = "kingofthehill")]
+    KingOfTheHill,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [
+        Variant::Standard,
+        Variant::Chess960,
+        Variant::Crazyhouse,
+        Variant::KingOfTheHill,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::Chess960 => "chess960",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::KingOfTheHill => "kingofthehill",
+        }
+    }
+
+    /// Variants offered by this server, read from the comma-separated `ENABLED_VARIANTS`
+    /// env var. Every variant is enabled when it is unset.
+    pub fn enabled() -> Vec<Variant> {
+        match env::var("ENABLED_VARIANTS") {
+            Ok(list) => list.split(',').filter_map(|v| v.trim().parse().ok()).collect(),
+            Err(_) => Variant::ALL.to_vec(),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Variant::ALL
+            .into_iter()
+            .find(|v| v.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown variant '{}'", s))
+    }
+}
+
+impl From<GameVariant> for Variant {
+    fn from(value: GameVariant) -> Self {
+        match value {
+            GameVariant::Standard => Variant::Standard,
+            GameVariant::Chess960 => Variant::Chess960,
+            GameVariant::Crazyhouse => Variant::Crazyhouse,
+            GameVariant::KingOfTheHill => Variant::KingOfTheHill,
+        }
+    }
+}
+
+impl From<Variant> for GameVariant {
+    fn from(value: Variant) -> Self {
+        match value {
+            Variant::Standard => GameVariant::Standard,
+            Variant::Chess960 => GameVariant::Chess960,
+            Variant::Crazyhouse => GameVariant::Crazyhouse,
+            Variant::KingOfTheHill => GameVariant::KingOfTheHill,
+        }
+    }
+}
+
+/// Speed category of a game's time control, see [`chess::time_control::time_class`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeClass {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+impl From<time_control::TimeClass> for TimeClass {
+    fn from(value: time_control::TimeClass) -> Self {
+        match value {
+            time_control::TimeClass::Bullet => TimeClass::Bullet,
+            time_control::TimeClass::Blitz => TimeClass::Blitz,
+            time_control::TimeClass::Rapid => TimeClass::Rapid,
+            time_control::TimeClass::Classical => TimeClass::Classical,
+            time_control::TimeClass::Correspondence => TimeClass::Correspondence,
+        }
+    }
+}
+
+impl TimeClass {
+    /// Class of a `initial_secs`+`increment_secs` clock, or `None` when the game has
+    /// no time control recorded.
+    pub fn of(initial_secs: i32, increment_secs: i32) -> Option<Self> {
+        if initial_secs <= 0 && increment_secs <= 0 {
+            return None;
+        }
+        let tc = time_control::TimeControl {
+            initial_time: Duration::from_secs(initial_secs.max(0) as u64),
+            increment: Duration::from_secs(increment_secs.max(0) as u64),
+            delay: Duration::ZERO,
+        };
+        Some(time_control::time_class(&tc).into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CapturedPiece {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl From<Role> for CapturedPiece {
+    fn from(value: Role) -> Self {
+        match value {
+            Role::Pawn => CapturedPiece::Pawn,
+            Role::Knight => CapturedPiece::Knight,
+            Role::Bishop => CapturedPiece::Bishop,
+            Role::Rook => CapturedPiece::Rook,
+            Role::Queen => CapturedPiece::Queen,
+            Role::King => CapturedPiece::King,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum GameStatus {
     #[serde(rename = "waiting")]
     Waiting,
@@ -34,19 +205,42 @@ pub enum GameStatus {
     Aborted,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub enum GameResult {
-    #[serde(rename = "white_win")]
-    WhiteWin,
-    #[serde(rename = "black_win")]
-    BlackWin,
-    #[serde(rename = "draw")]
-    Draw,
-    #[serde(rename = "in_progress")]
-    InProgress,
+/// Form game responses write results in. Colour, the form the `result` column
+/// stores, is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    /// `white`, `black` or `draw`; `null` while the game is unfinished
+    #[default]
+    Color,
+    /// `1-0`, `0-1` or `1/2-1/2`; `*` while the game is unfinished
+    Pgn,
+}
+
+impl ResultFormat {
+    pub fn write(self, result: Option<ResultSide>) -> Option<String> {
+        match self {
+            ResultFormat::Color => result.map(|side| side.to_value()),
+            ResultFormat::Pgn => Some(pgn_token(result).to_string()),
+        }
+    }
+}
+
+impl GameStatus {
+    /// Derives the lifecycle status of a stored game: it is in progress until it has
+    /// an end time, then a result means it was played out and an `aborted`
+    /// termination without one means it was called off.
+    pub fn of(game: &Model) -> Self {
+        match (game.ended_at, game.result, game.termination) {
+            (None, _, _) => GameStatus::InProgress,
+            (Some(_), None, Some(Termination::Aborted)) => GameStatus::Aborted,
+            (Some(_), _, _) => GameStatus::Completed,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_starting_position"))]
 pub struct CreateGameRequest {
     #[validate(range(min = 60, max = 7200, message = "Time control must be between 1 minute and 2 hours"))]
     pub time_control: i32,
@@ -56,12 +250,64 @@ pub struct CreateGameRequest {
     
     pub player_color: Option<PlayerColor>,
     pub opponent_id: Option<Uuid>,
+
+    #[serde(default)]
+    #[validate(custom = "validate_variant_enabled")]
+    pub variant: Variant,
+
+    #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
+    pub starting_fen: Option<String>,
+
+    /// How move counters no real game could reach are treated: `strict` rejects the
+    /// position, `lenient` accepts it and lists them in the response's `warnings`
+    #[serde(default)]
+    pub fen_strictness: FenStrictness,
+
+    /// Finished game between the same two players that this one is a rematch of,
+    /// continuing its series
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub rematch_of: Option<Uuid>,
+}
+
+impl CreateGameRequest {
+    /// Counter issues accepted in the starting position under `lenient` strictness.
+    pub fn fen_warnings(&self) -> Vec<String> {
+        self.starting_fen
+            .as_deref()
+            .and_then(|fen| chess::fen::validate(fen, chess::fen::Strictness::Lenient).ok())
+            .unwrap_or_default()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FenStrictness {
+    Lenient,
+    #[default]
+    Strict,
+}
+
+impl From<FenStrictness> for chess::fen::Strictness {
+    fn from(strictness: FenStrictness) -> Self {
+        match strictness {
+            FenStrictness::Lenient => chess::fen::Strictness::Lenient,
+            FenStrictness::Strict => chess::fen::Strictness::Strict,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GameDisplayDTO {
     #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
     pub id: Uuid,
+
+    /// Short id for shareable links, accepted wherever a game id is
+    #[schema(example = "aZ3kP9qX")]
+    pub public_id: String,
     
     #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174001")]
     pub white_player_id: Uuid,
@@ -70,14 +316,45 @@ pub struct GameDisplayDTO {
     pub black_player_id: Option<Uuid>,
     
     pub status: GameStatus,
-    pub result: GameResult,
+
+    /// In the form `result_format` asks for, by colour unless it says `pgn`
+    #[schema(example = "white")]
+    pub result: Option<String>,
+
+    #[schema(value_type = Option<String>, example = "agreement")]
+    pub termination: Option<Termination>,
+
+    pub variant: Variant,
     
     #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
     pub current_fen: String,
     
     pub move_history: Vec<String>,
+
+    /// Moves played in this game so far, counted from its starting position
+    #[schema(example = 24)]
+    pub ply: u32,
+
+    /// Read from `current_fen`, so custom and Chess960 starts are handled
+    pub side_to_move: Side,
+
+    /// `true` once the game has ended, whatever the outcome
+    pub is_finished: bool,
+
+    /// Whether the side to move is in check in `current_fen`
+    pub in_check: bool,
+
+    /// Square of the king in check, absent otherwise
+    #[schema(example = "e8")]
+    pub checked_king: Option<String>,
+
     pub time_control: i32,
     pub increment: i32,
+
+    /// Derived from `time_control` and `increment`; absent without a time control
+    pub time_class: Option<TimeClass>,
+
+    /// Seconds left on each clock right now, `0` in untimed games
     pub white_time_remaining: i32,
     pub black_time_remaining: i32,
     
@@ -86,9 +363,172 @@ pub struct GameDisplayDTO {
     
     #[schema(value_type = Option<String>, format = "date-time")]
     pub started_at: Option<DateTime<Utc>>,
+
+    /// Set once the game is over
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub ended_at: Option<DateTime<Utc>>,
     
     #[schema(value_type = String, format = "date-time")]
     pub updated_at: DateTime<Utc>,
+
+    #[schema(example = "B90")]
+    pub eco: Option<String>,
+
+    #[schema(example = "Sicilian Defense, Najdorf Variation")]
+    pub opening_name: Option<String>,
+
+    /// White's material minus Black's in pawns (N and B 3, R 5, Q 9); crazyhouse
+    /// pockets count as material
+    #[schema(example = 2)]
+    pub material_balance: i32,
+
+    /// Pieces White has taken, oldest first; in crazyhouse, White's pocket
+    pub captured_by_white: Vec<CapturedPiece>,
+
+    pub captured_by_black: Vec<CapturedPiece>,
+}
+
+/// Reads the SAN move list from a stored PGN document, accepting either a JSON
+/// array or a whitespace separated string with optional move numbers.
+pub fn pgn_moves(pgn: &serde_json::Value) -> Vec<String> {
+    match pgn.get("moves") {
+        Some(serde_json::Value::Array(moves)) => moves
+            .iter()
+            .filter_map(|m| m.as_str().map(str::to_string))
+            .collect(),
+        Some(serde_json::Value::String(moves)) => moves
+            .split_whitespace()
+            .filter(|token| !token.ends_with('.') && *token != "...")
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Starting position of a stored game; games without one began from the standard position.
+pub fn pgn_starting_fen(pgn: &serde_json::Value) -> &str {
+    pgn.get("starting_fen")
+        .and_then(|fen| fen.as_str())
+        .unwrap_or(chess::fen::STARTING_FEN)
+}
+
+/// Side to move in a stored game. The current FEN is written from the authoritative
+/// position after every move; should it not parse, the side is worked out from the
+/// starting position and the number of moves played.
+pub fn side_to_move(game: &Model, plies: usize) -> Side {
+    if let Ok(fen) = Fen::from_str(&game.fen) {
+        return fen.side_to_move.into();
+    }
+    let first = Fen::from_str(pgn_starting_fen(&game.pgn))
+        .map(|fen| fen.side_to_move)
+        .unwrap_or(Color::White);
+    if plies.is_multiple_of(2) { first.into() } else { first.opposite().into() }
+}
+
+/// Square of the king in check in a stored game's current position, if any. Read
+/// from the FEN alone, so it holds for every variant, drops included.
+pub fn checked_king(game: &Model) -> Option<String> {
+    Position::from_str(&without_pockets(&game.fen))
+        .ok()
+        .and_then(|position| position.checked_king())
+        .map(square_name)
+}
+
+/// Both clocks of a timed game at one moment, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockReading {
+    pub white_ms: i64,
+    pub black_ms: i64,
+}
+
+impl ClockReading {
+    /// Whole seconds on each clock, the unit `clock` messages and the REST API use.
+    pub fn secs(&self) -> (u32, u32) {
+        ((self.white_ms / 1000) as u32, (self.black_ms / 1000) as u32)
+    }
+}
+
+/// Time class of a stored game, `None` when it is untimed.
+pub fn game_time_class(game: &Model) -> Option<TimeClass> {
+    TimeClass::of(game.clock_initial_secs?, game.clock_increment_secs.unwrap_or(0))
+}
+
+/// Clocks of a timed game at `now`, or `None` for an untimed one. The side to move
+/// has been using its time since `last_move_at`, less whatever the clock spent
+/// paused; a paused clock reads as it stood when it stopped, a finished game as it
+/// stood when it ended.
+pub fn clock_at(game: &Model, now: DateTime<Utc>) -> Option<ClockReading> {
+    let mut reading = ClockReading { white_ms: game.white_remaining_ms?, black_ms: game.black_remaining_ms? };
+    let Some(since) = game.last_move_at else { return Some(reading) };
+    let until = game.clock_paused_at.or(game.ended_at).map_or(now, |at| at.with_timezone(&Utc));
+    let used = ((until - since.with_timezone(&Utc)).num_milliseconds() - game.clock_paused_ms).max(0);
+    let clock = match side_to_move(game, pgn_moves(&game.pgn).len()) {
+        Side::White => &mut reading.white_ms,
+        Side::Black => &mut reading.black_ms,
+    };
+    *clock = (*clock - used).max(0);
+    Some(reading)
+}
+
+/// Material of a stored game, replayed from its moves so that captures are known.
+/// Should the moves not replay, the balance is read from the current FEN alone.
+pub fn material_of(game: &Model) -> Material {
+    let pockets = game.variant == GameVariant::Crazyhouse;
+    match GameHistory::replay(pgn_starting_fen(&game.pgn), &pgn_moves(&game.pgn)) {
+        Ok(history) => Material::of(&history, pockets),
+        Err(_) => Position::from_str(&game.fen)
+            .map(|position| Material::of_position(&position))
+            .unwrap_or_default(),
+    }
+}
+
+impl From<Model> for GameDisplayDTO {
+    fn from(value: Model) -> Self {
+        GameDisplayDTO::new(value, ResultFormat::default())
+    }
+}
+
+impl GameDisplayDTO {
+    pub fn new(value: Model, result_format: ResultFormat) -> Self {
+        let move_history = pgn_moves(&value.pgn);
+        let side = side_to_move(&value, move_history.len());
+        let material = material_of(&value);
+        let checked_king = checked_king(&value);
+        let status = GameStatus::of(&value);
+        let time_class = game_time_class(&value);
+        let (white_secs, black_secs) = clock_at(&value, Utc::now()).map_or((0, 0), |clock| clock.secs());
+        Self {
+            id: value.id,
+            public_id: value.public_id,
+            white_player_id: value.white_player,
+            black_player_id: Some(value.black_player),
+            status,
+            result: result_format.write(value.result),
+            termination: value.termination,
+            variant: value.variant.into(),
+            current_fen: value.fen,
+            ply: move_history.len() as u32,
+            side_to_move: side,
+            is_finished: value.ended_at.is_some(),
+            in_check: checked_king.is_some(),
+            checked_king,
+            move_history,
+            time_control: value.clock_initial_secs.unwrap_or(0),
+            increment: value.clock_increment_secs.unwrap_or(0),
+            time_class,
+            white_time_remaining: white_secs as i32,
+            black_time_remaining: black_secs as i32,
+            created_at: value.created_at.with_timezone(&Utc),
+            started_at: Some(value.started_at.with_timezone(&Utc)),
+            ended_at: value.ended_at.map(|at| at.with_timezone(&Utc)),
+            updated_at: value.updated_at.with_timezone(&Utc),
+            eco: value.eco,
+            opening_name: value.opening_name,
+            material_balance: material.balance,
+            captured_by_white: material.captured_by_white.into_iter().map(CapturedPiece::from).collect(),
+            captured_by_black: material.captured_by_black.into_iter().map(CapturedPiece::from).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
@@ -101,11 +541,47 @@ pub struct MakeMoveRequest {
     pub chess_move: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
-pub struct JoinGameRequest {
-    #[validate(custom = "validate_uuid")]
-    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
-    pub player_id: Uuid,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DrawAction {
+    Offer,
+    Accept,
+    Decline,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DrawActionRequest {
+    #[schema(example = "offer")]
+    pub action: DrawAction,
+}
+
+// The variant must be one this server is configured to offer
+pub fn validate_variant_enabled(variant: &Variant) -> Result<(), ValidationError> {
+    if !Variant::enabled().contains(variant) {
+        let mut error = ValidationError::new("variant_disabled");
+        error.message = Some(format!("Variant '{}' is not enabled on this server", variant.as_str()).into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+// Imported positions must be well-formed; move counters a real game could not reach
+// are rejected unless the request asks for lenient checking
+pub fn validate_starting_position(request: &CreateGameRequest) -> Result<(), ValidationError> {
+    match &request.starting_fen {
+        Some(fen) => validate_starting_fen(fen, request.fen_strictness),
+        None => Ok(()),
+    }
+}
+
+pub fn validate_starting_fen(fen: &str, strictness: FenStrictness) -> Result<(), ValidationError> {
+    chess::fen::validate(fen, strictness.into())
+        .map(|_| ())
+        .map_err(|err| {
+            let mut error = ValidationError::new("invalid_fen");
+            error.message = Some(err.to_string().into());
+            error
+        })
 }
 
 // UUID validation function
@@ -115,3 +591,317 @@ pub fn validate_uuid(uuid: &Uuid) -> Result<(), ValidationError> {
     }
     Ok(())
 }
+
+#[derive(Debug, Deserialize, Serialize, ToSchema, Validate)]
+pub struct ListGamesQuery {
+    #[schema(example = "waiting")]
+    pub status: Option<String>,
+
+    #[schema(value_type = Option<String>, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub player_id: Option<Uuid>,
+
+    #[validate(regex(
+        path = "ECO_FILTER_REGEX",
+        message = "ECO filter must be a code (B20), a prefix (B2*) or 'none'"
+    ))]
+    #[schema(example = "B2*")]
+    pub eco: Option<String>,
+
+    #[validate(length(min = 2, max = 100, message = "Opening filter must be between 2 and 100 characters"))]
+    #[schema(example = "Sicilian")]
+    pub opening: Option<String>,
+
+    #[schema(default = 1, example = 1)]
+    pub page: Option<i32>,
+
+    #[schema(default = 10, example = 10)]
+    pub limit: Option<i32>,
+
+    #[serde(default)]
+    pub result_format: ResultFormat,
+}
+
+/// Picks the form a game response writes its result in.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct ResultFormatQuery {
+    #[serde(default)]
+    pub result_format: ResultFormat,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_export_window"))]
+pub struct ExportGamesQuery {
+    /// Only games started at or after this instant
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only games started before this instant
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub to: Option<DateTime<Utc>>,
+
+    #[schema(example = "standard")]
+    pub variant: Option<Variant>,
+}
+
+// An export window must not end before it starts
+pub fn validate_export_window(query: &ExportGamesQuery) -> Result<(), ValidationError> {
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && to < from
+    {
+        let mut error = ValidationError::new("invalid_window");
+        error.message = Some("'to' must not be earlier than 'from'".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Most plies a single replay request may span.
+pub const MAX_REPLAY_PAGE_PLIES: u32 = 200;
+
+/// A ply range of a game: `from` plies are skipped and the page ends after ply `to`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_ply_range"))]
+pub struct ReplayQuery {
+    /// Plies played before the page starts; 0 starts at the initial position
+    #[schema(example = 0)]
+    pub from: Option<u32>,
+
+    /// Last ply of the page; defaults to `from` + 200
+    #[schema(example = 200)]
+    pub to: Option<u32>,
+}
+
+pub fn validate_ply_range(query: &ReplayQuery) -> Result<(), ValidationError> {
+    let from = query.from.unwrap_or(0);
+    if let Some(to) = query.to {
+        if to < from {
+            let mut error = ValidationError::new("invalid_range");
+            error.message = Some("'to' must not be lower than 'from'".into());
+            return Err(error);
+        }
+        if to - from > MAX_REPLAY_PAGE_PLIES {
+            let mut error = ValidationError::new("range_too_large");
+            error.message = Some(
+                format!("A replay page spans at most {} plies", MAX_REPLAY_PAGE_PLIES).into(),
+            );
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayPly {
+    /// 1-based ply number
+    #[schema(example = 1)]
+    pub ply: u32,
+
+    #[schema(example = "e4")]
+    pub san: String,
+
+    /// Position after the ply
+    #[schema(example = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")]
+    pub fen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayPage {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+
+    pub total_plies: u32,
+    pub from: u32,
+    pub to: u32,
+
+    /// Position before the first ply of the page
+    #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
+    pub fen: String,
+
+    pub plies: Vec<ReplayPly>,
+}
+
+/// Rating change one player would see for each way the game can end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct OutcomeDeltas {
+    #[schema(value_type = String, format = "uuid")]
+    pub player_id: Uuid,
+
+    /// Rating the deltas are computed from
+    #[schema(example = 1500)]
+    pub rating: i32,
+
+    #[schema(example = 16)]
+    pub win: i32,
+    #[schema(example = 0)]
+    pub draw: i32,
+    #[schema(example = -16)]
+    pub loss: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RatingPreview {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+
+    /// False when a guest is playing; every delta is then zero
+    pub rated: bool,
+
+    pub white: OutcomeDeltas,
+    pub black: OutcomeDeltas,
+}
+
+/// Points of both players of a series; a win is 1 and a draw ½.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeriesScore {
+    #[schema(example = 1.5)]
+    pub player_one: f32,
+    #[schema(example = 0.5)]
+    pub player_two: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeriesGame {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+
+    #[schema(example = "aZ3kP9qX")]
+    pub public_id: String,
+
+    #[schema(value_type = String, format = "uuid")]
+    pub white_player_id: Uuid,
+
+    #[schema(value_type = String, format = "uuid")]
+    pub black_player_id: Uuid,
+
+    /// `white`, `black` or `draw`; `null` while the game is unfinished
+    #[schema(value_type = Option<String>, example = "white")]
+    pub result: Option<ResultSide>,
+
+    /// Running score once this game is counted
+    pub score: SeriesScore,
+}
+
+/// A game and its chain of rematches, in the order they were played.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GameSeries {
+    #[schema(value_type = String, format = "uuid")]
+    pub root_id: Uuid,
+
+    /// White in the first game
+    #[schema(value_type = String, format = "uuid")]
+    pub player_one: Uuid,
+
+    /// Black in the first game
+    #[schema(value_type = String, format = "uuid")]
+    pub player_two: Uuid,
+
+    /// Most games the series is followed for
+    #[schema(example = 5)]
+    pub length: u32,
+
+    pub games: Vec<SeriesGame>,
+
+    pub score: SeriesScore,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stored_game(fen: &str, pgn: serde_json::Value) -> Model {
+        Model {
+            fen: fen.to_string(),
+            pgn,
+            variant: GameVariant::Chess960,
+            ..Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    #[test]
+    fn turn_and_ply_follow_custom_start() {
+        // Black moves first from this start, so after one move it is white's turn
+        let start = "bqnrkrnb/pppppppp/8/8/8/8/PPPPPPPP/BQNRKRNB b - - 0 1";
+        let game = stored_game(
+            "bqnrkrnb/ppp1pppp/8/3p4/8/8/PPPPPPPP/BQNRKRNB w - d6 0 2",
+            json!({ "moves": ["d5"], "starting_fen": start }),
+        );
+
+        let dto = GameDisplayDTO::from(game);
+
+        assert_eq!(dto.ply, 1);
+        assert_eq!(dto.side_to_move, Side::White);
+        assert!(!dto.is_finished);
+    }
+
+    #[test]
+    fn display_carries_material_after_captures() {
+        let game = stored_game(
+            "rnb1kbnr/ppp1pppp/8/8/8/2N5/RPPP1PPP/2BQKBNR b Kkq - 0 4",
+            json!({ "moves": ["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxa2", "Rxa2"] }),
+        );
+
+        let dto = GameDisplayDTO::from(game);
+
+        assert_eq!(dto.material_balance, 8);
+        assert_eq!(dto.captured_by_white, vec![CapturedPiece::Pawn, CapturedPiece::Queen]);
+        assert_eq!(dto.captured_by_black, vec![CapturedPiece::Pawn, CapturedPiece::Pawn]);
+    }
+
+    #[test]
+    fn display_reports_the_king_in_check() {
+        let checked = stored_game(
+            "rnbqkbnr/ppppp2p/5p2/6pQ/3PP3/8/PPP2PPP/RNB1KBNR b KQkq - 1 3",
+            json!({ "moves": ["e4", "f6", "d4", "g5", "Qh5+"] }),
+        );
+        let quiet = stored_game(
+            "rnbqkbnr/ppppp2p/5p2/6p1/3PP3/8/PPP2PPP/RNBQKBNR w KQkq g6 0 3",
+            json!({ "moves": ["e4", "f6", "d4", "g5"] }),
+        );
+
+        let dto = GameDisplayDTO::from(checked);
+        assert!(dto.in_check);
+        assert_eq!(dto.checked_king.as_deref(), Some("e8"));
+
+        let dto = GameDisplayDTO::from(quiet);
+        assert!(!dto.in_check);
+        assert_eq!(dto.checked_king, None);
+    }
+
+    #[test]
+    fn result_is_written_in_the_requested_form() {
+        let unfinished = Model::fixture(Uuid::new_v4(), Uuid::new_v4());
+        let finished = Model { result: Some(ResultSide::Black), ..unfinished.clone() };
+
+        assert_eq!(GameDisplayDTO::from(finished.clone()).result.as_deref(), Some("black"));
+        assert_eq!(GameDisplayDTO::new(finished, ResultFormat::Pgn).result.as_deref(), Some("0-1"));
+        assert_eq!(GameDisplayDTO::from(unfinished.clone()).result, None);
+        assert_eq!(GameDisplayDTO::new(unfinished, ResultFormat::Pgn).result.as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn unreadable_fen_falls_back_to_move_parity() {
+        let start = "8/8/4k3/8/8/4K3/8/8 b - - 0 1";
+        let game = stored_game("corrupt", json!({ "moves": ["Kd5", "Kd3"], "starting_fen": start }));
+
+        assert_eq!(side_to_move(&game, 2), Side::Black);
+        assert_eq!(side_to_move(&game, 3), Side::White);
+    }
+
+    #[test]
+    fn lenient_strictness_accepts_odd_counters_as_warnings() {
+        // The fullmove number starts at 1
+        let mut request: CreateGameRequest = serde_json::from_value(json!({
+            "time_control": 300,
+            "increment": 2,
+            "starting_fen": "4k3/8/8/8/8/8/8/4K3 w - - 0 0"
+        }))
+        .unwrap();
+        assert_eq!(request.fen_strictness, FenStrictness::Strict);
+        assert!(request.validate().is_err());
+
+        request.fen_strictness = FenStrictness::Lenient;
+        assert!(request.validate().is_ok());
+        assert_eq!(request.fen_warnings(), vec!["fullmove number must be at least 1".to_string()]);
+    }
+}
@@ -1,5 +1,15 @@
 pub mod players;
+pub mod country;
+pub mod username;
 pub mod responses;
 pub mod games;
+pub mod seeks;
 pub mod auth;
-pub mod ai;
\ No newline at end of file
+pub mod ai;
+pub mod admin;
+pub mod pagination;
+pub mod time;
+pub mod stats;
+pub mod notifications;
+pub mod tournaments;
+pub mod webhooks;
\ No newline at end of file
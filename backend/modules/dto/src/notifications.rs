@@ -0,0 +1,104 @@
+use entity::notification_preference::Model;
+use entity::sea_orm_active_enums::NotificationChannel;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Events a player can be notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// The opponent moved in a correspondence game
+    CorrespondenceTurn,
+    GameResult,
+    DrawOffer,
+    TournamentStart,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 4] = [
+        NotificationEvent::CorrespondenceTurn,
+        NotificationEvent::GameResult,
+        NotificationEvent::DrawOffer,
+        NotificationEvent::TournamentStart,
+    ];
+}
+
+/// Where each event is delivered. Players without stored preferences get
+/// [`NotificationPreferencesDTO::default`], which only notifies about events that
+/// need an answer from them or could otherwise be missed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferencesDTO {
+    #[schema(value_type = String, example = "email")]
+    pub correspondence_turn: NotificationChannel,
+
+    #[schema(value_type = String, example = "none")]
+    pub game_result: NotificationChannel,
+
+    #[schema(value_type = String, example = "none")]
+    pub draw_offer: NotificationChannel,
+
+    #[schema(value_type = String, example = "email")]
+    pub tournament_start: NotificationChannel,
+
+    /// Target of events routed to `webhook`
+    #[schema(example = "https://example.com/starkmate/hooks")]
+    pub webhook_url: Option<String>,
+}
+
+impl Default for NotificationPreferencesDTO {
+    fn default() -> Self {
+        Self {
+            correspondence_turn: NotificationChannel::Email,
+            game_result: NotificationChannel::None,
+            draw_offer: NotificationChannel::None,
+            tournament_start: NotificationChannel::Email,
+            webhook_url: None,
+        }
+    }
+}
+
+impl NotificationPreferencesDTO {
+    pub fn channel(&self, event: NotificationEvent) -> NotificationChannel {
+        match event {
+            NotificationEvent::CorrespondenceTurn => self.correspondence_turn,
+            NotificationEvent::GameResult => self.game_result,
+            NotificationEvent::DrawOffer => self.draw_offer,
+            NotificationEvent::TournamentStart => self.tournament_start,
+        }
+    }
+}
+
+impl From<Model> for NotificationPreferencesDTO {
+    fn from(value: Model) -> Self {
+        Self {
+            correspondence_turn: value.correspondence_turn,
+            game_result: value.game_result,
+            draw_offer: value.draw_offer,
+            tournament_start: value.tournament_start,
+            webhook_url: value.webhook_url,
+        }
+    }
+}
+
+/// Partial update; omitted events keep their current channel. Unknown event names
+/// are rejected rather than silently ignored.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateNotificationPreferences {
+    #[schema(value_type = Option<String>, example = "webhook")]
+    pub correspondence_turn: Option<NotificationChannel>,
+
+    #[schema(value_type = Option<String>, example = "email")]
+    pub game_result: Option<NotificationChannel>,
+
+    #[schema(value_type = Option<String>, example = "none")]
+    pub draw_offer: Option<NotificationChannel>,
+
+    #[schema(value_type = Option<String>, example = "email")]
+    pub tournament_start: Option<NotificationChannel>,
+
+    #[validate(url(message = "Webhook URL must be a valid URL"))]
+    #[schema(example = "https://example.com/starkmate/hooks")]
+    pub webhook_url: Option<String>,
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Uniform envelope for every list endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+
+    #[schema(example = 42)]
+    pub total: u64,
+
+    #[schema(example = 1)]
+    pub page: u64,
+
+    #[schema(example = 10)]
+    pub page_size: u64,
+
+    /// Opaque cursor for the next page; only present on cursor-paginated endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: u64, page: u64, page_size: u64) -> Self {
+        Self {
+            items,
+            total,
+            page,
+            page_size,
+            next_cursor: None,
+        }
+    }
+
+    pub fn with_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            page: self.page,
+            page_size: self.page_size,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_is_omitted_for_offset_pages() {
+        let page = Page::new(vec![1, 2], 2, 1, 10);
+        let json = serde_json::to_value(&page).unwrap();
+        assert!(json.get("next_cursor").is_none());
+
+        let json = serde_json::to_value(page.with_cursor(Some("abc".to_string()))).unwrap();
+        assert_eq!(json["next_cursor"], "abc");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::country::{canonical_country, validate_country};
+use crate::username::validate_username;
 use entity::player::Model;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -6,11 +8,7 @@ use validator::Validate;
 
 #[derive(Debug, Deserialize, Serialize, ToSchema, Validate)]
 pub struct NewPlayer {
-    #[validate(length(
-        min = 4,
-        max = 20,
-        message = "Username must be between 4 and 20 characters"
-    ))]
+    #[validate(custom = "validate_username")]
     pub username: String,
 
     #[validate(email(message = "Must be a valid email address"))]
@@ -25,6 +23,12 @@ pub struct NewPlayer {
 
     #[validate(length(max = 100, message = "Real name must be less than 100 characters"))]
     pub real_name: String,
+
+    /// ISO 3166-1 alpha-2 code, or `XX` for "prefer not to say"
+    #[serde(default)]
+    #[validate(custom = "validate_country")]
+    #[schema(example = "NG")]
+    pub country: Option<String>,
 }
 
 pub enum InvalidPlayer {
@@ -35,18 +39,19 @@ pub enum InvalidPlayer {
 
 impl NewPlayer {
     pub fn test_player() -> Self {
-        let rnd: i32 = rand::random();
+        let rnd: u32 = rand::random();
         Self {
-            username: format!("Player {}", rnd),
+            username: format!("player_{}", rnd),
             email: format!("player{}@gmail.com", rnd),
             password: format!("PasswordIsVeryStrong"),
             real_name: format!("A new player"),
+            country: None,
         }
     }
 
     pub fn invalid_player(invalid_choice: InvalidPlayer) -> Self {
-        let rnd: i32 = rand::random();
-        let mut username = format!("Player {}", rnd);
+        let rnd: u32 = rand::random();
+        let mut username = format!("player_{}", rnd);
         let mut email = format!("player{}@gmail.com", rnd);
         let mut password = format!("PasswordIsVeryStrong");
 
@@ -60,20 +65,20 @@ impl NewPlayer {
             email,
             password,
             real_name: format!("A new player"),
+            country: None,
         }
     }
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema, Validate)]
 pub struct UpdatePlayer {
-    #[validate(length(
-        min = 4,
-        max = 20,
-        message = "Username must be between 3 and 20 characters"
-    ))]
+    #[validate(custom = "validate_username")]
     pub username: Option<String>,
     pub real_name: Option<String>,
     pub biography: Option<String>,
+    /// ISO 3166-1 alpha-2 code, or `XX` for "prefer not to say"
+    #[validate(custom = "validate_country")]
+    #[schema(example = "NG")]
     pub country: Option<String>,
     pub flair: Option<String>,
     pub location: Option<String>,
@@ -81,6 +86,18 @@ pub struct UpdatePlayer {
     pub social_links: Option<Vec<String>>,
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema, Validate)]
+pub struct ChangePassword {
+    pub current_password: String,
+
+    #[validate(length(
+        min = 8,
+        max = 64,
+        message = "Password must be between 8 and 64 characters"
+    ))]
+    pub new_password: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DisplayPlayer {
     #[schema(value_type = String, format = "uuid")]
@@ -88,6 +105,8 @@ pub struct DisplayPlayer {
     pub username: String,
     pub email: String,
     pub biography: Option<String>,
+    /// Canonical ISO 3166-1 alpha-2 code, `XX` when the player prefers not to say
+    #[schema(example = "NG")]
     pub country: Option<String>,
     pub flair: Option<String>,
     pub real_name: String,
@@ -100,6 +119,8 @@ pub struct UpdatedPlayer {
     pub username: String,
     pub email: String,
     pub biography: Option<String>,
+    /// Canonical ISO 3166-1 alpha-2 code, `XX` when the player prefers not to say
+    #[schema(example = "NG")]
     pub country: Option<String>,
     pub flair: Option<String>,
     pub real_name: String,
@@ -108,6 +129,14 @@ pub struct UpdatedPlayer {
     pub social_links: Option<Vec<String>>,
 }
 
+// Rows written before codes were enforced may still hold free text; never echo it
+fn display_country(country: Option<String>) -> Option<String> {
+    country
+        .as_deref()
+        .and_then(canonical_country)
+        .map(str::to_string)
+}
+
 impl From<Model> for UpdatedPlayer {
     fn from(value: Model) -> Self {
         Self {
@@ -115,7 +144,7 @@ impl From<Model> for UpdatedPlayer {
             username: value.username,
             email: value.email,
             biography: value.biography,
-            country: value.country,
+            country: display_country(value.country),
             flair: value.flair,
             real_name: value.real_name,
             location: value.location,
@@ -132,7 +161,7 @@ impl From<Model> for DisplayPlayer {
             username: value.username,
             email: value.email,
             biography: value.biography,
-            country: value.country,
+            country: display_country(value.country),
             flair: value.flair,
             real_name: value.real_name,
         }
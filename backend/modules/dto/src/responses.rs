@@ -45,6 +45,13 @@ pub struct PlayerDeleted{
     pub body: DeletedBody
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasswordChanged {
+    #[schema(example = "Password changed")]
+    pub message: String,
+    pub body: DeletedBody,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct InvalidCredentialsResponse {
     #[schema(example = "Invalid credentials")]
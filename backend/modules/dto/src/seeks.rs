@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use entity::game_seek::Model;
+use entity::sea_orm_active_enums::{SeekColor, SeekStatus};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::games::{PlayerColor, TimeClass, Variant, validate_variant_enabled};
+
+impl From<SeekColor> for PlayerColor {
+    fn from(value: SeekColor) -> Self {
+        match value {
+            SeekColor::White => PlayerColor::White,
+            SeekColor::Black => PlayerColor::Black,
+            SeekColor::Random => PlayerColor::Random,
+        }
+    }
+}
+
+impl From<PlayerColor> for SeekColor {
+    fn from(value: PlayerColor) -> Self {
+        match value {
+            PlayerColor::White => SeekColor::White,
+            PlayerColor::Black => SeekColor::Black,
+            PlayerColor::Random => SeekColor::Random,
+        }
+    }
+}
+
+/// A public offer to play, open to whoever claims it first.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateSeekRequest {
+    #[validate(range(min = 60, max = 7200, message = "Time control must be between 1 minute and 2 hours"))]
+    #[schema(example = 300)]
+    pub time_control: i32,
+
+    #[validate(range(min = 0, max = 60, message = "Increment must be between 0 and 60 seconds"))]
+    #[schema(example = 3)]
+    pub increment: i32,
+
+    /// Color the creator plays; `random` when left out.
+    pub color: Option<PlayerColor>,
+
+    #[serde(default)]
+    #[validate(custom = "validate_variant_enabled")]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema, Validate)]
+pub struct SeeksQuery {
+    #[validate(range(min = 1, message = "Page must be at least 1"))]
+    #[schema(example = 1)]
+    pub page: Option<i32>,
+
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    #[schema(example = 20)]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeekDTO {
+    #[schema(value_type = String, format = "uuid")]
+    pub id: Uuid,
+
+    #[schema(value_type = String, format = "uuid")]
+    pub creator_id: Uuid,
+
+    /// Color the creator plays.
+    pub color: PlayerColor,
+
+    pub variant: Variant,
+
+    #[schema(example = 300)]
+    pub time_control: i32,
+
+    #[schema(example = 3)]
+    pub increment: i32,
+
+    pub time_class: Option<TimeClass>,
+
+    /// `waiting` until someone accepts, then `accepted`.
+    #[schema(example = "waiting")]
+    pub status: String,
+
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub accepted_by: Option<Uuid>,
+
+    /// Game started by accepting the seek.
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub game_id: Option<Uuid>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for SeekDTO {
+    fn from(value: Model) -> Self {
+        Self {
+            id: value.id,
+            creator_id: value.creator,
+            color: value.color.into(),
+            variant: value.variant.into(),
+            time_control: value.initial_secs,
+            increment: value.increment_secs,
+            time_class: TimeClass::of(value.initial_secs, value.increment_secs),
+            status: match value.status {
+                SeekStatus::Waiting => "waiting",
+                SeekStatus::Accepted => "accepted",
+            }
+            .to_string(),
+            accepted_by: value.accepted_by,
+            game_id: value.game_id,
+            created_at: value.created_at.into(),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::games::Variant;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema, Validate)]
+pub struct ColorAdvantageQuery {
+    /// Variant to report on. Defaults to standard chess.
+    #[schema(example = "kingofthehill")]
+    pub variant: Option<Variant>,
+
+    /// Finished games required before percentages are reported. Defaults to 100.
+    #[validate(range(min = 1, max = 1000000, message = "Minimum sample must be between 1 and 1000000 games"))]
+    #[schema(example = 100)]
+    pub min_games: Option<i64>,
+}
+
+/// How often each side wins a variant, over every finished game on the platform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ColorAdvantageDTO {
+    pub variant: Variant,
+
+    /// Finished games the percentages are based on.
+    #[schema(example = 48210)]
+    pub games: i64,
+
+    #[schema(example = 100)]
+    pub min_games: i64,
+
+    /// `false` when fewer than `min_games` games have finished; the percentages are
+    /// then omitted rather than reported from too small a sample.
+    pub sufficient_sample: bool,
+
+    #[schema(example = 52.31)]
+    pub white_win_pct: Option<f64>,
+
+    #[schema(example = 41.07)]
+    pub black_win_pct: Option<f64>,
+
+    #[schema(example = 6.62)]
+    pub draw_pct: Option<f64>,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Server clock reading used by clients to estimate their offset from the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ServerTime {
+    /// Authoritative UTC time, RFC 3339 with millisecond precision.
+    #[schema(example = "2026-10-15T09:30:00.123Z")]
+    pub server_time: String,
+
+    /// The same instant in milliseconds since the Unix epoch.
+    #[schema(example = 1792056600123_i64)]
+    pub epoch_ms: i64,
+
+    /// Milliseconds on the server's monotonic clock. The origin is arbitrary and resets
+    /// when the server restarts; only the difference between two readings is meaningful.
+    #[schema(example = 86400000_u64)]
+    pub monotonic_ms: u64,
+}
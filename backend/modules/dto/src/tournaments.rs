@@ -0,0 +1,68 @@
+use entity::sea_orm_active_enums::TieBreak as DbTieBreak;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Tie-break used to order players on equal points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// Sum of the opponents' scores.
+    #[default]
+    Buchholz,
+    /// Sum of the scores of beaten opponents plus half those of drawn ones.
+    SonnebornBerger,
+}
+
+impl From<DbTieBreak> for TieBreak {
+    fn from(value: DbTieBreak) -> Self {
+        match value {
+            DbTieBreak::Buchholz => TieBreak::Buchholz,
+            DbTieBreak::SonnebornBerger => TieBreak::SonnebornBerger,
+        }
+    }
+}
+
+impl From<TieBreak> for DbTieBreak {
+    fn from(value: TieBreak) -> Self {
+        match value {
+            TieBreak::Buchholz => DbTieBreak::Buchholz,
+            TieBreak::SonnebornBerger => DbTieBreak::SonnebornBerger,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StandingDTO {
+    /// 1-based place; players level on points and both tie-breaks share a rank.
+    #[schema(example = 1)]
+    pub rank: u32,
+
+    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub player_id: Uuid,
+
+    /// Points from finished games and byes.
+    #[schema(example = 3.5)]
+    pub points: f64,
+
+    #[schema(example = 9.0)]
+    pub buchholz: f64,
+
+    #[schema(example = 7.25)]
+    pub sonneborn_berger: f64,
+
+    /// Finished games played over the board; byes are not counted.
+    #[schema(example = 4)]
+    pub games_played: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TournamentStandingsDTO {
+    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub tournament_id: Uuid,
+
+    /// Tie-break applied after points; the other one decides remaining ties.
+    pub tie_break: TieBreak,
+
+    pub standings: Vec<StandingDTO>,
+}
@@ -0,0 +1,198 @@
+use once_cell::sync::Lazy;
+use validator::ValidationError;
+
+/// Length bounds used when a deployment does not configure its own.
+pub const DEFAULT_MIN_USERNAME_LENGTH: usize = 4;
+pub const DEFAULT_MAX_USERNAME_LENGTH: usize = 20;
+
+/// Characters allowed between alphanumerics; a username cannot start or end with one.
+pub const USERNAME_SEPARATORS: [char; 3] = ['_', '-', '.'];
+
+/// Names that could pass for staff or the platform itself. Deployments add their own
+/// through `USERNAME_RESERVED`.
+pub const RESERVED_USERNAMES: [&str; 14] = [
+    "admin",
+    "administrator",
+    "anonymous",
+    "api",
+    "guest",
+    "mod",
+    "moderator",
+    "null",
+    "root",
+    "starkmate",
+    "staff",
+    "support",
+    "system",
+    "undefined",
+];
+
+/// Guest accounts are named `guest_<id>` by the server, so nobody can pick such a name.
+const GUEST_PREFIX: &str = "guest_";
+
+/// Form a username is compared in, matching the case-insensitive unique index on
+/// `lower(username)`.
+pub fn normalize_username(username: &str) -> String {
+    username.to_lowercase()
+}
+
+/// Username format rules, configurable through `USERNAME_MIN_LENGTH`,
+/// `USERNAME_MAX_LENGTH` and `USERNAME_RESERVED` (comma-separated).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsernameRules {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Normalized names nobody may register, on top of [`RESERVED_USERNAMES`].
+    pub reserved: Vec<String>,
+}
+
+impl Default for UsernameRules {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_MIN_USERNAME_LENGTH,
+            max_length: DEFAULT_MAX_USERNAME_LENGTH,
+            reserved: Vec::new(),
+        }
+    }
+}
+
+static RULES: Lazy<UsernameRules> = Lazy::new(UsernameRules::from_env);
+
+impl UsernameRules {
+    pub fn from_env() -> Self {
+        let length = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|len| *len > 0)
+                .unwrap_or(default)
+        };
+        let min_length = length("USERNAME_MIN_LENGTH", DEFAULT_MIN_USERNAME_LENGTH);
+        Self {
+            min_length,
+            max_length: length("USERNAME_MAX_LENGTH", DEFAULT_MAX_USERNAME_LENGTH).max(min_length),
+            reserved: std::env::var("USERNAME_RESERVED")
+                .unwrap_or_default()
+                .split(',')
+                .map(|name| normalize_username(name.trim()))
+                .filter(|name| !name.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Checks `username` against each rule in turn, reporting the first one it breaks.
+    pub fn check(&self, username: &str) -> Result<(), ValidationError> {
+        let length = username.chars().count();
+        if length < self.min_length || length > self.max_length {
+            return Err(violation(
+                "username_length",
+                format!(
+                    "Username must be between {} and {} characters",
+                    self.min_length, self.max_length
+                ),
+            ));
+        }
+        if let Some(invalid) = username
+            .chars()
+            .find(|c| !c.is_ascii_alphanumeric() && !USERNAME_SEPARATORS.contains(c))
+        {
+            return Err(violation(
+                "username_charset",
+                format!(
+                    "Username may only contain letters, digits, '_', '-' and '.', not '{}'",
+                    invalid
+                ),
+            ));
+        }
+        if username.starts_with(USERNAME_SEPARATORS) || username.ends_with(USERNAME_SEPARATORS) {
+            return Err(violation(
+                "username_separator",
+                "Username cannot start or end with '_', '-' or '.'".to_string(),
+            ));
+        }
+        let normalized = normalize_username(username);
+        if RESERVED_USERNAMES.contains(&normalized.as_str())
+            || self.reserved.contains(&normalized)
+            || normalized.starts_with(GUEST_PREFIX)
+        {
+            return Err(violation(
+                "username_reserved",
+                format!("Username '{}' is reserved", username),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn violation(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message.into());
+    error
+}
+
+/// Checks a username against the deployment's [`UsernameRules`]. Every path that
+/// creates or renames an account validates through this.
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    RULES.check(username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(username: &str) -> Option<String> {
+        UsernameRules::default()
+            .check(username)
+            .err()
+            .map(|error| error.code.to_string())
+    }
+
+    #[test]
+    fn accepts_letters_digits_and_inner_separators() {
+        for username in ["chess_master", "Magnus.C", "a-b_c.d", "1234"] {
+            assert_eq!(code(username), None, "{}", username);
+        }
+    }
+
+    #[test]
+    fn enforces_length_bounds() {
+        assert_eq!(code("abc").as_deref(), Some("username_length"));
+        assert_eq!(code(&"a".repeat(21)).as_deref(), Some("username_length"));
+        assert_eq!(code(&"a".repeat(20)), None);
+
+        let rules = UsernameRules { min_length: 2, max_length: 30, reserved: Vec::new() };
+        assert!(rules.check("ab").is_ok());
+        assert!(rules.check(&"a".repeat(30)).is_ok());
+    }
+
+    #[test]
+    fn rejects_characters_outside_the_allowed_set() {
+        for username in ["chess master", "mäster", "who@home", "tab\there"] {
+            assert_eq!(code(username).as_deref(), Some("username_charset"), "{}", username);
+        }
+    }
+
+    #[test]
+    fn rejects_leading_and_trailing_separators() {
+        for username in ["_chess", "chess-", ".chess", "chess."] {
+            assert_eq!(code(username).as_deref(), Some("username_separator"), "{}", username);
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_names_in_any_case() {
+        for username in ["admin", "System", "SUPPORT", "guest_3f2a9c41"] {
+            assert_eq!(code(username).as_deref(), Some("username_reserved"), "{}", username);
+        }
+
+        let rules = UsernameRules { reserved: vec!["arbiter".to_string()], ..UsernameRules::default() };
+        assert!(rules.check("Arbiter").is_err());
+    }
+
+    #[test]
+    fn violation_message_names_the_rule() {
+        let error = UsernameRules::default().check("_x_").unwrap_err();
+
+        assert_eq!(error.message.unwrap(), "Username must be between 4 and 20 characters");
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use entity::webhook_delivery::Model;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Default, Deserialize, ToSchema, Validate)]
+pub struct WebhookDeliveriesQuery {
+    #[validate(range(min = 1, message = "Page must be at least 1"))]
+    #[schema(example = 1)]
+    pub page: Option<i32>,
+
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    #[schema(example = 20)]
+    pub limit: Option<i32>,
+}
+
+/// One queued webhook notification and how its delivery went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDeliveryDTO {
+    #[schema(value_type = String, format = "uuid")]
+    pub id: Uuid,
+
+    /// Position in the subscriber's queue; deliveries are attempted in this order.
+    #[schema(example = 42)]
+    pub sequence: i64,
+
+    #[schema(example = "game_result")]
+    pub event: String,
+
+    /// Sent with every attempt so receivers can drop repeats.
+    #[schema(example = "game_result:123e4567-e89b-12d3-a456-426614174000")]
+    pub idempotency_key: String,
+
+    #[schema(example = "https://example.com/hooks/starkmate")]
+    pub target_url: String,
+
+    /// `pending`, `delivered`, or `dead` once every attempt has failed.
+    #[schema(example = "delivered")]
+    pub status: String,
+
+    #[schema(example = 2)]
+    pub attempts: i32,
+
+    pub last_error: Option<String>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub next_attempt_at: DateTime<Utc>,
+
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub delivered_at: Option<DateTime<Utc>>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for WebhookDeliveryDTO {
+    fn from(value: Model) -> Self {
+        Self {
+            id: value.id,
+            sequence: value.sequence,
+            event: value.event,
+            idempotency_key: value.idempotency_key,
+            target_url: value.target_url,
+            status: serde_json::to_value(value.status)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            attempts: value.attempts,
+            last_error: value.last_error,
+            next_attempt_at: value.next_attempt_at.into(),
+            delivered_at: value.delivered_at.map(Into::into),
+            created_at: value.created_at.into(),
+        }
+    }
+}
@@ -3,7 +3,8 @@ use argon2::password_hash::Error as Argon2HashError;
 use core::fmt;
 use sea_orm::DbErr;
 use serde_json::json;
-use validator::{ValidationErrors, ValidationErrorsKind};
+use std::borrow::Cow;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
 
 #[derive(Debug)]
 pub enum ApiError {
@@ -12,6 +13,15 @@ pub enum ApiError {
     NotFound(String),
     ValidationError(ValidationErrors),
     PasswordHashError(Argon2HashError),
+    Conflict(String),
+    Forbidden(String),
+    EngineUnavailable(String),
+    EngineTimeout(String),
+    /// A well-formed request naming something that does not exist, such as an unknown
+    /// player id; `field` is the request field that holds it.
+    InvalidReference { field: String, message: String },
+    /// A move from a player whose side is not the one to move.
+    NotYourTurn,
 }
 
 impl From<DbErr> for ApiError {
@@ -75,11 +85,26 @@ impl fmt::Display for ApiError {
             ApiError::PasswordHashError(err) => {
                 write!(f, "Unable to hash password: {}", err.to_string())
             }
+            ApiError::Conflict(v) => write!(f, "{}", v),
+            ApiError::Forbidden(v) => write!(f, "{}", v),
+            ApiError::EngineUnavailable(v) => write!(f, "{}", v),
+            ApiError::EngineTimeout(v) => write!(f, "{}", v),
+            ApiError::InvalidReference { message, .. } => write!(f, "{}", message),
+            ApiError::NotYourTurn => write!(f, "It is not your turn"),
         }
     }
 }
 
 impl ApiError {
+    /// Machine-readable name of errors clients are expected to tell apart from others
+    /// with the same status, sent as `reason` over both REST and WebSocket.
+    pub fn reason(&self) -> Option<&'static str> {
+        match self {
+            ApiError::NotYourTurn => Some("not_your_turn"),
+            _ => None,
+        }
+    }
+
     pub fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
         match self {
             ApiError::InvalidCredentials => HttpResponse::BadRequest().json(json!({
@@ -102,19 +127,83 @@ impl ApiError {
                 "error": self.to_string(),
                 "code":500
             })),
+            ApiError::Conflict(_) => HttpResponse::Conflict().json(json!({
+                "error": self.to_string(),
+                "code": 409
+            })),
+            ApiError::Forbidden(_) => HttpResponse::Forbidden().json(json!({
+                "error": self.to_string(),
+                "code": 403
+            })),
+            ApiError::EngineUnavailable(_) => HttpResponse::ServiceUnavailable().json(json!({
+                "error": self.to_string(),
+                "code": 503
+            })),
+            ApiError::EngineTimeout(_) => HttpResponse::GatewayTimeout().json(json!({
+                "error": self.to_string(),
+                "code": 504
+            })),
+            ApiError::InvalidReference { field, .. } => HttpResponse::UnprocessableEntity().json(json!({
+                "error": self.to_string(),
+                "code": 422,
+                "field": field
+            })),
+            ApiError::NotYourTurn => HttpResponse::Conflict().json(json!({
+                "error": self.to_string(),
+                "code": 409,
+                "reason": self.reason()
+            })),
         }
     }
 }
 
+/// serde messages that name a field of the body, as opposed to quoting a bad value
+/// such as an unknown enum variant.
+const FIELD_ERRORS: [&str; 3] = ["missing field `", "unknown field `", "duplicate field `"];
+
+/// Describes why a request body could not be deserialized, naming the field when
+/// serde reports one (missing, unknown or duplicate fields).
+fn json_body_error(err: &serde_json::Error) -> ApiError {
+    let message = match err.classify() {
+        serde_json::error::Category::Syntax | serde_json::error::Category::Eof => format!(
+            "Malformed JSON body at line {} column {}",
+            err.line(),
+            err.column()
+        ),
+        _ => {
+            let reason = err.to_string();
+            // serde_json appends the position to its message; the field is more useful
+            let reason = reason
+                .rsplit_once(" at line ")
+                .map_or(reason.as_str(), |(reason, _)| reason);
+            let field = FIELD_ERRORS
+                .iter()
+                .find_map(|prefix| reason.strip_prefix(prefix))
+                .and_then(|rest| rest.split('`').next());
+            match field {
+                Some(field) => format!("Invalid request body field `{}`: {}", field, reason),
+                // Unknown variants and invalid values quote the value, not the field
+                None => format!("Invalid request body: {}", reason),
+            }
+        }
+    };
+
+    let mut error = ValidationError::new("json");
+    error.message = Some(Cow::Owned(message));
+    let mut errors = ValidationErrors::new();
+    errors.add("body", error);
+    ApiError::ValidationError(errors)
+}
+
+/// `JsonConfig` error handler shared by every `web::Json` extractor, so malformed
+/// bodies get the same `{"error", "code"}` shape as other API errors.
 pub fn custom_json_error(err: JsonPayloadError, _: &HttpRequest) -> Error {
     let error_response = match &err {
         JsonPayloadError::ContentType => HttpResponse::UnsupportedMediaType().json(json!({
             "error":"Invalid Content-Type. Expecting application/json",
             "code": 415
         })),
-        // JsonPayloadError::Deserialize(err) => HttpResponse::BadRequest().json(json!({
-        //     "error":err.to_string()
-        // })),
+        JsonPayloadError::Deserialize(err) => json_body_error(err).error_response(),
         _ => HttpResponse::BadRequest().json(json!({
             "error":err.to_string(),
             "code":400
@@ -123,3 +212,57 @@ pub fn custom_json_error(err: JsonPayloadError, _: &HttpRequest) -> Error {
 
     actix_web::error::InternalError::from_response(err, error_response).into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Body {
+        elo: u32,
+        #[serde(default)]
+        color: Option<Color>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Color {
+        White,
+        Black,
+    }
+
+    fn message(body: &str) -> String {
+        let err = serde_json::from_str::<Body>(body).unwrap_err();
+        json_body_error(&err).to_string()
+    }
+
+    #[test]
+    fn names_the_field_that_failed() {
+        assert_eq!(message("{}"), "Invalid request body field `elo`: missing field `elo`. ");
+    }
+
+    #[test]
+    fn reports_where_malformed_json_breaks() {
+        assert_eq!(message("{\"elo\": "), "Malformed JSON body at line 1 column 8. ");
+    }
+
+    #[test]
+    fn names_an_unknown_field() {
+        assert_eq!(
+            message("{\"elo\": 1500, \"colour\": \"white\"}"),
+            "Invalid request body field `colour`: unknown field `colour`, expected `elo` or `color`. "
+        );
+    }
+
+    #[test]
+    fn does_not_mistake_an_unknown_variant_for_a_field() {
+        assert_eq!(
+            message("{\"elo\": 1500, \"color\": \"green\"}"),
+            "Invalid request body: unknown variant `green`, expected `white` or `black`. "
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use crate::jwt::Claims;
+use std::env;
+
+/// Returns true if the token's subject is listed in the comma-separated
+/// `ADMIN_PLAYER_IDS` env var. Nobody is an admin when it is unset.
+pub fn is_admin(claims: &Claims) -> bool {
+    env::var("ADMIN_PLAYER_IDS")
+        .map(|ids| ids.split(',').any(|id| id.trim() == claims.sub))
+        .unwrap_or(false)
+}
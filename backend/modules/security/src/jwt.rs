@@ -5,7 +5,7 @@ use actix_web::{
 };
 use futures_util::future::{ok, LocalBoxFuture, Ready};
 use std::task::{Context, Poll};
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 
@@ -14,6 +14,21 @@ pub struct Claims {
     pub sub: String,
     pub exp: usize,
     pub iat: usize,
+    /// Set on tokens issued to guest players, who may only play unrated games.
+    #[serde(default)]
+    pub guest: bool,
+}
+
+/// Signs `claims` with `secret` so that [`decode_token`] and the middleware accept them.
+pub fn encode_token(claims: &Claims, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    encode(&Header::new(Algorithm::HS256), claims, &EncodingKey::from_secret(secret.as_bytes()))
+}
+
+/// Validates `token` against `secret` exactly as the HTTP middleware does and returns its claims.
+pub fn decode_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let validation = Validation::new(Algorithm::HS256);
+    decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
+        .map(|token_data| token_data.claims)
 }
 
 pub struct JwtAuthMiddleware {
@@ -88,16 +103,11 @@ where
                 }
                 
                 let token = &header_str[7..]; // Remove "Bearer " prefix
-                let validation = Validation::new(Algorithm::HS256);
                 
-                match decode::<Claims>(
-                    token,
-                    &DecodingKey::from_secret(secret_key.as_bytes()),
-                    &validation,
-                ) {
-                    Ok(token_data) => {
+                match decode_token(token, &secret_key) {
+                    Ok(claims) => {
                         // Set user_id in request extensions for later use
-                        req.extensions_mut().insert(token_data.claims);
+                        req.extensions_mut().insert(claims);
                         true
                     }
                     Err(_) => false,
@@ -1,3 +1,5 @@
+pub mod admin;
 pub mod jwt;
 
-pub use jwt::{JwtAuthMiddleware, Claims};
+pub use admin::is_admin;
+pub use jwt::{JwtAuthMiddleware, Claims, decode_token, encode_token};
\ No newline at end of file
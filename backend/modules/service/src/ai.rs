@@ -0,0 +1,284 @@
+//! Engine-backed move suggestions and position analysis.
+//!
+//! Engine lines come back in UCI notation; responses carry them in SAN as well so
+//! clients can show them without replaying the position themselves.
+
+use std::time::Instant;
+
+use chess::bitboard::Board::Color;
+use chess::position::Position;
+use dto::ai::{AiSuggestionRequest, AiSuggestionResponse, AlternativeMove, PositionAnalysisRequest, PositionAnalysisResponse};
+use error::error::ApiError;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::engine::{Engine, EngineError, PvLine, RequestClass, UciEngine};
+
+/// Search depth of a suggestion when the request leaves it out.
+pub const DEFAULT_SUGGESTION_DEPTH: u8 = 10;
+
+/// Alternatives returned besides the best move when the request does not ask for a number.
+pub const DEFAULT_ALTERNATIVES: u8 = 3;
+
+/// Most alternatives a single request can get, whatever the engine returns.
+pub const MAX_ALTERNATIVES: u8 = 5;
+
+fn invalid_fen(reason: impl ToString) -> ApiError {
+    let mut invalid = ValidationError::new("invalid_fen");
+    invalid.message = Some(reason.to_string().into());
+    let mut errors = ValidationErrors::new();
+    errors.add("fen", invalid);
+    ApiError::ValidationError(errors)
+}
+
+/// Parses the requested position, rejecting ones the engine has nothing to say about.
+fn searchable_position(fen: &str) -> Result<Position, ApiError> {
+    let position: Position = fen.parse().map_err(invalid_fen)?;
+    if position.legal_moves().is_empty() {
+        return Err(invalid_fen("The position has no legal moves"));
+    }
+    Ok(position)
+}
+
+fn configured_engine(class: RequestClass) -> Result<UciEngine, ApiError> {
+    UciEngine::from_env(class).ok_or_else(|| ApiError::EngineUnavailable("ENGINE_PATH is not set".to_string()))
+}
+
+/// Renders an engine line in SAN. A line that does not replay from the position it
+/// was searched in means the engine misbehaved.
+fn line_san(position: &Position, moves: &[String]) -> Result<Vec<String>, ApiError> {
+    position
+        .san_line(moves)
+        .map_err(|err| EngineError::Protocol(format!("principal variation {}", err)).into())
+}
+
+/// Converts a score for the side to move into pawns from White's point of view.
+fn evaluation(position: &Position, score_cp: i32) -> f32 {
+    let pawns = score_cp as f32 / 100.0;
+    match position.turn {
+        Color::White => pawns,
+        Color::Black => -pawns,
+    }
+}
+
+/// Rough game phase from the non-pawn material left on the board.
+fn position_type(position: &Position) -> &'static str {
+    let board = &position.board;
+    let material = 3 * (board.knights().count() + board.bishops().count())
+        + 5 * board.rooks().count()
+        + 9 * board.queens().count();
+    if material <= 26 {
+        "Endgame"
+    } else if position.fullmove_number <= 10 && material >= 56 {
+        "Opening"
+    } else {
+        "Middlegame"
+    }
+}
+
+/// Orders engine lines best first: by score for the side to move, then by first move
+/// so lines the engine scores the same come out in the same order on every request.
+/// Empty lines are dropped.
+fn rank_lines(lines: Vec<PvLine>) -> Vec<PvLine> {
+    let mut lines: Vec<PvLine> = lines.into_iter().filter(|line| !line.moves.is_empty()).collect();
+    lines.sort_by(|a, b| b.score_cp.cmp(&a.score_cp).then_with(|| a.moves[0].cmp(&b.moves[0])));
+    lines
+}
+
+/// Splits ranked engine lines into the best one and at most `count` alternatives.
+fn best_and_alternatives(
+    position: &Position,
+    lines: Vec<PvLine>,
+    count: u8,
+) -> Result<(PvLine, Vec<AlternativeMove>), ApiError> {
+    let mut lines = rank_lines(lines).into_iter();
+    let best = lines
+        .next()
+        .ok_or_else(|| EngineError::Protocol("no principal variation".to_string()))?;
+    let alternatives = lines
+        .take(count.min(MAX_ALTERNATIVES) as usize)
+        .map(|line| {
+            Ok(AlternativeMove {
+                san: line_san(position, &line.moves[..1])?.remove(0),
+                chess_move: line.moves[0].clone(),
+                evaluation: evaluation(position, line.score_cp),
+            })
+        })
+        .collect::<Result<_, ApiError>>()?;
+    Ok((best, alternatives))
+}
+
+pub async fn suggest(request: &AiSuggestionRequest) -> Result<AiSuggestionResponse, ApiError> {
+    suggest_with(&configured_engine(RequestClass::Quick)?, request).await
+}
+
+pub async fn suggest_with<E: Engine>(engine: &E, request: &AiSuggestionRequest) -> Result<AiSuggestionResponse, ApiError> {
+    let position = searchable_position(&request.fen)?;
+    let depth = request.depth.unwrap_or(DEFAULT_SUGGESTION_DEPTH);
+    let count = request.alternatives.unwrap_or(DEFAULT_ALTERNATIVES).min(MAX_ALTERNATIVES);
+
+    let started = Instant::now();
+    let search = engine.analyse(&request.fen, depth, 1 + count).await?;
+    let computation_time_ms = started.elapsed().as_millis() as u32;
+
+    let (best, alternatives) = best_and_alternatives(&position, search.lines, count)?;
+    let principal_variation_san = line_san(&position, &best.moves)?;
+
+    Ok(AiSuggestionResponse {
+        best_move: best.moves[0].clone(),
+        best_move_san: principal_variation_san[0].clone(),
+        evaluation: evaluation(&position, best.score_cp),
+        depth,
+        principal_variation: best.moves,
+        principal_variation_san,
+        alternatives,
+        computation_time_ms,
+        truncated: search.truncated,
+    })
+}
+
+pub async fn analyze(request: &PositionAnalysisRequest) -> Result<PositionAnalysisResponse, ApiError> {
+    analyze_with(&configured_engine(RequestClass::Position)?, request).await
+}
+
+pub async fn analyze_with<E: Engine>(
+    engine: &E,
+    request: &PositionAnalysisRequest,
+) -> Result<PositionAnalysisResponse, ApiError> {
+    let position = searchable_position(&request.fen)?;
+
+    let search = engine
+        .analyse(&request.fen, request.depth, 1 + DEFAULT_ALTERNATIVES)
+        .await?;
+    let (best, alternatives) = best_and_alternatives(&position, search.lines, DEFAULT_ALTERNATIVES)?;
+
+    Ok(PositionAnalysisResponse {
+        evaluation: evaluation(&position, best.score_cp),
+        best_line_san: line_san(&position, &best.moves)?,
+        best_line: best.moves,
+        alternatives,
+        position_type: position_type(&position).to_string(),
+        truncated: search.truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Search;
+
+    /// Legal's mate, with White about to give up the queen.
+    const LEGAL_FEN: &str = "r2qkbnr/ppp2ppp/2np4/4p2b/2B1P3/2N2N1P/PPPP1PP1/R1BQK2R w KQkq - 1 6";
+
+    /// An engine that answers every search with the same lines.
+    struct ScriptedEngine {
+        lines: Vec<PvLine>,
+    }
+
+    impl Engine for ScriptedEngine {
+        async fn analyse(&self, _fen: &str, _depth: u8, multipv: u8) -> Result<Search, EngineError> {
+            Ok(Search::complete(self.lines.iter().take(multipv as usize).cloned().collect()))
+        }
+    }
+
+    fn line(score_cp: i32, moves: &[&str]) -> PvLine {
+        PvLine { score_cp, moves: moves.iter().map(|mv| mv.to_string()).collect() }
+    }
+
+    #[async_std::test]
+    async fn suggestion_carries_the_principal_variation_in_san() {
+        let engine = ScriptedEngine { lines: vec![line(500, &["f3e5", "h5d1", "c4f7", "e8e7", "c3d5"])] };
+        let request = AiSuggestionRequest { fen: LEGAL_FEN.to_string(), depth: Some(12), time_limit_ms: None, alternatives: None };
+
+        let response = suggest_with(&engine, &request).await.unwrap();
+
+        assert_eq!(response.best_move, "f3e5");
+        assert_eq!(response.best_move_san, "Nxe5");
+        assert_eq!(response.principal_variation_san, vec!["Nxe5", "Bxd1", "Bxf7+", "Ke7", "Nd5#"]);
+        assert_eq!((response.evaluation, response.depth), (5.0, 12));
+    }
+
+    #[async_std::test]
+    async fn analysis_renders_alternatives_in_san_from_whites_point_of_view() {
+        let engine = ScriptedEngine {
+            lines: vec![line(-30, &["e7e5", "g1f3"]), line(-45, &["c7c5"]), line(-60, &["g8f6"])],
+        };
+        let request = PositionAnalysisRequest {
+            fen: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_string(),
+            depth: 15,
+        };
+
+        let response = analyze_with(&engine, &request).await.unwrap();
+
+        assert_eq!(response.best_line_san, vec!["e5", "Nf3"]);
+        assert_eq!(response.evaluation, 0.3);
+        let alternatives: Vec<(&str, &str, f32)> = response
+            .alternatives
+            .iter()
+            .map(|alt| (alt.chess_move.as_str(), alt.san.as_str(), alt.evaluation))
+            .collect();
+        assert_eq!(alternatives, vec![("c7c5", "c5", 0.45), ("g8f6", "Nf6", 0.6)]);
+        assert_eq!(response.position_type, "Opening");
+    }
+
+    #[async_std::test]
+    async fn suggestion_alternatives_are_sorted_by_score_then_move_and_capped() {
+        // Engines report equal scores in no particular order
+        let engine = ScriptedEngine {
+            lines: vec![
+                line(20, &["g1f3"]),
+                line(35, &["e2e4"]),
+                line(20, &["c2c4"]),
+                line(35, &["d2d4"]),
+                line(-10, &["b1c3"]),
+                line(20, &["e2e3"]),
+            ],
+        };
+        let request = AiSuggestionRequest {
+            fen: chess::fen::STARTING_FEN.to_string(),
+            depth: None,
+            time_limit_ms: None,
+            alternatives: Some(3),
+        };
+
+        let first = suggest_with(&engine, &request).await.unwrap();
+        let again = suggest_with(&engine, &request).await.unwrap();
+
+        assert_eq!(first.best_move, "d2d4");
+        let moves: Vec<&str> = first.alternatives.iter().map(|alt| alt.chess_move.as_str()).collect();
+        assert_eq!(moves, vec!["e2e4", "c2c4", "g1f3"]);
+        let repeated: Vec<&str> = again.alternatives.iter().map(|alt| alt.chess_move.as_str()).collect();
+        assert_eq!(repeated, moves);
+    }
+
+    #[async_std::test]
+    async fn alternatives_never_exceed_the_cap() {
+        let moves = ["a2a3", "b2b3", "c2c3", "d2d3", "e2e3", "f2f3", "g2g3", "h2h3"];
+        let engine = ScriptedEngine { lines: moves.iter().map(|mv| line(0, &[mv])).collect() };
+        let request = AiSuggestionRequest {
+            fen: chess::fen::STARTING_FEN.to_string(),
+            depth: None,
+            time_limit_ms: None,
+            alternatives: Some(u8::MAX),
+        };
+
+        let response = suggest_with(&engine, &request).await.unwrap();
+
+        assert_eq!(response.alternatives.len(), MAX_ALTERNATIVES as usize);
+        assert!(response.alternatives.windows(2).all(|pair| pair[0].chess_move < pair[1].chess_move));
+    }
+
+    #[async_std::test]
+    async fn line_that_does_not_replay_is_an_engine_error() {
+        let engine = ScriptedEngine { lines: vec![line(0, &["e2e4", "e2e4"])] };
+        let request = AiSuggestionRequest {
+            fen: chess::fen::STARTING_FEN.to_string(),
+            depth: None,
+            time_limit_ms: None,
+            alternatives: None,
+        };
+
+        let result = suggest_with(&engine, &request).await;
+
+        assert!(matches!(result, Err(ApiError::EngineUnavailable(_))));
+    }
+}
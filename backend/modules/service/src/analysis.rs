@@ -0,0 +1,88 @@
+use chess::bitboard::Board::Color;
+use chess::history::GameHistory;
+use chess::position::Position;
+use dto::games::{pgn_moves, pgn_starting_fen};
+use entity::game;
+use error::error::ApiError;
+use sea_orm::DbErr;
+
+use crate::engine::{Engine, MATE_SCORE_CP};
+
+/// Evaluations are clamped to this many centipawns before losses are computed, so
+/// moves in positions that are already won or lost barely count.
+pub const EVAL_CLAMP_CP: i32 = 1000;
+
+/// Engine verdict on one move of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlyAnalysis {
+    /// 1-based ply number.
+    pub ply: u32,
+    pub mover: Color,
+    /// Played move in UCI notation.
+    pub played: String,
+    /// Engine's first choice in UCI notation.
+    pub best: Option<String>,
+    /// The position allowed a single legal move, so the move says nothing about the mover.
+    pub forced: bool,
+    /// How much worse, in clamped centipawns, the played move is than the engine's choice.
+    pub centipawn_loss: u32,
+}
+
+impl PlyAnalysis {
+    pub fn is_top_choice(&self) -> bool {
+        self.best.as_deref() == Some(self.played.as_str())
+    }
+}
+
+/// Clamped score of `position` for the side to move.
+async fn evaluate<E: Engine>(engine: &E, position: &Position, depth: u8) -> Result<(i32, Option<String>), ApiError> {
+    if position.legal_moves().is_empty() {
+        let score = if position.is_check() { -MATE_SCORE_CP } else { 0 };
+        return Ok((score.clamp(-EVAL_CLAMP_CP, EVAL_CLAMP_CP), None));
+    }
+    // A truncated search still gives the best evaluation found in the time allowed
+    let search = engine.analyse(&position.to_fen(), depth, 1).await?;
+    let best = search.lines.into_iter().next();
+    let score = best.as_ref().map(|line| line.score_cp).unwrap_or(0);
+    Ok((
+        score.clamp(-EVAL_CLAMP_CP, EVAL_CLAMP_CP),
+        best.and_then(|line| line.moves.into_iter().next()),
+    ))
+}
+
+/// Evaluates every position of `game` to `depth` and scores each move against the
+/// engine's choice. Each position is searched once: its score is both the best
+/// available to the mover and, negated, the value of the move that led to it.
+pub async fn analyse_game<E: Engine>(engine: &E, game: &game::Model, depth: u8) -> Result<Vec<PlyAnalysis>, ApiError> {
+    let moves = pgn_moves(&game.pgn);
+    let history = GameHistory::replay(pgn_starting_fen(&game.pgn), &moves).map_err(|err| {
+        ApiError::DatabaseError(DbErr::Custom(format!(
+            "Stored moves of game {} cannot be replayed: {}",
+            game.id, err
+        )))
+    })?;
+
+    let positions = history.positions();
+    let mut evaluations = Vec::with_capacity(positions.len());
+    for position in positions {
+        evaluations.push(evaluate(engine, position, depth).await?);
+    }
+
+    Ok(history
+        .moves()
+        .iter()
+        .enumerate()
+        .map(|(index, mv)| {
+            let (best_score, best) = &evaluations[index];
+            let played_score = -evaluations[index + 1].0;
+            PlyAnalysis {
+                ply: index as u32 + 1,
+                mover: positions[index].turn,
+                played: mv.to_uci(),
+                best: best.clone(),
+                forced: positions[index].legal_moves().len() == 1,
+                centipawn_loss: (best_score - played_score).max(0) as u32,
+            }
+        })
+        .collect())
+}
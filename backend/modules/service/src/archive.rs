@@ -0,0 +1,326 @@
+//! Cold storage for old finished games.
+//!
+//! Archiving moves a finished game's row out of `game` into `game_archive`, where it
+//! is kept whole as JSON, so the hot table only carries recent and ongoing games.
+//! Lookups by id or public id fall back to the archive (see
+//! [`crate::games::find_game_with`]), so archived games stay viewable and replayable.
+//! Rows that reference a game, such as rating history and tournament pairings, keep
+//! their ids when it is archived: the archive migration drops their foreign keys, so
+//! deleting the hot row neither cascades nor clears them. Readers that aggregate over
+//! finished games select through [`including_archived`].
+
+use std::env;
+
+use chrono::{DateTime, Duration, Utc};
+use db::db::db::get_db;
+use dto::admin::ArchiveRunDTO;
+use entity::{game, game_archive};
+use error::error::ApiError;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait,
+    Select, Set, TransactionTrait,
+    sea_query::{Alias, OnConflict},
+};
+use uuid::Uuid;
+
+/// Age in days after which a finished game is archived.
+pub const DEFAULT_ARCHIVE_AFTER_DAYS: i64 = 365;
+/// Games moved per transaction.
+pub const DEFAULT_ARCHIVE_BATCH_SIZE: u64 = 500;
+
+/// Archival settings, read from `GAME_ARCHIVE_*` variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivePolicy {
+    /// Whether the background sweep archives games at all (`GAME_ARCHIVE_ENABLED`).
+    pub enabled: bool,
+    /// Days after its end at which a game is archived (`GAME_ARCHIVE_AFTER_DAYS`).
+    pub after_days: i64,
+    /// Games moved per transaction (`GAME_ARCHIVE_BATCH_SIZE`).
+    pub batch_size: u64,
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_days: DEFAULT_ARCHIVE_AFTER_DAYS,
+            batch_size: DEFAULT_ARCHIVE_BATCH_SIZE,
+        }
+    }
+}
+
+impl ArchivePolicy {
+    pub fn from_env() -> Self {
+        fn setting<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+        let defaults = Self::default();
+        Self {
+            enabled: setting("GAME_ARCHIVE_ENABLED").unwrap_or(defaults.enabled),
+            after_days: setting("GAME_ARCHIVE_AFTER_DAYS").filter(|d| *d > 0).unwrap_or(defaults.after_days),
+            batch_size: setting("GAME_ARCHIVE_BATCH_SIZE").filter(|n| *n > 0).unwrap_or(defaults.batch_size),
+        }
+    }
+
+    /// Games that ended before the returned moment are due for archival.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.after_days)
+    }
+}
+
+/// Archives every finished game older than the policy's age, or `older_than_days`
+/// when given.
+pub async fn archive_finished_games(older_than_days: Option<i64>) -> Result<ArchiveRunDTO, ApiError> {
+    let db = get_db().await;
+    let mut policy = ArchivePolicy::from_env();
+    if let Some(days) = older_than_days {
+        policy.after_days = days;
+    }
+    archive_finished_games_with(&db, &policy, Utc::now()).await
+}
+
+/// Moves finished games that ended before the policy's cutoff into the archive, one
+/// batch per transaction, until none are left.
+pub async fn archive_finished_games_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    policy: &ArchivePolicy,
+    now: DateTime<Utc>,
+) -> Result<ArchiveRunDTO, ApiError> {
+    let cutoff = policy.cutoff(now);
+    let mut archived = 0;
+    loop {
+        let moved = archive_batch(db, cutoff, policy.batch_size, now).await?;
+        archived += moved;
+        if moved < policy.batch_size {
+            break;
+        }
+    }
+    Ok(ArchiveRunDTO { archived, cutoff })
+}
+
+async fn archive_batch<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    cutoff: DateTime<Utc>,
+    batch_size: u64,
+    now: DateTime<Utc>,
+) -> Result<u64, ApiError> {
+    let txn = db.begin().await?;
+    let games = game::Entity::find()
+        .filter(game::Column::EndedAt.lt(cutoff))
+        .order_by_asc(game::Column::EndedAt)
+        .order_by_asc(game::Column::Id)
+        .limit(batch_size)
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    if games.is_empty() {
+        txn.commit().await?;
+        return Ok(0);
+    }
+
+    let ids: Vec<Uuid> = games.iter().map(|game| game.id).collect();
+    let rows = games
+        .iter()
+        .map(|game| archived_row(game, now))
+        .collect::<Result<Vec<_>, _>>()?;
+    game_archive::Entity::insert_many(rows)
+        .on_conflict(OnConflict::column(game_archive::Column::Id).do_nothing().to_owned())
+        .exec_without_returning(&txn)
+        .await?;
+    game::Entity::delete_many()
+        .filter(game::Column::Id.is_in(ids))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(games.len() as u64)
+}
+
+fn archived_row(game: &game::Model, now: DateTime<Utc>) -> Result<game_archive::ActiveModel, ApiError> {
+    let ended_at = game
+        .ended_at
+        .ok_or_else(|| ApiError::Conflict(format!("Game {} has not ended", game.id)))?;
+    let json = serde_json::to_value(game).map_err(|err| DbErr::Json(err.to_string()))?;
+    Ok(game_archive::ActiveModel {
+        id: Set(game.id),
+        public_id: Set(game.public_id.clone()),
+        white_player: Set(game.white_player),
+        black_player: Set(game.black_player),
+        ended_at: Set(ended_at),
+        archived_at: Set(now.into()),
+        game: Set(json),
+    })
+}
+
+/// Points `select` at live and archived games alike. It reads the
+/// `game_with_archive` view under the `game` alias, so the query's filters and
+/// ordering apply to both unchanged.
+pub fn including_archived(mut select: Select<game::Entity>) -> Select<game::Entity> {
+    QueryTrait::query(&mut select)
+        .from_clear()
+        .from_as((Alias::new("smdb"), Alias::new("game_with_archive")), Alias::new("game"));
+    select
+}
+
+/// Looks an archived game up by UUID or public id.
+pub async fn find_archived_with<C: ConnectionTrait>(db: &C, key: &str) -> Result<Option<game::Model>, ApiError> {
+    let query = match Uuid::parse_str(key) {
+        Ok(id) => game_archive::Entity::find_by_id(id),
+        Err(_) => game_archive::Entity::find().filter(game_archive::Column::PublicId.eq(key)),
+    };
+    let Some(row) = query.one(db).await? else {
+        return Ok(None);
+    };
+    let game = serde_json::from_value(row.game).map_err(|err| DbErr::Json(err.to_string()))?;
+    Ok(Some(game))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::rating_history;
+    use entity::sea_orm_active_enums::{ResultSide, Termination};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use serde_json::json;
+
+    fn finished_game(ended_at: DateTime<Utc>) -> game::Model {
+        let started_at = (ended_at - Duration::minutes(1)).into();
+        game::Model {
+            public_id: "Ar7kD2qX".to_string(),
+            fen: "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string(),
+            pgn: json!({ "moves": ["f3", "e5", "g4", "Qh4#"] }),
+            result: Some(ResultSide::Black),
+            termination: Some(Termination::Checkmate),
+            started_at,
+            duration_sec: 60,
+            ended_at: Some(ended_at.into()),
+            eco: Some("A00".to_string()),
+            clock_initial_secs: Some(180),
+            clock_increment_secs: Some(2),
+            white_remaining_ms: Some(175_000),
+            black_remaining_ms: Some(178_000),
+            last_move_at: Some(ended_at.into()),
+            created_at: started_at,
+            updated_at: ended_at.into(),
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    #[async_std::test]
+    async fn archival_moves_games_that_ended_before_the_cutoff() {
+        let now = Utc::now();
+        let policy = ArchivePolicy {
+            after_days: 30,
+            ..ArchivePolicy::default()
+        };
+        let old = finished_game(now - Duration::days(400));
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![old.clone()]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let run = archive_finished_games_with(&db, &policy, now).await.unwrap();
+        assert_eq!(run.archived, 1);
+        assert_eq!(run.cutoff, now - Duration::days(30));
+
+        let statements: Vec<_> = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .collect();
+        // statements[0] is the batch's BEGIN
+        assert!(statements[1].sql.contains(r#""ended_at" < $1"#));
+        assert!(statements[1].sql.ends_with("FOR UPDATE"));
+        assert!(statements[2].sql.starts_with(r#"INSERT INTO "smdb"."game_archive""#));
+        assert!(statements[3].sql.starts_with(r#"DELETE FROM "smdb"."game""#));
+    }
+
+    #[async_std::test]
+    async fn archiving_a_rated_game_keeps_its_rating_history() {
+        let now = Utc::now();
+        let rated = finished_game(now - Duration::days(400));
+        let history = rating_history::Model {
+            id: Uuid::new_v4(),
+            player_id: rated.black_player,
+            game_id: rated.id,
+            rating_before: 1500,
+            rating_after: 1516,
+            created_at: rated.ended_at.unwrap(),
+        };
+        let archived = game_archive::Model {
+            id: rated.id,
+            public_id: rated.public_id.clone(),
+            white_player: rated.white_player,
+            black_player: rated.black_player,
+            ended_at: rated.ended_at.unwrap(),
+            archived_at: now.into(),
+            game: serde_json::to_value(&rated).unwrap(),
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![rated.clone()]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .append_query_results([vec![history.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([vec![archived]])
+            .into_connection();
+
+        let policy = ArchivePolicy { after_days: 30, ..ArchivePolicy::default() };
+        assert_eq!(archive_finished_games_with(&db, &policy, now).await.unwrap().archived, 1);
+
+        // The history row is still there and still leads to the game, now in the archive
+        let kept = rating_history::Entity::find()
+            .filter(rating_history::Column::GameId.eq(rated.id))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(kept, [history]);
+        let game = crate::games::find_game_with(&db, &kept[0].game_id.to_string()).await.unwrap();
+        assert_eq!(game, rated);
+
+        let statements: Vec<_> = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .collect();
+        let archival = &statements[..5];
+        assert!(archival.iter().all(|statement| !statement.sql.contains("rating_history")));
+    }
+
+    #[test]
+    fn including_archived_reads_the_union_view_as_game() {
+        let sql = including_archived(game::Entity::find())
+            .filter(game::Column::Result.is_not_null())
+            .build(DatabaseBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#"FROM "smdb"."game_with_archive" AS "game""#), "{}", sql);
+        assert!(sql.contains(r#""game"."result" IS NOT NULL"#), "{}", sql);
+    }
+
+    #[async_std::test]
+    async fn an_archived_game_is_still_found_by_either_id() {
+        let game = finished_game(Utc::now() - Duration::days(400));
+        let row = game_archive::Model {
+            id: game.id,
+            public_id: game.public_id.clone(),
+            white_player: game.white_player,
+            black_player: game.black_player,
+            ended_at: game.ended_at.unwrap(),
+            archived_at: Utc::now().into(),
+            game: serde_json::to_value(&game).unwrap(),
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([vec![row.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([vec![row]])
+            .into_connection();
+
+        assert_eq!(crate::games::find_game_with(&db, &game.id.to_string()).await.unwrap(), game);
+        assert_eq!(crate::games::find_game_with(&db, "Ar7kD2qX").await.unwrap(), game);
+    }
+}
@@ -0,0 +1,392 @@
+//! Persisted clocks of timed games.
+//!
+//! Nothing ticks on the server. The game row holds each side's remaining time as of
+//! the last move, and [`clock_at`] works out a reading from it whenever one is
+//! needed, so a clock picks up exactly where it was after a reconnect or a restart.
+//! A move made on a clock that reads zero loses on time instead, and
+//! [`flag_expired_clocks`] ends the games in which nobody moves to find out.
+//!
+//! A real-time clock stops while neither player is connected: [`pause_clock`] runs as
+//! players leave a game and [`resume_clock`] as they come back, and the time in
+//! between is charged to nobody. Clocks still running when the server shuts down are
+//! paused by [`pause_all_clocks`]. Correspondence clocks are never paused; the time
+//! since the last move counts whether anyone is connected or not.
+
+use chess::bitboard::Board::Color;
+use chrono::{DateTime, Utc};
+use db::db::db::get_db;
+use dto::games::{ClockReading, Side, TimeClass, clock_at, game_time_class, pgn_moves, side_to_move};
+use entity::game;
+use error::error::ApiError;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set, TransactionTrait,
+    sea_query::Expr,
+};
+use uuid::Uuid;
+
+use crate::lifecycle::{is_terminal, lock_game, time_out_with};
+use crate::rating::RatingEngine;
+
+/// Sets both clocks of a new game to `initial_secs`. A game with neither base time
+/// nor increment stays untimed.
+pub(crate) fn start_clocks(active: &mut game::ActiveModel, initial_secs: i32, increment_secs: i32) {
+    if initial_secs <= 0 && increment_secs <= 0 {
+        return;
+    }
+    let initial_ms = initial_secs.max(0) as i64 * 1000;
+    active.clock_initial_secs = Set(Some(initial_secs));
+    active.clock_increment_secs = Set(Some(increment_secs));
+    active.white_remaining_ms = Set(Some(initial_ms));
+    active.black_remaining_ms = Set(Some(initial_ms));
+}
+
+/// Charges `mover` for the move `game` is about to record at `now`, adds their
+/// increment and starts the opponent's clock. The first move of a game is free, and a
+/// move made while the clock was paused ends the pause.
+pub(crate) fn stamp_move(game: &game::Model, active: &mut game::ActiveModel, mover: Color, now: DateTime<Utc>) {
+    let Some(mut clock) = clock_at(game, now) else { return };
+    let increment = game.clock_increment_secs.unwrap_or(0).max(0) as i64 * 1000;
+    match mover {
+        Color::White => clock.white_ms += increment,
+        Color::Black => clock.black_ms += increment,
+    }
+    active.white_remaining_ms = Set(Some(clock.white_ms));
+    active.black_remaining_ms = Set(Some(clock.black_ms));
+    active.last_move_at = Set(Some(now.into()));
+    active.clock_paused_at = Set(None);
+    active.clock_paused_ms = Set(0);
+}
+
+/// The player who has run out of time in `game` at `now`: the side to move of an
+/// unfinished timed game whose clock reads zero. The first move is free, so nobody
+/// loses on time before it.
+pub(crate) fn flagged_player(game: &game::Model, now: DateTime<Utc>) -> Option<Uuid> {
+    if is_terminal(game) || game.last_move_at.is_none() {
+        return None;
+    }
+    let clock = clock_at(game, now)?;
+    match side_to_move(game, pgn_moves(&game.pgn).len()) {
+        Side::White if clock.white_ms == 0 => Some(game.white_player),
+        Side::Black if clock.black_ms == 0 => Some(game.black_player),
+        _ => None,
+    }
+}
+
+/// Whether `game` has a running real-time clock that could be stopped.
+fn pausable(game: &game::Model) -> bool {
+    !is_terminal(game)
+        && game.last_move_at.is_some()
+        && game.clock_paused_at.is_none()
+        && game_time_class(game).is_some_and(|class| class != TimeClass::Correspondence)
+}
+
+fn is_player(game: &game::Model, player_id: Uuid) -> bool {
+    player_id == game.white_player || player_id == game.black_player
+}
+
+pub async fn pause_clock(game_id: Uuid, connected: Vec<Uuid>) -> Result<Option<ClockReading>, ApiError> {
+    let db = get_db().await;
+    pause_clock_with(&db, game_id, &connected, Utc::now()).await
+}
+
+/// Stops the clock of `game_id` at `now` unless one of its players is among
+/// `connected`, and returns the reading it stopped at. `None` when the clock keeps
+/// running or there is none.
+pub async fn pause_clock_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    game_id: Uuid,
+    connected: &[Uuid],
+    now: DateTime<Utc>,
+) -> Result<Option<ClockReading>, ApiError> {
+    let txn = db.begin().await?;
+    let game = lock_game(&txn, game_id).await?;
+    if !pausable(&game) || connected.iter().any(|id| is_player(&game, *id)) {
+        return Ok(None);
+    }
+
+    let mut active: game::ActiveModel = game.into();
+    active.clock_paused_at = Set(Some(now.into()));
+    let game = active.update(&txn).await?;
+    txn.commit().await?;
+    Ok(clock_at(&game, now))
+}
+
+pub async fn resume_clock(game_id: Uuid, player_id: Uuid) -> Result<Option<ClockReading>, ApiError> {
+    let db = get_db().await;
+    resume_clock_with(&db, game_id, player_id, Utc::now()).await
+}
+
+/// Restarts a paused clock when one of the game's players connects at `now`, from
+/// the times it stopped at, and returns the current reading. Connections of anyone
+/// else change nothing and read `None`, as do untimed games.
+pub async fn resume_clock_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    game_id: Uuid,
+    player_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Option<ClockReading>, ApiError> {
+    let txn = db.begin().await?;
+    let game = lock_game(&txn, game_id).await?;
+    if !is_player(&game, player_id) {
+        return Ok(None);
+    }
+
+    let game = match game.clock_paused_at {
+        Some(paused_at) if !is_terminal(&game) => {
+            let stopped = (now - paused_at.with_timezone(&Utc)).num_milliseconds().max(0);
+            let paused_ms = game.clock_paused_ms + stopped;
+            let mut active: game::ActiveModel = game.into();
+            active.clock_paused_at = Set(None);
+            active.clock_paused_ms = Set(paused_ms);
+            active.update(&txn).await?
+        }
+        _ => game,
+    };
+    txn.commit().await?;
+    Ok(clock_at(&game, now))
+}
+
+pub async fn pause_all_clocks() -> Result<u64, ApiError> {
+    let db = get_db().await;
+    pause_all_clocks_with(&db, Utc::now()).await
+}
+
+/// Stops every running real-time clock at `now`, for a server about to go down.
+/// Returns how many were stopped.
+pub async fn pause_all_clocks_with<C: ConnectionTrait>(db: &C, now: DateTime<Utc>) -> Result<u64, ApiError> {
+    let running: Vec<Uuid> = game::Entity::find()
+        .filter(game::Column::EndedAt.is_null())
+        .filter(game::Column::LastMoveAt.is_not_null())
+        .filter(game::Column::ClockPausedAt.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(pausable)
+        .map(|game| game.id)
+        .collect();
+    if running.is_empty() {
+        return Ok(0);
+    }
+
+    let paused = game::Entity::update_many()
+        .col_expr(game::Column::ClockPausedAt, Expr::value(DateTimeWithTimeZone::from(now)))
+        .filter(game::Column::Id.is_in(running))
+        .exec(db)
+        .await?;
+    Ok(paused.rows_affected)
+}
+
+pub async fn flag_expired_clocks() -> Result<usize, ApiError> {
+    let db = get_db().await;
+    flag_expired_clocks_with(&db, &RatingEngine::from_env(), Utc::now()).await
+}
+
+/// Ends on time every game whose side to move has run out of time by `now`, so a
+/// player who stops moving loses without their opponent having to claim it. Returns
+/// how many games were ended.
+pub async fn flag_expired_clocks_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    now: DateTime<Utc>,
+) -> Result<usize, ApiError> {
+    let flagged: Vec<(Uuid, Uuid)> = game::Entity::find()
+        .filter(game::Column::EndedAt.is_null())
+        .filter(game::Column::LastMoveAt.is_not_null())
+        .filter(game::Column::WhiteRemainingMs.is_not_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|game| flagged_player(&game, now).map(|player| (game.id, player)))
+        .collect();
+
+    let mut timed_out = 0;
+    for (game_id, player) in flagged {
+        match time_out_with(db, engine, game_id, player).await {
+            Ok(_) => timed_out += 1,
+            // The game ended, or the player moved in time, since it was read
+            Err(ApiError::Conflict(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(timed_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use entity::sea_orm_active_enums::{ResultSide, Termination};
+    use entity::{player_rating, tournament_pairing};
+    use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+    use serde_json::json;
+
+    const AFTER_E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+
+    /// A game in which white has just played 1.e4 at `moved_at`, so black's clock runs.
+    fn timed_game(initial_secs: i32, moved_at: DateTime<Utc>) -> game::Model {
+        let initial_ms = initial_secs as i64 * 1000;
+        game::Model {
+            public_id: "Ck8mQ2vR".to_string(),
+            fen: AFTER_E4.to_string(),
+            pgn: json!({ "moves": ["e4"] }),
+            started_at: moved_at.into(),
+            clock_initial_secs: Some(initial_secs),
+            clock_increment_secs: Some(0),
+            white_remaining_ms: Some(initial_ms),
+            black_remaining_ms: Some(initial_ms),
+            last_move_at: Some(moved_at.into()),
+            created_at: moved_at.into(),
+            updated_at: moved_at.into(),
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    fn updates(db: DatabaseConnection) -> usize {
+        db.into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .filter(|s| s.sql.starts_with("UPDATE"))
+            .count()
+    }
+
+    #[async_std::test]
+    async fn clocks_resume_from_the_times_at_disconnect() {
+        let moved_at = Utc::now();
+        let left_at = moved_at + Duration::milliseconds(7_250);
+        let back_at = left_at + Duration::hours(2);
+        let game = timed_game(300, moved_at);
+        let mut paused = game.clone();
+        paused.clock_paused_at = Some(left_at.into());
+        let mut resumed = paused.clone();
+        resumed.clock_paused_at = None;
+        resumed.clock_paused_ms = 2 * 60 * 60 * 1000;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()], vec![paused.clone()]])
+            .append_query_results([vec![paused], vec![resumed.clone()]])
+            .into_connection();
+
+        // Both players gone, then white back two hours later, say after a restart
+        let at_disconnect = pause_clock_with(&db, game.id, &[], left_at).await.unwrap();
+        let at_reconnect = resume_clock_with(&db, game.id, game.white_player, back_at).await.unwrap();
+
+        let expected = ClockReading { white_ms: 300_000, black_ms: 292_750 };
+        assert_eq!(at_disconnect, Some(expected));
+        assert_eq!(at_reconnect, Some(expected));
+        assert_eq!(updates(db), 2);
+
+        // From there black's clock runs again, and only from the reconnect
+        let mut active: game::ActiveModel = resumed.clone().into();
+        stamp_move(&resumed, &mut active, Color::Black, back_at + Duration::seconds(3));
+        assert_eq!(active.white_remaining_ms.unwrap(), Some(300_000));
+        assert_eq!(active.black_remaining_ms.unwrap(), Some(289_750));
+        assert_eq!(active.clock_paused_ms.unwrap(), 0);
+    }
+
+    #[async_std::test]
+    async fn a_connected_player_keeps_the_clock_running() {
+        let moved_at = Utc::now();
+        let game = timed_game(300, moved_at);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let paused = pause_clock_with(&db, game.id, &[game.black_player], moved_at + Duration::seconds(5))
+            .await
+            .unwrap();
+
+        assert_eq!(paused, None);
+        assert_eq!(updates(db), 0);
+    }
+
+    #[async_std::test]
+    async fn correspondence_clocks_run_while_everyone_is_away() {
+        let moved_at = Utc::now();
+        let game = timed_game(3 * 24 * 60 * 60, moved_at);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()], vec![game.clone()]])
+            .into_connection();
+        let back_at = moved_at + Duration::days(1);
+
+        let paused = pause_clock_with(&db, game.id, &[], moved_at + Duration::hours(1)).await.unwrap();
+        let resumed = resume_clock_with(&db, game.id, game.black_player, back_at).await.unwrap();
+
+        assert_eq!(paused, None);
+        assert_eq!(resumed, Some(ClockReading { white_ms: 259_200_000, black_ms: 172_800_000 }));
+        assert_eq!(updates(db), 0);
+    }
+
+    #[async_std::test]
+    async fn shutdown_pauses_only_running_real_time_clocks() {
+        let moved_at = Utc::now();
+        let blitz = timed_game(300, moved_at);
+        let correspondence = timed_game(3 * 24 * 60 * 60, moved_at);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![blitz.clone(), correspondence]])
+            .append_exec_results([MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        let paused = pause_all_clocks_with(&db, moved_at + Duration::seconds(10)).await.unwrap();
+
+        assert_eq!(paused, 1);
+        let log = db.into_transaction_log();
+        let update = log.iter().flat_map(|t| t.statements().to_vec()).find(|s| s.sql.starts_with("UPDATE")).unwrap();
+        assert!(update.to_string().contains(&blitz.id.to_string()), "{}", update);
+    }
+
+    #[async_std::test]
+    async fn the_sweep_times_out_only_expired_clocks() {
+        let moved_at = Utc::now() - Duration::seconds(301);
+        let expired = timed_game(300, moved_at);
+        let running = timed_game(600, moved_at);
+        let finished = game::Model {
+            result: Some(ResultSide::White),
+            termination: Some(Termination::Timeout),
+            ended_at: Some(Utc::now().into()),
+            ..expired.clone()
+        };
+        let rating = |player_id| player_rating::Model { player_id, rating: 1500, games_played: 10, updated_at: Utc::now().into() };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![expired.clone(), running]])
+            .append_query_results([vec![expired.clone()], vec![finished]])
+            .append_query_results([Vec::<tournament_pairing::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(0i64))])]])
+            .append_query_results([vec![rating(expired.white_player), rating(expired.black_player)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let timed_out = flag_expired_clocks_with(&db, &RatingEngine::default(), Utc::now()).await.unwrap();
+
+        assert_eq!(timed_out, 1);
+        let log = db.into_transaction_log();
+        let timeouts: Vec<String> = log
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .map(|s| s.to_string())
+            .filter(|s| s.starts_with("UPDATE") && s.contains("'timeout'"))
+            .collect();
+        assert_eq!(timeouts.len(), 1);
+        assert!(timeouts[0].contains(&expired.id.to_string()), "{}", timeouts[0]);
+    }
+
+    #[test]
+    fn the_first_move_is_never_flagged() {
+        let game = game::Model {
+            clock_initial_secs: Some(0),
+            clock_increment_secs: Some(2),
+            white_remaining_ms: Some(0),
+            black_remaining_ms: Some(0),
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        };
+
+        assert_eq!(flagged_player(&game, Utc::now()), None);
+    }
+}
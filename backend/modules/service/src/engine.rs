@@ -0,0 +1,484 @@
+//! Chess engine access.
+//!
+//! Analysis features depend on the [`Engine`] trait. [`UciEngine`] drives any UCI
+//! engine binary, such as Stockfish, as a child process, with the threads, hash and
+//! time limit configured for the [`RequestClass`] it was created for.
+
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use error::error::ApiError;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::time::{Instant, timeout, timeout_at};
+
+/// Score of a forced mate in centipawns, reduced by the plies needed to deliver it.
+pub const MATE_SCORE_CP: i32 = 100_000;
+
+/// How long an engine told to `stop` gets to report its best move before it is killed.
+const STOP_GRACE: Duration = Duration::from_secs(1);
+
+/// One line of an engine search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    /// Score for the side to move; mates are scored with [`MATE_SCORE_CP`].
+    pub score_cp: i32,
+    /// Principal variation in UCI notation, best move first.
+    pub moves: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    /// No engine is configured, or it could not be started or talked to.
+    Unavailable(String),
+    /// The engine answered with something that is not valid UCI.
+    Protocol(String),
+    /// The search hit its time limit before finding any line.
+    Timeout(Duration),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Unavailable(msg) => write!(f, "Engine unavailable: {}", msg),
+            EngineError::Protocol(msg) => write!(f, "Engine protocol error: {}", msg),
+            EngineError::Timeout(limit) => {
+                write!(f, "Engine search found nothing within {} ms", limit.as_millis())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<std::io::Error> for EngineError {
+    fn from(value: std::io::Error) -> Self {
+        EngineError::Unavailable(value.to_string())
+    }
+}
+
+impl From<EngineError> for ApiError {
+    fn from(value: EngineError) -> Self {
+        match value {
+            EngineError::Timeout(_) => ApiError::EngineTimeout(value.to_string()),
+            _ => ApiError::EngineUnavailable(value.to_string()),
+        }
+    }
+}
+
+/// Lines found by one search.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Search {
+    /// Best first.
+    pub lines: Vec<PvLine>,
+    /// The search was stopped at its time limit, so the lines may be shallower than
+    /// the depth asked for.
+    pub truncated: bool,
+}
+
+impl Search {
+    pub fn complete(lines: Vec<PvLine>) -> Self {
+        Self { lines, truncated: false }
+    }
+}
+
+pub trait Engine {
+    /// Searches `fen` to `depth` plies and returns up to `multipv` lines, best first.
+    /// Positions without a legal move return no lines. A search that runs out of time
+    /// returns what it found so far as truncated, or [`EngineError::Timeout`] when
+    /// that is nothing.
+    fn analyse(
+        &self,
+        fen: &str,
+        depth: u8,
+        multipv: u8,
+    ) -> impl Future<Output = Result<Search, EngineError>> + Send;
+}
+
+/// Kinds of engine work, from cheap interactive queries to whole-game reviews.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    /// Move suggestions and eval-bar style queries that a player is waiting on.
+    Quick,
+    /// Analysis of a single position.
+    Position,
+    /// Analysis of every position of a game, such as fair-play reviews.
+    Game,
+}
+
+impl RequestClass {
+    pub const ALL: [RequestClass; 3] = [RequestClass::Quick, RequestClass::Position, RequestClass::Game];
+
+    /// Time limit of one search of this class, read from `ENGINE_<CLASS>_TIMEOUT_MS`.
+    pub fn timeout(self) -> Duration {
+        let default_ms = match self {
+            RequestClass::Quick => 2_000,
+            RequestClass::Position => 5_000,
+            RequestClass::Game => 10_000,
+        };
+        let ms = env::var(format!("{}_TIMEOUT_MS", self.env_prefix()))
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default_ms);
+        Duration::from_millis(ms)
+    }
+
+    fn env_prefix(self) -> &'static str {
+        match self {
+            RequestClass::Quick => "ENGINE_QUICK",
+            RequestClass::Position => "ENGINE_POSITION",
+            RequestClass::Game => "ENGINE_GAME",
+        }
+    }
+}
+
+/// Threads and transposition table size given to one search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineResources {
+    pub threads: u32,
+    pub hash_mb: u32,
+}
+
+impl EngineResources {
+    pub fn default_for(class: RequestClass) -> Self {
+        match class {
+            RequestClass::Quick => Self { threads: 1, hash_mb: 16 },
+            RequestClass::Position => Self { threads: 2, hash_mb: 64 },
+            RequestClass::Game => Self { threads: 4, hash_mb: 256 },
+        }
+    }
+
+    /// Resources configured for `class` through `ENGINE_<CLASS>_THREADS` and
+    /// `ENGINE_<CLASS>_HASH_MB`, where `<CLASS>` is `QUICK`, `POSITION` or `GAME`.
+    /// The values are not checked against the host; see [`EngineResources::clamp_to`].
+    pub fn from_env(class: RequestClass) -> Self {
+        let defaults = Self::default_for(class);
+        let read = |suffix: &str, default: u32| {
+            env::var(format!("{}_{}", class.env_prefix(), suffix))
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            threads: read("THREADS", defaults.threads),
+            hash_mb: read("HASH_MB", defaults.hash_mb),
+        }
+    }
+
+    /// Keeps a search within the host: at most one thread per core and a hash of at
+    /// most half the memory, leaving the rest to the server. Both get at least 1.
+    pub fn clamp_to(self, host: &HostResources) -> Self {
+        let max_hash = host
+            .memory_mb
+            .map(|memory| (memory / 2).clamp(1, u32::MAX as u64) as u32)
+            .unwrap_or(u32::MAX);
+        Self {
+            threads: self.threads.clamp(1, host.cores.max(1)),
+            hash_mb: self.hash_mb.clamp(1, max_hash),
+        }
+    }
+}
+
+/// Cores and memory of the machine the engine runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostResources {
+    pub cores: u32,
+    /// `None` where total memory cannot be read; hash sizes are then left alone.
+    pub memory_mb: Option<u64>,
+}
+
+impl HostResources {
+    /// The host, detected once per process.
+    pub fn current() -> &'static HostResources {
+        static HOST: OnceLock<HostResources> = OnceLock::new();
+        HOST.get_or_init(|| HostResources {
+            cores: std::thread::available_parallelism()
+                .map(|cores| cores.get() as u32)
+                .unwrap_or(1),
+            memory_mb: std::fs::read_to_string("/proc/meminfo")
+                .ok()
+                .and_then(|meminfo| total_memory_mb(&meminfo)),
+        })
+    }
+}
+
+/// Reads `MemTotal` from the contents of `/proc/meminfo`.
+fn total_memory_mb(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib / 1024)
+}
+
+/// Describes every request class whose configured resources exceed the host and
+/// will be clamped. Meant to be logged once at startup.
+pub fn resource_warnings(host: &HostResources) -> Vec<String> {
+    RequestClass::ALL
+        .into_iter()
+        .filter_map(|class| {
+            let configured = EngineResources::from_env(class);
+            let clamped = configured.clamp_to(host);
+            (clamped != configured).then(|| {
+                let prefix = class.env_prefix();
+                format!(
+                    "{}_THREADS/{}_HASH_MB ask for {} threads and {} MB of hash; clamped to {} threads and {} MB on this host",
+                    prefix,
+                    prefix,
+                    configured.threads,
+                    configured.hash_mb,
+                    clamped.threads,
+                    clamped.hash_mb
+                )
+            })
+        })
+        .collect()
+}
+
+/// A UCI engine binary, started afresh for every search.
+#[derive(Debug, Clone)]
+pub struct UciEngine {
+    path: PathBuf,
+    resources: EngineResources,
+    timeout: Duration,
+}
+
+impl UciEngine {
+    pub fn new(path: impl Into<PathBuf>, resources: EngineResources, timeout: Duration) -> Self {
+        Self { path: path.into(), resources, timeout }
+    }
+
+    /// The engine at `ENGINE_PATH`, set up for `class` within the limits of this
+    /// host, or `None` when no engine is configured.
+    pub fn from_env(class: RequestClass) -> Option<Self> {
+        let resources = EngineResources::from_env(class).clamp_to(HostResources::current());
+        env::var("ENGINE_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| Self::new(path, resources, class.timeout()))
+    }
+}
+
+/// Commands that configure a fresh engine process and start a search.
+fn search_commands(resources: EngineResources, fen: &str, depth: u8, multipv: u8) -> String {
+    format!(
+        "uci\nsetoption name Threads value {}\nsetoption name Hash value {}\nsetoption name MultiPV value {}\nisready\nposition fen {}\ngo depth {}\n",
+        resources.threads, resources.hash_mb, multipv, fen, depth
+    )
+}
+
+impl Engine for UciEngine {
+    async fn analyse(&self, fen: &str, depth: u8, multipv: u8) -> Result<Search, EngineError> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| EngineError::Unavailable("stdin is not piped".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| EngineError::Unavailable("stdout is not piped".to_string()))?;
+        let mut output = BufReader::new(stdout).lines();
+
+        let multipv = multipv.max(1);
+        let commands = search_commands(self.resources, fen, depth, multipv);
+        stdin.write_all(commands.as_bytes()).await?;
+        stdin.flush().await?;
+
+        // Deeper iterations overwrite shallower ones, so each slot ends with the final line
+        let mut lines: Vec<Option<PvLine>> = vec![None; multipv as usize];
+        let deadline = Instant::now() + self.timeout;
+        let mut truncated = false;
+        loop {
+            let next = if truncated {
+                timeout(STOP_GRACE, output.next_line()).await
+            } else {
+                timeout_at(deadline, output.next_line()).await
+            };
+            let line = match next {
+                Ok(line) => line?,
+                Err(_) if !truncated => {
+                    // Out of time: the engine answers `stop` with its best move so far
+                    truncated = true;
+                    stdin.write_all(b"stop\n").await?;
+                    stdin.flush().await?;
+                    continue;
+                }
+                // Ignored `stop`; the process is killed when dropped
+                Err(_) => break,
+            };
+            let Some(line) = line else {
+                if truncated {
+                    break;
+                }
+                return Err(EngineError::Protocol("exited before `bestmove`".to_string()));
+            };
+            if line.starts_with("bestmove") {
+                break;
+            }
+            if let Some((index, pv)) = parse_info(&line)
+                && let Some(slot) = lines.get_mut(index.saturating_sub(1))
+            {
+                *slot = Some(pv);
+            }
+        }
+
+        let _ = stdin.write_all(b"quit\n").await;
+        let _ = timeout(STOP_GRACE, child.wait()).await;
+
+        let lines: Vec<PvLine> = lines.into_iter().flatten().collect();
+        if truncated && lines.is_empty() {
+            return Err(EngineError::Timeout(self.timeout));
+        }
+        Ok(Search { lines, truncated })
+    }
+}
+
+/// Parses an `info` line carrying a principal variation into its 1-based `multipv`
+/// index and line. Bound-only scores from an interrupted iteration are skipped.
+pub fn parse_info(line: &str) -> Option<(usize, PvLine)> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("info") {
+        return None;
+    }
+
+    let mut index = 1;
+    let mut score = None;
+    let mut moves = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "multipv" => index = tokens.next()?.parse().ok()?,
+            "score" => {
+                let kind = tokens.next()?;
+                let value: i32 = tokens.next()?.parse().ok()?;
+                score = Some(match kind {
+                    "cp" => value,
+                    "mate" if value > 0 => MATE_SCORE_CP - value,
+                    "mate" => -MATE_SCORE_CP - value,
+                    _ => return None,
+                });
+            }
+            "lowerbound" | "upperbound" => return None,
+            "pv" => {
+                moves = tokens.by_ref().map(str::to_string).collect();
+            }
+            _ => {}
+        }
+    }
+
+    if moves.is_empty() {
+        return None;
+    }
+    Some((index, PvLine { score_cp: score?, moves }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multipv_lines() {
+        let line = "info depth 18 seldepth 24 multipv 2 score cp -35 nodes 912345 nps 1200000 pv d7d5 c2c4 e7e6";
+        assert_eq!(
+            parse_info(line),
+            Some((2, PvLine { score_cp: -35, moves: vec!["d7d5".into(), "c2c4".into(), "e7e6".into()] }))
+        );
+    }
+
+    #[test]
+    fn mate_scores_prefer_faster_mates() {
+        let (_, mate_in_two) = parse_info("info depth 5 score mate 2 pv d8h4 g2g3 h4g3").unwrap();
+        let (_, mated_in_one) = parse_info("info depth 5 score mate -1 pv e1e2 d8h4").unwrap();
+
+        assert_eq!(mate_in_two.score_cp, MATE_SCORE_CP - 2);
+        assert_eq!(mated_in_one.score_cp, -MATE_SCORE_CP + 1);
+    }
+
+    #[test]
+    fn resources_are_clamped_to_the_host() {
+        let host = HostResources { cores: 4, memory_mb: Some(1024) };
+
+        let greedy = EngineResources { threads: 32, hash_mb: 4096 }.clamp_to(&host);
+        let empty = EngineResources { threads: 0, hash_mb: 0 }.clamp_to(&host);
+        let unknown_memory = EngineResources { threads: 2, hash_mb: 4096 }
+            .clamp_to(&HostResources { cores: 4, memory_mb: None });
+
+        assert_eq!(greedy, EngineResources { threads: 4, hash_mb: 512 });
+        assert_eq!(empty, EngineResources { threads: 1, hash_mb: 1 });
+        assert_eq!(unknown_memory.hash_mb, 4096);
+    }
+
+    #[test]
+    fn searches_set_threads_and_hash_before_starting() {
+        let commands = search_commands(EngineResources { threads: 2, hash_mb: 64 }, "8/8/8/8/8/8/8/K6k w - - 0 1", 12, 3);
+
+        assert!(commands.starts_with(
+            "uci\nsetoption name Threads value 2\nsetoption name Hash value 64\nsetoption name MultiPV value 3\n"
+        ));
+        assert!(commands.ends_with("go depth 12\n"));
+    }
+
+    #[test]
+    fn reads_total_memory_from_meminfo() {
+        let meminfo = "MemTotal:       16318412 kB\nMemFree:         1234567 kB\n";
+
+        assert_eq!(total_memory_mb(meminfo), Some(15935));
+        assert_eq!(total_memory_mb("MemFree: 10 kB\n"), None);
+    }
+
+    /// Writes a fake UCI engine that answers `go` with `on_go` and only reports a best
+    /// move once told to `stop`, like an engine stuck in a long search.
+    #[cfg(unix)]
+    fn slow_engine(name: &str, on_go: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = env::temp_dir().join(format!("starkmate-{}-{}.sh", name, std::process::id()));
+        let script = format!(
+            "#!/bin/sh\nwhile read -r command; do\n  case \"$command\" in\n    go*) {} ;;\n    stop) echo \"bestmove e2e4\"; exit 0 ;;\n  esac\ndone\n",
+            on_go
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    async fn searches_past_the_time_limit_return_the_best_line_so_far() {
+        let path = slow_engine("truncated", "echo \"info depth 9 multipv 1 score cp 31 pv e2e4 e7e5\"");
+        let engine = UciEngine::new(&path, EngineResources::default_for(RequestClass::Quick), Duration::from_millis(200));
+
+        let search = engine.analyse(chess::fen::STARTING_FEN, 30, 1).await.unwrap();
+
+        assert!(search.truncated);
+        assert_eq!(search.lines, vec![PvLine { score_cp: 31, moves: vec!["e2e4".into(), "e7e5".into()] }]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    async fn searches_with_nothing_by_the_time_limit_time_out() {
+        let path = slow_engine("empty", ":");
+        let engine = UciEngine::new(&path, EngineResources::default_for(RequestClass::Quick), Duration::from_millis(200));
+
+        let result = engine.analyse(chess::fen::STARTING_FEN, 30, 1).await;
+
+        assert!(matches!(result, Err(EngineError::Timeout(_))));
+        assert!(matches!(ApiError::from(result.unwrap_err()), ApiError::EngineTimeout(_)));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn skips_lines_without_a_usable_pv() {
+        assert_eq!(parse_info("info depth 12 currmove e2e4 currmovenumber 1"), None);
+        assert_eq!(parse_info("info depth 12 score cp 40 lowerbound pv e2e4"), None);
+        assert_eq!(parse_info("bestmove e2e4 ponder e7e5"), None);
+    }
+}
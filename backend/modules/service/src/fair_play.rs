@@ -0,0 +1,317 @@
+//! Post-game fair-play review.
+//!
+//! Finished games are run through [`analyse_game`] and each side's moves are compared
+//! with the engine's: how often they match its first choice and how much they lose
+//! against it. Games where a side plays close to the engine throughout get a high
+//! `suspicion_score` and surface in the admin review queue. A high score only means
+//! the game deserves a look; strong players have engine-like games too.
+
+use std::env;
+
+use chess::bitboard::Board::Color;
+use db::db::db::get_db;
+use dto::admin::SuspiciousGameDTO;
+use dto::pagination::Page;
+use entity::game;
+use error::error::ApiError;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, sea_query::Expr};
+use uuid::Uuid;
+
+use crate::analysis::{PlyAnalysis, analyse_game};
+use crate::engine::{Engine, RequestClass, UciEngine};
+use crate::lifecycle::load_game;
+use crate::pagination::fetch_page;
+
+/// Tunable review settings, read from `FAIR_PLAY_*` variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FairPlayConfig {
+    /// Whether finished games are reviewed at all (`FAIR_PLAY_ENABLED`).
+    pub enabled: bool,
+    /// Search depth per position (`FAIR_PLAY_DEPTH`).
+    pub depth: u8,
+    /// Opening plies left out, since book moves match the engine anyway (`FAIR_PLAY_SKIP_PLIES`).
+    pub skip_plies: u32,
+    /// Moves a side must have left after skipping to be scored (`FAIR_PLAY_MIN_MOVES`).
+    pub min_moves: usize,
+    /// Loss up to which a move counts as accurate (`FAIR_PLAY_ACCURATE_CPL`).
+    pub accurate_cpl: u32,
+    /// Average loss at which the accuracy component bottoms out (`FAIR_PLAY_CPL_CEILING`).
+    pub cpl_ceiling: u32,
+    /// Score from which a game is listed for review (`FAIR_PLAY_FLAG_THRESHOLD`).
+    pub flag_threshold: f64,
+}
+
+impl Default for FairPlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth: 14,
+            skip_plies: 10,
+            min_moves: 20,
+            accurate_cpl: 10,
+            cpl_ceiling: 100,
+            flag_threshold: 0.9,
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+impl FairPlayConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: env_or("FAIR_PLAY_ENABLED", defaults.enabled),
+            depth: env_or("FAIR_PLAY_DEPTH", defaults.depth).max(1),
+            skip_plies: env_or("FAIR_PLAY_SKIP_PLIES", defaults.skip_plies),
+            min_moves: env_or("FAIR_PLAY_MIN_MOVES", defaults.min_moves).max(1),
+            accurate_cpl: env_or("FAIR_PLAY_ACCURATE_CPL", defaults.accurate_cpl),
+            cpl_ceiling: env_or("FAIR_PLAY_CPL_CEILING", defaults.cpl_ceiling).max(1),
+            flag_threshold: env_or("FAIR_PLAY_FLAG_THRESHOLD", defaults.flag_threshold).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// How one side's scored moves compare with the engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideAccuracy {
+    pub moves: usize,
+    /// Share of moves that were the engine's first choice.
+    pub top_match_rate: f64,
+    /// Share of moves losing at most `accurate_cpl`.
+    pub accurate_rate: f64,
+    pub average_cpl: f64,
+}
+
+impl SideAccuracy {
+    /// Mean of the top-choice rate, the accurate-move rate and the average loss scaled
+    /// against `cpl_ceiling`, each between 0 and 1.
+    pub fn suspicion_score(&self, config: &FairPlayConfig) -> f64 {
+        let ceiling = config.cpl_ceiling as f64;
+        let low_loss = 1.0 - self.average_cpl.min(ceiling) / ceiling;
+        (self.top_match_rate + self.accurate_rate + low_loss) / 3.0
+    }
+}
+
+/// Accuracy of `side` over its unforced moves after the opening, or `None` when too
+/// few are left to say anything.
+pub fn side_accuracy(plies: &[PlyAnalysis], side: Color, config: &FairPlayConfig) -> Option<SideAccuracy> {
+    let scored: Vec<&PlyAnalysis> = plies
+        .iter()
+        .filter(|p| p.mover == side && !p.forced && p.ply > config.skip_plies)
+        .collect();
+    if scored.len() < config.min_moves {
+        return None;
+    }
+
+    let moves = scored.len() as f64;
+    let matches = scored.iter().filter(|p| p.is_top_choice()).count() as f64;
+    let accurate = scored.iter().filter(|p| p.centipawn_loss <= config.accurate_cpl).count() as f64;
+    let total_loss: f64 = scored.iter().map(|p| p.centipawn_loss as f64).sum();
+    Some(SideAccuracy {
+        moves: scored.len(),
+        top_match_rate: matches / moves,
+        accurate_rate: accurate / moves,
+        average_cpl: total_loss / moves,
+    })
+}
+
+/// The game's score: that of whichever side played more like the engine.
+pub fn game_suspicion_score(plies: &[PlyAnalysis], config: &FairPlayConfig) -> Option<f64> {
+    [Color::White, Color::Black]
+        .into_iter()
+        .filter_map(|side| side_accuracy(plies, side, config))
+        .map(|accuracy| accuracy.suspicion_score(config))
+        .reduce(f64::max)
+}
+
+/// Reviews a finished game in the background once it ends. Does nothing unless
+/// reviews are enabled and an engine is configured; failures are logged.
+pub async fn review_finished_game(game_id: Uuid) {
+    let config = FairPlayConfig::from_env();
+    if !config.enabled {
+        return;
+    }
+    let Some(engine) = UciEngine::from_env(RequestClass::Game) else {
+        log::warn!("Fair-play review of game {} skipped: ENGINE_PATH is not set", game_id);
+        return;
+    };
+
+    let db = get_db().await;
+    if let Err(err) = review_game_with(&db, &engine, &config, game_id).await {
+        log::error!("Fair-play review of game {} failed: {}", game_id, err);
+    }
+}
+
+/// Analyses a finished game and stores its score. Games too short to score keep a
+/// NULL score and stay out of the review queue.
+pub async fn review_game_with<C: ConnectionTrait, E: Engine>(
+    db: &C,
+    engine: &E,
+    config: &FairPlayConfig,
+    game_id: Uuid,
+) -> Result<Option<f64>, ApiError> {
+    let game = load_game(db, game_id).await?;
+    if game.ended_at.is_none() {
+        return Err(ApiError::Conflict(format!("Game {} is still in progress", game_id)));
+    }
+
+    let plies = analyse_game(engine, &game, config.depth).await?;
+    let score = game_suspicion_score(&plies, config);
+    if let Some(score) = score {
+        game::Entity::update_many()
+            .col_expr(game::Column::SuspicionScore, Expr::value(score))
+            .filter(game::Column::Id.eq(game_id))
+            .exec(db)
+            .await?;
+    }
+    Ok(score)
+}
+
+pub async fn suspicious_games(
+    min_score: Option<f64>,
+    page: u64,
+    page_size: u64,
+) -> Result<Page<SuspiciousGameDTO>, ApiError> {
+    let db = get_db().await;
+    let min_score = min_score.unwrap_or(FairPlayConfig::from_env().flag_threshold);
+    suspicious_games_with(&db, min_score, page, page_size).await
+}
+
+/// Reviewed games scoring at least `min_score`, most engine-like first.
+pub async fn suspicious_games_with<C: ConnectionTrait>(
+    db: &C,
+    min_score: f64,
+    page: u64,
+    page_size: u64,
+) -> Result<Page<SuspiciousGameDTO>, ApiError> {
+    let query = game::Entity::find()
+        .filter(game::Column::SuspicionScore.gte(min_score))
+        .order_by_desc(game::Column::SuspicionScore)
+        .order_by_asc(game::Column::Id);
+    Ok(fetch_page(query, db, page, page_size).await?.map(SuspiciousGameDTO::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{EngineError, PvLine, Search};
+    use chess::history::GameHistory;
+    use entity::sea_orm_active_enums::{ResultSide, Termination};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Morphy's Opera Game (Paris, 1858), played here as if every move were the engine's.
+    const OPERA_GAME: [&str; 33] = [
+        "e4", "e5", "Nf3", "d6", "d4", "Bg4", "dxe5", "Bxf3", "Qxf3", "dxe5", "Bc4", "Nf6", "Qb3", "Qe7",
+        "Nc3", "c6", "Bg5", "b5", "Nxb5", "cxb5", "Bxb5+", "Nbd7", "O-O-O", "Rd8", "Rxd7", "Rxd7", "Rd1",
+        "Qe6", "Bxd7+", "Nxd7", "Qb8+", "Nxb8", "Rd8#",
+    ];
+
+    /// Answers from a fixed table of positions.
+    struct ScriptedEngine {
+        lines: HashMap<String, PvLine>,
+    }
+
+    impl Engine for ScriptedEngine {
+        async fn analyse(&self, fen: &str, _depth: u8, _multipv: u8) -> Result<Search, EngineError> {
+            Ok(Search::complete(self.lines.get(fen).cloned().into_iter().collect()))
+        }
+    }
+
+    /// An engine whose first choice in every position is the move played in `moves`,
+    /// scored `score_cp` for the side to move.
+    fn agreeing_engine(moves: &[&str], score_cp: impl Fn(usize) -> i32) -> ScriptedEngine {
+        let history = GameHistory::replay(chess::fen::STARTING_FEN, moves).unwrap();
+        let lines = history
+            .moves()
+            .iter()
+            .enumerate()
+            .map(|(i, mv)| {
+                let fen = history.positions()[i].to_fen();
+                (fen, PvLine { score_cp: score_cp(i), moves: vec![mv.to_uci()] })
+            })
+            .collect();
+        ScriptedEngine { lines }
+    }
+
+    fn finished_game(moves: &[&str]) -> game::Model {
+        let game = game::Model::fixture(Uuid::new_v4(), Uuid::new_v4());
+        game::Model {
+            public_id: "Fp3yZ6dM".to_string(),
+            pgn: json!({ "moves": moves }),
+            result: Some(ResultSide::White),
+            termination: Some(Termination::Checkmate),
+            duration_sec: 600,
+            ended_at: Some(game.started_at),
+            ..game
+        }
+    }
+
+    fn short_game_config() -> FairPlayConfig {
+        FairPlayConfig { enabled: true, skip_plies: 4, min_moves: 8, ..FairPlayConfig::default() }
+    }
+
+    #[async_std::test]
+    async fn engine_perfect_game_scores_high() {
+        let game = finished_game(&OPERA_GAME);
+        // White is winning and stays winning after every move
+        let engine = agreeing_engine(&OPERA_GAME, |ply| if ply % 2 == 0 { 300 } else { -300 });
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_exec_results([MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        let score = review_game_with(&db, &engine, &short_game_config(), game.id).await.unwrap();
+
+        assert_eq!(score, Some(1.0));
+        assert!(score.unwrap() >= short_game_config().flag_threshold);
+    }
+
+    #[test]
+    fn inaccurate_side_scores_low() {
+        let config = short_game_config();
+        let plies: Vec<PlyAnalysis> = (1..=40)
+            .map(|ply| PlyAnalysis {
+                ply,
+                mover: if ply % 2 == 1 { Color::White } else { Color::Black },
+                played: "a2a3".to_string(),
+                best: Some("e2e4".to_string()),
+                forced: false,
+                centipawn_loss: if ply % 4 == 1 { 5 } else { 150 },
+            })
+            .collect();
+
+        let white = side_accuracy(&plies, Color::White, &config).unwrap();
+        let black = side_accuracy(&plies, Color::Black, &config).unwrap();
+
+        assert_eq!(white.top_match_rate, 0.0);
+        assert_eq!(white.accurate_rate, 0.5);
+        assert!(white.suspicion_score(&config) < 0.3);
+        assert_eq!(black.suspicion_score(&config), 0.0);
+        assert!(game_suspicion_score(&plies, &config).unwrap() < config.flag_threshold);
+    }
+
+    #[test]
+    fn short_games_are_not_scored() {
+        let config = FairPlayConfig::default();
+        let plies: Vec<PlyAnalysis> = (1..=24)
+            .map(|ply| PlyAnalysis {
+                ply,
+                mover: if ply % 2 == 1 { Color::White } else { Color::Black },
+                played: "e2e4".to_string(),
+                best: Some("e2e4".to_string()),
+                forced: false,
+                centipawn_loss: 0,
+            })
+            .collect();
+
+        assert_eq!(game_suspicion_score(&plies, &config), None);
+    }
+}
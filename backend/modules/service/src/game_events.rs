@@ -0,0 +1,169 @@
+//! Structured event trail of game state transitions.
+//!
+//! Every transition emits one `tracing` event under the `game_events` target with the
+//! same fields, so a game's lifecycle can be rebuilt from the logs by filtering on
+//! `game_id`. Events are emitted only once the transition's transaction has
+//! committed; a transition that rolls back leaves no trace here.
+
+use entity::game;
+use sea_orm::ActiveEnum;
+use uuid::Uuid;
+
+use crate::lifecycle::{FinalizedGame, is_terminal, ply_count};
+use crate::notifications;
+
+/// Log target of every game event, for filtering with `RUST_LOG=game_events=info`.
+pub const TARGET: &str = "game_events";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    Created,
+    Joined,
+    MoveApplied,
+    DrawOffered,
+    DrawDeclined,
+    DrawAccepted,
+    DrawClaimed,
+    Resigned,
+    Aborted,
+    TimedOut,
+    /// Ended because a player never showed up.
+    Forfeited,
+    /// Follows the event that ended the game, whatever it was.
+    Finalized,
+}
+
+impl GameEvent {
+    pub const ALL: [GameEvent; 12] = [
+        GameEvent::Created,
+        GameEvent::Joined,
+        GameEvent::MoveApplied,
+        GameEvent::DrawOffered,
+        GameEvent::DrawDeclined,
+        GameEvent::DrawAccepted,
+        GameEvent::DrawClaimed,
+        GameEvent::Resigned,
+        GameEvent::Aborted,
+        GameEvent::TimedOut,
+        GameEvent::Forfeited,
+        GameEvent::Finalized,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameEvent::Created => "game.created",
+            GameEvent::Joined => "game.joined",
+            GameEvent::MoveApplied => "game.move_applied",
+            GameEvent::DrawOffered => "game.draw_offered",
+            GameEvent::DrawDeclined => "game.draw_declined",
+            GameEvent::DrawAccepted => "game.draw_accepted",
+            GameEvent::DrawClaimed => "game.draw_claimed",
+            GameEvent::Resigned => "game.resigned",
+            GameEvent::Aborted => "game.aborted",
+            GameEvent::TimedOut => "game.timed_out",
+            GameEvent::Forfeited => "game.forfeited",
+            GameEvent::Finalized => "game.finalized",
+        }
+    }
+}
+
+/// Status a game is left in, as reported in the `status` field.
+pub fn status_of(game: &game::Model) -> &'static str {
+    if is_terminal(game) { "finished" } else { "in_progress" }
+}
+
+/// Fields of one game event, with results and terminations in the snake_case form
+/// the database and the API use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFields {
+    pub event: &'static str,
+    pub game_id: Uuid,
+    pub public_id: String,
+    pub actor: Option<String>,
+    pub status: &'static str,
+    pub ply: u64,
+    pub result: Option<String>,
+    pub termination: Option<String>,
+}
+
+impl EventFields {
+    pub fn of(event: GameEvent, game: &game::Model, actor: Option<Uuid>) -> Self {
+        Self {
+            event: event.name(),
+            game_id: game.id,
+            public_id: game.public_id.clone(),
+            actor: actor.map(|id| id.to_string()),
+            status: status_of(game),
+            ply: ply_count(game) as u64,
+            result: game.result.map(|result| result.to_value()),
+            termination: game.termination.map(|termination| termination.to_value()),
+        }
+    }
+}
+
+/// Emits `event` for `game` as it stands after the transition. `actor` is the player
+/// who caused it, or `None` for the server itself.
+pub fn emit(event: GameEvent, game: &game::Model, actor: Option<Uuid>) {
+    let fields = EventFields::of(event, game, actor);
+    tracing::info!(
+        target: TARGET,
+        event = fields.event,
+        game_id = %fields.game_id,
+        public_id = fields.public_id.as_str(),
+        actor = fields.actor.as_deref(),
+        status = fields.status,
+        ply = fields.ply,
+        result = fields.result.as_deref(),
+        termination = fields.termination.as_deref(),
+        "{}",
+        fields.event
+    );
+}
+
+/// Emits the event that ended a game, if it has one of its own, followed by
+/// [`GameEvent::Finalized`] and the creation of any tournament games it started,
+/// whose players are notified that their round began.
+pub fn emit_finalized(event: Option<GameEvent>, finalized: &FinalizedGame, actor: Option<Uuid>) {
+    if let Some(event) = event {
+        emit(event, &finalized.game, actor);
+    }
+    emit(GameEvent::Finalized, &finalized.game, actor);
+    for game in &finalized.next_round {
+        emit(GameEvent::Created, game, None);
+    }
+    notifications::queue(notifications::round_start_notifications(&finalized.next_round));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{ResultSide, Termination};
+    use std::collections::HashSet;
+
+    #[test]
+    fn event_names_are_distinct_and_namespaced() {
+        let names: HashSet<&str> = GameEvent::ALL.iter().map(|event| event.name()).collect();
+
+        assert_eq!(names.len(), GameEvent::ALL.len());
+        assert!(names.iter().all(|name| name.starts_with("game.")));
+    }
+
+    #[test]
+    fn results_and_terminations_are_logged_in_snake_case() {
+        let white = Uuid::new_v4();
+        let game = game::Model {
+            result: Some(ResultSide::Draw),
+            termination: Some(Termination::FiftyMove),
+            ended_at: Some(chrono::Utc::now().into()),
+            ..game::Model::fixture(white, Uuid::new_v4())
+        };
+
+        let fields = EventFields::of(GameEvent::DrawClaimed, &game, Some(white));
+
+        assert_eq!(fields.event, "game.draw_claimed");
+        assert_eq!(fields.actor, Some(white.to_string()));
+        assert_eq!(fields.status, "finished");
+        assert_eq!(fields.result.as_deref(), Some("draw"));
+        assert_eq!(fields.termination.as_deref(), Some("fifty_move"));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use chess::pgn::PgnGame;
+use dto::games::{ExportGamesQuery, pgn_moves, pgn_starting_fen};
+use entity::result::pgn_token;
+use entity::sea_orm_active_enums::{GameVariant, Termination};
+use entity::{game, player};
+use error::error::ApiError;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, prelude::DateTimeWithTimeZone,
+};
+use uuid::Uuid;
+
+use crate::archive::including_archived;
+use crate::players::player_exists;
+use crate::replay::ensure_within_cap;
+
+/// Games loaded per cursor step while streaming an export.
+pub const EXPORT_BATCH_SIZE: u64 = 200;
+
+/// Keyset position `(started_at, id)` of the last exported game.
+pub type ExportCursor = (DateTimeWithTimeZone, Uuid);
+
+/// Value of the PGN `Termination` tag, which only distinguishes how a game stopped
+/// rather than why it was decided.
+fn termination_tag(termination: Termination) -> &'static str {
+    match termination {
+        Termination::Timeout => "time forfeit",
+        Termination::Abandonment | Termination::Aborted => "abandoned",
+        _ => "normal",
+    }
+}
+
+fn variant_tag(variant: GameVariant) -> Option<&'static str> {
+    match variant {
+        GameVariant::Standard => None,
+        GameVariant::Chess960 => Some("Chess960"),
+        GameVariant::Crazyhouse => Some("Crazyhouse"),
+        GameVariant::KingOfTheHill => Some("King of the Hill"),
+    }
+}
+
+/// Serializes a stored game to PGN; `white` and `black` are the players' usernames.
+pub fn game_pgn(game: &game::Model, white: &str, black: &str) -> PgnGame {
+    let mut pgn = PgnGame::new(pgn_token(game.result))
+        .tag("Event", "StarkMate game")
+        .tag("Site", "StarkMate")
+        .tag("Date", game.started_at.format("%Y.%m.%d").to_string())
+        .tag("Round", "-")
+        .tag("White", white)
+        .tag("Black", black)
+        .tag("GameId", game.id.to_string())
+        .starting_fen(pgn_starting_fen(&game.pgn))
+        .moves(&pgn_moves(&game.pgn));
+
+    if let Some(variant) = variant_tag(game.variant) {
+        pgn = pgn.tag("Variant", variant);
+    }
+    if let Some(eco) = &game.eco {
+        pgn = pgn.tag("ECO", eco.as_str());
+    }
+    if let Some(opening) = &game.opening_name {
+        pgn = pgn.tag("Opening", opening.as_str());
+    }
+    if let Some(termination) = game.termination {
+        pgn = pgn.tag("Termination", termination_tag(termination));
+    }
+    pgn
+}
+
+/// Fails with `NotFound` unless `player_id` is an enabled player, so exports can be
+/// rejected before a streaming response is started.
+pub async fn ensure_exportable<C: ConnectionTrait>(db: &C, player_id: Uuid) -> Result<(), ApiError> {
+    if !player_exists(db, player_id).await? {
+        return Err(ApiError::NotFound(format!("Player {}", player_id)));
+    }
+    Ok(())
+}
+
+/// Serializes the next batch of `player_id`'s finished games after `after`, oldest
+/// first, each followed by a blank line so batches concatenate into one multi-game
+/// PGN file. The returned cursor is `None` once the last batch has been read.
+///
+/// Games longer than `max_plies` are left out; a `%` escape line, which PGN readers
+/// skip, names each one so the omission is visible in the file.
+pub async fn export_batch_with<C: ConnectionTrait>(
+    db: &C,
+    player_id: Uuid,
+    query: &ExportGamesQuery,
+    after: Option<ExportCursor>,
+    limit: u64,
+    max_plies: usize,
+) -> Result<(String, Option<ExportCursor>), ApiError> {
+    // Only games with a result are exported; live ones would change after download.
+    // Archived games are part of a player's history, so they are exported too
+    let mut select = including_archived(game::Entity::find())
+        .filter(
+            Condition::any()
+                .add(game::Column::WhitePlayer.eq(player_id))
+                .add(game::Column::BlackPlayer.eq(player_id)),
+        )
+        .filter(game::Column::Result.is_not_null());
+    if let Some(from) = query.from {
+        select = select.filter(game::Column::StartedAt.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(game::Column::StartedAt.lt(to));
+    }
+    if let Some(variant) = query.variant {
+        select = select.filter(game::Column::Variant.eq(GameVariant::from(variant)));
+    }
+
+    let mut cursor = select.cursor_by((game::Column::StartedAt, game::Column::Id));
+    if let Some(after) = after {
+        cursor.after(after);
+    }
+    let games = cursor.first(limit).all(db).await?;
+
+    let player_ids: Vec<Uuid> = games
+        .iter()
+        .flat_map(|g| [g.white_player, g.black_player])
+        .collect();
+    let usernames: HashMap<Uuid, String> = if games.is_empty() {
+        HashMap::new()
+    } else {
+        player::Entity::find()
+            .filter(player::Column::Id.is_in(player_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|p| (p.id, p.username))
+            .collect()
+    };
+    let username = |id: Uuid| usernames.get(&id).map(String::as_str).unwrap_or("?");
+
+    let text = games
+        .iter()
+        .map(|g| match ensure_within_cap(g.id, pgn_moves(&g.pgn).len(), max_plies) {
+            Ok(()) => format!("{}\n", game_pgn(g, username(g.white_player), username(g.black_player))),
+            Err(err) => format!("% {}; omitted from this export\n\n", err),
+        })
+        .collect::<String>();
+    let next = match games.last() {
+        Some(last) if games.len() as u64 == limit => Some((last.started_at, last.id)),
+        _ => None,
+    };
+
+    Ok((text, next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::DEFAULT_MAX_PLIES;
+    use entity::sea_orm_active_enums::ResultSide;
+    use chrono::{TimeZone, Utc};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+    use serde_json::json;
+
+    fn finished_game(moves: &[&str], result: ResultSide) -> game::Model {
+        let started_at = Utc.with_ymd_and_hms(2026, 3, 7, 18, 30, 0).unwrap().into();
+        game::Model {
+            public_id: "Ex8gH3iJ".to_string(),
+            fen: "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4".to_string(),
+            pgn: json!({ "moves": moves }),
+            result: Some(result),
+            termination: Some(Termination::Checkmate),
+            started_at,
+            duration_sec: 95,
+            ended_at: Some(started_at),
+            eco: Some("C20".to_string()),
+            opening_name: Some("King's Pawn Game".to_string()),
+            created_at: started_at,
+            updated_at: started_at,
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    fn unfiltered() -> ExportGamesQuery {
+        ExportGamesQuery { from: None, to: None, variant: None }
+    }
+
+    #[test]
+    fn serializes_stored_game_with_tags() {
+        let game = finished_game(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"], ResultSide::White);
+        let pgn = game_pgn(&game, "alice", "bob").to_string();
+
+        assert!(pgn.starts_with("[Event \"StarkMate game\"]\n[Site \"StarkMate\"]\n[Date \"2026.03.07\"]\n"));
+        assert!(pgn.contains("[White \"alice\"]\n[Black \"bob\"]\n[Result \"1-0\"]\n"));
+        assert!(pgn.contains("[ECO \"C20\"]") && pgn.contains("[Termination \"normal\"]"));
+        assert!(!pgn.contains("[Variant"));
+        assert!(pgn.ends_with("\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"));
+    }
+
+    #[async_std::test]
+    async fn full_batch_returns_cursor_of_last_game() {
+        let first = finished_game(&["e4", "e5"], ResultSide::Draw);
+        let second = finished_game(&["d4", "d5"], ResultSide::Black);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone(), second.clone()]])
+            .append_query_results([Vec::<player::Model>::new()])
+            .into_connection();
+
+        let (text, next) = export_batch_with(&db, first.white_player, &unfiltered(), None, 2, DEFAULT_MAX_PLIES)
+            .await
+            .unwrap();
+
+        assert_eq!(next, Some((second.started_at, second.id)));
+        assert_eq!(text.matches("[Event ").count(), 2);
+        assert!(text.contains("1. e4 e5 1/2-1/2\n\n[Event "));
+        assert!(text.ends_with("1. d4 d5 0-1\n\n"));
+    }
+
+    #[async_std::test]
+    async fn games_over_the_ply_cap_are_named_and_skipped() {
+        let short = finished_game(&["e4", "e5"], ResultSide::Draw);
+        let long = finished_game(&["e4", "e5", "Nf3", "Nc6", "Bb5"], ResultSide::White);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![short.clone(), long.clone()]])
+            .append_query_results([Vec::<player::Model>::new()])
+            .into_connection();
+
+        let (text, _) = export_batch_with(&db, short.white_player, &unfiltered(), None, 2, 4)
+            .await
+            .unwrap();
+
+        assert_eq!(text.matches("[Event ").count(), 1);
+        assert!(text.ends_with(&format!(
+            "% Game {} has 5 plies, more than the limit of 4; omitted from this export\n\n",
+            long.id
+        )));
+    }
+
+    #[async_std::test]
+    async fn short_batch_ends_export_without_loading_players() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<game::Model>::new()])
+            .into_connection();
+
+        let (text, next) =
+            export_batch_with(&db, Uuid::new_v4(), &unfiltered(), None, EXPORT_BATCH_SIZE, DEFAULT_MAX_PLIES)
+                .await
+            .unwrap();
+
+        assert!(text.is_empty());
+        assert_eq!(next, None);
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+}
@@ -0,0 +1,673 @@
+use std::env;
+use std::time::Duration;
+
+use chess::time_control::{TimeClass, TimeControl, time_class};
+use db::db::db::get_db;
+use chrono::Utc;
+use dto::games::{CreateGameRequest, ListGamesQuery, PlayerColor, pgn_moves, pgn_starting_fen};
+use entity::{game, player};
+use entity::sea_orm_active_enums::{GameVariant, Termination};
+use error::error::ApiError;
+use dto::pagination::Page;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, NotSet, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Select, Set, SqlErr, TransactionTrait,
+    sea_query::{Expr, extension::postgres::PgExpr},
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::archive::find_archived_with;
+use crate::clocks::start_clocks;
+use crate::game_events::{self, GameEvent};
+use crate::pagination::{fetch_page, page_bounds};
+
+/// Unfinished games a player may have at once when starting a real-time game.
+pub const DEFAULT_MAX_CONCURRENT_GAMES: u64 = 5;
+/// Correspondence games last days, so players keep many more of them going.
+pub const DEFAULT_MAX_CONCURRENT_CORRESPONDENCE_GAMES: u64 = 50;
+
+/// Length of a game's public id: 62^8 codes keep collisions rare for a long time.
+pub const PUBLIC_ID_LEN: usize = 8;
+/// Codes drawn for a new game before its insert gives up.
+pub const PUBLIC_ID_ATTEMPTS: usize = 5;
+/// Unique index on `game.public_id`, named by Postgres when a code is taken.
+const PUBLIC_ID_INDEX: &str = "idx_game_public_id";
+
+/// A random base62 code for a game's shareable links.
+pub fn generate_public_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(PUBLIC_ID_LEN)
+        .map(char::from)
+        .collect()
+}
+
+fn is_public_id_collision(err: Option<SqlErr>) -> bool {
+    matches!(err, Some(SqlErr::UniqueConstraintViolation(message)) if message.contains(PUBLIC_ID_INDEX))
+}
+
+/// Foreign keys from `game` to `player`, with the request field each seat comes from.
+const PLAYER_FOREIGN_KEYS: [(&str, &str); 2] =
+    [("fk_game_white_player", "white_player"), ("fk_game_black_player", "black_player")];
+
+/// Turns an insert rejected for naming a player that does not exist into an
+/// `InvalidReference` on that seat, instead of an opaque database error.
+fn unknown_player(err: Option<SqlErr>, game: &game::ActiveModel) -> Option<ApiError> {
+    let Some(SqlErr::ForeignKeyConstraintViolation(message)) = err else {
+        return None;
+    };
+    let (_, field) = PLAYER_FOREIGN_KEYS
+        .iter()
+        .find(|(constraint, _)| message.contains(constraint))?;
+    let seat = if *field == "white_player" { &game.white_player } else { &game.black_player };
+    let id = seat.try_as_ref().map(Uuid::to_string).unwrap_or_default();
+    Some(ApiError::InvalidReference {
+        field: field.to_string(),
+        message: format!("Player {} given as {} does not exist", id, field),
+    })
+}
+
+/// Inserts a new game under a freshly drawn public id, drawing again when the code
+/// is already taken. Each attempt runs in its own savepoint so that a collision does
+/// not abort a transaction the caller has open.
+pub async fn insert_game_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    mut game: game::ActiveModel,
+) -> Result<game::Model, ApiError> {
+    for _ in 0..PUBLIC_ID_ATTEMPTS {
+        game.public_id = Set(generate_public_id());
+        let attempt = db.begin().await?;
+        match game.clone().insert(&attempt).await {
+            Ok(inserted) => {
+                attempt.commit().await?;
+                return Ok(inserted);
+            }
+            Err(err) if is_public_id_collision(err.sql_err()) => attempt.rollback().await?,
+            Err(err) => return Err(unknown_player(err.sql_err(), &game).unwrap_or_else(|| err.into())),
+        }
+    }
+    Err(ApiError::DatabaseError(DbErr::Custom(format!(
+        "No free public id after {} attempts",
+        PUBLIC_ID_ATTEMPTS
+    ))))
+}
+
+pub async fn create_game(creator: Uuid, opponent: Uuid, request: &CreateGameRequest) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    create_game_with(&db, &GameLimits::from_env(), creator, opponent, request).await
+}
+
+/// Speed category of the clock `request` asks for.
+pub fn request_class(request: &CreateGameRequest) -> TimeClass {
+    time_class(&TimeControl {
+        initial_time: Duration::from_secs(request.time_control.max(0) as u64),
+        increment: Duration::from_secs(request.increment.max(0) as u64),
+        delay: Duration::ZERO,
+    })
+}
+
+/// Speed category of a stored game's clock. An untimed game reads as a zero clock,
+/// so it takes the real-time cap.
+pub fn game_class(game: &game::Model) -> TimeClass {
+    time_class(&TimeControl {
+        initial_time: Duration::from_secs(game.clock_initial_secs.unwrap_or(0).max(0) as u64),
+        increment: Duration::from_secs(game.clock_increment_secs.unwrap_or(0).max(0) as u64),
+        delay: Duration::ZERO,
+    })
+}
+
+/// Starts a game between `creator` and `opponent`, seating the creator on the colour
+/// they asked for, or a random one. Either player not existing is an
+/// `InvalidReference` naming their seat.
+///
+/// The creator's cap of unfinished games is checked in the transaction that inserts
+/// the game, so two games started at once cannot both slip under it.
+pub async fn create_game_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    limits: &GameLimits,
+    creator: Uuid,
+    opponent: Uuid,
+    request: &CreateGameRequest,
+) -> Result<game::Model, ApiError> {
+    let txn = db.begin().await?;
+    ensure_can_start_game_with(&txn, limits, creator, request_class(request)).await?;
+    if let Some(parent_id) = request.rematch_of {
+        ensure_rematchable(&txn, parent_id, [creator, opponent]).await?;
+    }
+    let creator_plays_white = match request.player_color {
+        Some(PlayerColor::White) => true,
+        Some(PlayerColor::Black) => false,
+        Some(PlayerColor::Random) | None => rand::random(),
+    };
+    let (white_player, black_player) = if creator_plays_white {
+        (creator, opponent)
+    } else {
+        (opponent, creator)
+    };
+
+    let mut game = new_game(
+        white_player,
+        black_player,
+        request.variant.into(),
+        request.starting_fen.as_deref(),
+    );
+    game.parent_game_id = Set(request.rematch_of);
+    start_clocks(&mut game, request.time_control, request.increment);
+    let game = insert_game_with(&txn, game).await?;
+    txn.commit().await?;
+    game_events::emit(GameEvent::Created, &game, Some(creator));
+    Ok(game)
+}
+
+/// A rematch continues a finished game between the same two players.
+async fn ensure_rematchable<C: ConnectionTrait>(db: &C, parent_id: Uuid, players: [Uuid; 2]) -> Result<(), ApiError> {
+    let invalid = |message: String| ApiError::InvalidReference { field: "rematch_of".to_string(), message };
+    let parent = game::Entity::find_by_id(parent_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| invalid(format!("Game {} does not exist", parent_id)))?;
+
+    let mut seated = [parent.white_player, parent.black_player];
+    let mut requested = players;
+    seated.sort();
+    requested.sort();
+    if seated != requested {
+        return Err(invalid(format!("Game {} was not played between the same players", parent_id)));
+    }
+    if parent.ended_at.is_none() {
+        return Err(ApiError::Conflict(format!("Game {} is still in progress", parent_id)));
+    }
+    Ok(())
+}
+
+/// A game between two players that has not seen a move yet, ready for
+/// [`insert_game_with`]. It starts from `starting_fen`, or the standard position.
+pub(crate) fn new_game(
+    white_player: Uuid,
+    black_player: Uuid,
+    variant: GameVariant,
+    starting_fen: Option<&str>,
+) -> game::ActiveModel {
+    let mut pgn = json!({ "moves": [] });
+    if let Some(starting_fen) = starting_fen {
+        pgn["starting_fen"] = json!(starting_fen);
+    }
+    let (eco, opening_name) = classify_opening(&pgn);
+    let now = Utc::now();
+    game::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        public_id: NotSet,
+        white_player: Set(white_player),
+        black_player: Set(black_player),
+        fen: Set(starting_fen.unwrap_or(chess::fen::STARTING_FEN).to_string()),
+        pgn: Set(pgn),
+        result: Set(None),
+        termination: Set(None),
+        draw_offered_by: Set(None),
+        variant: Set(variant),
+        started_at: Set(now.into()),
+        duration_sec: Set(0),
+        ended_at: Set(None),
+        rated_at: Set(None),
+        eco: Set(eco),
+        opening_name: Set(opening_name),
+        suspicion_score: Set(None),
+        parent_game_id: Set(None),
+        white_draw_offers: Set(0),
+        black_draw_offers: Set(0),
+        white_last_draw_offer_ply: Set(None),
+        black_last_draw_offer_ply: Set(None),
+        clock_initial_secs: Set(None),
+        clock_increment_secs: Set(None),
+        white_remaining_ms: Set(None),
+        black_remaining_ms: Set(None),
+        last_move_at: Set(None),
+        clock_paused_at: Set(None),
+        clock_paused_ms: Set(0),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+    }
+}
+
+pub async fn find_game(key: &str) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    find_game_with(&db, key).await
+}
+
+/// Loads a game by either of its ids: `key` is read as the UUID when it parses as
+/// one, and as the public id otherwise. Games no longer in the hot table are read
+/// from the archive.
+pub async fn find_game_with<C: ConnectionTrait>(db: &C, key: &str) -> Result<game::Model, ApiError> {
+    let query = match Uuid::parse_str(key) {
+        Ok(id) => game::Entity::find_by_id(id),
+        Err(_) if key.len() == PUBLIC_ID_LEN && key.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            game::Entity::find().filter(game::Column::PublicId.eq(key))
+        }
+        Err(_) => return Err(ApiError::NotFound(format!("Game {}", key))),
+    };
+    if let Some(game) = query.one(db).await? {
+        return Ok(game);
+    }
+    find_archived_with(db, key)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Game {}", key)))
+}
+
+/// How an `eco` query parameter narrows the game list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcoFilter {
+    /// A full code such as `B20`.
+    Exact(String),
+    /// A volume or sub-volume such as `B*` or `B2*`.
+    Prefix(String),
+    /// Games that have not been classified.
+    Unclassified,
+}
+
+impl EcoFilter {
+    /// Parses a filter that already passed `ListGamesQuery` validation.
+    pub fn parse(raw: &str) -> Self {
+        if raw.eq_ignore_ascii_case("none") {
+            return EcoFilter::Unclassified;
+        }
+
+        let code = raw.to_ascii_uppercase();
+        match code.strip_suffix('*') {
+            Some(prefix) => EcoFilter::Prefix(prefix.to_string()),
+            None => EcoFilter::Exact(code),
+        }
+    }
+
+    fn apply(self, select: Select<game::Entity>) -> Select<game::Entity> {
+        match self {
+            EcoFilter::Exact(code) => select.filter(game::Column::Eco.eq(code)),
+            EcoFilter::Prefix(prefix) => select.filter(game::Column::Eco.starts_with(prefix)),
+            EcoFilter::Unclassified => select.filter(game::Column::Eco.is_null()),
+        }
+    }
+}
+
+/// Escapes LIKE wildcards so user input is matched literally.
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Returns the `(eco, opening_name)` pair to store for a game with the given PGN.
+/// ECO lines are played from the standard position, so games set up from any other
+/// position are never classified.
+pub fn classify_opening(pgn: &serde_json::Value) -> (Option<String>, Option<String>) {
+    if pgn_starting_fen(pgn) != chess::fen::STARTING_FEN {
+        return (None, None);
+    }
+    match chess::eco::classify(&pgn_moves(pgn)) {
+        Some(opening) => (Some(opening.eco.to_string()), Some(opening.name.to_string())),
+        None => (None, None),
+    }
+}
+
+pub async fn list_games(query: ListGamesQuery) -> Result<Page<game::Model>, ApiError> {
+    let db = get_db().await;
+    list_games_with(&db, query).await
+}
+
+/// Returns one page of games matching `query`.
+pub async fn list_games_with<C: ConnectionTrait>(
+    db: &C,
+    query: ListGamesQuery,
+) -> Result<Page<game::Model>, ApiError> {
+    let (page, page_size) = page_bounds(query.page, query.limit);
+
+    let mut select = game::Entity::find();
+
+    if let Some(status) = query.status.as_deref() {
+        select = match status {
+            "completed" => select.filter(game::Column::Result.is_not_null()),
+            "in_progress" => select.filter(game::Column::EndedAt.is_null()),
+            "aborted" => select
+                .filter(game::Column::Result.is_null())
+                .filter(game::Column::Termination.eq(Termination::Aborted)),
+            // Games are created with both players seated, so none are waiting.
+            _ => select.filter(Expr::value(false)),
+        };
+    }
+    if let Some(player_id) = query.player_id {
+        select = select.filter(
+            Condition::any()
+                .add(game::Column::WhitePlayer.eq(player_id))
+                .add(game::Column::BlackPlayer.eq(player_id)),
+        );
+    }
+    if let Some(eco) = query.eco.as_deref() {
+        select = EcoFilter::parse(eco).apply(select);
+    }
+    if let Some(opening) = query.opening.as_deref() {
+        let pattern = format!("%{}%", escape_like(opening));
+        select = select.filter(Expr::col(game::Column::OpeningName).ilike(pattern));
+    }
+
+    fetch_page(select.order_by_desc(game::Column::StartedAt), db, page, page_size).await
+}
+
+/// How many unfinished games a player may have when starting another one, read from
+/// `MAX_CONCURRENT_GAMES` and `MAX_CONCURRENT_CORRESPONDENCE_GAMES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameLimits {
+    pub real_time: u64,
+    pub correspondence: u64,
+}
+
+impl GameLimits {
+    pub fn from_env() -> Self {
+        let limit = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            real_time: limit("MAX_CONCURRENT_GAMES", DEFAULT_MAX_CONCURRENT_GAMES),
+            correspondence: limit(
+                "MAX_CONCURRENT_CORRESPONDENCE_GAMES",
+                DEFAULT_MAX_CONCURRENT_CORRESPONDENCE_GAMES,
+            ),
+        }
+    }
+
+    /// Cap that applies when the new game is of `class`.
+    pub fn limit(&self, class: TimeClass) -> u64 {
+        match class {
+            TimeClass::Correspondence => self.correspondence,
+            _ => self.real_time,
+        }
+    }
+}
+
+pub async fn ensure_can_start_game(player_id: Uuid, class: TimeClass) -> Result<(), ApiError> {
+    let db = get_db().await;
+    ensure_can_start_game_with(&db, &GameLimits::from_env(), player_id, class).await
+}
+
+/// Fails with `Conflict` when `player_id` already has as many unfinished games as
+/// the cap for a new game of `class` allows. Every unfinished game counts, whatever
+/// its own time control.
+///
+/// The player's row is locked first, so callers that insert the new game in the same
+/// transaction serialize with every other game that player starts.
+pub async fn ensure_can_start_game_with<C: ConnectionTrait>(
+    db: &C,
+    limits: &GameLimits,
+    player_id: Uuid,
+    class: TimeClass,
+) -> Result<(), ApiError> {
+    player::Entity::find_by_id(player_id).lock_exclusive().one(db).await?;
+    let in_progress = game::Entity::find()
+        .filter(
+            Condition::any()
+                .add(game::Column::WhitePlayer.eq(player_id))
+                .add(game::Column::BlackPlayer.eq(player_id)),
+        )
+        .filter(game::Column::EndedAt.is_null())
+        .count(db)
+        .await?;
+
+    let limit = limits.limit(class);
+    if in_progress >= limit {
+        return Err(ApiError::Conflict(format!(
+            "Player {} already has {} games in progress; at most {} are allowed",
+            player_id, in_progress, limit
+        )));
+    }
+    Ok(())
+}
+
+pub async fn ensure_can_join_game(player_id: Uuid, game_id: Uuid) -> Result<(), ApiError> {
+    let db = get_db().await;
+    ensure_can_join_game_with(&db, &GameLimits::from_env(), player_id, game_id).await
+}
+
+/// Checks `player_id`'s cap of unfinished games against the time class of the game
+/// they are joining.
+pub async fn ensure_can_join_game_with<C: ConnectionTrait>(
+    db: &C,
+    limits: &GameLimits,
+    player_id: Uuid,
+    game_id: Uuid,
+) -> Result<(), ApiError> {
+    let game = game::Entity::find_by_id(game_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Game {}", game_id)))?;
+    ensure_can_start_game_with(db, limits, player_id, game_class(&game)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, RuntimeErr, Value};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn new_game(public_id: &str) -> game::Model {
+        game::Model { public_id: public_id.to_string(), ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4()) }
+    }
+
+    #[test]
+    fn public_ids_are_short_and_url_safe() {
+        let id = generate_public_id();
+
+        assert_eq!(id.len(), PUBLIC_ID_LEN);
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn only_a_violation_of_the_public_id_index_is_a_collision() {
+        let taken = |constraint: &str| {
+            Some(SqlErr::UniqueConstraintViolation(format!(
+                r#"duplicate key value violates unique constraint "{}""#,
+                constraint
+            )))
+        };
+
+        assert!(is_public_id_collision(taken("idx_game_public_id")));
+        assert!(!is_public_id_collision(taken("game_pkey")));
+        assert!(!is_public_id_collision(Some(SqlErr::ForeignKeyConstraintViolation(
+            "idx_game_public_id".to_string()
+        ))));
+        assert!(!is_public_id_collision(None));
+    }
+
+    #[async_std::test]
+    async fn other_insert_errors_are_not_drawn_again() {
+        let stored = new_game("Q7xb2LmP");
+        // Only a unique violation reported by Postgres counts, however the message reads
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_errors([DbErr::Query(RuntimeErr::Internal(
+                r#"duplicate key value violates unique constraint "idx_game_public_id""#.to_string(),
+            ))])
+            .append_query_results([vec![stored.clone()]])
+            .into_connection();
+
+        let result = insert_game_with(&db, stored.into()).await;
+
+        assert!(matches!(result, Err(ApiError::DatabaseError(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn a_missing_player_is_reported_on_its_seat() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game: game::ActiveModel = game::Model::fixture(white, black).into();
+        let violated = |constraint: &str| {
+            Some(SqlErr::ForeignKeyConstraintViolation(format!(
+                r#"insert or update on table "game" violates foreign key constraint "{}""#,
+                constraint
+            )))
+        };
+
+        match unknown_player(violated("fk_game_black_player"), &game) {
+            Some(ApiError::InvalidReference { field, message }) => {
+                assert_eq!(field, "black_player");
+                assert!(message.contains(&black.to_string()));
+            }
+            other => panic!("expected an invalid reference, got {:?}", other),
+        }
+        assert!(unknown_player(violated("game_parent_game_id_fkey"), &game).is_none());
+        assert!(unknown_player(Some(SqlErr::UniqueConstraintViolation("fk_game_white_player".to_string())), &game).is_none());
+        assert!(unknown_player(None, &game).is_none());
+    }
+
+    fn blitz_request(opponent: Uuid) -> CreateGameRequest {
+        CreateGameRequest {
+            time_control: 300,
+            increment: 2,
+            player_color: Some(PlayerColor::Black),
+            opponent_id: Some(opponent),
+            variant: Default::default(),
+            starting_fen: None,
+            fen_strictness: Default::default(),
+            rematch_of: None,
+        }
+    }
+
+    #[async_std::test]
+    async fn the_game_cap_is_checked_in_the_inserting_transaction() {
+        let stored = new_game("Q7xb2LmP");
+        let limits = GameLimits { real_time: 3, correspondence: 10 };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(2i64))])]])
+            .append_query_results([vec![stored.clone()]])
+            .into_connection();
+
+        let created = create_game_with(&db, &limits, stored.black_player, stored.white_player, &blitz_request(stored.white_player))
+            .await
+            .unwrap();
+        assert_eq!(created, stored);
+
+        let log = db.into_transaction_log();
+        let statements: Vec<_> = log.iter().flat_map(|t| t.statements().to_vec()).collect();
+        assert!(statements[0].sql.starts_with("BEGIN"));
+        assert!(statements[1].sql.starts_with(r#"SELECT "player"."#), "{}", statements[1].sql);
+        assert!(statements[1].sql.ends_with("FOR UPDATE"), "{}", statements[1].sql);
+        assert!(statements[2].sql.contains("COUNT(*)"), "{}", statements[2].sql);
+        assert!(statements[4].sql.starts_with(r#"INSERT INTO "smdb"."game""#), "{}", statements[4].sql);
+        assert!(statements.last().unwrap().sql.starts_with("COMMIT"));
+    }
+
+    #[async_std::test]
+    async fn a_game_over_the_cap_is_not_inserted() {
+        let limits = GameLimits { real_time: 3, correspondence: 10 };
+        let (creator, opponent) = (Uuid::new_v4(), Uuid::new_v4());
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(3i64))])]])
+            .into_connection();
+
+        let result = create_game_with(&db, &limits, creator, opponent, &blitz_request(opponent)).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+        let log = db.into_transaction_log();
+        assert!(log.iter().flat_map(|t| t.statements().to_vec()).all(|s| !s.sql.starts_with("INSERT")));
+    }
+
+    #[async_std::test]
+    async fn games_are_found_by_either_id() {
+        let game = new_game("Q7xb2LmP");
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()], vec![game.clone()]])
+            .into_connection();
+
+        assert_eq!(find_game_with(&db, &game.id.to_string()).await.unwrap(), game);
+        assert_eq!(find_game_with(&db, "Q7xb2LmP").await.unwrap(), game);
+        assert!(matches!(find_game_with(&db, "not-a-game-id").await, Err(ApiError::NotFound(_))));
+    }
+
+    #[test]
+    fn parses_eco_filters() {
+        assert_eq!(EcoFilter::parse("b20"), EcoFilter::Exact("B20".to_string()));
+        assert_eq!(EcoFilter::parse("B2*"), EcoFilter::Prefix("B2".to_string()));
+        assert_eq!(EcoFilter::parse("C*"), EcoFilter::Prefix("C".to_string()));
+        assert_eq!(EcoFilter::parse("NONE"), EcoFilter::Unclassified);
+    }
+
+    #[test]
+    fn only_games_from_the_standard_position_are_classified() {
+        let sicilian = json!({ "moves": ["e4", "c5"] });
+        assert_eq!(
+            classify_opening(&sicilian),
+            (Some("B20".to_string()), Some("Sicilian Defense".to_string()))
+        );
+
+        let set_up = json!({
+            "moves": ["e4", "c5"],
+            "starting_fen": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+        });
+        assert_eq!(classify_opening(&set_up), (None, None));
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("50%_off"), "50\\%\\_off");
+    }
+
+    fn in_progress(count: i64) -> sea_orm::DatabaseConnection {
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(count))])]])
+            .into_connection()
+    }
+
+    #[async_std::test]
+    async fn rejects_a_game_over_the_concurrent_cap() {
+        let limits = GameLimits { real_time: 3, correspondence: 10 };
+        let player_id = Uuid::new_v4();
+
+        assert!(ensure_can_start_game_with(&in_progress(2), &limits, player_id, TimeClass::Blitz).await.is_ok());
+        let result = ensure_can_start_game_with(&in_progress(3), &limits, player_id, TimeClass::Blitz).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[async_std::test]
+    async fn correspondence_games_have_a_higher_cap() {
+        let limits = GameLimits { real_time: 3, correspondence: 10 };
+        let player_id = Uuid::new_v4();
+
+        let result =
+            ensure_can_start_game_with(&in_progress(3), &limits, player_id, TimeClass::Correspondence).await;
+
+        assert!(result.is_ok());
+    }
+
+    fn joining(game: game::Model, count: i64) -> sea_orm::DatabaseConnection {
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game]])
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(count))])]])
+            .into_connection()
+    }
+
+    #[async_std::test]
+    async fn joining_checks_the_cap_of_the_games_time_class() {
+        let limits = GameLimits { real_time: 3, correspondence: 10 };
+        let player_id = Uuid::new_v4();
+        let timed = |initial_secs| game::Model {
+            clock_initial_secs: Some(initial_secs),
+            clock_increment_secs: Some(0),
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        };
+        let correspondence = timed(3 * 24 * 60 * 60);
+        let blitz = timed(180);
+
+        let db = joining(correspondence.clone(), 3);
+        assert!(ensure_can_join_game_with(&db, &limits, player_id, correspondence.id).await.is_ok());
+        let db = joining(blitz.clone(), 3);
+        let result = ensure_can_join_game_with(&db, &limits, player_id, blitz.id).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+}
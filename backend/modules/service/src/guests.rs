@@ -0,0 +1,377 @@
+//! Guest players.
+//!
+//! A guest is a player row created without credentials so that people can try casual
+//! games before registering. Guests never log in: the short-lived token issued with
+//! the row is their only way back to it. Their games are never rated, and they can be
+//! converted into a full account that keeps the same id, and with it every game they
+//! have started. Guests who stop showing up are collected by
+//! [`collect_inactive_guests`].
+
+use std::collections::HashSet;
+use std::env;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use db::db::db::get_db;
+use dto::auth::ConvertGuestRequest;
+use entity::{game, player};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect, Set, sea_query::Expr,
+};
+use uuid::Uuid;
+
+use crate::helper::password::PasswordHasher;
+use crate::players::{email_exists, username_exists};
+
+const DEFAULT_TOKEN_TTL_SECS: u64 = 4 * 60 * 60;
+const DEFAULT_INACTIVITY_SECS: u64 = 24 * 60 * 60;
+
+/// Guest lifetimes, read from `GUEST_TOKEN_TTL_SECS` and `GUEST_INACTIVITY_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestConfig {
+    /// Lifetime of a guest token; guests get no refresh token.
+    pub token_ttl: Duration,
+    /// Time without activity after which a guest is collected.
+    pub inactivity: Duration,
+}
+
+impl Default for GuestConfig {
+    fn default() -> Self {
+        Self {
+            token_ttl: Duration::from_secs(DEFAULT_TOKEN_TTL_SECS),
+            inactivity: Duration::from_secs(DEFAULT_INACTIVITY_SECS),
+        }
+    }
+}
+
+impl GuestConfig {
+    pub fn from_env() -> Self {
+        let secs = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+        };
+        let defaults = Self::default();
+        Self {
+            token_ttl: secs("GUEST_TOKEN_TTL_SECS").unwrap_or(defaults.token_ttl),
+            inactivity: secs("GUEST_INACTIVITY_SECS").unwrap_or(defaults.inactivity),
+        }
+    }
+}
+
+/// Outcome of one cleanup sweep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuestSweep {
+    /// Guests without any game, removed outright.
+    pub deleted: u64,
+    /// Guests with finished games, disabled so their opponents keep the games.
+    pub disabled: u64,
+}
+
+pub async fn create_guest() -> Result<player::Model, ApiError> {
+    let db = get_db().await;
+    create_guest_with(&db).await
+}
+
+/// Inserts a new guest. The username and email are derived from the id, so they
+/// cannot clash with each other or with registered players, whose emails must be
+/// deliverable.
+pub async fn create_guest_with<C: ConnectionTrait>(db: &C) -> Result<player::Model, ApiError> {
+    let id = Uuid::new_v4();
+    let key = id.simple().to_string();
+    let guest = player::ActiveModel {
+        id: Set(id),
+        username: Set(format!("guest_{}", &key[..12])),
+        email: Set(format!("{}@guest.invalid", key)),
+        password_hash: Set(Vec::new()),
+        biography: Set(None),
+        country: Set(Some("XX".to_string())),
+        flair: Set(None),
+        real_name: Set(String::new()),
+        is_enabled: Set(true),
+        is_guest: Set(true),
+        last_seen_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+
+    Ok(guest.insert(db).await?)
+}
+
+/// Whether either player of a game is a guest, in which case it is not rated.
+pub async fn includes_guest<C: ConnectionTrait>(db: &C, players: [Uuid; 2]) -> Result<bool, ApiError> {
+    let guests = player::Entity::find()
+        .filter(player::Column::Id.is_in(players))
+        .filter(player::Column::IsGuest.eq(true))
+        .count(db)
+        .await?;
+
+    Ok(guests > 0)
+}
+
+/// The guests among `players`, whose games a batch of results leaves unrated.
+pub async fn guests_among<C: ConnectionTrait>(db: &C, players: &[Uuid]) -> Result<HashSet<Uuid>, ApiError> {
+    let guests: Vec<Uuid> = player::Entity::find()
+        .select_only()
+        .column(player::Column::Id)
+        .filter(player::Column::Id.is_in(players.iter().copied()))
+        .filter(player::Column::IsGuest.eq(true))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(guests.into_iter().collect())
+}
+
+pub async fn touch_guest(id: Uuid) -> Result<(), ApiError> {
+    let db = get_db().await;
+    touch_guest_with(&db, id).await
+}
+
+/// Records activity of a guest so the cleanup sweep leaves it alone. Does nothing for
+/// registered players.
+pub async fn touch_guest_with<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<(), ApiError> {
+    player::Entity::update_many()
+        .col_expr(player::Column::LastSeenAt, Expr::current_timestamp().into())
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsGuest.eq(true))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn convert_guest(id: Uuid, payload: ConvertGuestRequest) -> Result<player::Model, ApiError> {
+    let db = get_db().await;
+    convert_guest_with(&db, &PasswordHasher::from_env(), id, payload).await
+}
+
+/// Gives a guest credentials and makes it a registered player. The player id does not
+/// change, so games in progress carry over and the guest's current token stays valid
+/// until it expires.
+pub async fn convert_guest_with<C: ConnectionTrait>(
+    db: &C,
+    hasher: &PasswordHasher,
+    id: Uuid,
+    payload: ConvertGuestRequest,
+) -> Result<player::Model, ApiError> {
+    let guest = player::Entity::find()
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsEnabled.eq(true))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Player {}", id)))?;
+    if !guest.is_guest {
+        return Err(ApiError::Conflict(format!("Player {} already has an account", id)));
+    }
+    if username_exists(db, &payload.username, Some(id)).await? {
+        return Err(ApiError::Conflict(format!("Username {} is already taken", payload.username)));
+    }
+    if email_exists(db, &payload.email).await? {
+        return Err(ApiError::Conflict(format!("Email {} is already registered", payload.email)));
+    }
+
+    let mut account: player::ActiveModel = guest.into();
+    account.username = Set(payload.username);
+    account.email = Set(payload.email);
+    account.password_hash = Set(hasher.hash(&payload.password)?.into_bytes());
+    account.is_guest = Set(false);
+    account.last_seen_at = Set(Utc::now().into());
+    // Guarded so two conversions racing on the same guest cannot both succeed
+    player::Entity::update_many()
+        .set(account)
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsGuest.eq(true))
+        .exec_with_returning(db)
+        .await?
+        .pop()
+        .ok_or_else(|| ApiError::Conflict(format!("Player {} already has an account", id)))
+}
+
+pub async fn collect_inactive_guests() -> Result<GuestSweep, ApiError> {
+    let db = get_db().await;
+    let inactivity = chrono::Duration::from_std(GuestConfig::from_env().inactivity)
+        .unwrap_or(chrono::Duration::seconds(DEFAULT_INACTIVITY_SECS as i64));
+    collect_inactive_guests_with(&db, Utc::now() - inactivity).await
+}
+
+/// Removes guests last seen before `cutoff`. Guests with an unfinished game are kept
+/// until it ends. Games cascade with their players, so guests who finished games are
+/// only disabled, which keeps those games in their opponents' histories.
+pub async fn collect_inactive_guests_with<C: ConnectionTrait>(
+    db: &C,
+    cutoff: DateTime<Utc>,
+) -> Result<GuestSweep, ApiError> {
+    let stale: Vec<Uuid> = player::Entity::find()
+        .filter(player::Column::IsGuest.eq(true))
+        .filter(player::Column::IsEnabled.eq(true))
+        .filter(player::Column::LastSeenAt.lt(cutoff))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|guest| guest.id)
+        .collect();
+    if stale.is_empty() {
+        return Ok(GuestSweep::default());
+    }
+
+    let games = game::Entity::find()
+        .filter(
+            Condition::any()
+                .add(game::Column::WhitePlayer.is_in(stale.clone()))
+                .add(game::Column::BlackPlayer.is_in(stale.clone())),
+        )
+        .all(db)
+        .await?;
+    let (mut playing, mut played) = (HashSet::new(), HashSet::new());
+    for game in &games {
+        let players = if game.ended_at.is_none() { &mut playing } else { &mut played };
+        players.extend([game.white_player, game.black_player]);
+    }
+
+    let (unplayed, finished): (Vec<Uuid>, Vec<Uuid>) = stale
+        .into_iter()
+        .filter(|id| !playing.contains(id))
+        .partition(|id| !played.contains(id));
+
+    let mut sweep = GuestSweep::default();
+    if !unplayed.is_empty() {
+        sweep.deleted = player::Entity::delete_many()
+            .filter(player::Column::Id.is_in(unplayed))
+            .filter(player::Column::IsGuest.eq(true))
+            .exec(db)
+            .await?
+            .rows_affected;
+    }
+    if !finished.is_empty() {
+        sweep.disabled = player::Entity::update_many()
+            .col_expr(player::Column::IsEnabled, Expr::value(false))
+            .filter(player::Column::Id.is_in(finished))
+            .filter(player::Column::IsGuest.eq(true))
+            .exec(db)
+            .await?
+            .rows_affected;
+    }
+
+    Ok(sweep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+
+    fn guest(id: Uuid) -> player::Model {
+        player::Model {
+            id,
+            username: format!("guest_{}", &id.simple().to_string()[..12]),
+            email: format!("{}@guest.invalid", id.simple()),
+            password_hash: Vec::new(),
+            biography: None,
+            country: Some("XX".to_string()),
+            flair: None,
+            real_name: String::new(),
+            location: None,
+            fide_rating: None,
+            social_links: None,
+            is_enabled: true,
+            is_guest: true,
+            last_seen_at: Utc::now().into(),
+        }
+    }
+
+    fn game_between(white: Uuid, black: Uuid, finished: bool) -> game::Model {
+        let game = game::Model::fixture(white, black);
+        game::Model {
+            public_id: "Gs6pD4kH".to_string(),
+            ended_at: finished.then_some(game.started_at),
+            ..game
+        }
+    }
+
+    fn count(n: i64) -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([("num_items", Value::from(n))])
+    }
+
+    #[async_std::test]
+    async fn registered_player_cannot_be_converted() {
+        let id = Uuid::new_v4();
+        let mut registered = guest(id);
+        registered.is_guest = false;
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![registered]])
+            .into_connection();
+        let payload = ConvertGuestRequest {
+            username: "newcomer".to_string(),
+            email: "newcomer@example.com".to_string(),
+            password: "Secure_password123!".to_string(),
+        };
+
+        let result = convert_guest_with(&db, &PasswordHasher::new(1024, 1, 1).unwrap(), id, payload).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+        assert_eq!(db.into_transaction_log().len(), 1, "nothing is written");
+    }
+
+    #[async_std::test]
+    async fn conversion_keeps_the_player_id() {
+        let id = Uuid::new_v4();
+        let mut converted = guest(id);
+        converted.username = "newcomer".to_string();
+        converted.is_guest = false;
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![guest(id)]])
+            .append_query_results([vec![count(0)]])
+            .append_query_results([vec![count(0)]])
+            .append_query_results([vec![converted]])
+            .into_connection();
+        let payload = ConvertGuestRequest {
+            username: "newcomer".to_string(),
+            email: "newcomer@example.com".to_string(),
+            password: "Secure_password123!".to_string(),
+        };
+
+        let player = convert_guest_with(&db, &PasswordHasher::new(1024, 1, 1).unwrap(), id, payload)
+            .await
+            .unwrap();
+
+        assert_eq!((player.id, player.is_guest), (id, false));
+    }
+
+    #[async_std::test]
+    async fn sweep_deletes_idle_guests_and_disables_those_with_finished_games() {
+        let (idle, finished, playing) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let opponent = Uuid::new_v4();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![guest(idle), guest(finished), guest(playing)]])
+            .append_query_results([vec![
+                game_between(finished, opponent, true),
+                game_between(opponent, playing, false),
+            ]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let sweep = collect_inactive_guests_with(&db, Utc::now()).await.unwrap();
+
+        assert_eq!(sweep, GuestSweep { deleted: 1, disabled: 1 });
+        let log = db.into_transaction_log();
+        let values = |verb: &str| {
+            log.iter()
+                .flat_map(|t| t.statements())
+                .find(|s| s.sql.starts_with(verb))
+                .map(|s| format!("{:?}", s.values))
+                .unwrap_or_default()
+        };
+        let (deleted, disabled) = (values("DELETE"), values("UPDATE"));
+        assert!(deleted.contains(&idle.to_string()) && !deleted.contains(&finished.to_string()));
+        assert!(disabled.contains(&finished.to_string()) && !disabled.contains(&idle.to_string()));
+        assert!(!deleted.contains(&playing.to_string()) && !disabled.contains(&playing.to_string()));
+    }
+}
@@ -1,24 +1,140 @@
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
-
+use argon2::{
+    ARGON2ID_IDENT, Algorithm, Argon2, Params, PasswordHash, PasswordHasher as _,
+    PasswordVerifier as _, Version, password_hash::SaltString,
+};
 use rand::rngs::OsRng;
+use std::env;
+
+/// Argon2id password hashing with configurable cost.
+///
+/// Hashes are stored in PHC string format, which records the algorithm and
+/// parameters they were made with. Verification reads those from the stored hash,
+/// so hashes made with older parameters (or Argon2i/Argon2d) keep verifying after
+/// the configuration changes; [`PasswordHasher::needs_rehash`] tells callers when to
+/// upgrade them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+impl Default for PasswordHasher {
+    /// OWASP's recommended Argon2id baseline: 19 MiB, 2 iterations, 1 lane.
+    fn default() -> Self {
+        Self { params: Params::default() }
+    }
+}
 
-pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2.hash_password(password.as_bytes(), &salt)?; // ? propagates errors
-    Ok(hash.to_string())
+fn env_cost(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
 }
 
-pub fn verify_password<'a>(
-    password: &'a str,
-    hashed_password: &'a str,
-) -> Result<(), argon2::password_hash::Error> {
-    let password_hash = PasswordHash::new(&hashed_password)?;
-    // Trait objects for algorithms to support
-    let algs: &[&dyn PasswordVerifier] = &[&Argon2::default()];
-
-    match password_hash.verify_password(algs, password) {
-        Ok(_) => Ok(()),
-        Err(_err) => Err(_err),
+impl PasswordHasher {
+    /// `memory_kib` is the memory cost in KiB, `iterations` the time cost and
+    /// `parallelism` the number of lanes.
+    pub fn new(
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        let params = Params::new(memory_kib, iterations, parallelism, None)?;
+        Ok(Self { params })
+    }
+
+    /// Reads the cost from `PASSWORD_HASH_MEMORY_KIB`, `PASSWORD_HASH_ITERATIONS` and
+    /// `PASSWORD_HASH_PARALLELISM`. Unset values use the defaults; a combination
+    /// Argon2 rejects falls back to the defaults entirely.
+    pub fn from_env() -> Self {
+        Self::new(
+            env_cost("PASSWORD_HASH_MEMORY_KIB", Params::DEFAULT_M_COST),
+            env_cost("PASSWORD_HASH_ITERATIONS", Params::DEFAULT_T_COST),
+            env_cost("PASSWORD_HASH_PARALLELISM", Params::DEFAULT_P_COST),
+        )
+        .unwrap_or_default()
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, argon2::password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self.argon2().hash_password(password.as_bytes(), &salt)?;
+        Ok(hash.to_string())
+    }
+
+    /// Checks `password` against a stored PHC hash. A wrong password is reported as
+    /// `Error::Password`; any other error means the stored hash is malformed.
+    pub fn verify(&self, password: &str, stored: &str) -> Result<(), argon2::password_hash::Error> {
+        let hash = PasswordHash::new(stored)?;
+        self.argon2().verify_password(password.as_bytes(), &hash)
+    }
+
+    /// `true` if `stored` was not made with Argon2id and the configured parameters.
+    /// Unparseable hashes report `false`; they cannot be verified to begin with.
+    pub fn needs_rehash(&self, stored: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(stored) else {
+            return false;
+        };
+        if hash.algorithm != ARGON2ID_IDENT || hash.version != Some(Version::V0x13.into()) {
+            return true;
+        }
+        match Params::try_from(&hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small costs keep the tests fast; production costs come from the environment
+    fn cheap() -> PasswordHasher {
+        PasswordHasher::new(1024, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn verifies_the_hashed_password() {
+        let hasher = cheap();
+        let stored = hasher.hash("correct horse battery").unwrap();
+
+        assert!(stored.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert!(hasher.verify("correct horse battery", &stored).is_ok());
+        assert!(!hasher.needs_rehash(&stored));
+    }
+
+    #[test]
+    fn rejects_a_wrong_password() {
+        let hasher = cheap();
+        let stored = hasher.hash("correct horse battery").unwrap();
+
+        assert_eq!(
+            hasher.verify("incorrect horse battery", &stored),
+            Err(argon2::password_hash::Error::Password)
+        );
+    }
+
+    #[test]
+    fn legacy_hashes_verify_but_need_rehash() {
+        let salt = SaltString::generate(&mut OsRng);
+        let legacy = Argon2::new(Algorithm::Argon2i, Version::V0x13, Params::new(1024, 1, 1, None).unwrap())
+            .hash_password(b"correct horse battery", &salt)
+            .unwrap()
+            .to_string();
+        let weaker = cheap().hash("correct horse battery").unwrap();
+        let hasher = PasswordHasher::new(2048, 1, 1).unwrap();
+
+        assert!(hasher.verify("correct horse battery", &legacy).is_ok());
+        assert!(hasher.needs_rehash(&legacy));
+        assert!(hasher.verify("correct horse battery", &weaker).is_ok());
+        assert!(hasher.needs_rehash(&weaker));
     }
 }
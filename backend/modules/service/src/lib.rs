@@ -1,2 +1,24 @@
 pub mod players;
+pub mod ai;
+pub mod analysis;
+pub mod archive;
+pub mod clocks;
+pub mod engine;
+pub mod fair_play;
+pub mod games;
+pub mod game_events;
+pub mod game_export;
+pub mod guests;
+pub mod lifecycle;
+pub mod match_analytics;
+pub mod notifications;
+pub mod pagination;
+pub mod rating;
+pub mod rating_recompute;
+pub mod replay;
+pub mod seeks;
+pub mod series;
+pub mod stats;
+pub mod tournaments;
+pub mod webhooks;
 pub mod helper;
\ No newline at end of file
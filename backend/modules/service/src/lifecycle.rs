@@ -0,0 +1,1273 @@
+use crate::clocks;
+use crate::game_events::{self, GameEvent};
+use crate::games::classify_opening;
+use crate::guests::includes_guest;
+use crate::rating::{RatingEngine, RatingUpdate, apply_game_rating_with};
+use crate::tournaments::{ingest_result_with, is_tournament_game};
+use chess::bitboard::Board::Color;
+use chess::history::{DrawClaim, GameHistory};
+use chess::position::{Move, MoveError};
+use chess::variant::{self, GameStatus, Outcome, Pockets, StalemateRule, Variant, WithStalemateRule};
+use chrono::Utc;
+use db::db::db::get_db;
+use dto::games::{Side, Variant as VariantName, pgn_moves, pgn_starting_fen, side_to_move};
+use entity::game;
+use entity::sea_orm_active_enums::{GameVariant, ResultSide, Termination};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    Set, TransactionTrait,
+};
+use serde_json::json;
+use std::env;
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+/// Until both players have made a move a game can only be aborted, not drawn or rated.
+pub const ABORT_WINDOW_PLIES: usize = 2;
+
+/// Moves a player makes after offering a draw before they may offer another.
+pub const DEFAULT_DRAW_OFFER_COOLDOWN_MOVES: usize = 5;
+/// Draw offers a player may make in one game.
+pub const DEFAULT_MAX_DRAW_OFFERS: i32 = 3;
+
+/// Throttle on repeated draw offers, read from `DRAW_OFFER_COOLDOWN_MOVES` and
+/// `DRAW_OFFER_MAX_PER_GAME`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawOfferLimits {
+    pub cooldown_moves: usize,
+    pub max_offers: i32,
+}
+
+impl Default for DrawOfferLimits {
+    fn default() -> Self {
+        Self {
+            cooldown_moves: DEFAULT_DRAW_OFFER_COOLDOWN_MOVES,
+            max_offers: DEFAULT_MAX_DRAW_OFFERS,
+        }
+    }
+}
+
+impl DrawOfferLimits {
+    pub fn from_env() -> Self {
+        Self {
+            cooldown_moves: env::var("DRAW_OFFER_COOLDOWN_MOVES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_DRAW_OFFER_COOLDOWN_MOVES),
+            max_offers: env::var("DRAW_OFFER_MAX_PER_GAME")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|max: &i32| *max > 0)
+                .unwrap_or(DEFAULT_MAX_DRAW_OFFERS),
+        }
+    }
+
+    /// Refuses an offer from the side playing `color` when it has used up its offers
+    /// or offered too recently.
+    fn check(&self, game: &game::Model, color: Color) -> Result<(), ApiError> {
+        let (offers, last_ply) = match color {
+            Color::White => (game.white_draw_offers, game.white_last_draw_offer_ply),
+            Color::Black => (game.black_draw_offers, game.black_last_draw_offer_ply),
+        };
+        if offers >= self.max_offers {
+            return Err(ApiError::Conflict(format!(
+                "You have already offered {} draws in this game",
+                offers
+            )));
+        }
+        if let Some(last_ply) = last_ply {
+            let cooldown = self.cooldown_moves * 2;
+            let since = ply_count(game).saturating_sub(last_ply.max(0) as usize);
+            if since < cooldown {
+                return Err(ApiError::Conflict(format!(
+                    "You can offer another draw in {} moves",
+                    (cooldown - since).div_ceil(2)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A game that has just reached a terminal state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinalizedGame {
+    pub game: game::Model,
+    /// `None` for aborted games and games with a guest, which are not rated, and for
+    /// tournament games, which are rated with their round.
+    pub ratings: Option<RatingUpdate>,
+    /// Games of the tournament round this game's result completed the previous round
+    /// for; empty for all other games.
+    pub next_round: Vec<game::Model>,
+}
+
+/// A player-initiated change to a live game, shared by the REST and WebSocket entry points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameAction {
+    Resign,
+    Abort,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    ClaimDraw(DrawClaim),
+}
+
+/// What a [`GameAction`] did to the game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionOutcome {
+    DrawOffered(game::Model),
+    DrawDeclined(game::Model),
+    Finalized(FinalizedGame),
+}
+
+impl ActionOutcome {
+    pub fn game(&self) -> &game::Model {
+        match self {
+            ActionOutcome::DrawOffered(game) | ActionOutcome::DrawDeclined(game) => game,
+            ActionOutcome::Finalized(finalized) => &finalized.game,
+        }
+    }
+}
+
+/// A move accepted by [`apply_move_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveOutcome {
+    /// The game after the move, including its end if the move finished it.
+    pub game: game::Model,
+    pub mv: Move,
+    pub san: String,
+    /// Set when the move delivered checkmate or left a drawn position.
+    pub finalized: Option<FinalizedGame>,
+}
+
+/// A game is over once it has an end time; `result` and `termination` only say how.
+pub fn is_terminal(game: &game::Model) -> bool {
+    game.ended_at.is_some()
+}
+
+pub fn ply_count(game: &game::Model) -> usize {
+    pgn_moves(&game.pgn).len()
+}
+
+/// Rebuilds every position of `game` from its stored moves.
+pub fn history_of(game: &game::Model) -> Result<GameHistory, ApiError> {
+    GameHistory::replay(pgn_starting_fen(&game.pgn), &pgn_moves(&game.pgn)).map_err(|err| {
+        ApiError::DatabaseError(DbErr::Custom(format!(
+            "Stored moves of game {} cannot be replayed: {}",
+            game.id, err
+        )))
+    })
+}
+
+pub(crate) async fn load_game<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<game::Model, ApiError> {
+    game::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Game {}", id)))
+}
+
+/// Loads `id` with `SELECT ... FOR UPDATE`, so concurrent transitions of the same game
+/// queue behind the caller's transaction and see its outcome.
+pub(crate) async fn lock_game<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<game::Model, ApiError> {
+    game::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Game {}", id)))
+}
+
+/// Returns the other player in `game`, rejecting callers who are not seated in it.
+pub(crate) fn opponent_of(game: &game::Model, player_id: Uuid) -> Result<Uuid, ApiError> {
+    if player_id == game.white_player {
+        Ok(game.black_player)
+    } else if player_id == game.black_player {
+        Ok(game.white_player)
+    } else {
+        Err(ApiError::Forbidden(format!(
+            "Player {} is not part of game {}",
+            player_id, game.id
+        )))
+    }
+}
+
+/// Whose turn it is, decided in one place for moves from every entry point: the
+/// side to move in the game's stored FEN must be the colour `player_id` plays. Returns
+/// that colour; callers not seated in the game are `Forbidden`.
+pub fn assert_players_turn(game: &game::Model, player_id: Uuid) -> Result<Color, ApiError> {
+    opponent_of(game, player_id)?;
+    let color = if player_id == game.white_player { Color::White } else { Color::Black };
+    let to_move = match side_to_move(game, ply_count(game)) {
+        Side::White => Color::White,
+        Side::Black => Color::Black,
+    };
+    if color != to_move {
+        return Err(ApiError::NotYourTurn);
+    }
+    Ok(color)
+}
+
+fn ensure_drawable(game: &game::Model) -> Result<(), ApiError> {
+    if is_terminal(game) {
+        return Err(ApiError::Conflict(format!("Game {} is already over", game.id)));
+    }
+    if ply_count(game) < ABORT_WINDOW_PLIES {
+        return Err(ApiError::Conflict(
+            "A draw cannot be agreed before both players have moved".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Moves `game` to a terminal state and, when it has a result and no guest took part,
+/// rates it. Tournament games are rated when their round is over instead.
+/// Must run inside the caller's transaction so the game row and ratings change together.
+///
+/// Finalizing is idempotent: the update only matches a row that is still live, so when
+/// two triggers race (a timeout sweep and a resignation, say) the second one gets a
+/// `Conflict` and never rates the game a second time.
+///
+/// Tournament games also score their board, which may rate the round and start the next one.
+pub async fn finalize_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game: game::Model,
+    result: Option<ResultSide>,
+    termination: Termination,
+) -> Result<FinalizedGame, ApiError> {
+    let already_over = |id: Uuid| ApiError::Conflict(format!("Game {} is already over", id));
+    if is_terminal(&game) {
+        return Err(already_over(game.id));
+    }
+
+    let id = game.id;
+    let now = Utc::now();
+    let duration = (now - game.started_at.with_timezone(&Utc)).num_seconds().max(0);
+
+    let mut active: game::ActiveModel = game.into();
+    active.result = Set(result);
+    active.termination = Set(Some(termination));
+    active.draw_offered_by = Set(None);
+    active.duration_sec = Set(duration as i32);
+    active.ended_at = Set(Some(now.into()));
+    active.updated_at = Set(now.into());
+    let game = game::Entity::update_many()
+        .set(active)
+        .filter(game::Column::Id.eq(id))
+        .filter(game::Column::EndedAt.is_null())
+        .exec_with_returning(db)
+        .await?
+        .pop()
+        .ok_or_else(|| already_over(id))?;
+
+    // Tournament games are rated with the rest of their round once it is over
+    let next_round = ingest_result_with(db, engine, &game).await?;
+    let ratings = match (result, &next_round) {
+        (Some(result), None) if !includes_guest(db, [game.white_player, game.black_player]).await? => {
+            Some(apply_game_rating_with(db, engine, &game, result).await?)
+        }
+        _ => None,
+    };
+
+    Ok(FinalizedGame { game, ratings, next_round: next_round.unwrap_or_default() })
+}
+
+pub async fn offer_draw(game_id: Uuid, player_id: Uuid) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    offer_draw_with(&db, &DrawOfferLimits::from_env(), game_id, player_id).await
+}
+
+/// Records a standing draw offer from `player_id`, counting it against their
+/// [`DrawOfferLimits`].
+pub async fn offer_draw_with<C: ConnectionTrait>(
+    db: &C,
+    limits: &DrawOfferLimits,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Result<game::Model, ApiError> {
+    let game = load_game(db, game_id).await?;
+    let opponent = opponent_of(&game, player_id)?;
+    ensure_drawable(&game)?;
+    if game.draw_offered_by == Some(opponent) {
+        return Err(ApiError::Conflict(
+            "Your opponent has already offered a draw; accept it instead".to_string(),
+        ));
+    }
+    let color = if game.white_player == player_id { Color::White } else { Color::Black };
+    limits.check(&game, color)?;
+
+    let ply = ply_count(&game) as i32;
+    let mut active: game::ActiveModel = game.clone().into();
+    match color {
+        Color::White => {
+            active.white_draw_offers = Set(game.white_draw_offers + 1);
+            active.white_last_draw_offer_ply = Set(Some(ply));
+        }
+        Color::Black => {
+            active.black_draw_offers = Set(game.black_draw_offers + 1);
+            active.black_last_draw_offer_ply = Set(Some(ply));
+        }
+    }
+    active.draw_offered_by = Set(Some(player_id));
+    active.updated_at = Set(Utc::now().into());
+    let game = active.update(db).await?;
+    game_events::emit(GameEvent::DrawOffered, &game, Some(player_id));
+    Ok(game)
+}
+
+pub async fn decline_draw(game_id: Uuid, player_id: Uuid) -> Result<game::Model, ApiError> {
+    let db = get_db().await;
+    decline_draw_with(&db, game_id, player_id).await
+}
+
+/// Withdraws the opponent's pending offer.
+pub async fn decline_draw_with<C: ConnectionTrait>(
+    db: &C,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Result<game::Model, ApiError> {
+    let game = load_game(db, game_id).await?;
+    let opponent = opponent_of(&game, player_id)?;
+    if is_terminal(&game) || game.draw_offered_by != Some(opponent) {
+        return Err(ApiError::Conflict("There is no pending draw offer".to_string()));
+    }
+
+    let mut active: game::ActiveModel = game.into();
+    active.draw_offered_by = Set(None);
+    active.updated_at = Set(Utc::now().into());
+    let game = active.update(db).await?;
+    game_events::emit(GameEvent::DrawDeclined, &game, Some(player_id));
+    Ok(game)
+}
+
+pub async fn accept_draw(game_id: Uuid, player_id: Uuid) -> Result<FinalizedGame, ApiError> {
+    let db = get_db().await;
+    accept_draw_with(&db, &RatingEngine::from_env(), game_id, player_id).await
+}
+
+/// Accepts the opponent's pending offer, ending the game as a draw by agreement.
+pub async fn accept_draw_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    let opponent = opponent_of(&game, player_id)?;
+    ensure_drawable(&game)?;
+    if game.draw_offered_by != Some(opponent) {
+        return Err(ApiError::Conflict("There is no pending draw offer".to_string()));
+    }
+
+    let finalized = finalize_with(
+        &txn,
+        engine,
+        game,
+        Some(ResultSide::Draw),
+        Termination::Agreement,
+    )
+    .await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::DrawAccepted), &finalized, Some(player_id));
+    Ok(finalized)
+}
+
+pub async fn resign(game_id: Uuid, player_id: Uuid) -> Result<FinalizedGame, ApiError> {
+    let db = get_db().await;
+    resign_with(&db, &RatingEngine::from_env(), game_id, player_id).await
+}
+
+/// Ends the game as a win for the resigning player's opponent.
+pub async fn resign_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    let opponent = opponent_of(&game, player_id)?;
+    let winner = if opponent == game.white_player {
+        ResultSide::White
+    } else {
+        ResultSide::Black
+    };
+
+    let finalized = finalize_with(&txn, engine, game, Some(winner), Termination::Resignation).await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::Resigned), &finalized, Some(player_id));
+    Ok(finalized)
+}
+
+pub async fn abort(game_id: Uuid, player_id: Uuid) -> Result<FinalizedGame, ApiError> {
+    let db = get_db().await;
+    abort_with(&db, &RatingEngine::from_env(), game_id, player_id).await
+}
+
+/// Cancels a game that is still inside the abort window. Aborted games have no result and are not rated.
+pub async fn abort_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    opponent_of(&game, player_id)?;
+    if !is_terminal(&game) && ply_count(&game) >= ABORT_WINDOW_PLIES {
+        return Err(ApiError::Conflict(
+            "A game can only be aborted before both players have moved".to_string(),
+        ));
+    }
+    // Tournament boards that are never played are forfeited instead
+    if is_tournament_game(&txn, game.id).await? {
+        return Err(ApiError::Conflict("Tournament games cannot be aborted".to_string()));
+    }
+
+    let finalized = finalize_with(&txn, engine, game, None, Termination::Aborted).await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::Aborted), &finalized, Some(player_id));
+    Ok(finalized)
+}
+
+pub async fn claim_draw(game_id: Uuid, player_id: Uuid, claim: DrawClaim) -> Result<FinalizedGame, ApiError> {
+    let db = get_db().await;
+    claim_draw_with(&db, &RatingEngine::from_env(), game_id, player_id, claim).await
+}
+
+/// Grants a draw by threefold repetition or the fifty-move rule when the server's
+/// own replay of the game confirms it. The claim must be made on the claimant's turn.
+pub async fn claim_draw_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+    claim: DrawClaim,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    opponent_of(&game, player_id)?;
+    if is_terminal(&game) {
+        return Err(ApiError::Conflict(format!("Game {} is already over", game.id)));
+    }
+
+    let history = history_of(&game)?;
+    let claimant = if player_id == game.white_player { Color::White } else { Color::Black };
+    if history.current().turn != claimant {
+        return Err(ApiError::Conflict("A draw can only be claimed on your own turn".to_string()));
+    }
+    if !history.can_claim(claim) {
+        let reason = match claim {
+            DrawClaim::Repetition => format!(
+                "the current position has occurred {} time(s)",
+                history.repetitions()
+            ),
+            DrawClaim::FiftyMove => format!(
+                "only {} plies have passed without a capture or pawn move",
+                history.current().halfmove_clock
+            ),
+        };
+        return Err(ApiError::Conflict(format!("Draw claim rejected: {}", reason)));
+    }
+
+    let termination = match claim {
+        DrawClaim::Repetition => Termination::Repetition,
+        DrawClaim::FiftyMove => Termination::FiftyMove,
+    };
+    let finalized = finalize_with(&txn, engine, game, Some(ResultSide::Draw), termination).await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::DrawClaimed), &finalized, Some(player_id));
+    Ok(finalized)
+}
+
+pub async fn time_out(game_id: Uuid, flagged_player: Uuid) -> Result<FinalizedGame, ApiError> {
+    let db = get_db().await;
+    time_out_with(&db, &RatingEngine::from_env(), game_id, flagged_player).await
+}
+
+/// Ends the game as a win for the opponent of `flagged_player`, whose clock ran out.
+/// Timeouts are detected by the server, so the event carries no actor. `Conflict`
+/// while the player still has time left.
+pub async fn time_out_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    flagged_player: Uuid,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    let opponent = opponent_of(&game, flagged_player)?;
+    if !is_terminal(&game) && clocks::flagged_player(&game, Utc::now()) != Some(flagged_player) {
+        return Err(ApiError::Conflict(format!("Player {} still has time in game {}", flagged_player, game_id)));
+    }
+    let winner = if opponent == game.white_player {
+        ResultSide::White
+    } else {
+        ResultSide::Black
+    };
+
+    let finalized = finalize_with(&txn, engine, game, Some(winner), Termination::Timeout).await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::TimedOut), &finalized, None);
+    Ok(finalized)
+}
+
+/// Ends a game in which the players have not both moved as abandoned, without a
+/// result and unrated. A tournament board played by it is scored by the tournament's
+/// forfeit policy instead.
+pub async fn forfeit_no_show_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+) -> Result<FinalizedGame, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    if !is_terminal(&game) && ply_count(&game) >= ABORT_WINDOW_PLIES {
+        return Err(ApiError::Conflict(format!("Both players of game {} have moved", game_id)));
+    }
+
+    let finalized = finalize_with(&txn, engine, game, None, Termination::Abandonment).await?;
+
+    txn.commit().await?;
+    game_events::emit_finalized(Some(GameEvent::Forfeited), &finalized, None);
+    Ok(finalized)
+}
+
+fn invalid_move(error: MoveError) -> ApiError {
+    let mut invalid = ValidationError::new("invalid_move");
+    invalid.message = Some(error.to_string().into());
+    let mut errors = ValidationErrors::new();
+    errors.add("chess_move", invalid);
+    ApiError::ValidationError(errors)
+}
+
+pub async fn apply_move(game_id: Uuid, player_id: Uuid, mv: &str) -> Result<MoveOutcome, ApiError> {
+    let db = get_db().await;
+    apply_move_with(&db, &RatingEngine::from_env(), game_id, player_id, mv).await
+}
+
+/// The one place a move is played: it is checked against the server's replay of the
+/// game, appended to the stored moves together with the new position, and the game is
+/// finalized when the move ends it. `mv` may be UCI (`e2e4`) or SAN (`e4`). A mover
+/// whose clock has run out gets `Conflict` and loses the game on time.
+pub async fn apply_move_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+    mv: &str,
+) -> Result<MoveOutcome, ApiError> {
+    let txn = db.begin().await?;
+
+    let game = lock_game(&txn, game_id).await?;
+    let opponent = opponent_of(&game, player_id)?;
+    if is_terminal(&game) {
+        return Err(ApiError::Conflict(format!("Game {} is already over", game.id)));
+    }
+
+    let mover = assert_players_turn(&game, player_id)?;
+
+    // A move that arrives after the mover's flag fell is not played; it loses on time
+    let now = Utc::now();
+    if clocks::flagged_player(&game, now) == Some(player_id) {
+        txn.rollback().await?;
+        time_out_with(db, engine, game_id, player_id).await?;
+        return Err(ApiError::Conflict(format!("Your time in game {} has run out", game_id)));
+    }
+
+    let mut history = history_of(&game)?;
+
+    let position = history.current();
+    let parsed = match position.parse_uci(mv) {
+        Err(MoveError::Unparseable(_)) => position.parse_san(mv),
+        parsed => parsed,
+    }
+    .map_err(invalid_move)?;
+    let san = position.san(&parsed);
+    history.push(parsed).map_err(invalid_move)?;
+
+    let mut moves = pgn_moves(&game.pgn);
+    moves.push(san.clone());
+    let mut pgn = json!({ "moves": moves });
+    if let Some(starting_fen) = game.pgn.get("starting_fen") {
+        pgn["starting_fen"] = starting_fen.clone();
+    }
+    let (eco, opening_name) = classify_opening(&pgn);
+
+    // Moving answers a pending offer from the opponent; the mover's own offer stands.
+    let draw_offered_by = game.draw_offered_by.filter(|offered_by| *offered_by != opponent);
+
+    let position = history.current();
+    let mut active: game::ActiveModel = game.clone().into();
+    clocks::stamp_move(&game, &mut active, mover, now);
+    active.fen = Set(position.to_fen());
+    active.pgn = Set(pgn);
+    active.eco = Set(eco);
+    active.opening_name = Set(opening_name);
+    active.draw_offered_by = Set(draw_offered_by);
+    active.updated_at = Set(now.into());
+    let game = active.update(&txn).await?;
+
+    let pockets = match game.variant {
+        GameVariant::Crazyhouse => Pockets::of(&history),
+        _ => Pockets::default(),
+    };
+    let ending = match variant_rules(game.variant).game_status_with_pockets(position, &pockets) {
+        GameStatus::Ongoing => None,
+        GameStatus::Checkmate(outcome) => Some((result_side(outcome), Termination::Checkmate)),
+        GameStatus::Stalemate(outcome) => Some((result_side(outcome), Termination::Stalemate)),
+        GameStatus::InsufficientMaterial => Some((ResultSide::Draw, Termination::InsufficientMaterial)),
+    };
+    let finalized = match ending {
+        Some((result, termination)) => {
+            Some(finalize_with(&txn, engine, game.clone(), Some(result), termination).await?)
+        }
+        None => None,
+    };
+
+    txn.commit().await?;
+    game_events::emit(GameEvent::MoveApplied, &game, Some(player_id));
+    if let Some(finalized) = &finalized {
+        game_events::emit_finalized(None, finalized, Some(player_id));
+    }
+    Ok(MoveOutcome {
+        game: finalized.as_ref().map(|f| f.game.clone()).unwrap_or(game),
+        mv: parsed,
+        san,
+        finalized,
+    })
+}
+
+/// Rules a stored game is played under: its variant's, scoring stalemate by the
+/// rule configured for the variant in `STALEMATE_RULE_<VARIANT>` (e.g.
+/// `STALEMATE_RULE_CRAZYHOUSE=stalemated_wins`), or by the variant's own rule.
+pub fn variant_rules(game_variant: GameVariant) -> WithStalemateRule<&'static dyn Variant> {
+    rules_with(game_variant, configured_stalemate_rule(game_variant))
+}
+
+fn configured_stalemate_rule(game_variant: GameVariant) -> Option<StalemateRule> {
+    let name = format!("STALEMATE_RULE_{}", VariantName::from(game_variant).as_str().to_uppercase());
+    env::var(name).ok().and_then(|rule| rule.parse().ok())
+}
+
+fn rules_with(game_variant: GameVariant, stalemate: Option<StalemateRule>) -> WithStalemateRule<&'static dyn Variant> {
+    let variant: &'static dyn Variant = match game_variant {
+        GameVariant::Standard => &variant::Standard,
+        GameVariant::Chess960 => &variant::Chess960,
+        GameVariant::Crazyhouse => &variant::Crazyhouse,
+        GameVariant::KingOfTheHill => &variant::KingOfTheHill,
+    };
+    WithStalemateRule { variant, rule: stalemate.unwrap_or_else(|| variant.stalemate_rule()) }
+}
+
+fn result_side(outcome: Outcome) -> ResultSide {
+    match outcome {
+        Outcome::Win(Color::White) => ResultSide::White,
+        Outcome::Win(Color::Black) => ResultSide::Black,
+        Outcome::Draw => ResultSide::Draw,
+    }
+}
+
+pub async fn apply_action(
+    game_id: Uuid,
+    player_id: Uuid,
+    action: GameAction,
+) -> Result<ActionOutcome, ApiError> {
+    let db = get_db().await;
+    apply_action_with(&db, &RatingEngine::from_env(), game_id, player_id, action).await
+}
+
+/// Single entry point for player actions, so every transport runs the same state transitions.
+pub async fn apply_action_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game_id: Uuid,
+    player_id: Uuid,
+    action: GameAction,
+) -> Result<ActionOutcome, ApiError> {
+    match action {
+        GameAction::Resign => resign_with(db, engine, game_id, player_id)
+            .await
+            .map(ActionOutcome::Finalized),
+        GameAction::Abort => abort_with(db, engine, game_id, player_id)
+            .await
+            .map(ActionOutcome::Finalized),
+        GameAction::OfferDraw => offer_draw_with(db, &DrawOfferLimits::from_env(), game_id, player_id)
+            .await
+            .map(ActionOutcome::DrawOffered),
+        GameAction::AcceptDraw => accept_draw_with(db, engine, game_id, player_id)
+            .await
+            .map(ActionOutcome::Finalized),
+        GameAction::DeclineDraw => decline_draw_with(db, game_id, player_id)
+            .await
+            .map(ActionOutcome::DrawDeclined),
+        GameAction::ClaimDraw(claim) => claim_draw_with(db, engine, game_id, player_id, claim)
+            .await
+            .map(ActionOutcome::Finalized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::sea_orm_active_enums::GameVariant;
+    use entity::{player_rating, tournament_pairing};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn live_game(white: Uuid, black: Uuid, moves: &[&str]) -> game::Model {
+        game::Model {
+            public_id: "Lc2hJ7fK".to_string(),
+            fen: GameHistory::replay(chess::fen::STARTING_FEN, moves).unwrap().current().to_fen(),
+            pgn: json!({ "moves": moves }),
+            draw_offered_by: Some(white),
+            ..game::Model::fixture(white, black)
+        }
+    }
+
+    fn no_guests() -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([("num_items", Value::from(0i64))])
+    }
+
+    fn not_in_a_tournament() -> Vec<tournament_pairing::Model> {
+        Vec::new()
+    }
+
+    fn rating(player_id: Uuid, rating: i32) -> player_rating::Model {
+        player_rating::Model {
+            player_id,
+            rating,
+            games_played: 10,
+            updated_at: Utc::now().into(),
+        }
+    }
+
+    #[async_std::test]
+    async fn agreed_draw_adjusts_both_ratings_symmetrically() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+        let mut finished = game.clone();
+        finished.result = Some(ResultSide::Draw);
+        finished.termination = Some(Termination::Agreement);
+        finished.draw_offered_by = None;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1800), rating(black, 1400)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let outcome = accept_draw_with(&db, &RatingEngine::default(), game.id, black)
+            .await
+            .expect("draw should be accepted");
+
+        assert_eq!(outcome.game.result, Some(ResultSide::Draw));
+        assert_eq!(outcome.game.termination, Some(Termination::Agreement));
+        let ratings = outcome.ratings.expect("agreed draws are rated");
+        assert!(ratings.white_delta() < 0, "higher rated player loses points on a draw");
+        assert_eq!(ratings.white_delta(), -ratings.black_delta());
+    }
+
+    #[async_std::test]
+    async fn rapid_second_draw_offer_is_rejected_until_the_cooldown_passes() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let limits = DrawOfferLimits { cooldown_moves: 3, max_offers: 3 };
+        let mut game = live_game(white, black, &["e4", "e5", "Nf3", "Nc6"]);
+        game.draw_offered_by = None;
+        game.white_draw_offers = 1;
+        game.white_last_draw_offer_ply = Some(2);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+        let result = offer_draw_with(&db, &limits, game.id, white).await;
+        match result {
+            Err(ApiError::Conflict(message)) => assert!(message.contains("in 2 moves"), "{}", message),
+            other => panic!("expected the offer to be throttled, got {:?}", other),
+        }
+
+        let mut later = live_game(white, black, &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6"]);
+        later.id = game.id;
+        later.draw_offered_by = None;
+        later.white_draw_offers = 1;
+        later.white_last_draw_offer_ply = Some(2);
+        let mut offered = later.clone();
+        offered.draw_offered_by = Some(white);
+        offered.white_draw_offers = 2;
+        offered.white_last_draw_offer_ply = Some(8);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![later]])
+            .append_query_results([vec![offered.clone()]])
+            .into_connection();
+        let game = offer_draw_with(&db, &limits, game.id, white)
+            .await
+            .expect("three moves after the last offer another one is allowed");
+
+        assert_eq!(game, offered);
+    }
+
+    #[async_std::test]
+    async fn draw_offers_beyond_the_cap_are_rejected() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut game = live_game(white, black, &["e4", "e5"]);
+        game.draw_offered_by = None;
+        game.black_draw_offers = 3;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = offer_draw_with(&db, &DrawOfferLimits::default(), game.id, black).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[async_std::test]
+    async fn draw_cannot_be_agreed_in_abort_window() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4"]);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = accept_draw_with(&db, &RatingEngine::default(), game.id, black).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[async_std::test]
+    async fn draw_cannot_be_agreed_after_game_ended() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut game = live_game(white, black, &["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]);
+        game.result = Some(ResultSide::White);
+        game.termination = Some(Termination::Checkmate);
+        game.ended_at = Some(Utc::now().into());
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = accept_draw_with(&db, &RatingEngine::default(), game.id, black).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[async_std::test]
+    async fn a_move_after_the_flag_loses_on_time() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = game::Model {
+            draw_offered_by: None,
+            clock_initial_secs: Some(60),
+            clock_increment_secs: Some(5),
+            white_remaining_ms: Some(1_000),
+            black_remaining_ms: Some(58_000),
+            last_move_at: Some((Utc::now() - chrono::Duration::seconds(5)).into()),
+            ..live_game(white, black, &["e4", "e5"])
+        };
+        let finished = game::Model {
+            result: Some(ResultSide::Black),
+            termination: Some(Termination::Timeout),
+            ended_at: Some(Utc::now().into()),
+            ..game.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()], vec![game.clone()]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1500), rating(black, 1500)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let result = apply_move_with(&db, &RatingEngine::default(), game.id, white, "Nf3").await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))), "{:?}", result);
+        let statements: Vec<String> = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .map(|s| s.to_string())
+            .collect();
+        assert!(statements.contains(&"ROLLBACK".to_string()), "the move's own transaction is rolled back");
+        let update = statements.iter().find(|s| s.starts_with("UPDATE")).unwrap();
+        assert!(update.contains("'timeout'") && !update.contains("Nf3"), "{}", update);
+    }
+
+    #[async_std::test]
+    async fn a_player_with_time_left_cannot_be_timed_out() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = game::Model {
+            clock_initial_secs: Some(60),
+            clock_increment_secs: Some(0),
+            white_remaining_ms: Some(30_000),
+            black_remaining_ms: Some(30_000),
+            last_move_at: Some(Utc::now().into()),
+            ..live_game(white, black, &["e4", "e5"])
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = time_out_with(&db, &RatingEngine::default(), game.id, white).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))), "{:?}", result);
+    }
+
+    #[async_std::test]
+    async fn resignation_awards_the_game_to_the_opponent() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+        let mut finished = game.clone();
+        finished.result = Some(ResultSide::Black);
+        finished.termination = Some(Termination::Resignation);
+        finished.draw_offered_by = None;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1500), rating(black, 1500)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let outcome = apply_action_with(&db, &RatingEngine::default(), game.id, white, GameAction::Resign)
+            .await
+            .expect("resignation should finalize the game");
+
+        assert_eq!(outcome.game().result, Some(ResultSide::Black));
+        let ActionOutcome::Finalized(finalized) = outcome else {
+            panic!("resignation must finalize the game");
+        };
+        assert_eq!(finalized.ratings.map(|r| r.black_delta()), Some(16));
+    }
+
+    #[async_std::test]
+    async fn rating_preview_matches_the_change_applied_on_finalization() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+        let engine = RatingEngine::default();
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1720), rating(black, 1480)]])
+            .into_connection();
+        let preview = crate::rating::rating_preview_with(&db, &engine, &game.id.to_string())
+            .await
+            .expect("a live game can be previewed");
+
+        let mut finished = game.clone();
+        finished.result = Some(ResultSide::Black);
+        finished.termination = Some(Termination::Resignation);
+        finished.draw_offered_by = None;
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1720), rating(black, 1480)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+        let ActionOutcome::Finalized(finalized) =
+            apply_action_with(&db, &engine, game.id, white, GameAction::Resign).await.unwrap()
+        else {
+            panic!("resignation must finalize the game");
+        };
+
+        let ratings = finalized.ratings.expect("the game is rated");
+        assert!(preview.rated);
+        assert_eq!(preview.white.loss, ratings.white_delta());
+        assert_eq!(preview.black.win, ratings.black_delta());
+        assert_eq!((preview.white.rating, preview.black.rating), (ratings.white_before, ratings.black_before));
+    }
+
+    #[async_std::test]
+    async fn games_with_a_guest_finish_unrated() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+        let mut finished = game.clone();
+        finished.result = Some(ResultSide::Black);
+        finished.termination = Some(Termination::Resignation);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(1i64))])]])
+            .into_connection();
+
+        let finalized = resign_with(&db, &RatingEngine::default(), game.id, white)
+            .await
+            .expect("guests can resign");
+
+        assert_eq!(finalized.game.result, Some(ResultSide::Black));
+        assert!(finalized.ratings.is_none());
+        let writes = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .filter(|s| s.sql.starts_with("INSERT"))
+            .count();
+        assert_eq!(writes, 0, "no rating rows are written");
+    }
+
+    #[async_std::test]
+    async fn abort_is_rejected_after_both_players_moved() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = abort_with(&db, &RatingEngine::default(), game.id, black).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    const KNIGHT_SHUFFLE: [&str; 8] = ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"];
+
+    #[async_std::test]
+    async fn repetition_claim_is_granted_when_history_confirms_it() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &KNIGHT_SHUFFLE);
+        let mut drawn = game.clone();
+        drawn.result = Some(ResultSide::Draw);
+        drawn.termination = Some(Termination::Repetition);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![drawn]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1500), rating(black, 1500)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let finalized = claim_draw_with(&db, &RatingEngine::default(), game.id, white, DrawClaim::Repetition)
+            .await
+            .expect("the starting position has occurred three times");
+
+        assert_eq!(finalized.game.result, Some(ResultSide::Draw));
+        assert_eq!(finalized.game.termination, Some(Termination::Repetition));
+    }
+
+    #[async_std::test]
+    async fn repetition_claim_is_rejected_when_history_disagrees() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &KNIGHT_SHUFFLE[..4]);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = claim_draw_with(&db, &RatingEngine::default(), game.id, white, DrawClaim::Repetition).await;
+        match result {
+            Err(ApiError::Conflict(message)) => assert!(message.contains("2 time(s)"), "{}", message),
+            other => panic!("expected the claim to be rejected, got {:?}", other),
+        }
+    }
+
+    #[async_std::test]
+    async fn move_is_stored_as_san_and_answers_opponents_offer() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut game = live_game(white, black, &["e4", "e5"]);
+        game.draw_offered_by = Some(black);
+        let mut moved = game.clone();
+        moved.pgn = json!({ "moves": ["e4", "e5", "Nf3"] });
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![moved]])
+            .into_connection();
+
+        let outcome = apply_move_with(&db, &RatingEngine::default(), game.id, white, "g1f3")
+            .await
+            .expect("a legal move should be applied");
+        assert_eq!(outcome.san, "Nf3");
+        assert!(outcome.finalized.is_none());
+
+        let log = db.into_transaction_log();
+        let update = log
+            .iter()
+            .flat_map(|t| t.statements())
+            .find(|s| s.sql.starts_with("UPDATE"))
+            .expect("the game row is updated");
+        let values = format!("{:?}", update.values);
+        assert!(values.contains("Nf3"), "move not stored: {}", values);
+        assert!(values.contains("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"));
+        assert!(!values.contains(&black.to_string()), "offer should be cleared: {}", values);
+    }
+
+    #[async_std::test]
+    async fn move_out_of_turn_is_rejected() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = apply_move_with(&db, &RatingEngine::default(), game.id, black, "d7d5").await;
+        assert!(matches!(result, Err(ApiError::NotYourTurn)));
+    }
+
+    #[test]
+    fn the_stored_fen_decides_whose_turn_it_is() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut game = live_game(white, black, &["e4"]);
+
+        assert_eq!(assert_players_turn(&game, black).unwrap(), Color::Black);
+        assert!(matches!(assert_players_turn(&game, white), Err(ApiError::NotYourTurn)));
+        assert!(matches!(assert_players_turn(&game, Uuid::new_v4()), Err(ApiError::Forbidden(_))));
+
+        // The FEN is the authority, not the number of moves played
+        game.fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 2".to_string();
+        assert_eq!(assert_players_turn(&game, white).unwrap(), Color::White);
+    }
+
+    #[async_std::test]
+    async fn illegal_move_is_a_validation_error() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .into_connection();
+
+        let result = apply_move_with(&db, &RatingEngine::default(), game.id, white, "e4e5").await;
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+
+    #[async_std::test]
+    async fn mating_move_finalizes_the_game() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["f3", "e5", "g4"]);
+        let mut moved = game.clone();
+        moved.pgn = json!({ "moves": ["f3", "e5", "g4", "Qh4#"] });
+        let mut finished = moved.clone();
+        finished.result = Some(ResultSide::Black);
+        finished.termination = Some(Termination::Checkmate);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![game.clone()]])
+            .append_query_results([vec![moved]])
+            .append_query_results([vec![finished]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1500), rating(black, 1500)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let outcome = apply_move_with(&db, &RatingEngine::default(), game.id, black, "Qh4")
+            .await
+            .expect("mate should be applied");
+
+        assert_eq!(outcome.san, "Qh4#");
+        assert_eq!(outcome.game.termination, Some(Termination::Checkmate));
+        let finalized = outcome.finalized.expect("checkmate ends the game");
+        assert_eq!(finalized.game.result, Some(ResultSide::Black));
+    }
+
+    #[test]
+    fn configured_stalemate_rule_overrides_the_variants_own() {
+        let stalemated: chess::position::Position = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1".parse().unwrap();
+
+        let own = rules_with(GameVariant::Crazyhouse, None);
+        let configured = rules_with(GameVariant::Crazyhouse, Some(StalemateRule::StalematedLoses));
+
+        assert_eq!(own.game_status(&stalemated), GameStatus::Stalemate(Outcome::Draw));
+        assert_eq!(configured.game_status(&stalemated), GameStatus::Stalemate(Outcome::Win(Color::White)));
+        assert_eq!(configured.name(), "Crazyhouse");
+    }
+
+    #[async_std::test]
+    async fn racing_finalizations_rate_the_game_once() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = live_game(white, black, &["e4", "e5"]);
+        let mut timed_out = game.clone();
+        timed_out.result = Some(ResultSide::White);
+        timed_out.termination = Some(Termination::Timeout);
+
+        // Both triggers read the game while it was live; the loser's guarded update
+        // finds no live row.
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![timed_out]])
+            .append_query_results([not_in_a_tournament()])
+            .append_query_results([vec![no_guests()]])
+            .append_query_results([vec![rating(white, 1500), rating(black, 1500)]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .append_query_results([Vec::<game::Model>::new()])
+            .into_connection();
+
+        let engine = RatingEngine::default();
+        let (sweep, resignation) = futures::join!(
+            finalize_with(&db, &engine, game.clone(), Some(ResultSide::White), Termination::Timeout),
+            finalize_with(&db, &engine, game.clone(), Some(ResultSide::Black), Termination::Resignation),
+        );
+
+        let winner = sweep.expect("the first finalization wins");
+        assert_eq!(winner.game.termination, Some(Termination::Timeout));
+        assert!(matches!(resignation, Err(ApiError::Conflict(_))));
+
+        let inserts = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            // Seeding missing rating rows before locking them writes nothing that counts
+            .filter(|s| s.sql.starts_with("INSERT") && !s.sql.ends_with("DO NOTHING"))
+            .count();
+        assert_eq!(inserts, 2, "ratings and history must be written exactly once");
+    }
+}
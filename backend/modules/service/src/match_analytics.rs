@@ -0,0 +1,203 @@
+use chrono::{DateTime, Duration, Utc};
+use db::db::db::get_db;
+use dto::admin::{AnalyticsBucketDTO, MatchAnalyticsDTO, MatchTypeAnalyticsDTO};
+use entity::match_analytics;
+use entity::sea_orm_active_enums::MatchType;
+use error::error::ApiError;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect, Set};
+use uuid::Uuid;
+
+pub const DEFAULT_WINDOW_HOURS: i64 = 24;
+
+/// Lower bounds of the rating gap buckets.
+const RATING_GAP_BUCKETS: [i64; 6] = [0, 25, 50, 100, 200, 400];
+/// Lower bounds of the wait time buckets, in milliseconds.
+const WAIT_TIME_BUCKETS_MS: [i64; 6] = [0, 10_000, 30_000, 60_000, 120_000, 300_000];
+
+/// Quality of a single pairing made by the matchmaker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchRecord {
+    pub match_id: Uuid,
+    pub match_type: MatchType,
+    pub rating_gap: i32,
+    /// Rating window the queued player had expanded to when paired; `None` outside rated play.
+    pub elo_window: Option<i32>,
+    /// How long the longer-waiting player spent in the queue.
+    pub wait_time_ms: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stores a pairing. The matchmaker spawns this rather than awaiting it, so a slow
+/// or failing write only loses the sample and never delays a match.
+pub async fn record_match(record: MatchRecord) {
+    let db = get_db().await;
+    let _ = record_match_with(&db, record).await;
+}
+
+pub async fn record_match_with<C: ConnectionTrait>(db: &C, record: MatchRecord) -> Result<(), ApiError> {
+    let row = match_analytics::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        match_id: Set(record.match_id),
+        match_type: Set(record.match_type),
+        rating_gap: Set(record.rating_gap),
+        elo_window: Set(record.elo_window),
+        wait_time_ms: Set(record.wait_time_ms),
+        created_at: Set(record.created_at.into()),
+    };
+    match_analytics::Entity::insert(row)
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn summarize(hours: Option<i64>) -> Result<MatchAnalyticsDTO, ApiError> {
+    let db = get_db().await;
+    let window_end = Utc::now();
+    let window_start = window_end - Duration::hours(hours.unwrap_or(DEFAULT_WINDOW_HOURS));
+    summarize_with(&db, window_start, window_end).await
+}
+
+/// Summarizes the pairings recorded in `[window_start, window_end)`.
+pub async fn summarize_with<C: ConnectionTrait>(
+    db: &C,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+) -> Result<MatchAnalyticsDTO, ApiError> {
+    let samples: Vec<(MatchType, i32, i64)> = match_analytics::Entity::find()
+        .select_only()
+        .column(match_analytics::Column::MatchType)
+        .column(match_analytics::Column::RatingGap)
+        .column(match_analytics::Column::WaitTimeMs)
+        .filter(match_analytics::Column::CreatedAt.gte(window_start))
+        .filter(match_analytics::Column::CreatedAt.lt(window_end))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(summarize_samples(window_start, window_end, &samples))
+}
+
+fn summarize_samples(
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    samples: &[(MatchType, i32, i64)],
+) -> MatchAnalyticsDTO {
+    let mut gaps: Vec<i64> = samples.iter().map(|(_, gap, _)| *gap as i64).collect();
+    let mut waits: Vec<i64> = samples.iter().map(|(_, _, wait)| *wait).collect();
+    gaps.sort_unstable();
+    waits.sort_unstable();
+
+    let by_match_type = [MatchType::Rated, MatchType::Casual, MatchType::Private]
+        .into_iter()
+        .filter_map(|match_type| {
+            let (gaps, waits): (Vec<i64>, Vec<i64>) = samples
+                .iter()
+                .filter(|(t, _, _)| *t == match_type)
+                .map(|(_, gap, wait)| (*gap as i64, *wait))
+                .unzip();
+            (!gaps.is_empty()).then(|| MatchTypeAnalyticsDTO {
+                match_type: serde_json::to_value(match_type)
+                    .ok()
+                    .and_then(|t| t.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+                matches: gaps.len() as u64,
+                avg_rating_gap: mean(&gaps),
+                avg_wait_time_ms: mean(&waits),
+            })
+        })
+        .collect();
+
+    MatchAnalyticsDTO {
+        window_start,
+        window_end,
+        total_matches: samples.len() as u64,
+        avg_rating_gap: mean(&gaps),
+        p90_rating_gap: percentile(&gaps, 90),
+        avg_wait_time_ms: mean(&waits),
+        p50_wait_time_ms: percentile(&waits, 50),
+        p90_wait_time_ms: percentile(&waits, 90),
+        rating_gap_distribution: histogram(&RATING_GAP_BUCKETS, &gaps),
+        wait_time_distribution: histogram(&WAIT_TIME_BUCKETS_MS, &waits),
+        by_match_type,
+    }
+}
+
+fn mean(values: &[i64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<i64>() as f64 / values.len() as f64
+}
+
+/// Nearest-rank percentile of already sorted `values`.
+fn percentile(sorted: &[i64], pct: usize) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn histogram(bounds: &[i64], values: &[i64]) -> Vec<AnalyticsBucketDTO> {
+    bounds
+        .iter()
+        .enumerate()
+        .map(|(i, &min)| {
+            let max = bounds.get(i + 1).copied();
+            let count = values
+                .iter()
+                .filter(|&&v| v >= min && max.is_none_or(|max| v < max))
+                .count() as u64;
+            AnalyticsBucketDTO { min, max, count }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_reports_averages_and_distributions() {
+        let end = Utc::now();
+        let start = end - Duration::hours(DEFAULT_WINDOW_HOURS);
+        let samples = [
+            (MatchType::Rated, 20, 5_000),
+            (MatchType::Rated, 180, 90_000),
+            (MatchType::Rated, 450, 400_000),
+            (MatchType::Casual, 300, 1_000),
+        ];
+
+        let summary = summarize_samples(start, end, &samples);
+
+        assert_eq!(summary.total_matches, 4);
+        assert_eq!(summary.avg_rating_gap, 237.5);
+        assert_eq!(summary.avg_wait_time_ms, 124_000.0);
+        assert_eq!(summary.p50_wait_time_ms, 5_000);
+        assert_eq!(summary.p90_wait_time_ms, 400_000);
+        assert_eq!(summary.p90_rating_gap, 450);
+
+        let gap_counts: Vec<u64> = summary.rating_gap_distribution.iter().map(|b| b.count).collect();
+        assert_eq!(gap_counts, [1, 0, 0, 1, 1, 1]);
+        assert_eq!(summary.rating_gap_distribution.last().unwrap().max, None);
+        let wait_counts: Vec<u64> = summary.wait_time_distribution.iter().map(|b| b.count).collect();
+        assert_eq!(wait_counts, [2, 0, 0, 1, 0, 1]);
+
+        assert_eq!(summary.by_match_type.len(), 2);
+        assert_eq!(summary.by_match_type[0].match_type, "rated");
+        assert_eq!(summary.by_match_type[0].matches, 3);
+        assert_eq!(summary.by_match_type[1].avg_rating_gap, 300.0);
+    }
+
+    #[test]
+    fn empty_window_summarizes_to_zeroes() {
+        let end = Utc::now();
+        let summary = summarize_samples(end - Duration::hours(1), end, &[]);
+
+        assert_eq!(summary.total_matches, 0);
+        assert_eq!(summary.avg_wait_time_ms, 0.0);
+        assert_eq!(summary.p90_wait_time_ms, 0);
+        assert!(summary.rating_gap_distribution.iter().all(|b| b.count == 0));
+        assert!(summary.by_match_type.is_empty());
+    }
+}
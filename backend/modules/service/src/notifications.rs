@@ -0,0 +1,368 @@
+use chrono::Utc;
+use db::db::db::get_db;
+use dto::notifications::{
+    NotificationEvent, NotificationPreferencesDTO, UpdateNotificationPreferences,
+};
+use entity::{game, notification_preference};
+use entity::sea_orm_active_enums::NotificationChannel;
+use error::error::ApiError;
+use sea_orm::{ConnectionTrait, EntityTrait, Set, sea_query::OnConflict};
+use serde_json::json;
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::players::player_exists;
+use crate::webhooks;
+
+/// A notification waiting to be dispatched: recipient, event, idempotency key and payload.
+pub type Pending = (Uuid, NotificationEvent, String, serde_json::Value);
+
+/// A notification routed to one of the player's channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub player_id: Uuid,
+    pub event: NotificationEvent,
+    pub channel: NotificationChannel,
+    /// Webhook URL for `Webhook` deliveries.
+    pub target: Option<String>,
+    /// Identifies the event, so it is delivered to the player at most once however
+    /// often it is raised.
+    pub idempotency_key: String,
+    pub payload: serde_json::Value,
+}
+
+/// Transport for routed notifications. Delivery failures are the transport's to
+/// handle; the dispatcher only decides whether and where to send.
+pub trait Notifier {
+    fn send(&self, notification: Notification) -> impl Future<Output = ()> + Send;
+}
+
+/// Writes each notification to stdout until an email transport exists.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: Notification) {
+        println!(
+            "Notify {} of {:?} via {:?}: {}",
+            notification.player_id, notification.event, notification.channel, notification.payload
+        );
+    }
+}
+
+/// Queues webhook notifications for the delivery worker and logs the rest.
+pub struct OutboxNotifier;
+
+impl Notifier for OutboxNotifier {
+    async fn send(&self, notification: Notification) {
+        if notification.channel != NotificationChannel::Webhook {
+            return LogNotifier.send(notification).await;
+        }
+        let db = get_db().await;
+        if let Err(err) = webhooks::enqueue_with(&db, &notification).await {
+            log::warn!(
+                "Failed to queue {:?} webhook for {}: {}",
+                notification.event, notification.player_id, err
+            );
+        }
+    }
+}
+
+async fn stored_preferences<C: ConnectionTrait>(
+    db: &C,
+    player_id: Uuid,
+) -> Result<NotificationPreferencesDTO, ApiError> {
+    Ok(notification_preference::Entity::find_by_id(player_id)
+        .one(db)
+        .await?
+        .map(NotificationPreferencesDTO::from)
+        .unwrap_or_default())
+}
+
+pub async fn preferences(player_id: Uuid) -> Result<NotificationPreferencesDTO, ApiError> {
+    let db = get_db().await;
+    preferences_with(&db, player_id).await
+}
+
+pub async fn preferences_with<C: ConnectionTrait>(
+    db: &C,
+    player_id: Uuid,
+) -> Result<NotificationPreferencesDTO, ApiError> {
+    if !player_exists(db, player_id).await? {
+        return Err(ApiError::NotFound(format!("Player {}", player_id)));
+    }
+    stored_preferences(db, player_id).await
+}
+
+pub async fn update_preferences(
+    player_id: Uuid,
+    update: UpdateNotificationPreferences,
+) -> Result<NotificationPreferencesDTO, ApiError> {
+    let db = get_db().await;
+    update_preferences_with(&db, player_id, update).await
+}
+
+/// Merges `update` into the player's preferences and stores the result.
+pub async fn update_preferences_with<C: ConnectionTrait>(
+    db: &C,
+    player_id: Uuid,
+    update: UpdateNotificationPreferences,
+) -> Result<NotificationPreferencesDTO, ApiError> {
+    let mut prefs = preferences_with(db, player_id).await?;
+    prefs.correspondence_turn = update
+        .correspondence_turn
+        .unwrap_or(prefs.correspondence_turn);
+    prefs.game_result = update.game_result.unwrap_or(prefs.game_result);
+    prefs.draw_offer = update.draw_offer.unwrap_or(prefs.draw_offer);
+    prefs.tournament_start = update.tournament_start.unwrap_or(prefs.tournament_start);
+    if update.webhook_url.is_some() {
+        prefs.webhook_url = update.webhook_url;
+    }
+
+    let uses_webhook = NotificationEvent::ALL
+        .iter()
+        .any(|event| prefs.channel(*event) == NotificationChannel::Webhook);
+    if uses_webhook && prefs.webhook_url.is_none() {
+        let mut error = ValidationError::new("webhook_url_required");
+        error.message = Some("A webhook URL is required to route events to a webhook".into());
+        let mut errors = ValidationErrors::new();
+        errors.add("webhook_url", error);
+        return Err(ApiError::ValidationError(errors));
+    }
+
+    let row = notification_preference::ActiveModel {
+        player_id: Set(player_id),
+        correspondence_turn: Set(prefs.correspondence_turn),
+        game_result: Set(prefs.game_result),
+        draw_offer: Set(prefs.draw_offer),
+        tournament_start: Set(prefs.tournament_start),
+        webhook_url: Set(prefs.webhook_url.clone()),
+        updated_at: Set(Utc::now().into()),
+    };
+    notification_preference::Entity::insert(row)
+        .on_conflict(
+            OnConflict::column(notification_preference::Column::PlayerId)
+                .update_columns([
+                    notification_preference::Column::CorrespondenceTurn,
+                    notification_preference::Column::GameResult,
+                    notification_preference::Column::DrawOffer,
+                    notification_preference::Column::TournamentStart,
+                    notification_preference::Column::WebhookUrl,
+                    notification_preference::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(prefs)
+}
+
+/// Channel and target `event` goes to, or `None` when the player opted out of it.
+pub fn route(
+    prefs: &NotificationPreferencesDTO,
+    event: NotificationEvent,
+) -> Option<(NotificationChannel, Option<String>)> {
+    match prefs.channel(event) {
+        NotificationChannel::None => None,
+        NotificationChannel::Email => Some((NotificationChannel::Email, None)),
+        // Preferences are validated on write, but never post to a missing URL
+        NotificationChannel::Webhook => prefs
+            .webhook_url
+            .clone()
+            .map(|url| (NotificationChannel::Webhook, Some(url))),
+    }
+}
+
+/// Sends `event` to `player_id` if their preferences ask for it. Returns whether a
+/// notification was sent.
+pub async fn dispatch_with<C: ConnectionTrait, N: Notifier>(
+    db: &C,
+    notifier: &N,
+    player_id: Uuid,
+    event: NotificationEvent,
+    idempotency_key: String,
+    payload: serde_json::Value,
+) -> Result<bool, ApiError> {
+    let prefs = stored_preferences(db, player_id).await?;
+    let Some((channel, target)) = route(&prefs, event) else {
+        return Ok(false);
+    };
+
+    notifier
+        .send(Notification {
+            player_id,
+            event,
+            channel,
+            target,
+            idempotency_key,
+            payload,
+        })
+        .await;
+    Ok(true)
+}
+
+/// Fire-and-forget entry point for event sources; failures are logged, never raised.
+pub async fn notify(
+    player_id: Uuid,
+    event: NotificationEvent,
+    idempotency_key: String,
+    payload: serde_json::Value,
+) {
+    let db = get_db().await;
+    if let Err(err) = dispatch_with(&db, &OutboxNotifier, player_id, event, idempotency_key, payload).await {
+        log::warn!("Failed to notify {} of {:?}: {}", player_id, event, err);
+    }
+}
+
+/// Dispatches `pending` one after another in the background, so webhooks keep the
+/// order the events happened in. Nothing is spawned for an empty batch.
+pub fn queue(pending: Vec<Pending>) {
+    if pending.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        for (player_id, event, key, payload) in pending {
+            notify(player_id, event, key, payload).await;
+        }
+    });
+}
+
+/// Tells both players of each game of a tournament round that the round has started.
+pub fn round_start_notifications(games: &[game::Model]) -> Vec<Pending> {
+    games
+        .iter()
+        .flat_map(|game| {
+            [(game.white_player, game.black_player), (game.black_player, game.white_player)].map(
+                |(player_id, opponent)| {
+                    let payload = json!({ "game_id": game.id, "opponent": opponent });
+                    (player_id, NotificationEvent::TournamentStart, format!("tournament_start:{}", game.id), payload)
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, Value};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: Mutex<Vec<Notification>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        async fn send(&self, notification: Notification) {
+            self.sent.lock().unwrap().push(notification);
+        }
+    }
+
+    fn stored(
+        player_id: Uuid,
+        draw_offer: NotificationChannel,
+        webhook_url: Option<&str>,
+    ) -> notification_preference::Model {
+        notification_preference::Model {
+            player_id,
+            correspondence_turn: NotificationChannel::Email,
+            game_result: NotificationChannel::None,
+            draw_offer,
+            tournament_start: NotificationChannel::Email,
+            webhook_url: webhook_url.map(str::to_string),
+            updated_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn defaults_only_notify_about_turns_and_tournaments() {
+        let prefs = NotificationPreferencesDTO::default();
+
+        assert_eq!(
+            route(&prefs, NotificationEvent::CorrespondenceTurn),
+            Some((NotificationChannel::Email, None))
+        );
+        assert_eq!(
+            route(&prefs, NotificationEvent::TournamentStart),
+            Some((NotificationChannel::Email, None))
+        );
+        assert_eq!(route(&prefs, NotificationEvent::GameResult), None);
+        assert_eq!(route(&prefs, NotificationEvent::DrawOffer), None);
+    }
+
+    #[async_std::test]
+    async fn dispatcher_routes_by_stored_preferences() {
+        let player_id = Uuid::new_v4();
+        let url = "https://example.com/hooks";
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![stored(
+                player_id,
+                NotificationChannel::Webhook,
+                Some(url),
+            )]])
+            .append_query_results([vec![stored(player_id, NotificationChannel::None, None)]])
+            .into_connection();
+        let notifier = RecordingNotifier::default();
+
+        let sent = dispatch_with(
+            &db,
+            &notifier,
+            player_id,
+            NotificationEvent::DrawOffer,
+            "draw_offer:1".to_string(),
+            json!({}),
+        )
+        .await
+        .unwrap();
+        let muted = dispatch_with(
+            &db,
+            &notifier,
+            player_id,
+            NotificationEvent::DrawOffer,
+            "draw_offer:1".to_string(),
+            json!({}),
+        )
+        .await
+        .unwrap();
+
+        assert!(sent && !muted);
+        let sent = notifier.sent.into_inner().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].channel, NotificationChannel::Webhook);
+        assert_eq!(sent[0].target.as_deref(), Some(url));
+    }
+
+    #[test]
+    fn a_started_round_notifies_both_players_of_every_board() {
+        let (a, b, c, d) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let games = [game::Model::fixture(a, b), game::Model::fixture(c, d)];
+
+        let pending = round_start_notifications(&games);
+
+        let recipients: Vec<Uuid> = pending.iter().map(|(player_id, ..)| *player_id).collect();
+        assert_eq!(recipients, vec![a, b, c, d]);
+        assert!(pending.iter().all(|(_, event, ..)| *event == NotificationEvent::TournamentStart));
+        let (_, _, key, payload) = &pending[1];
+        assert_eq!(key, &format!("tournament_start:{}", games[0].id));
+        assert_eq!(payload, &json!({ "game_id": games[0].id, "opponent": a }));
+    }
+
+    #[async_std::test]
+    async fn webhook_channel_requires_a_url() {
+        let player_id = Uuid::new_v4();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(1i64))])]])
+            .append_query_results([Vec::<notification_preference::Model>::new()])
+            .into_connection();
+        let update = UpdateNotificationPreferences {
+            game_result: Some(NotificationChannel::Webhook),
+            ..Default::default()
+        };
+
+        let result = update_preferences_with(&db, player_id, update).await;
+
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+}
@@ -0,0 +1,45 @@
+use dto::pagination::Page;
+use error::error::ApiError;
+use sea_orm::{ConnectionTrait, PaginatorTrait, SelectorTrait};
+
+pub const DEFAULT_PAGE_SIZE: u64 = 10;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Normalizes client supplied `page`/`limit` values into a 1-based page and a bounded size.
+pub fn page_bounds(page: Option<i32>, limit: Option<i32>) -> (u64, u64) {
+    let page = page.unwrap_or(1).max(1) as u64;
+    let page_size = limit
+        .map(|limit| (limit.max(1) as u64).min(MAX_PAGE_SIZE))
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    (page, page_size)
+}
+
+/// Runs `query` through a sea-orm paginator and wraps the requested page in a `Page`.
+pub async fn fetch_page<'db, C, S>(
+    query: S,
+    db: &'db C,
+    page: u64,
+    page_size: u64,
+) -> Result<Page<<S::Selector as SelectorTrait>::Item>, ApiError>
+where
+    C: ConnectionTrait,
+    S: PaginatorTrait<'db, C>,
+{
+    let paginator = query.paginate(db, page_size);
+    let total = paginator.num_items().await?;
+    let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+
+    Ok(Page::new(items, total, page, page_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_bounds_are_clamped() {
+        assert_eq!(page_bounds(None, None), (1, DEFAULT_PAGE_SIZE));
+        assert_eq!(page_bounds(Some(0), Some(0)), (1, 1));
+        assert_eq!(page_bounds(Some(3), Some(500)), (3, MAX_PAGE_SIZE));
+    }
+}
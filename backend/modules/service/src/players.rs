@@ -1,34 +1,52 @@
-use crate::helper::password;
+use crate::helper::password::PasswordHasher;
 use db::db::db::get_db;
-use dto::players::{NewPlayer, UpdatePlayer};
+use dto::{
+    country::canonical_country,
+    players::{ChangePassword, NewPlayer, UpdatePlayer},
+    username::normalize_username,
+};
 use entity::player::{self, Model};
 use error::error::ApiError;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, Set,
+    sea_query::{Expr, Func},
+};
 use uuid::Uuid;
 
-async fn is_username_taken(username: String) -> bool {
-    let db = get_db().await;
-
-    let user = player::Entity::find()
-        .filter(player::Column::Username.eq(username))
-        .one(&db)
-        .await
-        .unwrap();
+/// Returns `true` if at least one enabled player matches `id`, without loading the row.
+pub async fn player_exists<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<bool, ApiError> {
+    let count = player::Entity::find()
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsEnabled.eq(true))
+        .count(db)
+        .await?;
 
-    user.is_some()
+    Ok(count > 0)
 }
 
-async fn is_email_taken(email: String) -> bool {
-    let db = get_db().await;
+/// Usernames are unique regardless of case, so `Magnus` is taken once `magnus` is.
+pub(crate) async fn username_exists<C: ConnectionTrait>(
+    db: &C,
+    username: &str,
+    excluding: Option<Uuid>,
+) -> Result<bool, ApiError> {
+    let mut query = player::Entity::find().filter(
+        Expr::expr(Func::lower(Expr::col(player::Column::Username))).eq(normalize_username(username)),
+    );
+    if let Some(id) = excluding {
+        query = query.filter(player::Column::Id.ne(id));
+    }
 
-    match player::Entity::find()
+    Ok(query.count(db).await? > 0)
+}
+
+pub(crate) async fn email_exists<C: ConnectionTrait>(db: &C, email: &str) -> Result<bool, ApiError> {
+    let count = player::Entity::find()
         .filter(player::Column::Email.eq(email))
-        .one(&db)
-        .await
-    {
-        Ok(user) => user.is_some(),
-        Err(_) => false, // Just assume user does not exist
-    }
+        .count(db)
+        .await?;
+
+    Ok(count > 0)
 }
 
 pub async fn find_player_by_id(id: Uuid) -> Result<player::Model, ApiError> {
@@ -61,22 +79,37 @@ pub async fn get_player_by_username(username: String) -> Result<Option<Model>, A
 }
 
 pub async fn add_player(payload: NewPlayer) -> Result<player::Model, ApiError> {
-    let email_available = is_email_taken(payload.email.clone()).await;
-    let username_available = is_username_taken(payload.username.clone()).await;
-    if email_available && username_available {
-        return Err(ApiError::InvalidCredentials);
+    let db = get_db().await;
+    add_player_with(&db, &PasswordHasher::from_env(), payload).await
+}
+
+/// Registers a player; a username or email someone already has is a `Conflict`.
+pub async fn add_player_with<C: ConnectionTrait>(
+    db: &C,
+    hasher: &PasswordHasher,
+    payload: NewPlayer,
+) -> Result<player::Model, ApiError> {
+    if username_exists(db, &payload.username, None).await? {
+        return Err(ApiError::Conflict(format!("Username {} is already taken", payload.username)));
+    }
+    if email_exists(db, &payload.email).await? {
+        return Err(ApiError::Conflict(format!("Email {} is already registered", payload.email)));
     }
     let new_player = player::ActiveModel {
         id: Set(Uuid::new_v4()),
         username: Set(payload.username),
         email: Set(payload.email),
-        password_hash: Set(password::hash_password(&payload.password)?.into_bytes()),
+        password_hash: Set(hasher.hash(&payload.password)?.into_bytes()),
         real_name: Set(payload.real_name),
+        country: Set(payload
+            .country
+            .as_deref()
+            .and_then(canonical_country)
+            .map(str::to_string)),
         ..Default::default()
     };
 
-    let db = get_db().await;
-    let new_player = new_player.insert(&db).await;
+    let new_player = new_player.insert(db).await;
 
     match new_player {
         Ok(plyr) => Ok(plyr),
@@ -84,11 +117,115 @@ pub async fn add_player(payload: NewPlayer) -> Result<player::Model, ApiError> {
     }
 }
 
+/// Verified against when the username is unknown; made with the default cost.
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$Lp2tmJelrYCUt17Tnf2LDA$KcY4CXIgpy2Q2fMTw58Dowx8qC+HESD7ZtOZkm9EGWM";
+
+/// Maps a failed verification to `InvalidCredentials`, keeping malformed stored
+/// hashes distinguishable as a server-side error.
+fn verify_stored(hasher: &PasswordHasher, password: &str, stored: &[u8]) -> Result<(), ApiError> {
+    let stored = std::str::from_utf8(stored).map_err(|_| ApiError::InvalidCredentials)?;
+    match hasher.verify(password, stored) {
+        Ok(()) => Ok(()),
+        Err(argon2::password_hash::Error::Password) => Err(ApiError::InvalidCredentials),
+        Err(err) => Err(ApiError::PasswordHashError(err)),
+    }
+}
+
+pub async fn authenticate(username: &str, password: &str) -> Result<player::Model, ApiError> {
+    let db = get_db().await;
+    authenticate_with(&db, &PasswordHasher::from_env(), username, password).await
+}
+
+/// Verifies a login and, if the stored hash predates the configured parameters,
+/// replaces it with a fresh one while the plaintext is at hand.
+pub async fn authenticate_with<C: ConnectionTrait>(
+    db: &C,
+    hasher: &PasswordHasher,
+    username: &str,
+    password: &str,
+) -> Result<player::Model, ApiError> {
+    // Guests have no password to log in with
+    let user = player::Entity::find()
+        .filter(player::Column::Username.eq(username))
+        .filter(player::Column::IsEnabled.eq(true))
+        .filter(player::Column::IsGuest.eq(false))
+        .one(db)
+        .await?;
+    let Some(mut user) = user else {
+        // Spend the same Argon2 work as a real check so the response time does not
+        // reveal whether the username exists
+        let _ = hasher.verify(password, DUMMY_HASH);
+        return Err(ApiError::InvalidCredentials);
+    };
+
+    verify_stored(hasher, password, &user.password_hash)?;
+
+    let outdated = std::str::from_utf8(&user.password_hash).is_ok_and(|h| hasher.needs_rehash(h));
+    if outdated {
+        let rehashed = hasher.hash(password)?.into_bytes();
+        // Guarded on the old hash so a concurrent password change is never overwritten.
+        // The upgrade is best-effort: the old hash still verifies on the next login.
+        let upgraded = player::Entity::update_many()
+            .col_expr(player::Column::PasswordHash, Expr::value(rehashed.clone()))
+            .filter(player::Column::Id.eq(user.id))
+            .filter(player::Column::PasswordHash.eq(user.password_hash.clone()))
+            .exec(db)
+            .await;
+        if matches!(upgraded, Ok(ref res) if res.rows_affected > 0) {
+            user.password_hash = rehashed;
+        }
+    }
+
+    Ok(user)
+}
+
+pub async fn change_password(id: Uuid, payload: ChangePassword) -> Result<(), ApiError> {
+    let db = get_db().await;
+    change_password_with(&db, &PasswordHasher::from_env(), id, payload).await
+}
+
+/// Replaces the password after checking the current one.
+pub async fn change_password_with<C: ConnectionTrait>(
+    db: &C,
+    hasher: &PasswordHasher,
+    id: Uuid,
+    payload: ChangePassword,
+) -> Result<(), ApiError> {
+    let user = player::Entity::find()
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsEnabled.eq(true))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Player {}", id)))?;
+
+    verify_stored(hasher, &payload.current_password, &user.password_hash)?;
+
+    player::Entity::update_many()
+        .col_expr(
+            player::Column::PasswordHash,
+            Expr::value(hasher.hash(&payload.new_password)?.into_bytes()),
+        )
+        .filter(player::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
 pub async fn update_player(id: Uuid, payload: UpdatePlayer) -> Result<player::Model, ApiError> {
     let db = get_db().await;
-    let existing_player = find_player_by_id(id).await?;
+    update_player_with(&db, id, payload).await
+}
 
-    let mut active_model: player::ActiveModel = existing_player.clone().into();
+/// Applies `payload` with a single `UPDATE ... RETURNING`; an empty result means the
+/// player is missing or disabled, so no existence check is needed beforehand.
+pub async fn update_player_with<C: ConnectionTrait>(
+    db: &C,
+    id: Uuid,
+    payload: UpdatePlayer,
+) -> Result<player::Model, ApiError> {
+    let mut active_model = <player::ActiveModel as Default>::default();
 
     if let Some(biography) = payload.biography {
         active_model.biography = Set(Some(biography));
@@ -96,8 +233,8 @@ pub async fn update_player(id: Uuid, payload: UpdatePlayer) -> Result<player::Mo
     if let Some(real_name) = payload.real_name {
         active_model.real_name = Set(real_name);
     }
-    if let Some(country) = payload.country {
-        active_model.country = Set(Some(country));
+    if let Some(country) = payload.country.as_deref().and_then(canonical_country) {
+        active_model.country = Set(Some(country.to_string()));
     }
     if let Some(flair) = payload.flair {
         active_model.flair = Set(Some(flair));
@@ -111,40 +248,227 @@ pub async fn update_player(id: Uuid, payload: UpdatePlayer) -> Result<player::Mo
     if let Some(social_links) = payload.social_links {
         active_model.social_links = Set(Some(social_links));
     }
-    if let Some(ref username) = payload.username {
-        let existing_username = get_player_by_username(username.clone()).await?;
-        match existing_username {
-            Some(ref user) => {
-                if user.email == existing_player.email {
-                    active_model.username = Set(username.clone());
-                }
-            }
-            None => {
-                active_model.username = Set(username.clone());
-            }
+    if let Some(username) = payload.username {
+        if username_exists(db, &username, Some(id)).await? {
+            return Err(ApiError::Conflict(format!("Username {} is already taken", username)));
         }
+        active_model.username = Set(username);
+    }
+
+    if !active_model.is_changed() {
+        let user = player::Entity::find()
+            .filter(player::Column::Id.eq(id))
+            .filter(player::Column::IsEnabled.eq(true))
+            .one(db)
+            .await?;
+
+        return user.ok_or_else(|| ApiError::NotFound(format!("Player {}", id)));
     }
 
-    let updated_player = active_model
-        .update(&db)
+    let mut updated = player::Entity::update_many()
+        .set(active_model)
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsEnabled.eq(true))
+        .exec_with_returning(db)
         .await
         .map_err(ApiError::DatabaseError)?;
 
-    Ok(updated_player)
+    match updated.pop() {
+        Some(plyr) => Ok(plyr),
+        None => Err(ApiError::NotFound(format!("Player {}", id))),
+    }
 }
 
 pub async fn delete_player(id: Uuid) -> Result<(), ApiError> {
     let db = get_db().await;
-    let existing_player = find_player_by_id(id).await?;
-
-    let mut active_model: player::ActiveModel = existing_player.into();
-
-    active_model.is_enabled = Set(false);
+    delete_player_with(&db, id).await
+}
 
-    active_model
-        .update(&db)
+pub async fn delete_player_with<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<(), ApiError> {
+    let result = player::Entity::update_many()
+        .col_expr(player::Column::IsEnabled, Expr::value(false))
+        .filter(player::Column::Id.eq(id))
+        .filter(player::Column::IsEnabled.eq(true))
+        .exec(db)
         .await
         .map_err(ApiError::DatabaseError)?;
 
+    if result.rows_affected == 0 {
+        return Err(ApiError::NotFound(format!("Player {}", id)));
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+
+    fn rename_payload() -> UpdatePlayer {
+        UpdatePlayer {
+            username: None,
+            real_name: Some("Renamed Player".to_string()),
+            biography: None,
+            country: None,
+            flair: None,
+            location: None,
+            fide_rating: None,
+            social_links: None,
+        }
+    }
+
+    #[async_std::test]
+    async fn update_missing_player_is_not_found_without_select() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<player::Model>::new()])
+            .into_connection();
+
+        let result = update_player_with(&db, Uuid::new_v4(), rename_payload()).await;
+
+        let err = result.expect_err("missing player must not update");
+        assert!(matches!(err, ApiError::NotFound(_)));
+        assert_eq!(err.error_response().status().as_u16(), 404);
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1, "expected a single round-trip");
+        let sql = &log[0].statements()[0].sql;
+        assert!(sql.starts_with("UPDATE"), "unexpected statement: {}", sql);
+        assert!(!sql.contains("SELECT"), "no prior select expected: {}", sql);
+    }
+
+    #[async_std::test]
+    async fn renaming_to_a_taken_username_is_a_conflict() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(1i64))])]])
+            .into_connection();
+        let payload = UpdatePlayer { username: Some("Alice".to_string()), ..rename_payload() };
+
+        let result = update_player_with(&db, Uuid::new_v4(), payload).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+        // Nothing is written once the username is known to be taken
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn registering_a_taken_username_or_email_is_a_conflict() {
+        let count = |n: i64| vec![BTreeMap::from([("num_items", Value::from(n))])];
+        let taken_username = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([count(1)])
+            .into_connection();
+        let taken_email = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([count(0), count(1)])
+            .into_connection();
+
+        for db in [taken_username, taken_email] {
+            let result = add_player_with(&db, &cheap_hasher(1024), NewPlayer::test_player()).await;
+            assert!(matches!(result, Err(ApiError::Conflict(_))), "{:?}", result);
+        }
+    }
+
+    fn cheap_hasher(memory_kib: u32) -> PasswordHasher {
+        PasswordHasher::new(memory_kib, 1, 1).unwrap()
+    }
+
+    fn player_with_hash(hash: &str) -> player::Model {
+        player::Model {
+            id: Uuid::new_v4(),
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password_hash: hash.as_bytes().to_vec(),
+            biography: None,
+            country: Some("XX".to_string()),
+            flair: None,
+            real_name: "Alice".to_string(),
+            location: None,
+            fide_rating: None,
+            social_links: None,
+            is_enabled: true,
+            is_guest: false,
+            last_seen_at: chrono::Utc::now().into(),
+        }
+    }
+
+    #[async_std::test]
+    async fn login_with_current_parameters_does_not_rehash() {
+        let hasher = cheap_hasher(1024);
+        let user = player_with_hash(&hasher.hash("Secret_pass1!").unwrap());
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![user.clone()]])
+            .into_connection();
+
+        let authenticated = authenticate_with(&db, &hasher, "alice", "Secret_pass1!").await.unwrap();
+
+        assert_eq!(authenticated, user);
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn login_with_wrong_password_is_rejected() {
+        let hasher = cheap_hasher(1024);
+        let user = player_with_hash(&hasher.hash("Secret_pass1!").unwrap());
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![user]])
+            .into_connection();
+
+        let result = authenticate_with(&db, &hasher, "alice", "Wrong_pass1!").await;
+
+        assert!(matches!(result, Err(ApiError::InvalidCredentials)));
+    }
+
+    #[async_std::test]
+    async fn login_as_unknown_user_is_rejected_like_a_wrong_password() {
+        let hasher = cheap_hasher(1024);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<player::Model>::new()])
+            .into_connection();
+
+        let result = authenticate_with(&db, &hasher, "nobody", "Secret_pass1!").await;
+
+        assert!(matches!(result, Err(ApiError::InvalidCredentials)));
+        // The dummy hash must parse, or the unknown-user path would skip the Argon2 work
+        assert_eq!(
+            hasher.verify("Secret_pass1!", DUMMY_HASH),
+            Err(argon2::password_hash::Error::Password)
+        );
+    }
+
+    #[async_std::test]
+    async fn login_rehashes_when_parameters_change() {
+        let old_hash = cheap_hasher(1024).hash("Secret_pass1!").unwrap();
+        let hasher = cheap_hasher(2048);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![player_with_hash(&old_hash)]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+
+        let user = authenticate_with(&db, &hasher, "alice", "Secret_pass1!").await.unwrap();
+
+        let new_hash = String::from_utf8(user.password_hash).unwrap();
+        assert!(new_hash.starts_with("$argon2id$v=19$m=2048,t=1,p=1$"));
+        assert!(hasher.verify("Secret_pass1!", &new_hash).is_ok());
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2);
+        assert!(log[1].statements()[0].sql.starts_with("UPDATE"));
+    }
+
+    #[async_std::test]
+    async fn delete_missing_player_is_not_found() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 0,
+            }])
+            .into_connection();
+
+        let result = delete_player_with(&db, Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+}
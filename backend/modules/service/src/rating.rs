@@ -0,0 +1,545 @@
+use chrono::{DateTime, Utc};
+use db::db::db::get_db;
+use dto::games::{OutcomeDeltas, RatingPreview};
+use entity::sea_orm_active_enums::ResultSide;
+use entity::{game, player_rating, rating_history};
+use error::error::ApiError;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, Insert, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, OnConflict},
+};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use uuid::Uuid;
+
+use crate::games::find_game_with;
+use crate::guests::{guests_among, includes_guest};
+use crate::lifecycle::is_terminal;
+
+/// Rating assigned to players without any rated games.
+pub const DEFAULT_RATING: i32 = 1500;
+const DEFAULT_K_FACTOR: f64 = 32.0;
+
+/// Elo rating engine.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingEngine {
+    pub k_factor: f64,
+}
+
+impl Default for RatingEngine {
+    fn default() -> Self {
+        Self { k_factor: DEFAULT_K_FACTOR }
+    }
+}
+
+impl RatingEngine {
+    /// Reads the K-factor from `RATING_K_FACTOR`, falling back to 32.
+    pub fn from_env() -> Self {
+        let k_factor = env::var("RATING_K_FACTOR")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|k: &f64| *k > 0.0)
+            .unwrap_or(DEFAULT_K_FACTOR);
+        Self { k_factor }
+    }
+
+    /// Expected score of a player rated `rating` against `opponent`.
+    pub fn expected_score(rating: i32, opponent: i32) -> f64 {
+        1.0 / (1.0 + 10f64.powf((opponent - rating) as f64 / 400.0))
+    }
+
+    /// Returns the new `(white, black)` ratings after a game.
+    ///
+    /// A single rounded delta is applied to both sides so rating points are
+    /// conserved: whatever white gains, black loses.
+    pub fn rate(&self, white: i32, black: i32, result: ResultSide) -> (i32, i32) {
+        let score = match result {
+            ResultSide::White => 1.0,
+            ResultSide::Black => 0.0,
+            ResultSide::Draw => 0.5,
+        };
+        let delta = (self.k_factor * (score - Self::expected_score(white, black))).round() as i32;
+        (white + delta, black - delta)
+    }
+}
+
+/// Ratings of both players before and after a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatingUpdate {
+    pub white_before: i32,
+    pub white_after: i32,
+    pub black_before: i32,
+    pub black_after: i32,
+}
+
+impl RatingUpdate {
+    pub fn white_delta(&self) -> i32 {
+        self.white_after - self.white_before
+    }
+
+    pub fn black_delta(&self) -> i32 {
+        self.black_after - self.black_before
+    }
+}
+
+/// Insert of `ratings` that overwrites the rows of players who are already rated.
+fn upsert_ratings<I>(ratings: I) -> Insert<player_rating::ActiveModel>
+where
+    I: IntoIterator<Item = player_rating::ActiveModel>,
+{
+    player_rating::Entity::insert_many(ratings).on_conflict(
+        OnConflict::column(player_rating::Column::PlayerId)
+            .update_columns([
+                player_rating::Column::Rating,
+                player_rating::Column::GamesPlayed,
+                player_rating::Column::UpdatedAt,
+            ])
+            .to_owned(),
+    )
+}
+
+/// Current `(rating, games_played)` of each of `players` who has a rating row.
+async fn current_ratings<C: ConnectionTrait>(
+    db: &C,
+    players: &[Uuid],
+) -> Result<HashMap<Uuid, (i32, i32)>, ApiError> {
+    Ok(player_rating::Entity::find()
+        .filter(player_rating::Column::PlayerId.is_in(players.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.player_id, (r.rating, r.games_played)))
+        .collect())
+}
+
+/// Rates `game` on top of `current`, the players' `(rating, games_played)`, and
+/// updates it to the ratings the game leaves behind.
+fn rate_game(
+    engine: &RatingEngine,
+    current: &mut HashMap<Uuid, (i32, i32)>,
+    game: &game::Model,
+    result: ResultSide,
+) -> RatingUpdate {
+    let rating_of = |id: Uuid| current.get(&id).copied().unwrap_or((DEFAULT_RATING, 0));
+    let (white_before, white_games) = rating_of(game.white_player);
+    let (black_before, black_games) = rating_of(game.black_player);
+    let (white_after, black_after) = engine.rate(white_before, black_before, result);
+
+    current.insert(game.white_player, (white_after, white_games + 1));
+    current.insert(game.black_player, (black_after, black_games + 1));
+    RatingUpdate { white_before, white_after, black_before, black_after }
+}
+
+/// History rows recording `update` for both players of `game`.
+fn history_rows(game: &game::Model, update: &RatingUpdate, now: DateTime<Utc>) -> [rating_history::ActiveModel; 2] {
+    [
+        (game.white_player, update.white_before, update.white_after),
+        (game.black_player, update.black_before, update.black_after),
+    ]
+    .map(|(player_id, before, after)| rating_history::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        player_id: Set(player_id),
+        game_id: Set(game.id),
+        rating_before: Set(before),
+        rating_after: Set(after),
+        created_at: Set(now.into()),
+    })
+}
+
+/// Writes what rating `games` left behind: each player's rating row once, the
+/// history, and the games' `rated_at`.
+async fn record_ratings<C: ConnectionTrait>(
+    db: &C,
+    ratings: HashMap<Uuid, (i32, i32)>,
+    history: Vec<rating_history::ActiveModel>,
+    games: Vec<Uuid>,
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    let ratings = ratings
+        .into_iter()
+        .map(|(player_id, (rating, games_played))| player_rating::ActiveModel {
+            player_id: Set(player_id),
+            rating: Set(rating),
+            games_played: Set(games_played),
+            updated_at: Set(now.into()),
+        });
+    upsert_ratings(ratings).exec_without_returning(db).await?;
+    rating_history::Entity::insert_many(history)
+        .exec_without_returning(db)
+        .await?;
+    game::Entity::update_many()
+        .col_expr(game::Column::RatedAt, Expr::value(DateTimeWithTimeZone::from(now)))
+        .filter(game::Column::Id.is_in(games))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Locks the rating rows of `players` until the surrounding transaction ends and
+/// returns each one's `(rating, games_played)`. Players without a row get one at the
+/// default rating first, so even their first rated games wait on each other instead
+/// of both writing a rating computed from the default.
+async fn lock_ratings<C: ConnectionTrait>(db: &C, players: &[Uuid]) -> Result<HashMap<Uuid, (i32, i32)>, ApiError> {
+    let now = Utc::now();
+    let defaults = players.iter().map(|&player_id| player_rating::ActiveModel {
+        player_id: Set(player_id),
+        rating: Set(DEFAULT_RATING),
+        games_played: Set(0),
+        updated_at: Set(now.into()),
+    });
+    player_rating::Entity::insert_many(defaults)
+        .on_conflict(OnConflict::column(player_rating::Column::PlayerId).do_nothing().to_owned())
+        .exec_without_returning(db)
+        .await?;
+
+    // Rows are locked in id order so two finalizations never wait on each other crosswise
+    Ok(player_rating::Entity::find()
+        .filter(player_rating::Column::PlayerId.is_in(players.iter().copied()))
+        .order_by_asc(player_rating::Column::PlayerId)
+        .lock_exclusive()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.player_id, (r.rating, r.games_played)))
+        .collect())
+}
+
+/// Rates a finished game, updating both players' ratings and history.
+/// Callers run this inside the transaction that finalizes the game; both players'
+/// rating rows stay locked until it commits, so games finishing at the same time
+/// are rated one after the other rather than from the same starting ratings.
+pub async fn apply_game_rating_with<C: ConnectionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game: &game::Model,
+    result: ResultSide,
+) -> Result<RatingUpdate, ApiError> {
+    let mut current = lock_ratings(db, &[game.white_player, game.black_player]).await?;
+    let update = rate_game(engine, &mut current, game, result);
+    let now = Utc::now();
+    record_ratings(db, current, history_rows(game, &update, now).to_vec(), vec![game.id], now).await?;
+    Ok(update)
+}
+
+/// Rating changes of every outcome of a game between players rated `white` and
+/// `black`, through the same [`RatingEngine::rate`] finalization applies. An unrated
+/// game changes nothing whatever its result.
+pub fn preview(engine: &RatingEngine, game: &game::Model, white: i32, black: i32, rated: bool) -> RatingPreview {
+    let deltas = |result: ResultSide| {
+        if !rated {
+            return (0, 0);
+        }
+        let (white_after, black_after) = engine.rate(white, black, result);
+        (white_after - white, black_after - black)
+    };
+    let (white_win, black_loss) = deltas(ResultSide::White);
+    let (white_draw, black_draw) = deltas(ResultSide::Draw);
+    let (white_loss, black_win) = deltas(ResultSide::Black);
+
+    RatingPreview {
+        game_id: game.id,
+        rated,
+        white: OutcomeDeltas {
+            player_id: game.white_player,
+            rating: white,
+            win: white_win,
+            draw: white_draw,
+            loss: white_loss,
+        },
+        black: OutcomeDeltas {
+            player_id: game.black_player,
+            rating: black,
+            win: black_win,
+            draw: black_draw,
+            loss: black_loss,
+        },
+    }
+}
+
+pub async fn rating_preview(key: &str) -> Result<RatingPreview, ApiError> {
+    let db = get_db().await;
+    rating_preview_with(&db, &RatingEngine::from_env(), key).await
+}
+
+/// Previews what each result of a game still in play would do to both ratings,
+/// reading the ratings the game would be rated from if it ended now. Nothing is written.
+pub async fn rating_preview_with<C: ConnectionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    key: &str,
+) -> Result<RatingPreview, ApiError> {
+    let game = find_game_with(db, key).await?;
+    if is_terminal(&game) {
+        return Err(ApiError::Conflict(format!("Game {} is already over", game.id)));
+    }
+    let rated = !includes_guest(db, [game.white_player, game.black_player]).await?;
+    let current = current_ratings(db, &[game.white_player, game.black_player]).await?;
+    let rating_of = |id: Uuid| current.get(&id).map_or(DEFAULT_RATING, |(rating, _)| *rating);
+
+    Ok(preview(engine, &game, rating_of(game.white_player), rating_of(game.black_player), rated))
+}
+
+pub async fn player_rating(player_id: Uuid) -> Result<i32, ApiError> {
+    let db = get_db().await;
+    player_rating_with(&db, player_id).await
+}
+
+/// The stored rating of `player_id`, or the default rating before their first rated game.
+pub async fn player_rating_with<C: ConnectionTrait>(db: &C, player_id: Uuid) -> Result<i32, ApiError> {
+    let row = player_rating::Entity::find_by_id(player_id).one(db).await?;
+    Ok(row.map_or(DEFAULT_RATING, |row| row.rating))
+}
+
+pub async fn apply_results(results: &[(Uuid, ResultSide)]) -> Result<Vec<(Uuid, RatingUpdate)>, ApiError> {
+    let db = get_db().await;
+    apply_results_with(&db, &RatingEngine::from_env(), results).await
+}
+
+/// Rates a batch of finished games, such as a tournament round, in one transaction.
+/// See [`rate_results_with`].
+pub async fn apply_results_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    results: &[(Uuid, ResultSide)],
+) -> Result<Vec<(Uuid, RatingUpdate)>, ApiError> {
+    let txn = db.begin().await?;
+    let updates = rate_results_with(&txn, engine, results).await?;
+    txn.commit().await?;
+    Ok(updates)
+}
+
+/// Rates a batch of games inside the caller's transaction and returns the change of
+/// each game it rated.
+///
+/// Only games that have ended with a result, have not been rated yet and have no
+/// guest in them are rated; the others are skipped, so a batch can be retried. Games
+/// are rated in the order given, each against the ratings left by the games before
+/// it, so the outcome matches rating them one by one. Every player's rating row is
+/// written once at the end instead of once per game.
+pub async fn rate_results_with<C: ConnectionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    results: &[(Uuid, ResultSide)],
+) -> Result<Vec<(Uuid, RatingUpdate)>, ApiError> {
+    if results.is_empty() {
+        return Ok(Vec::new());
+    }
+    let games: HashMap<Uuid, game::Model> = game::Entity::find()
+        .filter(game::Column::Id.is_in(results.iter().map(|(id, _)| *id)))
+        .filter(game::Column::EndedAt.is_not_null())
+        .filter(game::Column::Result.is_not_null())
+        .filter(game::Column::RatedAt.is_null())
+        .lock_exclusive()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|g| (g.id, g))
+        .collect();
+    if games.is_empty() {
+        return Ok(Vec::new());
+    }
+    let players = players_of(games.values());
+    let guests = guests_among(db, &players).await?;
+    let rated: Vec<(&game::Model, ResultSide)> = results
+        .iter()
+        .filter_map(|(id, result)| games.get(id).map(|game| (game, *result)))
+        .filter(|(game, _)| !guests.contains(&game.white_player) && !guests.contains(&game.black_player))
+        .collect();
+    if rated.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let players = players_of(rated.iter().map(|(game, _)| *game));
+    let mut current = lock_ratings(db, &players).await?;
+    let now = Utc::now();
+    let mut updates = Vec::with_capacity(rated.len());
+    let mut history = Vec::with_capacity(rated.len() * 2);
+    for (game, result) in rated {
+        let update = rate_game(engine, &mut current, game, result);
+        history.extend(history_rows(game, &update, now));
+        updates.push((game.id, update));
+    }
+    let game_ids = updates.iter().map(|(id, _)| *id).collect();
+    record_ratings(db, current, history, game_ids, now).await?;
+
+    Ok(updates)
+}
+
+/// Everyone seated at `games`, sorted so rows are always read in the same order.
+fn players_of<'a>(games: impl Iterator<Item = &'a game::Model>) -> Vec<Uuid> {
+    games
+        .flat_map(|g| [g.white_player, g.black_player])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_players_split_k_factor() {
+        let engine = RatingEngine::default();
+        assert_eq!(engine.rate(1500, 1500, ResultSide::White), (1516, 1484));
+        assert_eq!(engine.rate(1500, 1500, ResultSide::Black), (1484, 1516));
+        assert_eq!(engine.rate(1500, 1500, ResultSide::Draw), (1500, 1500));
+    }
+
+    #[test]
+    fn draw_moves_ratings_toward_each_other() {
+        let engine = RatingEngine::default();
+        let (white, black) = engine.rate(1800, 1400, ResultSide::Draw);
+        assert!(white < 1800 && black > 1400);
+        assert_eq!(1800 - white, black - 1400);
+    }
+
+    #[test]
+    fn preview_of_an_unrated_game_changes_nothing() {
+        let engine = RatingEngine::default();
+        let game = finished_game(Uuid::new_v4(), Uuid::new_v4());
+
+        let rated = preview(&engine, &game, 1600, 1400, true);
+        let unrated = preview(&engine, &game, 1600, 1400, false);
+
+        assert_eq!(rated.white.win, -rated.black.loss);
+        assert!(rated.white.win < rated.black.win, "the underdog stands to gain more");
+        for deltas in [unrated.white, unrated.black] {
+            assert_eq!((deltas.win, deltas.draw, deltas.loss), (0, 0, 0));
+        }
+    }
+
+    fn finished_game(white: Uuid, black: Uuid) -> game::Model {
+        game::Model { public_id: "Rt5uW1cV".to_string(), ..game::Model::fixture(white, black) }
+    }
+
+    fn no_guests() -> Vec<std::collections::BTreeMap<&'static str, sea_orm::Value>> {
+        Vec::new()
+    }
+
+    fn rating_rows(ratings: &HashMap<Uuid, (i32, i32)>, players: &[Uuid]) -> Vec<player_rating::Model> {
+        players
+            .iter()
+            .filter_map(|id| ratings.get(id).map(|&(rating, games_played)| (id, rating, games_played)))
+            .map(|(id, rating, games_played)| player_rating::Model {
+                player_id: *id,
+                rating,
+                games_played,
+                updated_at: Utc::now().into(),
+            })
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn stored_rating_defaults_before_the_first_rated_game() {
+        use sea_orm::{DatabaseBackend, MockDatabase};
+
+        let (rated, unrated) = (Uuid::new_v4(), Uuid::new_v4());
+        let stored = HashMap::from([(rated, (2210, 40))]);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([rating_rows(&stored, &[rated]), rating_rows(&stored, &[unrated])])
+            .into_connection();
+
+        assert_eq!(player_rating_with(&db, rated).await.unwrap(), 2210);
+        assert_eq!(player_rating_with(&db, unrated).await.unwrap(), DEFAULT_RATING);
+    }
+
+    #[async_std::test]
+    async fn rating_a_game_locks_both_rating_rows() {
+        use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = finished_game(white, black);
+        let stored = HashMap::from([(white, (1600, 12))]);
+        let written = |rows_affected| MockExecResult { last_insert_id: 0, rows_affected };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([written(1)])
+            .append_query_results([rating_rows(&stored, &[white])])
+            .append_exec_results([written(2), written(2), written(1)])
+            .into_connection();
+
+        let update = apply_game_rating_with(&db, &RatingEngine::default(), &game, ResultSide::Black)
+            .await
+            .unwrap();
+        assert_eq!((update.white_before, update.black_before), (1600, DEFAULT_RATING));
+
+        let log = db.into_transaction_log();
+        let statements: Vec<_> = log.iter().flat_map(|t| t.statements().to_vec()).collect();
+        assert!(statements[0].sql.starts_with(r#"INSERT INTO "smdb"."player_rating""#));
+        assert!(statements[0].sql.ends_with("DO NOTHING"));
+        assert!(statements[1].sql.ends_with("FOR UPDATE"), "{}", statements[1].sql);
+    }
+
+    #[async_std::test]
+    async fn batch_matches_rating_games_one_by_one() {
+        use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+
+        let engine = RatingEngine::default();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let games = [finished_game(a, b), finished_game(b, c), finished_game(c, a)];
+        let results = [ResultSide::White, ResultSide::Draw, ResultSide::Black];
+        let initial = HashMap::from([(a, (1600, 12)), (b, (1450, 3))]);
+        let written = || MockExecResult { last_insert_id: 0, rows_affected: 2 };
+
+        let mut ratings = initial.clone();
+        let mut sequential = Vec::new();
+        for (game, result) in games.iter().zip(results) {
+            let db = MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([rating_rows(&ratings, &[game.white_player, game.black_player])])
+                .append_exec_results([written(), written(), written(), written()])
+                .into_connection();
+            let update = apply_game_rating_with(&db, &engine, game, result).await.unwrap();
+            for (player_id, after) in [(game.white_player, update.white_after), (game.black_player, update.black_after)] {
+                let games_played = ratings.get(&player_id).map_or(0, |r| r.1);
+                ratings.insert(player_id, (after, games_played + 1));
+            }
+            sequential.push(update);
+        }
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([games.to_vec()])
+            .append_query_results([no_guests()])
+            .append_query_results([rating_rows(&initial, &[a, b, c])])
+            .append_exec_results([written(), written(), written(), written()])
+            .into_connection();
+        let batch: Vec<(Uuid, ResultSide)> = games.iter().map(|g| g.id).zip(results).collect();
+        let batched = apply_results_with(&db, &engine, &batch).await.unwrap();
+
+        let (rated, batched): (Vec<Uuid>, Vec<RatingUpdate>) = batched.into_iter().unzip();
+        assert_eq!(rated, games.map(|g| g.id));
+        assert_eq!(batched, sequential);
+    }
+
+    #[async_std::test]
+    async fn batch_skips_unfinished_rated_and_guest_games() {
+        use std::collections::BTreeMap;
+        use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+
+        let (a, b, guest) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (rated, with_guest) = (finished_game(a, b), finished_game(a, guest));
+        let written = || MockExecResult { last_insert_id: 0, rows_affected: 2 };
+        // Unfinished and already rated games are filtered out by the query itself
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![rated.clone(), with_guest.clone()]])
+            .append_query_results([vec![BTreeMap::from([("id", Value::from(guest))])]])
+            .append_query_results([rating_rows(&HashMap::new(), &[])])
+            .append_exec_results([written(), written(), written(), written()])
+            .into_connection();
+        let batch = [(rated.id, ResultSide::White), (Uuid::new_v4(), ResultSide::Draw), (with_guest.id, ResultSide::Black)];
+
+        let updates = apply_results_with(&db, &RatingEngine::default(), &batch).await.unwrap();
+        assert_eq!(updates.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [rated.id]);
+
+        let log = db.into_transaction_log();
+        let statements: Vec<_> = log.iter().flat_map(|t| t.statements().to_vec()).collect();
+        let games = &statements[1].sql;
+        assert!(games.contains(r#""game"."ended_at" IS NOT NULL"#), "{}", games);
+        assert!(games.contains(r#""game"."rated_at" IS NULL"#), "{}", games);
+        assert!(games.ends_with("FOR UPDATE"), "{}", games);
+        assert!(statements.last().unwrap().sql.starts_with("COMMIT"));
+        let marked = statements.iter().find(|s| s.sql.starts_with(r#"UPDATE "smdb"."game""#)).unwrap();
+        assert!(marked.sql.contains(r#""rated_at" = $1"#), "{}", marked.sql);
+    }
+}
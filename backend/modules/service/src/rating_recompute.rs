@@ -0,0 +1,665 @@
+use crate::archive::including_archived;
+use crate::rating::{DEFAULT_RATING, RatingEngine};
+use chrono::Utc;
+use db::db::db::get_db;
+use entity::sea_orm_active_enums::RecomputeStatus;
+use entity::{
+    game, player, player_rating, rating_history, rating_recompute_history, rating_recompute_job,
+    rating_recompute_rating,
+};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbBackend, EntityTrait,
+    FromQueryResult, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select, Set, Statement,
+    TransactionTrait,
+    sea_query::{OnConflict, Query},
+};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+/// Games replayed per transaction. Each batch commits together with the job's
+/// cursor, so an interrupted job resumes after the last committed batch.
+const BATCH_SIZE: u64 = 500;
+/// Largest individual changes kept in a job report.
+const REPORT_CHANGE_LIMIT: usize = 50;
+
+/// Finished games without a guest in them, which are the ones that get rated,
+/// archived ones included, in the order they ended and so were rated.
+fn rated_games() -> Select<game::Entity> {
+    let guests = Query::select()
+        .column(player::Column::Id)
+        .from(player::Entity)
+        .and_where(player::Column::IsGuest.eq(true))
+        .to_owned();
+    including_archived(game::Entity::find())
+        .filter(game::Column::Result.is_not_null())
+        .filter(game::Column::WhitePlayer.not_in_subquery(guests.clone()))
+        .filter(game::Column::BlackPlayer.not_in_subquery(guests))
+        .filter(game::Column::EndedAt.is_not_null())
+        .order_by_asc(game::Column::EndedAt)
+        .order_by_asc(game::Column::Id)
+}
+
+/// Jobs currently being driven by this process.
+static ACTIVE_JOBS: OnceLock<Mutex<HashSet<Uuid>>> = OnceLock::new();
+
+const RATING_CHANGES_SQL: &str = r#"
+    SELECT COALESCE(s."player_id", p."player_id") AS "player_id",
+           p."rating" AS "current_rating",
+           s."rating" AS "recomputed_rating"
+    FROM (SELECT "player_id", "rating" FROM "smdb"."rating_recompute_rating" WHERE "job_id" = $1) s
+    FULL OUTER JOIN "smdb"."player_rating" p ON p."player_id" = s."player_id"
+    WHERE s."rating" IS DISTINCT FROM p."rating"
+"#;
+
+/// Keeps finalizations from rating games while a job publishes: they lock or upsert
+/// `player_rating` rows, which this mode waits for and then blocks.
+const LOCK_RATINGS_SQL: &str = r#"LOCK TABLE "smdb"."player_rating" IN EXCLUSIVE MODE"#;
+
+const PUBLISH_RATINGS_SQL: &str = r#"
+    INSERT INTO "smdb"."player_rating" ("player_id", "rating", "games_played", "updated_at")
+    SELECT "player_id", "rating", "games_played", now()
+    FROM "smdb"."rating_recompute_rating" WHERE "job_id" = $1
+"#;
+
+const PUBLISH_HISTORY_SQL: &str = r#"
+    INSERT INTO "smdb"."rating_history" ("id", "player_id", "game_id", "rating_before", "rating_after", "created_at")
+    SELECT gen_random_uuid(), "player_id", "game_id", "rating_before", "rating_after", "created_at"
+    FROM "smdb"."rating_recompute_history" WHERE "job_id" = $1
+"#;
+
+#[derive(Debug, Clone, PartialEq, FromQueryResult)]
+pub struct RatingChange {
+    pub player_id: Uuid,
+    pub current_rating: Option<i32>,
+    pub recomputed_rating: Option<i32>,
+}
+
+impl RatingChange {
+    fn delta(&self) -> i32 {
+        self.recomputed_rating.unwrap_or(DEFAULT_RATING) - self.current_rating.unwrap_or(DEFAULT_RATING)
+    }
+}
+
+/// Summarizes how a replay differs from the ratings currently in use.
+pub fn summarize_changes(dry_run: bool, games_replayed: i64, mut changes: Vec<RatingChange>) -> serde_json::Value {
+    changes.sort_by_key(|c| std::cmp::Reverse(c.delta().abs()));
+
+    let players_changed = changes.len();
+    let max_abs_delta = changes.first().map(|c| c.delta().abs()).unwrap_or(0);
+    let mean_abs_delta = if players_changed == 0 {
+        0.0
+    } else {
+        changes.iter().map(|c| c.delta().abs() as f64).sum::<f64>() / players_changed as f64
+    };
+    let largest: Vec<serde_json::Value> = changes
+        .iter()
+        .take(REPORT_CHANGE_LIMIT)
+        .map(|c| {
+            json!({
+                "player_id": c.player_id,
+                "current_rating": c.current_rating,
+                "recomputed_rating": c.recomputed_rating,
+                "delta": c.delta()
+            })
+        })
+        .collect();
+
+    json!({
+        "dry_run": dry_run,
+        "games_replayed": games_replayed,
+        "players_changed": players_changed,
+        "max_abs_delta": max_abs_delta,
+        "mean_abs_delta": mean_abs_delta,
+        "largest_changes": largest
+    })
+}
+
+pub async fn create_job(dry_run: bool) -> Result<rating_recompute_job::Model, ApiError> {
+    let db = get_db().await;
+    create_job_with(&db, dry_run).await
+}
+
+/// Records a new job. Only one job may be running at a time.
+pub async fn create_job_with<C: ConnectionTrait>(
+    db: &C,
+    dry_run: bool,
+) -> Result<rating_recompute_job::Model, ApiError> {
+    let running = rating_recompute_job::Entity::find()
+        .filter(rating_recompute_job::Column::Status.eq(RecomputeStatus::Running))
+        .one(db)
+        .await?;
+    if let Some(job) = running {
+        return Err(ApiError::Conflict(format!(
+            "Rating recompute job {} is already running",
+            job.id
+        )));
+    }
+
+    let total_games = rated_games()
+        .count(db)
+        .await? as i64;
+    let now = Utc::now();
+
+    let job = rating_recompute_job::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        status: Set(RecomputeStatus::Running),
+        dry_run: Set(dry_run),
+        total_games: Set(total_games),
+        processed_games: Set(0),
+        cursor_ended_at: Set(None),
+        cursor_game_id: Set(None),
+        report: Set(None),
+        error: Set(None),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        finished_at: Set(None),
+    };
+
+    Ok(job.insert(db).await?)
+}
+
+pub async fn get_job(id: Uuid) -> Result<rating_recompute_job::Model, ApiError> {
+    let db = get_db().await;
+    get_job_with(&db, id).await
+}
+
+pub async fn get_job_with<C: ConnectionTrait>(
+    db: &C,
+    id: Uuid,
+) -> Result<rating_recompute_job::Model, ApiError> {
+    rating_recompute_job::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Rating recompute job {}", id)))
+}
+
+pub async fn resume_job(id: Uuid) -> Result<rating_recompute_job::Model, ApiError> {
+    let db = get_db().await;
+    resume_job_with(&db, id).await
+}
+
+/// Marks a failed or interrupted job as running again so it can continue from its cursor.
+pub async fn resume_job_with<C: ConnectionTrait>(
+    db: &C,
+    id: Uuid,
+) -> Result<rating_recompute_job::Model, ApiError> {
+    let job = get_job_with(db, id).await?;
+    match job.status {
+        RecomputeStatus::Completed => Err(ApiError::Conflict(format!(
+            "Rating recompute job {} has already completed",
+            id
+        ))),
+        _ if is_active(id) => Err(ApiError::Conflict(format!(
+            "Rating recompute job {} is already running",
+            id
+        ))),
+        _ => {
+            let job = rating_recompute_job::ActiveModel {
+                id: Set(id),
+                status: Set(RecomputeStatus::Running),
+                error: Set(None),
+                updated_at: Set(Utc::now().into()),
+                ..Default::default()
+            };
+            Ok(job.update(db).await?)
+        }
+    }
+}
+
+fn is_active(id: Uuid) -> bool {
+    ACTIVE_JOBS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .contains(&id)
+}
+
+/// Drives a job to completion in the background, recording any failure on the job.
+pub async fn run_job(id: Uuid) {
+    if !ACTIVE_JOBS.get_or_init(Default::default).lock().unwrap().insert(id) {
+        return;
+    }
+
+    let db = get_db().await;
+    if let Err(err) = run_job_with(&db, id, &RatingEngine::from_env()).await {
+        let failed = rating_recompute_job::ActiveModel {
+            id: Set(id),
+            status: Set(RecomputeStatus::Failed),
+            error: Set(Some(err.to_string())),
+            updated_at: Set(Utc::now().into()),
+            ..Default::default()
+        };
+        let _ = failed.update(&db).await;
+    }
+
+    ACTIVE_JOBS.get_or_init(Default::default).lock().unwrap().remove(&id);
+}
+
+/// Replays batches until every game has been processed, then publishes the result.
+pub async fn run_job_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    id: Uuid,
+    engine: &RatingEngine,
+) -> Result<rating_recompute_job::Model, ApiError> {
+    loop {
+        let job = get_job_with(db, id).await?;
+        if job.status != RecomputeStatus::Running {
+            return Ok(job);
+        }
+        if replay_batch(db, &job, engine).await? {
+            return finish_job(db, job, engine).await;
+        }
+    }
+}
+
+/// Replays the next batch of games after the job's cursor into the shadow tables.
+/// Returns `true` once there are no games left.
+async fn replay_batch<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    job: &rating_recompute_job::Model,
+    engine: &RatingEngine,
+) -> Result<bool, ApiError> {
+    let txn = db.begin().await?;
+
+    let mut query = rated_games();
+    if let (Some(ended_at), Some(game_id)) = (job.cursor_ended_at, job.cursor_game_id) {
+        query = query.filter(
+            Condition::any()
+                .add(game::Column::EndedAt.gt(ended_at))
+                .add(
+                    Condition::all()
+                        .add(game::Column::EndedAt.eq(ended_at))
+                        .add(game::Column::Id.gt(game_id)),
+                ),
+        );
+    }
+    let games = query.limit(BATCH_SIZE).all(&txn).await?;
+
+    let Some(last) = games.last() else {
+        txn.commit().await?;
+        return Ok(true);
+    };
+    replay_games(&txn, job.id, engine, &games).await?;
+
+    let progress = rating_recompute_job::ActiveModel {
+        id: Set(job.id),
+        processed_games: Set(job.processed_games + games.len() as i64),
+        cursor_ended_at: Set(last.ended_at),
+        cursor_game_id: Set(Some(last.id)),
+        updated_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+    progress.update(&txn).await?;
+
+    txn.commit().await?;
+    Ok((games.len() as u64) < BATCH_SIZE)
+}
+
+/// Rates `games` in order on top of the job's shadow ratings and records the
+/// changes in its shadow tables.
+async fn replay_games<C: ConnectionTrait>(
+    db: &C,
+    job_id: Uuid,
+    engine: &RatingEngine,
+    games: &[game::Model],
+) -> Result<(), ApiError> {
+    if games.is_empty() {
+        return Ok(());
+    }
+    let player_ids: HashSet<Uuid> = games
+        .iter()
+        .flat_map(|g| [g.white_player, g.black_player])
+        .collect();
+    let mut ratings: HashMap<Uuid, (i32, i32)> = rating_recompute_rating::Entity::find()
+        .filter(rating_recompute_rating::Column::JobId.eq(job_id))
+        .filter(rating_recompute_rating::Column::PlayerId.is_in(player_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.player_id, (r.rating, r.games_played)))
+        .collect();
+
+    let mut history = Vec::with_capacity(games.len() * 2);
+    for game in games {
+        let Some(result) = game.result else { continue };
+        let (white, white_games) = *ratings.get(&game.white_player).unwrap_or(&(DEFAULT_RATING, 0));
+        let (black, black_games) = *ratings.get(&game.black_player).unwrap_or(&(DEFAULT_RATING, 0));
+        let (new_white, new_black) = engine.rate(white, black, result);
+
+        ratings.insert(game.white_player, (new_white, white_games + 1));
+        ratings.insert(game.black_player, (new_black, black_games + 1));
+        for (player_id, before, after) in [
+            (game.white_player, white, new_white),
+            (game.black_player, black, new_black),
+        ] {
+            history.push(rating_recompute_history::ActiveModel {
+                job_id: Set(job_id),
+                game_id: Set(game.id),
+                player_id: Set(player_id),
+                rating_before: Set(before),
+                rating_after: Set(after),
+                created_at: Set(game.ended_at.unwrap_or(game.started_at)),
+            });
+        }
+    }
+
+    let rows = ratings
+        .into_iter()
+        .map(|(player_id, (rating, games_played))| rating_recompute_rating::ActiveModel {
+            job_id: Set(job_id),
+            player_id: Set(player_id),
+            rating: Set(rating),
+            games_played: Set(games_played),
+        });
+    rating_recompute_rating::Entity::insert_many(rows)
+        .on_conflict(
+            OnConflict::columns([
+                rating_recompute_rating::Column::JobId,
+                rating_recompute_rating::Column::PlayerId,
+            ])
+            .update_columns([
+                rating_recompute_rating::Column::Rating,
+                rating_recompute_rating::Column::GamesPlayed,
+            ])
+            .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    rating_recompute_history::Entity::insert_many(history)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Games that ended since `job` was created and are not in its replay yet: they
+/// ended behind its cursor's read, or after its last batch.
+fn unreplayed_games(job: &rating_recompute_job::Model) -> Select<game::Entity> {
+    let replayed = Query::select()
+        .column(rating_recompute_history::Column::GameId)
+        .from(rating_recompute_history::Entity)
+        .and_where(rating_recompute_history::Column::JobId.eq(job.id))
+        .to_owned();
+    rated_games()
+        .filter(game::Column::EndedAt.gte(job.created_at))
+        .filter(game::Column::Id.not_in_subquery(replayed))
+}
+
+/// Writes the report and, unless this is a dry run, swaps the shadow tables in.
+///
+/// Games keep ending while a job runs. Publishing first blocks live rating writes,
+/// then replays every game that ended since the job began and is not in its replay
+/// yet, so the ratings swapped in include each of them and none is rated twice.
+async fn finish_job<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    job: rating_recompute_job::Model,
+    engine: &RatingEngine,
+) -> Result<rating_recompute_job::Model, ApiError> {
+    let txn = db.begin().await?;
+
+    if !job.dry_run {
+        txn.execute_unprepared(LOCK_RATINGS_SQL).await?;
+    }
+    let tail = unreplayed_games(&job).all(&txn).await?;
+    replay_games(&txn, job.id, engine, &tail).await?;
+    let processed_games = job.processed_games + tail.len() as i64;
+
+    let changes = RatingChange::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        RATING_CHANGES_SQL,
+        [job.id.into()],
+    ))
+    .all(&txn)
+    .await?;
+    let report = summarize_changes(job.dry_run, processed_games, changes);
+
+    if !job.dry_run {
+        player_rating::Entity::delete_many().exec(&txn).await?;
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            PUBLISH_RATINGS_SQL,
+            [job.id.into()],
+        ))
+        .await?;
+
+        rating_history::Entity::delete_many().exec(&txn).await?;
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            PUBLISH_HISTORY_SQL,
+            [job.id.into()],
+        ))
+        .await?;
+    }
+
+    rating_recompute_rating::Entity::delete_many()
+        .filter(rating_recompute_rating::Column::JobId.eq(job.id))
+        .exec(&txn)
+        .await?;
+    rating_recompute_history::Entity::delete_many()
+        .filter(rating_recompute_history::Column::JobId.eq(job.id))
+        .exec(&txn)
+        .await?;
+
+    let now = Utc::now();
+    let finished = rating_recompute_job::ActiveModel {
+        id: Set(job.id),
+        status: Set(RecomputeStatus::Completed),
+        processed_games: Set(processed_games),
+        report: Set(Some(report)),
+        updated_at: Set(now.into()),
+        finished_at: Set(Some(now.into())),
+        ..Default::default()
+    };
+    let job = finished.update(&txn).await?;
+
+    txn.commit().await?;
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+    use entity::sea_orm_active_enums::ResultSide;
+    use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+
+    fn ended_game(white: Uuid, black: Uuid, result: ResultSide, ended_at: DateTime<Utc>) -> game::Model {
+        game::Model {
+            result: Some(result),
+            ended_at: Some(ended_at.into()),
+            ..game::Model::fixture(white, black)
+        }
+    }
+
+    fn job(dry_run: bool, created_at: DateTime<Utc>, replayed: &[&game::Model]) -> rating_recompute_job::Model {
+        let last = replayed.last();
+        rating_recompute_job::Model {
+            id: Uuid::new_v4(),
+            status: RecomputeStatus::Running,
+            dry_run,
+            total_games: replayed.len() as i64,
+            processed_games: replayed.len() as i64,
+            cursor_ended_at: last.and_then(|game| game.ended_at),
+            cursor_game_id: last.map(|game| game.id),
+            report: None,
+            error: None,
+            created_at: created_at.into(),
+            updated_at: created_at.into(),
+            finished_at: None,
+        }
+    }
+
+    fn shadow_rating(job: &rating_recompute_job::Model, player_id: Uuid, rating: i32) -> rating_recompute_rating::Model {
+        rating_recompute_rating::Model { job_id: job.id, player_id, rating, games_played: 1 }
+    }
+
+    fn no_changes() -> Vec<BTreeMap<&'static str, Value>> {
+        Vec::new()
+    }
+
+    fn written(count: usize) -> Vec<MockExecResult> {
+        vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }; count]
+    }
+
+    fn statements(db: DatabaseConnection) -> Vec<String> {
+        db.into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn position(statements: &[String], pattern: &str) -> usize {
+        statements
+            .iter()
+            .position(|s| s.contains(pattern))
+            .unwrap_or_else(|| panic!("no statement contains {}", pattern))
+    }
+
+    #[async_std::test]
+    async fn replay_rates_games_in_the_order_they_ended() {
+        let engine = RatingEngine::default();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let start = Utc::now() - Duration::hours(1);
+        let first = ended_game(a, b, ResultSide::White, start - Duration::minutes(2));
+        let second = ended_game(a, c, ResultSide::White, start - Duration::minutes(1));
+        let running = job(false, start, &[]);
+        let completed = rating_recompute_job::Model { status: RecomputeStatus::Completed, ..running.clone() };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![running.clone()]])
+            .append_query_results([vec![first.clone(), second.clone()]])
+            .append_query_results([Vec::<rating_recompute_rating::Model>::new()])
+            .append_query_results([vec![running.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([no_changes()])
+            .append_query_results([vec![completed]])
+            .append_exec_results(written(9))
+            .into_connection();
+
+        let finished = run_job_with(&db, running.id, &engine).await.unwrap();
+
+        assert_eq!(finished.status, RecomputeStatus::Completed);
+        let log = statements(db);
+        let batch = &log[position(&log, r#"FROM "smdb"."game_with_archive""#)];
+        assert!(batch.contains(r#"ORDER BY "game"."ended_at" ASC, "game"."id" ASC"#), "{}", batch);
+        let (a_first, _) = engine.rate(DEFAULT_RATING, DEFAULT_RATING, ResultSide::White);
+        let (a_second, _) = engine.rate(a_first, DEFAULT_RATING, ResultSide::White);
+        let history = &log[position(&log, r#"INSERT INTO "smdb"."rating_recompute_history""#)];
+        assert!(history.contains(&format!("'{}', {}, {}", a, a_first, a_second)), "{}", history);
+    }
+
+    #[async_std::test]
+    async fn a_resumed_job_continues_after_its_cursor_from_its_shadow_ratings() {
+        let engine = RatingEngine::default();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let start = Utc::now() - Duration::hours(1);
+        let replayed = ended_game(a, b, ResultSide::White, start - Duration::minutes(2));
+        let next = ended_game(a, c, ResultSide::White, start - Duration::minutes(1));
+        let interrupted = job(true, start, &[&replayed]);
+        let (a_first, _) = engine.rate(DEFAULT_RATING, DEFAULT_RATING, ResultSide::White);
+        let completed = rating_recompute_job::Model { status: RecomputeStatus::Completed, ..interrupted.clone() };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![interrupted.clone()]])
+            .append_query_results([vec![next.clone()]])
+            .append_query_results([vec![shadow_rating(&interrupted, a, a_first)]])
+            .append_query_results([vec![interrupted.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([no_changes()])
+            .append_query_results([vec![completed]])
+            .append_exec_results(written(4))
+            .into_connection();
+
+        run_job_with(&db, interrupted.id, &engine).await.unwrap();
+
+        let log = statements(db);
+        let batch = &log[position(&log, r#"FROM "smdb"."game_with_archive""#)];
+        assert!(batch.contains(r#""game"."ended_at" > '"#), "{}", batch);
+        assert!(batch.contains(&replayed.id.to_string()), "{}", batch);
+        let (a_second, _) = engine.rate(a_first, DEFAULT_RATING, ResultSide::White);
+        let history = &log[position(&log, r#"INSERT INTO "smdb"."rating_recompute_history""#)];
+        assert!(history.contains(&format!("'{}', {}, {}", a, a_first, a_second)), "{}", history);
+        assert!(!history.contains(&replayed.id.to_string()), "{}", history);
+        assert!(log.iter().all(|s| !s.starts_with("LOCK")), "dry runs publish nothing");
+    }
+
+    #[async_std::test]
+    async fn a_game_finalized_mid_job_is_replayed_before_publishing() {
+        let engine = RatingEngine::default();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let start = Utc::now() - Duration::hours(1);
+        let replayed = ended_game(a, b, ResultSide::White, start - Duration::minutes(1));
+        // Ended, and was rated live, after the job started but behind its last read
+        let late = ended_game(b, c, ResultSide::White, start + Duration::minutes(1));
+        let running = job(false, start, &[&replayed]);
+        let (_, b_first) = engine.rate(DEFAULT_RATING, DEFAULT_RATING, ResultSide::White);
+        let completed = rating_recompute_job::Model {
+            status: RecomputeStatus::Completed,
+            processed_games: 2,
+            ..running.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![running.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .append_query_results([vec![late.clone()]])
+            .append_query_results([vec![shadow_rating(&running, b, b_first)]])
+            .append_query_results([no_changes()])
+            .append_query_results([vec![completed]])
+            .append_exec_results(written(9))
+            .into_connection();
+
+        let finished = run_job_with(&db, running.id, &engine).await.unwrap();
+
+        assert_eq!(finished.processed_games, 2);
+        let log = statements(db);
+        let lock = position(&log, "LOCK TABLE");
+        let tail = position(&log, r#"NOT IN (SELECT "game_id" FROM "#);
+        let publish = position(&log, r#"INSERT INTO "smdb"."player_rating""#);
+        assert!(lock < tail && tail < publish, "{:?}", log);
+        assert!(log[tail].contains("rating_recompute_history"), "{}", log[tail]);
+        assert!(log[tail].contains("rating_recompute_history"), "{}", log[tail]);
+        let (b_second, _) = engine.rate(b_first, DEFAULT_RATING, ResultSide::White);
+        let history = &log[position(&log, r#"INSERT INTO "smdb"."rating_recompute_history""#)];
+        assert!(history.contains(&format!("'{}', {}, {}", b, b_first, b_second)), "{}", history);
+        assert!(log.iter().any(|s| s.contains(r#""processed_games" = 2"#)), "{:?}", log);
+    }
+
+    fn change(current: Option<i32>, recomputed: Option<i32>) -> RatingChange {
+        RatingChange {
+            player_id: Uuid::new_v4(),
+            current_rating: current,
+            recomputed_rating: recomputed,
+        }
+    }
+
+    #[test]
+    fn report_orders_changes_by_magnitude() {
+        let changes = vec![
+            change(Some(1500), Some(1510)),
+            change(Some(1600), Some(1540)),
+            change(None, Some(1530)),
+        ];
+        let report = summarize_changes(true, 12, changes);
+
+        assert_eq!(report["dry_run"], true);
+        assert_eq!(report["games_replayed"], 12);
+        assert_eq!(report["players_changed"], 3);
+        assert_eq!(report["max_abs_delta"], 60);
+        assert_eq!(report["mean_abs_delta"], 100.0 / 3.0);
+        assert_eq!(report["largest_changes"][0]["delta"], -60);
+        assert_eq!(report["largest_changes"][1]["delta"], 30);
+    }
+
+    #[test]
+    fn report_without_changes_is_empty() {
+        let report = summarize_changes(false, 0, vec![]);
+        assert_eq!(report["players_changed"], 0);
+        assert_eq!(report["max_abs_delta"], 0);
+        assert_eq!(report["largest_changes"], json!([]));
+    }
+}
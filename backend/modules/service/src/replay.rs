@@ -0,0 +1,154 @@
+use chess::position::Position;
+use db::db::db::get_db;
+use dto::games::{MAX_REPLAY_PAGE_PLIES, ReplayPage, ReplayPly, ReplayQuery, pgn_moves, pgn_starting_fen};
+use entity::game;
+use error::error::ApiError;
+use sea_orm::{ConnectionTrait, DbErr};
+use std::env;
+use uuid::Uuid;
+
+use crate::games::find_game_with;
+
+/// Longest game, in plies, that is replayed or exported.
+pub const DEFAULT_MAX_PLIES: usize = 2000;
+
+/// Reads the ply cap from `MAX_GAME_PLIES`, falling back to 2000.
+pub fn max_plies() -> usize {
+    env::var("MAX_GAME_PLIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|max: &usize| *max > 0)
+        .unwrap_or(DEFAULT_MAX_PLIES)
+}
+
+/// Rejects games longer than `max_plies` before any position is rebuilt for them.
+pub fn ensure_within_cap(game_id: Uuid, plies: usize, max_plies: usize) -> Result<(), ApiError> {
+    if plies > max_plies {
+        return Err(ApiError::Conflict(format!(
+            "Game {} has {} plies, more than the limit of {}",
+            game_id, plies, max_plies
+        )));
+    }
+    Ok(())
+}
+
+pub async fn replay_game(key: &str, query: ReplayQuery) -> Result<ReplayPage, ApiError> {
+    let db = get_db().await;
+    replay_game_with(&db, key, query, max_plies()).await
+}
+
+/// Replays the game with UUID or public id `key`.
+pub async fn replay_game_with<C: ConnectionTrait>(
+    db: &C,
+    key: &str,
+    query: ReplayQuery,
+    max_plies: usize,
+) -> Result<ReplayPage, ApiError> {
+    let game = find_game_with(db, key).await?;
+    replay_page(&game, query, max_plies)
+}
+
+/// Plays `game` forward to the end of the requested range, keeping only the plies
+/// inside it, so memory stays bounded by the page rather than the game.
+pub fn replay_page(game: &game::Model, query: ReplayQuery, max_plies: usize) -> Result<ReplayPage, ApiError> {
+    let moves = pgn_moves(&game.pgn);
+    ensure_within_cap(game.id, moves.len(), max_plies)?;
+
+    let total = moves.len() as u32;
+    let from = query.from.unwrap_or(0).min(total);
+    let to = query.to.unwrap_or(from + MAX_REPLAY_PAGE_PLIES).min(total);
+
+    let corrupt = |detail: String| {
+        ApiError::DatabaseError(DbErr::Custom(format!(
+            "Stored moves of game {} cannot be replayed: {}",
+            game.id, detail
+        )))
+    };
+    let mut position: Position = pgn_starting_fen(&game.pgn)
+        .parse()
+        .map_err(|err| corrupt(format!("invalid starting position: {}", err)))?;
+
+    let mut fen = None;
+    let mut plies = Vec::with_capacity((to - from) as usize);
+    for (ply, san) in (1..=to).zip(&moves) {
+        if ply == from + 1 {
+            fen = Some(position.to_fen());
+        }
+        let mv = position
+            .parse_san(san)
+            .map_err(|err| corrupt(format!("ply {}: {}", ply, err)))?;
+        let san = position.san(&mv);
+        position = position
+            .play(&mv)
+            .map_err(|err| corrupt(format!("ply {}: {}", ply, err)))?;
+        if ply > from {
+            plies.push(ReplayPly { ply, san, fen: position.to_fen() });
+        }
+    }
+
+    Ok(ReplayPage {
+        game_id: game.id,
+        total_plies: total,
+        from,
+        to,
+        // An empty page starts and ends where the replay stopped
+        fen: fen.unwrap_or_else(|| position.to_fen()),
+        plies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KNIGHT_SHUFFLE: [&str; 4] = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+    /// A legal 1000-ply game: the knights shuffle back and forth 250 times.
+    fn thousand_ply_game() -> game::Model {
+        let moves: Vec<&str> = KNIGHT_SHUFFLE.iter().copied().cycle().take(1000).collect();
+        game::Model {
+            public_id: "Rp1qS5lG".to_string(),
+            pgn: json!({ "moves": moves }),
+            ..game::Model::fixture(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    #[test]
+    fn long_game_is_replayed_one_page_at_a_time() {
+        let game = thousand_ply_game();
+
+        let first = replay_page(&game, ReplayQuery::default(), DEFAULT_MAX_PLIES).unwrap();
+        assert_eq!((first.total_plies, first.from, first.to), (1000, 0, 200));
+        assert_eq!(first.fen, chess::fen::STARTING_FEN);
+        assert_eq!(first.plies.len(), 200);
+        assert_eq!(first.plies[0].san, "Nf3");
+
+        let query = ReplayQuery { from: Some(900), to: Some(1000) };
+        let last = replay_page(&game, query, DEFAULT_MAX_PLIES).unwrap();
+        assert_eq!(last.plies.len(), 100);
+        let final_ply = last.plies.last().unwrap();
+        assert_eq!((final_ply.ply, final_ply.san.as_str()), (1000, "Ng8"));
+        assert_eq!(final_ply.fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1000 501");
+    }
+
+    #[test]
+    fn range_past_the_end_is_clamped() {
+        let game = thousand_ply_game();
+        let page = replay_page(&game, ReplayQuery { from: Some(1200), to: None }, DEFAULT_MAX_PLIES).unwrap();
+
+        assert_eq!((page.from, page.to), (1000, 1000));
+        assert!(page.plies.is_empty());
+    }
+
+    #[test]
+    fn game_over_the_cap_is_rejected() {
+        let game = thousand_ply_game();
+        let result = replay_page(&game, ReplayQuery::default(), 999);
+
+        match result {
+            Err(ApiError::Conflict(message)) => assert!(message.contains("1000 plies"), "{}", message),
+            other => panic!("expected the cap to reject the game, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Public seeks: games advertised on a board and started by whoever claims the open
+//! seat first.
+
+use std::time::Duration;
+
+use chess::fen::generate_chess960_fen;
+use chess::time_control::{TimeClass, TimeControl, time_class};
+use chrono::Utc;
+use db::db::db::get_db;
+use dto::pagination::Page;
+use dto::seeks::{CreateSeekRequest, SeekDTO, SeeksQuery};
+use entity::sea_orm_active_enums::{GameVariant, SeekColor, SeekStatus};
+use entity::{game, game_seek};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::clocks::start_clocks;
+use crate::game_events::{self, GameEvent};
+use crate::games::{GameLimits, ensure_can_start_game_with, insert_game_with, new_game};
+use crate::pagination::{fetch_page, page_bounds};
+
+/// A seek that was claimed, with the game it started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptedSeek {
+    pub seek: game_seek::Model,
+    pub game: game::Model,
+}
+
+/// Speed category of a seek's clock, which decides the acceptor's game cap.
+fn seek_class(seek: &game_seek::Model) -> TimeClass {
+    time_class(&TimeControl {
+        initial_time: Duration::from_secs(seek.initial_secs.max(0) as u64),
+        increment: Duration::from_secs(seek.increment_secs.max(0) as u64),
+        delay: Duration::ZERO,
+    })
+}
+
+/// Chess960 seeks start from a random one of its 960 positions; every other variant
+/// starts from the standard position.
+fn seek_start(variant: GameVariant) -> Option<String> {
+    (variant == GameVariant::Chess960).then(generate_chess960_fen)
+}
+
+fn no_longer_open(seek_id: Uuid) -> ApiError {
+    ApiError::Conflict(format!("Seek {} is no longer open", seek_id))
+}
+
+pub async fn create_seek(creator: Uuid, request: CreateSeekRequest) -> Result<SeekDTO, ApiError> {
+    let db = get_db().await;
+    create_seek_with(&db, creator, request).await
+}
+
+pub async fn create_seek_with<C: ConnectionTrait>(
+    db: &C,
+    creator: Uuid,
+    request: CreateSeekRequest,
+) -> Result<SeekDTO, ApiError> {
+    let now = Utc::now();
+    let seek = game_seek::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        creator: Set(creator),
+        color: Set(request.color.map(SeekColor::from).unwrap_or(SeekColor::Random)),
+        variant: Set(request.variant.into()),
+        initial_secs: Set(request.time_control),
+        increment_secs: Set(request.increment),
+        status: Set(SeekStatus::Waiting),
+        accepted_by: Set(None),
+        game_id: Set(None),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+    }
+    .insert(db)
+    .await?;
+    Ok(seek.into())
+}
+
+pub async fn open_seeks(query: &SeeksQuery) -> Result<Page<SeekDTO>, ApiError> {
+    let db = get_db().await;
+    open_seeks_with(&db, query).await
+}
+
+/// Seeks still waiting for an opponent, oldest first.
+pub async fn open_seeks_with<C: ConnectionTrait>(db: &C, query: &SeeksQuery) -> Result<Page<SeekDTO>, ApiError> {
+    let (page, page_size) = page_bounds(query.page, query.limit);
+    let select = game_seek::Entity::find()
+        .filter(game_seek::Column::Status.eq(SeekStatus::Waiting))
+        .order_by_asc(game_seek::Column::CreatedAt)
+        .order_by_asc(game_seek::Column::Id);
+    Ok(fetch_page(select, db, page, page_size).await?.map(SeekDTO::from))
+}
+
+pub async fn accept_seek(seek_id: Uuid, acceptor: Uuid) -> Result<AcceptedSeek, ApiError> {
+    let db = get_db().await;
+    accept_seek_with(&db, &GameLimits::from_env(), seek_id, acceptor).await
+}
+
+/// Claims the open seat of a seek for `acceptor` and starts its game. The acceptor
+/// must be under their cap of unfinished games for the seek's time class.
+///
+/// The seat is claimed by an update that only matches while the seek is still
+/// waiting, so when several players accept at once exactly one of them gets the game;
+/// the others get `Conflict` and their transaction, game included, is rolled back.
+pub async fn accept_seek_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    limits: &GameLimits,
+    seek_id: Uuid,
+    acceptor: Uuid,
+) -> Result<AcceptedSeek, ApiError> {
+    let txn = db.begin().await?;
+
+    let seek = game_seek::Entity::find_by_id(seek_id)
+        .one(&txn)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Seek {}", seek_id)))?;
+    if seek.creator == acceptor {
+        return Err(ApiError::Conflict("You cannot accept your own seek".to_string()));
+    }
+    if seek.status != SeekStatus::Waiting {
+        return Err(no_longer_open(seek_id));
+    }
+    ensure_can_start_game_with(&txn, limits, acceptor, seek_class(&seek)).await?;
+
+    let creator_plays_white = match seek.color {
+        SeekColor::White => true,
+        SeekColor::Black => false,
+        SeekColor::Random => rand::random(),
+    };
+    let (white_player, black_player) = if creator_plays_white {
+        (seek.creator, acceptor)
+    } else {
+        (acceptor, seek.creator)
+    };
+
+    let now = Utc::now();
+    let starting_fen = seek_start(seek.variant);
+    let mut game = new_game(white_player, black_player, seek.variant, starting_fen.as_deref());
+    start_clocks(&mut game, seek.initial_secs, seek.increment_secs);
+    let game = insert_game_with(&txn, game).await?;
+
+    let claimed = game_seek::ActiveModel {
+        status: Set(SeekStatus::Accepted),
+        accepted_by: Set(Some(acceptor)),
+        game_id: Set(Some(game.id)),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let seek = game_seek::Entity::update_many()
+        .set(claimed)
+        .filter(game_seek::Column::Id.eq(seek_id))
+        .filter(game_seek::Column::Status.eq(SeekStatus::Waiting))
+        .exec_with_returning(&txn)
+        .await?
+        .pop()
+        .ok_or_else(|| no_longer_open(seek_id))?;
+
+    txn.commit().await?;
+    game_events::emit(GameEvent::Created, &game, Some(seek.creator));
+    game_events::emit(GameEvent::Joined, &game, Some(acceptor));
+    Ok(AcceptedSeek { seek, game })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::fen::Fen;
+    use entity::player;
+    use sea_orm::{DatabaseBackend, MockDatabase, Value};
+    use std::collections::BTreeMap;
+
+    const LIMITS: GameLimits = GameLimits { real_time: 3, correspondence: 10 };
+
+    fn no_games_in_progress() -> Vec<BTreeMap<&'static str, Value>> {
+        vec![BTreeMap::from([("num_items", Value::from(0i64))])]
+    }
+
+    fn seek(creator: Uuid, color: SeekColor, status: SeekStatus) -> game_seek::Model {
+        let now = Utc::now().into();
+        game_seek::Model {
+            id: Uuid::new_v4(),
+            creator,
+            color,
+            variant: GameVariant::Standard,
+            initial_secs: 300,
+            increment_secs: 3,
+            status,
+            accepted_by: None,
+            game_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn started_game(white: Uuid, black: Uuid) -> game::Model {
+        game::Model { public_id: "Sk9aB2eL".to_string(), ..game::Model::fixture(white, black) }
+    }
+
+    #[async_std::test]
+    async fn only_one_of_two_racing_acceptors_gets_the_seat() {
+        let (creator, first, second) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let open = seek(creator, SeekColor::White, SeekStatus::Waiting);
+        let claimed = game_seek::Model {
+            status: SeekStatus::Accepted,
+            ..open.clone()
+        };
+        // Both acceptors read the seek while it is still waiting; the conditional
+        // update then matches for the first claim only
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![open.clone()]])
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([no_games_in_progress()])
+            .append_query_results([vec![started_game(creator, first)]])
+            .append_query_results([vec![claimed]])
+            .append_query_results([vec![open.clone()]])
+            .append_query_results([Vec::<player::Model>::new()])
+            .append_query_results([no_games_in_progress()])
+            .append_query_results([vec![started_game(creator, second)]])
+            .append_query_results([Vec::<game_seek::Model>::new()])
+            .into_connection();
+
+        let (a, b) = futures::join!(
+            accept_seek_with(&db, &LIMITS, open.id, first),
+            accept_seek_with(&db, &LIMITS, open.id, second),
+        );
+
+        let outcomes = [a, b];
+        assert_eq!(outcomes.iter().filter(|outcome| outcome.is_ok()).count(), 1);
+        assert_eq!(
+            outcomes
+                .iter()
+                .filter(|outcome| matches!(outcome, Err(ApiError::Conflict(_))))
+                .count(),
+            1
+        );
+        let won = outcomes.into_iter().find_map(Result::ok).unwrap();
+        assert_eq!(won.seek.status, SeekStatus::Accepted);
+        assert_eq!(won.game.white_player, creator);
+    }
+
+    #[async_std::test]
+    async fn accepted_seeks_cannot_be_claimed_again() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![seek(Uuid::new_v4(), SeekColor::Random, SeekStatus::Accepted)]])
+            .into_connection();
+
+        let result = accept_seek_with(&db, &LIMITS, Uuid::new_v4(), Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[async_std::test]
+    async fn creators_cannot_accept_their_own_seek() {
+        let creator = Uuid::new_v4();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![seek(creator, SeekColor::Black, SeekStatus::Waiting)]])
+            .into_connection();
+
+        let result = accept_seek_with(&db, &LIMITS, Uuid::new_v4(), creator).await;
+
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[test]
+    fn chess960_seeks_start_from_a_random_back_rank() {
+        let fen = seek_start(GameVariant::Chess960).unwrap();
+
+        assert!(fen.parse::<Fen>().is_ok());
+        assert_eq!(fen.split(' ').nth(2), Some("KQkq"));
+        for variant in [GameVariant::Standard, GameVariant::Crazyhouse, GameVariant::KingOfTheHill] {
+            assert_eq!(seek_start(variant), None);
+        }
+    }
+}
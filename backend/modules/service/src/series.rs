@@ -0,0 +1,170 @@
+//! Rematch series.
+//!
+//! A game created as a rematch records the game it follows in `parent_game_id`, so
+//! a series is the chain of rematches starting at some game. When one game was
+//! rematched twice, the series follows the newer rematch.
+
+use std::env;
+
+use db::db::db::get_db;
+use dto::games::{GameSeries, SeriesGame, SeriesScore};
+use entity::game;
+use entity::sea_orm_active_enums::ResultSide;
+use error::error::ApiError;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use uuid::Uuid;
+
+use crate::archive::including_archived;
+use crate::games::find_game_with;
+
+/// Games a series is followed for when `GAME_SERIES_LENGTH` is not set.
+pub const DEFAULT_SERIES_LENGTH: usize = 5;
+
+/// Reads the series length from `GAME_SERIES_LENGTH`, falling back to 5.
+pub fn series_length() -> usize {
+    env::var("GAME_SERIES_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|length: &usize| *length > 0)
+        .unwrap_or(DEFAULT_SERIES_LENGTH)
+}
+
+pub async fn game_series(key: &str) -> Result<GameSeries, ApiError> {
+    let db = get_db().await;
+    game_series_with(&db, key, series_length()).await
+}
+
+/// Loads the series starting at the game `key` names, up to `length` games.
+pub async fn game_series_with<C: ConnectionTrait>(db: &C, key: &str, length: usize) -> Result<GameSeries, ApiError> {
+    let mut games = vec![find_game_with(db, key).await?];
+    while games.len() < length {
+        let previous = games[games.len() - 1].id;
+        let rematch = including_archived(game::Entity::find())
+            .filter(game::Column::ParentGameId.eq(previous))
+            .order_by_desc(game::Column::CreatedAt)
+            .order_by_desc(game::Column::Id)
+            .one(db)
+            .await?;
+        match rematch {
+            Some(rematch) => games.push(rematch),
+            None => break,
+        }
+    }
+
+    Ok(score_series(&games, length))
+}
+
+/// Points `player` took from `game`; nothing while it is unfinished or aborted.
+fn points(game: &game::Model, player: Uuid) -> f32 {
+    match game.result {
+        Some(ResultSide::Draw) => 0.5,
+        Some(ResultSide::White) if game.white_player == player => 1.0,
+        Some(ResultSide::Black) if game.black_player == player => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Scores a chain of games, oldest first, from the view of the first game's players.
+pub fn score_series(games: &[game::Model], length: usize) -> GameSeries {
+    let root = &games[0];
+    let (player_one, player_two) = (root.white_player, root.black_player);
+    let mut score = SeriesScore::default();
+    let games = games
+        .iter()
+        .map(|game| {
+            score.player_one += points(game, player_one);
+            score.player_two += points(game, player_two);
+            SeriesGame {
+                game_id: game.id,
+                public_id: game.public_id.clone(),
+                white_player_id: game.white_player,
+                black_player_id: game.black_player,
+                result: game.result,
+                score,
+            }
+        })
+        .collect();
+
+    GameSeries {
+        root_id: root.id,
+        player_one,
+        player_two,
+        length: length as u32,
+        games,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use entity::sea_orm_active_enums::Termination;
+    use sea_orm::{DatabaseBackend, MockDatabase};
+    use serde_json::json;
+
+    fn played(white: Uuid, black: Uuid, result: ResultSide, parent: Option<&game::Model>) -> game::Model {
+        let created = Utc::now() + Duration::minutes(parent.map_or(0, |_| 10));
+        game::Model {
+            public_id: "Sr3sGm1x".to_string(),
+            pgn: json!({ "moves": ["e4", "e5"] }),
+            result: Some(result),
+            termination: Some(Termination::Resignation),
+            started_at: created.into(),
+            ended_at: Some(created.into()),
+            parent_game_id: parent.map(|game| game.id),
+            created_at: created.into(),
+            updated_at: created.into(),
+            ..game::Model::fixture(white, black)
+        }
+    }
+
+    #[async_std::test]
+    async fn three_game_series_keeps_a_running_score_for_both_players() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = played(alice, bob, ResultSide::White, None);
+        let second = played(bob, alice, ResultSide::Draw, Some(&first));
+        let third = played(alice, bob, ResultSide::Black, Some(&second));
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone()], vec![second.clone()], vec![third.clone()]])
+            .append_query_results([Vec::<game::Model>::new()])
+            .into_connection();
+
+        let series = game_series_with(&db, &first.id.to_string(), 5).await.unwrap();
+
+        assert_eq!((series.root_id, series.player_one, series.player_two), (first.id, alice, bob));
+        let order: Vec<Uuid> = series.games.iter().map(|game| game.game_id).collect();
+        assert_eq!(order, vec![first.id, second.id, third.id]);
+        let running: Vec<(f32, f32)> = series
+            .games
+            .iter()
+            .map(|game| (game.score.player_one, game.score.player_two))
+            .collect();
+        assert_eq!(running, vec![(1.0, 0.0), (1.5, 0.5), (1.5, 1.5)]);
+        assert_eq!(series.games[2].result, Some(ResultSide::Black));
+        assert_eq!(series.score, SeriesScore { player_one: 1.5, player_two: 1.5 });
+
+        // With two rematches of one game, the newest is followed
+        let log = db.into_transaction_log();
+        let sql = &log[1].statements()[0].sql;
+        assert!(sql.contains(r#"ORDER BY "game"."created_at" DESC, "game"."id" DESC"#), "{}", sql);
+    }
+
+    #[async_std::test]
+    async fn series_stops_at_its_length() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = played(alice, bob, ResultSide::White, None);
+        let second = played(bob, alice, ResultSide::White, Some(&first));
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone()], vec![second.clone()]])
+            .into_connection();
+
+        let series = game_series_with(&db, &first.id.to_string(), 2).await.unwrap();
+
+        assert_eq!(series.games.len(), 2);
+        assert_eq!(series.score, SeriesScore { player_one: 1.0, player_two: 1.0 });
+        assert_eq!(db.into_transaction_log().len(), 2, "no rematch is looked up past the length");
+    }
+}
@@ -0,0 +1,97 @@
+use db::db::db::get_db;
+use dto::games::Variant;
+use dto::stats::{ColorAdvantageDTO, ColorAdvantageQuery};
+use entity::game;
+use entity::sea_orm_active_enums::{GameVariant, ResultSide};
+use error::error::ApiError;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect};
+
+use crate::archive::including_archived;
+
+/// Finished games a variant needs before its color statistics are reported.
+pub const DEFAULT_MIN_GAMES: i64 = 100;
+
+pub async fn color_advantage(query: ColorAdvantageQuery) -> Result<ColorAdvantageDTO, ApiError> {
+    let db = get_db().await;
+    color_advantage_with(
+        &db,
+        query.variant.unwrap_or_default(),
+        query.min_games.unwrap_or(DEFAULT_MIN_GAMES),
+    )
+    .await
+}
+
+/// Counts the finished games of `variant`, archived ones included, per result with a
+/// single grouped aggregate.
+pub async fn color_advantage_with<C: ConnectionTrait>(
+    db: &C,
+    variant: Variant,
+    min_games: i64,
+) -> Result<ColorAdvantageDTO, ApiError> {
+    let counts: Vec<(ResultSide, i64)> = including_archived(game::Entity::find())
+        .select_only()
+        .column(game::Column::Result)
+        .column_as(game::Column::Id.count(), "games")
+        .filter(game::Column::Variant.eq(GameVariant::from(variant)))
+        .filter(game::Column::Result.is_not_null())
+        .group_by(game::Column::Result)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(summarize_results(variant, &counts, min_games))
+}
+
+fn summarize_results(variant: Variant, counts: &[(ResultSide, i64)], min_games: i64) -> ColorAdvantageDTO {
+    let count_of = |side: ResultSide| {
+        counts
+            .iter()
+            .filter(|(result, _)| *result == side)
+            .map(|(_, count)| count)
+            .sum::<i64>()
+    };
+    let games: i64 = counts.iter().map(|(_, count)| count).sum();
+    let sufficient_sample = games >= min_games && games > 0;
+    // Percentages are rounded to two decimals
+    let pct = |side: ResultSide| {
+        sufficient_sample.then(|| (count_of(side) as f64 * 10000.0 / games as f64).round() / 100.0)
+    };
+
+    ColorAdvantageDTO {
+        variant,
+        games,
+        min_games,
+        sufficient_sample,
+        white_win_pct: pct(ResultSide::White),
+        black_win_pct: pct(ResultSide::Black),
+        draw_pct: pct(ResultSide::Draw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentages_cover_every_result() {
+        let counts = [(ResultSide::White, 520), (ResultSide::Black, 410), (ResultSide::Draw, 70)];
+        let stats = summarize_results(Variant::KingOfTheHill, &counts, DEFAULT_MIN_GAMES);
+
+        assert_eq!(stats.games, 1000);
+        assert!(stats.sufficient_sample);
+        assert_eq!(stats.white_win_pct, Some(52.0));
+        assert_eq!(stats.black_win_pct, Some(41.0));
+        assert_eq!(stats.draw_pct, Some(7.0));
+    }
+
+    #[test]
+    fn small_samples_report_counts_only() {
+        let counts = [(ResultSide::White, 3), (ResultSide::Draw, 1)];
+        let stats = summarize_results(Variant::Standard, &counts, DEFAULT_MIN_GAMES);
+
+        assert_eq!(stats.games, 4);
+        assert!(!stats.sufficient_sample);
+        assert_eq!(stats.white_win_pct, None);
+        assert_eq!(stats.draw_pct, None);
+    }
+}
@@ -0,0 +1,694 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+use chrono::{DateTime, Utc};
+use db::db::db::get_db;
+use dto::tournaments::{StandingDTO, TieBreak, TournamentStandingsDTO};
+use entity::sea_orm_active_enums::{ForfeitPolicy, GameVariant, ResultSide};
+use entity::{game, tournament, tournament_pairing};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::game_events::{self, GameEvent};
+use crate::games::{insert_game_with, new_game};
+use crate::lifecycle::{ABORT_WINDOW_PLIES, forfeit_no_show_with, ply_count};
+use crate::notifications;
+use crate::rating::{RatingEngine, rate_results_with};
+
+/// Points awarded for a bye.
+pub const BYE_POINTS: f64 = 1.0;
+
+/// Time a tournament game may go without both players moving before the one on move
+/// is forfeited.
+pub const DEFAULT_NO_SHOW_SECS: u64 = 600;
+
+/// One board of a round: White, and Black or `None` for a bye.
+pub type Board = (Uuid, Option<Uuid>);
+
+/// What one round meant for one player. Rounds whose game has not finished yet are
+/// left out entirely.
+#[derive(Debug, Clone, Copy)]
+enum RoundOutcome {
+    Played { opponent: Uuid, points: f64 },
+    Bye,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    points: f64,
+    rounds: Vec<RoundOutcome>,
+}
+
+impl Tally {
+    /// Score used when this player is someone's opponent: byes count as draws, so
+    /// an opponent's free point does not inflate anyone's tie-break.
+    fn adjusted_points(&self) -> f64 {
+        self.rounds
+            .iter()
+            .map(|round| match round {
+                RoundOutcome::Played { points, .. } => *points,
+                RoundOutcome::Bye => 0.5,
+            })
+            .sum()
+    }
+
+    fn games_played(&self) -> u32 {
+        self.rounds
+            .iter()
+            .filter(|round| matches!(round, RoundOutcome::Played { .. }))
+            .count() as u32
+    }
+}
+
+fn tally_pairings(pairings: &[tournament_pairing::Model]) -> HashMap<Uuid, Tally> {
+    let mut tallies: HashMap<Uuid, Tally> = HashMap::new();
+    for pairing in pairings {
+        // Everyone paired appears in the standings, finished or not
+        tallies.entry(pairing.white_player).or_default();
+        let Some(black) = pairing.black_player else {
+            let white = tallies.entry(pairing.white_player).or_default();
+            white.points += BYE_POINTS;
+            white.rounds.push(RoundOutcome::Bye);
+            continue;
+        };
+        tallies.entry(black).or_default();
+
+        let Some(result) = pairing.result else {
+            continue;
+        };
+        let (white_points, black_points) = match result {
+            ResultSide::White => (1.0, 0.0),
+            ResultSide::Black => (0.0, 1.0),
+            ResultSide::Draw => (0.5, 0.5),
+        };
+        for (player, opponent, points) in [
+            (pairing.white_player, black, white_points),
+            (black, pairing.white_player, black_points),
+        ] {
+            let tally = tallies.entry(player).or_default();
+            tally.points += points;
+            tally.rounds.push(RoundOutcome::Played { opponent, points });
+        }
+    }
+    tallies
+}
+
+/// Ranks the players of a tournament by points, then `tie_break`, then the other
+/// tie-break, from its pairings.
+///
+/// Buchholz sums the opponents' scores and Sonneborn-Berger weights each by the
+/// points taken from that opponent. Opponents' scores count their byes as draws, and
+/// a player's own bye is scored as a draw against a virtual opponent holding the
+/// player's own score. Games without a result yet contribute nothing.
+pub fn compute_standings(pairings: &[tournament_pairing::Model], tie_break: TieBreak) -> Vec<StandingDTO> {
+    let tallies = tally_pairings(pairings);
+    let adjusted: HashMap<Uuid, f64> = tallies
+        .iter()
+        .map(|(player, tally)| (*player, tally.adjusted_points()))
+        .collect();
+
+    let mut standings: Vec<StandingDTO> = tallies
+        .iter()
+        .map(|(player, tally)| {
+            let (mut buchholz, mut sonneborn_berger) = (0.0, 0.0);
+            for round in &tally.rounds {
+                let (opponent_score, points) = match round {
+                    RoundOutcome::Played { opponent, points } => (adjusted[opponent], *points),
+                    RoundOutcome::Bye => (adjusted[player], 0.5),
+                };
+                buchholz += opponent_score;
+                sonneborn_berger += opponent_score * points;
+            }
+            StandingDTO {
+                rank: 0,
+                player_id: *player,
+                points: tally.points,
+                buchholz,
+                sonneborn_berger,
+                games_played: tally.games_played(),
+            }
+        })
+        .collect();
+
+    let tie_breaks = |standing: &StandingDTO| match tie_break {
+        TieBreak::Buchholz => [standing.points, standing.buchholz, standing.sonneborn_berger],
+        TieBreak::SonnebornBerger => [standing.points, standing.sonneborn_berger, standing.buchholz],
+    };
+    // Scores are sums of quarters, which f64 represents exactly
+    let by_score = |a: &StandingDTO, b: &StandingDTO| {
+        tie_breaks(b)
+            .partial_cmp(&tie_breaks(a))
+            .unwrap_or(Ordering::Equal)
+    };
+    standings.sort_by(|a, b| by_score(a, b).then(a.player_id.cmp(&b.player_id)));
+
+    for index in 0..standings.len() {
+        standings[index].rank = if index > 0 && by_score(&standings[index - 1], &standings[index]).is_eq() {
+            standings[index - 1].rank
+        } else {
+            index as u32 + 1
+        };
+    }
+    standings
+}
+
+pub async fn standings(tournament_id: Uuid) -> Result<TournamentStandingsDTO, ApiError> {
+    let db = get_db().await;
+    standings_with(&db, tournament_id).await
+}
+
+pub async fn standings_with<C: ConnectionTrait>(
+    db: &C,
+    tournament_id: Uuid,
+) -> Result<TournamentStandingsDTO, ApiError> {
+    let tournament = tournament::Entity::find_by_id(tournament_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Tournament {}", tournament_id)))?;
+    let pairings = tournament_pairing::Entity::find()
+        .filter(tournament_pairing::Column::TournamentId.eq(tournament_id))
+        .order_by_asc(tournament_pairing::Column::Round)
+        .all(db)
+        .await?;
+
+    let tie_break = TieBreak::from(tournament.tie_break);
+    Ok(TournamentStandingsDTO {
+        tournament_id,
+        tie_break,
+        standings: compute_standings(&pairings, tie_break),
+    })
+}
+
+/// Pairs the next round from the pairings played so far, Swiss-style: players are
+/// taken in standings order and each is paired with the highest-placed player left
+/// that they have not met yet, falling back to a rematch only when everyone left is
+/// a past opponent. With an odd number of players the lowest-placed one who has not
+/// had a bye yet gets it. Whoever has had White less often gets White, the
+/// higher-placed player on a tie.
+pub fn swiss_pairings(pairings: &[tournament_pairing::Model], tie_break: TieBreak) -> Vec<Board> {
+    let mut met: HashSet<(Uuid, Uuid)> = HashSet::new();
+    let mut had_bye: HashSet<Uuid> = HashSet::new();
+    let mut white_balance: HashMap<Uuid, i32> = HashMap::new();
+    for pairing in pairings {
+        match pairing.black_player {
+            Some(black) => {
+                met.insert((pairing.white_player, black));
+                met.insert((black, pairing.white_player));
+                *white_balance.entry(pairing.white_player).or_default() += 1;
+                *white_balance.entry(black).or_default() -= 1;
+            }
+            None => {
+                had_bye.insert(pairing.white_player);
+            }
+        }
+    }
+
+    let mut players: Vec<Uuid> = compute_standings(pairings, tie_break)
+        .into_iter()
+        .map(|standing| standing.player_id)
+        .collect();
+    let bye = (players.len() % 2 == 1).then(|| {
+        let index = players
+            .iter()
+            .rposition(|player| !had_bye.contains(player))
+            .unwrap_or(players.len() - 1);
+        players.remove(index)
+    });
+
+    let mut boards = Vec::new();
+    while !players.is_empty() {
+        let top = players.remove(0);
+        let index = players
+            .iter()
+            .position(|opponent| !met.contains(&(top, *opponent)))
+            .unwrap_or(0);
+        let opponent = players.remove(index);
+        let balance = |player: &Uuid| white_balance.get(player).copied().unwrap_or(0);
+        if balance(&opponent) < balance(&top) {
+            boards.push((opponent, Some(top)));
+        } else {
+            boards.push((top, Some(opponent)));
+        }
+    }
+    boards.extend(bye.map(|player| (player, None)));
+    boards
+}
+
+/// Result a board is scored with when its game ended without one: under
+/// [`ForfeitPolicy::Loss`] the player who was on move, and so never showed up, loses.
+fn forfeit_result(game: &game::Model, policy: ForfeitPolicy) -> ResultSide {
+    match policy {
+        ForfeitPolicy::Draw => ResultSide::Draw,
+        ForfeitPolicy::Loss if ply_count(game).is_multiple_of(2) => ResultSide::Black,
+        ForfeitPolicy::Loss => ResultSide::White,
+    }
+}
+
+async fn lock_tournament<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<tournament::Model, ApiError> {
+    tournament::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Tournament {}", id)))
+}
+
+async fn tournament_pairings<C: ConnectionTrait>(
+    db: &C,
+    tournament_id: Uuid,
+) -> Result<Vec<tournament_pairing::Model>, ApiError> {
+    Ok(tournament_pairing::Entity::find()
+        .filter(tournament_pairing::Column::TournamentId.eq(tournament_id))
+        .order_by_asc(tournament_pairing::Column::Round)
+        .all(db)
+        .await?)
+}
+
+/// A round is over once every board that is not a bye has a result.
+fn round_over(pairings: &[tournament_pairing::Model], round: i32) -> bool {
+    pairings
+        .iter()
+        .filter(|pairing| pairing.round == round)
+        .all(|pairing| pairing.black_player.is_none() || pairing.result.is_some())
+}
+
+/// Records `boards` as `round` of the tournament and starts a game for each one that
+/// is not a bye. Returns the games started.
+async fn start_round_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    tournament_id: Uuid,
+    round: i32,
+    boards: &[Board],
+) -> Result<Vec<game::Model>, ApiError> {
+    let mut games = Vec::new();
+    for (white, black) in boards {
+        let game_id = match black {
+            Some(black) => {
+                let game = insert_game_with(db, new_game(*white, *black, GameVariant::Standard, None)).await?;
+                let id = game.id;
+                games.push(game);
+                Some(id)
+            }
+            None => None,
+        };
+        tournament_pairing::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            tournament_id: Set(tournament_id),
+            round: Set(round),
+            white_player: Set(*white),
+            black_player: Set(*black),
+            game_id: Set(game_id),
+            result: Set(None),
+            created_at: Set(Utc::now().into()),
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(games)
+}
+
+pub async fn start_round(tournament_id: Uuid, boards: Vec<Board>) -> Result<Vec<game::Model>, ApiError> {
+    let db = get_db().await;
+    start_next_round_with(&db, tournament_id, &boards).await
+}
+
+/// Starts the tournament's next round with boards paired by the caller, which is how
+/// the first round begins; later rounds are paired by [`ingest_result_with`]. The
+/// previous round has to be over and nobody may sit at two boards.
+pub async fn start_next_round_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    tournament_id: Uuid,
+    boards: &[Board],
+) -> Result<Vec<game::Model>, ApiError> {
+    let txn = db.begin().await?;
+
+    let tournament = lock_tournament(&txn, tournament_id).await?;
+    let pairings = tournament_pairings(&txn, tournament_id).await?;
+    let last_round = pairings.iter().map(|pairing| pairing.round).max().unwrap_or(0);
+    if last_round >= tournament.rounds {
+        return Err(ApiError::Conflict(format!("Tournament {} has played all its rounds", tournament_id)));
+    }
+    if last_round > 0 && !round_over(&pairings, last_round) {
+        return Err(ApiError::Conflict(format!("Round {} is still being played", last_round)));
+    }
+    let mut seated = HashSet::new();
+    for player in boards.iter().flat_map(|(white, black)| std::iter::once(*white).chain(*black)) {
+        if !seated.insert(player) {
+            return Err(ApiError::Conflict(format!("Player {} is paired twice", player)));
+        }
+    }
+
+    let games = start_round_with(&txn, tournament_id, last_round + 1, boards).await?;
+
+    txn.commit().await?;
+    for game in &games {
+        game_events::emit(GameEvent::Created, game, None);
+    }
+    notifications::queue(notifications::round_start_notifications(&games));
+    Ok(games)
+}
+
+/// Scores the board `game` was played on, if it is a tournament game. When that was
+/// the last unfinished board of the round, the round's games are rated together
+/// through [`rate_results_with`] and the next round is paired and started.
+/// A game that ended without a result is scored by the tournament's forfeit policy
+/// and left unrated. Returns `None` for a game outside any tournament, otherwise the
+/// games of the round it started, if any.
+///
+/// Runs inside the finalizing transaction. The tournament row is locked first, so
+/// when the last two boards of a round finish at once the second one to commit sees
+/// the first one's result and exactly one of them rates the round and pairs the next.
+pub async fn ingest_result_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    game: &game::Model,
+) -> Result<Option<Vec<game::Model>>, ApiError> {
+    let Some(pairing) = tournament_pairing::Entity::find()
+        .filter(tournament_pairing::Column::GameId.eq(game.id))
+        .filter(tournament_pairing::Column::Result.is_null())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let tournament = lock_tournament(db, pairing.tournament_id).await?;
+
+    let round = pairing.round;
+    let mut scored: tournament_pairing::ActiveModel = pairing.into();
+    scored.result = Set(Some(
+        game.result
+            .unwrap_or_else(|| forfeit_result(game, tournament.forfeit_policy)),
+    ));
+    scored.update(db).await?;
+
+    let pairings = tournament_pairings(db, tournament.id).await?;
+    let last_round = pairings.iter().map(|pairing| pairing.round).max().unwrap_or(0);
+    if round != last_round || !round_over(&pairings, round) {
+        return Ok(Some(Vec::new()));
+    }
+
+    // Nobody sits at two boards of a round, so the order they are rated in is moot
+    let results: Vec<(Uuid, ResultSide)> = pairings
+        .iter()
+        .filter(|pairing| pairing.round == round)
+        .filter_map(|pairing| Some((pairing.game_id?, pairing.result?)))
+        .collect();
+    rate_results_with(db, engine, &results).await?;
+
+    if round >= tournament.rounds {
+        return Ok(Some(Vec::new()));
+    }
+    let boards = swiss_pairings(&pairings, TieBreak::from(tournament.tie_break));
+    Ok(Some(start_round_with(db, tournament.id, round + 1, &boards).await?))
+}
+
+pub async fn is_tournament_game<C: ConnectionTrait>(db: &C, game_id: Uuid) -> Result<bool, ApiError> {
+    Ok(tournament_pairing::Entity::find()
+        .filter(tournament_pairing::Column::GameId.eq(game_id))
+        .one(db)
+        .await?
+        .is_some())
+}
+
+pub async fn forfeit_no_shows() -> Result<usize, ApiError> {
+    let db = get_db().await;
+    let no_show_secs = env::var("TOURNAMENT_NO_SHOW_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_NO_SHOW_SECS);
+    let cutoff = Utc::now() - chrono::Duration::seconds(no_show_secs as i64);
+    forfeit_no_shows_with(&db, &RatingEngine::from_env(), cutoff).await
+}
+
+/// Forfeits every unfinished tournament game started before `cutoff` in which the
+/// players have not both moved yet. Returns how many were forfeited.
+pub async fn forfeit_no_shows_with<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    engine: &RatingEngine,
+    cutoff: DateTime<Utc>,
+) -> Result<usize, ApiError> {
+    let game_ids: Vec<Uuid> = tournament_pairing::Entity::find()
+        .filter(tournament_pairing::Column::GameId.is_not_null())
+        .filter(tournament_pairing::Column::Result.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|pairing| pairing.game_id)
+        .collect();
+    if game_ids.is_empty() {
+        return Ok(0);
+    }
+    let stalled: Vec<game::Model> = game::Entity::find()
+        .filter(game::Column::Id.is_in(game_ids))
+        .filter(game::Column::EndedAt.is_null())
+        .filter(game::Column::StartedAt.lt(cutoff))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|game| ply_count(game) < ABORT_WINDOW_PLIES)
+        .collect();
+
+    let mut forfeited = 0;
+    for game in stalled {
+        match forfeit_no_show_with(db, engine, game.id).await {
+            Ok(_) => forfeited += 1,
+            // A player moved, or the game ended, since it was read
+            Err(ApiError::Conflict(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(forfeited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::sea_orm_active_enums::TieBreak as DbTieBreak;
+    use entity::player_rating;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+
+    fn player(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn pairing(round: i32, white: u128, black: Option<u128>, result: Option<ResultSide>) -> tournament_pairing::Model {
+        tournament_pairing::Model {
+            id: Uuid::new_v4(),
+            tournament_id: Uuid::nil(),
+            round,
+            white_player: player(white),
+            black_player: black.map(player),
+            game_id: None,
+            result,
+            created_at: Utc::now().into(),
+        }
+    }
+
+    fn order(standings: &[StandingDTO]) -> Vec<(u32, Uuid)> {
+        standings.iter().map(|s| (s.rank, s.player_id)).collect()
+    }
+
+    #[test]
+    fn buchholz_separates_players_on_equal_points() {
+        let (a, b, c, d, e, f) = (2, 3, 1, 4, 5, 6);
+        let pairings = [
+            pairing(1, a, Some(b), Some(ResultSide::White)),
+            pairing(1, c, Some(d), Some(ResultSide::White)),
+            pairing(1, e, Some(f), Some(ResultSide::Draw)),
+            pairing(2, a, Some(e), Some(ResultSide::White)),
+            pairing(2, c, Some(f), Some(ResultSide::White)),
+            pairing(2, b, Some(d), Some(ResultSide::White)),
+        ];
+
+        let standings = compute_standings(&pairings, TieBreak::Buchholz);
+
+        // A and C both have 2 points, but A beat stronger opposition
+        assert_eq!(
+            order(&standings),
+            vec![(1, player(a)), (2, player(c)), (3, player(b)), (4, player(e)), (4, player(f)), (6, player(d))]
+        );
+        assert_eq!(standings[0].buchholz, 1.5);
+        assert_eq!(standings[1].buchholz, 0.5);
+        assert_eq!(standings[3].sonneborn_berger, 0.25);
+    }
+
+    #[test]
+    fn byes_count_as_draws_and_pending_games_are_ignored() {
+        let (a, b, c) = (1, 2, 3);
+        let pairings = [
+            pairing(1, a, Some(b), Some(ResultSide::White)),
+            pairing(1, c, None, None),
+            pairing(2, c, Some(a), None),
+            pairing(2, b, None, None),
+        ];
+
+        let standings = compute_standings(&pairings, TieBreak::Buchholz);
+
+        assert!(standings.iter().all(|s| s.points == 1.0));
+        assert_eq!(order(&standings), vec![(1, player(b)), (2, player(a)), (3, player(c))]);
+        // B's only point is a bye, which A's Buchholz counts as a draw
+        assert_eq!((standings[1].buchholz, standings[1].games_played), (0.5, 1));
+        assert_eq!((standings[2].buchholz, standings[2].games_played), (0.5, 0));
+    }
+
+    #[test]
+    fn swiss_pairing_avoids_rematches() {
+        let pairings = [
+            pairing(1, 1, Some(2), Some(ResultSide::Draw)),
+            pairing(1, 3, Some(4), Some(ResultSide::Draw)),
+        ];
+
+        let boards = swiss_pairings(&pairings, TieBreak::Buchholz);
+
+        // 1 and 2 lead on the player-id tie-break but have already met
+        assert_eq!(boards, vec![(player(1), Some(player(3))), (player(2), Some(player(4)))]);
+    }
+
+    #[test]
+    fn swiss_pairing_gives_the_bye_to_the_lowest_placed_player_without_one() {
+        let pairings = [
+            pairing(1, 1, Some(2), Some(ResultSide::White)),
+            pairing(1, 3, Some(4), Some(ResultSide::Draw)),
+            pairing(1, 5, None, None),
+        ];
+
+        let boards = swiss_pairings(&pairings, TieBreak::Buchholz);
+
+        // 5 leads on Buchholz, 1 gets Black after playing White, and 3 and 4 are
+        // left with nobody but each other
+        assert_eq!(
+            boards,
+            vec![(player(5), Some(player(1))), (player(4), Some(player(3))), (player(2), None)]
+        );
+    }
+
+    fn unplayed_game(moves: &[&str]) -> game::Model {
+        game::Model {
+            public_id: "T0urn4mt".to_string(),
+            pgn: serde_json::json!({ "moves": moves }),
+            ..game::Model::fixture(player(1), player(2))
+        }
+    }
+
+    #[test]
+    fn no_shows_lose_or_draw_by_policy() {
+        assert_eq!(forfeit_result(&unplayed_game(&[]), ForfeitPolicy::Loss), ResultSide::Black);
+        assert_eq!(forfeit_result(&unplayed_game(&["e4"]), ForfeitPolicy::Loss), ResultSide::White);
+        assert_eq!(forfeit_result(&unplayed_game(&[]), ForfeitPolicy::Draw), ResultSide::Draw);
+    }
+
+    fn two_round_tournament() -> tournament::Model {
+        tournament::Model {
+            id: Uuid::nil(),
+            name: "Club championship".to_string(),
+            tie_break: DbTieBreak::Buchholz,
+            rounds: 2,
+            forfeit_policy: ForfeitPolicy::Loss,
+            created_at: Utc::now().into(),
+            updated_at: Utc::now().into(),
+        }
+    }
+
+    #[async_std::test]
+    async fn last_result_of_a_round_starts_the_next_one() {
+        let mut finished = unplayed_game(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]);
+        finished.result = Some(ResultSide::White);
+        finished.ended_at = Some(Utc::now().into());
+        let board = tournament_pairing::Model { game_id: Some(finished.id), ..pairing(1, 1, Some(2), None) };
+        let scored = tournament_pairing::Model { result: Some(ResultSide::White), ..board.clone() };
+        let rematch = unplayed_game(&[]);
+        let written = || MockExecResult { last_insert_id: 0, rows_affected: 2 };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![board]])
+            .append_query_results([vec![two_round_tournament()]])
+            .append_query_results([vec![scored.clone()], vec![scored.clone()]])
+            .append_query_results([vec![finished.clone()]])
+            .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+            .append_query_results([Vec::<player_rating::Model>::new()])
+            .append_exec_results([written(), written(), written(), written()])
+            .append_query_results([vec![rematch.clone()]])
+            .append_query_results([vec![pairing(2, 2, Some(1), None)]])
+            .into_connection();
+
+        let next_round = ingest_result_with(&db, &RatingEngine::default(), &finished).await.unwrap();
+
+        assert_eq!(next_round, Some(vec![rematch]));
+        let log = db.into_transaction_log();
+        let rated = log
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .filter(|s| s.sql.starts_with(r#"INSERT INTO "smdb"."rating_history""#))
+            .count();
+        assert_eq!(rated, 1);
+    }
+
+    #[async_std::test]
+    async fn finishing_the_last_round_rates_it_without_pairing_another() {
+        let mut finished = unplayed_game(&["e4", "e5"]);
+        finished.result = Some(ResultSide::Draw);
+        finished.ended_at = Some(Utc::now().into());
+        let board = tournament_pairing::Model { game_id: Some(finished.id), ..pairing(2, 1, Some(2), None) };
+        let scored = tournament_pairing::Model { result: Some(ResultSide::Draw), ..board.clone() };
+        let written = || MockExecResult { last_insert_id: 0, rows_affected: 2 };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![board]])
+            .append_query_results([vec![two_round_tournament()]])
+            .append_query_results([vec![scored.clone()], vec![scored]])
+            .append_query_results([vec![finished.clone()]])
+            .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+            .append_query_results([Vec::<player_rating::Model>::new()])
+            .append_exec_results([written(), written(), written(), written()])
+            .into_connection();
+
+        let next_round = ingest_result_with(&db, &RatingEngine::default(), &finished).await.unwrap();
+
+        assert_eq!(next_round, Some(Vec::new()));
+    }
+
+    #[async_std::test]
+    async fn games_outside_a_tournament_are_left_alone() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<tournament_pairing::Model>::new()])
+            .into_connection();
+
+        let ingested = ingest_result_with(&db, &RatingEngine::default(), &unplayed_game(&[])).await.unwrap();
+
+        assert_eq!(ingested, None);
+    }
+
+    #[async_std::test]
+    async fn result_while_other_boards_play_on_only_scores_its_board() {
+        let mut finished = unplayed_game(&["e4", "e5"]);
+        finished.result = Some(ResultSide::Draw);
+        let board = tournament_pairing::Model { game_id: Some(finished.id), ..pairing(1, 1, Some(2), None) };
+        let scored = tournament_pairing::Model { result: Some(ResultSide::Draw), ..board.clone() };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![board]])
+            .append_query_results([vec![two_round_tournament()]])
+            .append_query_results([vec![scored.clone()], vec![scored, pairing(1, 3, Some(4), None)]])
+            .into_connection();
+
+        let next_round = ingest_result_with(&db, &RatingEngine::default(), &finished).await.unwrap();
+
+        assert_eq!(next_round, Some(Vec::new()));
+    }
+
+    #[async_std::test]
+    async fn unknown_tournament_is_not_found() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<tournament::Model>::new()])
+            .into_connection();
+
+        let result = standings_with(&db, Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+}
@@ -0,0 +1,500 @@
+//! Reliable webhook delivery.
+//!
+//! Webhook notifications are written to an outbox and posted by [`deliver_pending`].
+//! Each event is queued at most once per subscriber, identified by its idempotency
+//! key, and a subscriber's deliveries go out strictly in the order they were queued:
+//! while one is waiting for a retry, the ones behind it wait too. Failed attempts are
+//! retried with exponential backoff until the delivery is dead-lettered.
+
+use std::env;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use db::db::db::get_db;
+use dto::pagination::Page;
+use dto::webhooks::WebhookDeliveryDTO;
+use entity::sea_orm_active_enums::DeliveryStatus;
+use entity::{webhook_attempt, webhook_delivery};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, sea_query::OnConflict,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::notifications::Notification;
+use crate::pagination::fetch_page;
+use crate::players::player_exists;
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 8;
+const DEFAULT_BACKOFF_BASE_SECS: u64 = 10;
+const DEFAULT_BACKOFF_MAX_SECS: u64 = 3600;
+const DEFAULT_RATE_PER_MINUTE: u64 = 30;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Delivery policy, read from `WEBHOOK_MAX_ATTEMPTS`, `WEBHOOK_BACKOFF_BASE_SECS`,
+/// `WEBHOOK_BACKOFF_MAX_SECS`, `WEBHOOK_RATE_PER_MINUTE` and `WEBHOOK_TIMEOUT_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Attempts before a delivery is dead-lettered.
+    pub max_attempts: i32,
+    /// Wait after the first failure; each further failure doubles it.
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Attempts allowed per subscriber in any one-minute window.
+    pub rate_per_minute: u64,
+    pub timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff_base: Duration::from_secs(DEFAULT_BACKOFF_BASE_SECS),
+            backoff_max: Duration::from_secs(DEFAULT_BACKOFF_MAX_SECS),
+            rate_per_minute: DEFAULT_RATE_PER_MINUTE,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        fn setting<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+        let defaults = Self::default();
+        Self {
+            max_attempts: setting("WEBHOOK_MAX_ATTEMPTS").filter(|n| *n > 0).unwrap_or(defaults.max_attempts),
+            backoff_base: setting("WEBHOOK_BACKOFF_BASE_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.backoff_base),
+            backoff_max: setting("WEBHOOK_BACKOFF_MAX_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.backoff_max),
+            rate_per_minute: setting("WEBHOOK_RATE_PER_MINUTE").filter(|n| *n > 0).unwrap_or(defaults.rate_per_minute),
+            timeout: setting("WEBHOOK_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.timeout),
+        }
+    }
+
+    /// Wait before the attempt after `failed_attempts` consecutive failures.
+    pub fn backoff(&self, failed_attempts: i32) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).clamp(0, 31) as u32;
+        self.backoff_base
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.backoff_max)
+    }
+}
+
+/// Posts a delivery body to a subscriber's URL.
+pub trait WebhookTransport {
+    /// `Err` carries a description of why the receiver did not accept the body.
+    fn post(&self, url: &str, body: &serde_json::Value) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+/// HTTP transport; any 2xx response counts as delivered.
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+}
+
+impl WebhookTransport for HttpTransport {
+    async fn post(&self, url: &str, body: &serde_json::Value) -> Result<(), String> {
+        let response = self
+            .client
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Receiver answered {}", response.status()))
+        }
+    }
+}
+
+/// What is posted for a delivery. Retries send the identical body.
+pub fn delivery_body(delivery: &webhook_delivery::Model) -> serde_json::Value {
+    json!({
+        "idempotency_key": delivery.idempotency_key,
+        "sequence": delivery.sequence,
+        "event": delivery.event,
+        "payload": delivery.payload,
+    })
+}
+
+/// Queues a webhook notification. Returns `false` when an event with the same
+/// idempotency key was already queued for the player, whatever became of it.
+pub async fn enqueue_with<C: ConnectionTrait>(db: &C, notification: &Notification) -> Result<bool, ApiError> {
+    let Some(target_url) = notification.target.clone() else {
+        return Ok(false);
+    };
+    let event = serde_json::to_value(notification.event)
+        .ok()
+        .and_then(|event| event.as_str().map(str::to_string))
+        .unwrap_or_default();
+
+    let row = webhook_delivery::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        player_id: Set(notification.player_id),
+        event: Set(event),
+        idempotency_key: Set(notification.idempotency_key.clone()),
+        target_url: Set(target_url),
+        payload: Set(notification.payload.clone()),
+        status: Set(DeliveryStatus::Pending),
+        attempts: Set(0),
+        next_attempt_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+    let inserted = webhook_delivery::Entity::insert(row)
+        .on_conflict(
+            OnConflict::columns([
+                webhook_delivery::Column::PlayerId,
+                webhook_delivery::Column::IdempotencyKey,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(inserted > 0)
+}
+
+/// The delivery a subscriber should receive next, if it is due. `queue` holds the
+/// subscriber's pending deliveries; only the earliest may be attempted, so a
+/// delivery backing off holds back everything queued after it.
+pub fn next_due(queue: &[webhook_delivery::Model], now: DateTime<Utc>) -> Option<&webhook_delivery::Model> {
+    queue
+        .iter()
+        .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+        .min_by_key(|delivery| delivery.sequence)
+        .filter(|delivery| delivery.next_attempt_at <= now)
+}
+
+/// Applies the outcome of an attempt: delivered, scheduled for a retry, or
+/// dead-lettered once `max_attempts` have failed.
+pub fn record_attempt(
+    delivery: &webhook_delivery::Model,
+    outcome: Result<(), String>,
+    now: DateTime<Utc>,
+    config: &WebhookConfig,
+) -> webhook_delivery::Model {
+    let mut updated = delivery.clone();
+    updated.attempts += 1;
+    updated.last_attempt_at = Some(now.into());
+    match outcome {
+        Ok(()) => {
+            updated.status = DeliveryStatus::Delivered;
+            updated.delivered_at = Some(now.into());
+            updated.last_error = None;
+        }
+        Err(err) => {
+            updated.last_error = Some(err);
+            if updated.attempts >= config.max_attempts {
+                updated.status = DeliveryStatus::Dead;
+            } else {
+                let wait = chrono::Duration::from_std(config.backoff(updated.attempts))
+                    .unwrap_or_else(|_| chrono::Duration::seconds(DEFAULT_BACKOFF_MAX_SECS as i64));
+                updated.next_attempt_at = (now + wait).into();
+            }
+        }
+    }
+    updated
+}
+
+/// Attempts `player_id`'s deliveries in order until one is not due, fails, or the
+/// subscriber's rate limit is reached. Every attempt is logged to `webhook_attempt`,
+/// which the rate limit counts, so retries of one delivery each use up budget.
+/// Returns the number of attempts made.
+pub async fn deliver_subscriber_with<C: ConnectionTrait, T: WebhookTransport>(
+    db: &C,
+    transport: &T,
+    config: &WebhookConfig,
+    player_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<u64, ApiError> {
+    let window_start = now - chrono::Duration::minutes(1);
+    let recent = webhook_attempt::Entity::find()
+        .filter(webhook_attempt::Column::PlayerId.eq(player_id))
+        .filter(webhook_attempt::Column::AttemptedAt.gte(window_start))
+        .count(db)
+        .await?;
+    let budget = config.rate_per_minute.saturating_sub(recent);
+
+    let mut queue = webhook_delivery::Entity::find()
+        .filter(webhook_delivery::Column::PlayerId.eq(player_id))
+        .filter(webhook_delivery::Column::Status.eq(DeliveryStatus::Pending))
+        .order_by_asc(webhook_delivery::Column::Sequence)
+        .limit(budget)
+        .all(db)
+        .await?;
+
+    let mut attempts = 0;
+    while attempts < budget {
+        let Some(delivery) = next_due(&queue, now).cloned() else {
+            break;
+        };
+        let outcome = transport.post(&delivery.target_url, &delivery_body(&delivery)).await;
+        let failed = outcome.is_err();
+        let updated = record_attempt(&delivery, outcome, now, config);
+        webhook_attempt::Entity::insert(webhook_attempt::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            delivery_id: Set(delivery.id),
+            player_id: Set(delivery.player_id),
+            attempted_at: Set(now.into()),
+            error: Set(if failed { updated.last_error.clone() } else { None }),
+        })
+        .exec_without_returning(db)
+        .await?;
+        let row = updated.into_active_model().reset_all();
+        row.update(db).await?;
+        attempts += 1;
+
+        queue.retain(|queued| queued.id != delivery.id);
+        if failed {
+            break;
+        }
+    }
+    Ok(attempts)
+}
+
+/// One pass of the delivery worker over every subscriber with a delivery due.
+pub async fn deliver_pending_with<C: ConnectionTrait, T: WebhookTransport>(
+    db: &C,
+    transport: &T,
+    config: &WebhookConfig,
+    now: DateTime<Utc>,
+) -> Result<u64, ApiError> {
+    let subscribers: Vec<Uuid> = webhook_delivery::Entity::find()
+        .select_only()
+        .column(webhook_delivery::Column::PlayerId)
+        .distinct()
+        .filter(webhook_delivery::Column::Status.eq(DeliveryStatus::Pending))
+        .filter(webhook_delivery::Column::NextAttemptAt.lte(now))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut attempts = 0;
+    for player_id in subscribers {
+        attempts += deliver_subscriber_with(db, transport, config, player_id, now).await?;
+    }
+    Ok(attempts)
+}
+
+/// Entry point for the periodic delivery worker; errors are logged and retried on
+/// the next pass.
+pub async fn deliver_pending() {
+    let db = get_db().await;
+    let config = WebhookConfig::from_env();
+    let transport = HttpTransport::new(config.timeout);
+    if let Err(err) = deliver_pending_with(&db, &transport, &config, Utc::now()).await {
+        log::error!("Webhook delivery pass failed: {}", err);
+    }
+}
+
+pub async fn deliveries(player_id: Uuid, page: u64, page_size: u64) -> Result<Page<WebhookDeliveryDTO>, ApiError> {
+    let db = get_db().await;
+    deliveries_with(&db, player_id, page, page_size).await
+}
+
+/// The player's webhook deliveries, newest first.
+pub async fn deliveries_with<C: ConnectionTrait>(
+    db: &C,
+    player_id: Uuid,
+    page: u64,
+    page_size: u64,
+) -> Result<Page<WebhookDeliveryDTO>, ApiError> {
+    if !player_exists(db, player_id).await? {
+        return Err(ApiError::NotFound(format!("Player {}", player_id)));
+    }
+    let query = webhook_delivery::Entity::find()
+        .filter(webhook_delivery::Column::PlayerId.eq(player_id))
+        .order_by_desc(webhook_delivery::Column::Sequence);
+    Ok(fetch_page(query, db, page, page_size).await?.map(WebhookDeliveryDTO::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dto::notifications::NotificationEvent;
+    use entity::sea_orm_active_enums::NotificationChannel;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// Fails the first `failures` posts, then accepts everything.
+    struct FlakyTransport {
+        failures: Mutex<u32>,
+        received: Mutex<Vec<serde_json::Value>>,
+    }
+
+    impl FlakyTransport {
+        fn new(failures: u32) -> Self {
+            Self { failures: Mutex::new(failures), received: Mutex::new(Vec::new()) }
+        }
+
+        fn received_keys(&self) -> Vec<String> {
+            self.received
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|body| body["idempotency_key"].as_str().unwrap().to_string())
+                .collect()
+        }
+    }
+
+    impl WebhookTransport for FlakyTransport {
+        async fn post(&self, _url: &str, body: &serde_json::Value) -> Result<(), String> {
+            self.received.lock().unwrap().push(body.clone());
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("Receiver answered 503 Service Unavailable".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    fn queued(sequence: i64, key: &str, now: DateTime<Utc>) -> webhook_delivery::Model {
+        webhook_delivery::Model {
+            id: Uuid::new_v4(),
+            sequence,
+            player_id: Uuid::nil(),
+            event: "game_result".to_string(),
+            idempotency_key: key.to_string(),
+            target_url: "https://example.com/hooks".to_string(),
+            payload: json!({}),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now.into(),
+            last_attempt_at: None,
+            last_error: None,
+            delivered_at: None,
+            created_at: now.into(),
+        }
+    }
+
+    #[test]
+    fn a_delivery_backing_off_holds_back_later_ones() {
+        let now = Utc::now();
+        let config = WebhookConfig::default();
+        let started = queued(1, "started", now);
+        let finished = queued(2, "finished", now);
+
+        let retrying = record_attempt(&started, Err("timeout".to_string()), now, &config);
+        let queue = [finished.clone(), retrying.clone()];
+
+        assert_eq!(next_due(&queue, now), None);
+        let retry_at = now + chrono::Duration::from_std(config.backoff_base).unwrap();
+        assert_eq!(next_due(&queue, retry_at).map(|d| d.sequence), Some(1));
+    }
+
+    #[test]
+    fn failures_back_off_exponentially_then_dead_letter() {
+        let now = Utc::now();
+        let config = WebhookConfig { max_attempts: 3, ..WebhookConfig::default() };
+        let mut delivery = queued(1, "started", now);
+
+        delivery = record_attempt(&delivery, Err("503".to_string()), now, &config);
+        let expected: chrono::DateTime<chrono::FixedOffset> = (now + chrono::Duration::seconds(10)).into();
+        assert_eq!(delivery.next_attempt_at, expected);
+        delivery = record_attempt(&delivery, Err("503".to_string()), now, &config);
+        let expected: chrono::DateTime<chrono::FixedOffset> = (now + chrono::Duration::seconds(20)).into();
+        assert_eq!(delivery.next_attempt_at, expected);
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        delivery = record_attempt(&delivery, Err("503".to_string()), now, &config);
+
+        assert_eq!(delivery.status, DeliveryStatus::Dead);
+        assert_eq!(next_due(&[delivery], now + chrono::Duration::days(1)), None);
+    }
+
+    #[async_std::test]
+    async fn retries_keep_order_and_deliver_each_key_once() {
+        let start = Utc::now();
+        let config = WebhookConfig::default();
+        let transport = FlakyTransport::new(1);
+        let mut queue = vec![queued(1, "started", start), queued(2, "finished", start)];
+
+        // Simulate worker passes a second apart for two minutes
+        for tick in 0..120 {
+            let now = start + chrono::Duration::seconds(tick);
+            while let Some(delivery) = next_due(&queue, now).cloned() {
+                let outcome = transport.post(&delivery.target_url, &delivery_body(&delivery)).await;
+                let failed = outcome.is_err();
+                let updated = record_attempt(&delivery, outcome, now, &config);
+                queue.retain(|d| d.id != delivery.id);
+                queue.push(updated);
+                if failed {
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(transport.received_keys(), ["started", "started", "finished"]);
+        assert!(queue.iter().all(|d| d.status == DeliveryStatus::Delivered));
+    }
+
+    #[async_std::test]
+    async fn the_rate_limit_counts_logged_attempts() {
+        let now = Utc::now();
+        let config = WebhookConfig { rate_per_minute: 3, ..WebhookConfig::default() };
+        let transport = FlakyTransport::new(0);
+        let started = queued(1, "started", now);
+        let delivered = record_attempt(&started, Ok(()), now, &config);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(2i64))])]])
+            .append_query_results([vec![started.clone(), queued(2, "finished", now)]])
+            .append_exec_results([MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .append_query_results([vec![delivered]])
+            .into_connection();
+
+        let attempts = deliver_subscriber_with(&db, &transport, &config, Uuid::nil(), now).await.unwrap();
+        assert_eq!(attempts, 1);
+        assert_eq!(transport.received_keys(), ["started"]);
+
+        let statements: Vec<_> = db
+            .into_transaction_log()
+            .iter()
+            .flat_map(|t| t.statements().to_vec())
+            .collect();
+        assert!(statements[0].sql.contains(r#"FROM "smdb"."webhook_attempt""#));
+        assert!(statements[2].sql.starts_with(r#"INSERT INTO "smdb"."webhook_attempt""#));
+    }
+
+    #[async_std::test]
+    async fn the_same_event_is_queued_once() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 0 },
+            ])
+            .into_connection();
+        let notification = Notification {
+            player_id: Uuid::new_v4(),
+            event: NotificationEvent::GameResult,
+            channel: NotificationChannel::Webhook,
+            target: Some("https://example.com/hooks".to_string()),
+            idempotency_key: "game_result:1".to_string(),
+            payload: json!({}),
+        };
+
+        assert!(enqueue_with(&db, &notification).await.unwrap());
+        assert!(!enqueue_with(&db, &notification).await.unwrap());
+    }
+}